@@ -0,0 +1,5 @@
+pub mod compiler;
+pub mod core;
+pub mod disasm;
+pub mod nkb;
+pub mod vm;
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+
+use super::core::{Function, Idx, Op, Program};
+use crate::interp::value::Value;
+use crate::lexer::{Span, Token};
+use crate::parser::ast::core::*;
+use crate::semantics::{core::ENTRY_POINT, intrinsics::INTRINSICS};
+
+/// Compiles a checked program to bytecode. Globals and functions are numbered in order of
+/// declaration; a function's locals get a slot each, w/ slots reused by sibling blocks.
+pub fn compile(ast_root: &TranslationUnit) -> Program {
+    let mut fn_ids = HashMap::new();
+    let mut global_ids = HashMap::new();
+
+    for decl in ast_root {
+        match decl {
+            Decl::Fn(f) => fn_ids.insert(f.ident.as_str(), fn_ids.len() as Idx),
+            Decl::Var(v) => global_ids.insert(v.ident.as_str(), global_ids.len() as Idx),
+        };
+    }
+
+    let mut c = Compiler {
+        consts: vec![],
+        fn_ids,
+        global_ids,
+    };
+
+    let mut functions = vec![];
+    let mut init = FnCompiler::new("<init>", &[]);
+
+    for decl in ast_root {
+        match decl {
+            Decl::Fn(f) => functions.push(c.function(f)),
+            Decl::Var(v) => {
+                init.mark(v.span);
+                c.expr(&mut init, &v.expr);
+                init.emit(Op::StoreGlobal(c.global_ids[v.ident.as_str()]));
+            }
+        }
+    }
+
+    init.emit(Op::RetVoid);
+    functions.push(init.finish());
+
+    Program {
+        n_globals: c.global_ids.len() as Idx,
+        init: (functions.len() - 1) as Idx,
+        entry: c.fn_ids.get(ENTRY_POINT).copied(),
+        consts: c.consts,
+        functions,
+    }
+}
+
+struct Compiler<'a> {
+    consts: Vec<Value>,
+    fn_ids: HashMap<&'a str, Idx>,
+    global_ids: HashMap<&'a str, Idx>,
+}
+
+// The function being compiled
+struct FnCompiler<'a> {
+    name: String,
+    arity: u32,
+    code: Vec<Op>,
+    spans: Vec<(Idx, Span)>,
+    scopes: Vec<Vec<(&'a str, Idx)>>, // innermost last
+    next_slot: Idx,
+    n_locals: Idx,
+    breaks: Vec<Vec<usize>>, // per enclosing loop, jumps to patch w/ its end
+}
+
+impl<'a> FnCompiler<'a> {
+    fn new(name: &str, params: &'a [Param]) -> Self {
+        let mut f = Self {
+            name: name.to_string(),
+            arity: params.len() as u32,
+            code: vec![],
+            spans: vec![],
+            scopes: vec![vec![]],
+            next_slot: 0,
+            n_locals: 0,
+            breaks: vec![],
+        };
+
+        for p in params {
+            f.declare(&p.ident);
+        }
+
+        f
+    }
+
+    fn finish(self) -> Function {
+        Function {
+            name: self.name,
+            arity: self.arity,
+            n_locals: self.n_locals,
+            code: self.code,
+            spans: self.spans,
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    fn pc(&self) -> Idx {
+        self.code.len() as Idx
+    }
+
+    // Points the jump at `at` to the next op
+    fn patch(&mut self, at: usize) {
+        let target = self.pc();
+        match &mut self.code[at] {
+            Op::Jump(t) | Op::JumpIfFalse(t) | Op::JumpIfFalseOrPop(t) | Op::JumpIfTrueOrPop(t) => {
+                *t = target
+            }
+            op => unreachable!("attempted to patch {op:?}"),
+        }
+    }
+
+    // Attributes the ops that follow to the statement at `span`
+    fn mark(&mut self, span: Span) {
+        let pc = self.pc();
+        match self.spans.last_mut() {
+            Some((last_pc, last_span)) if *last_pc == pc => *last_span = span,
+            _ => self.spans.push((pc, span)),
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    // Frees the scope's slots for reuse
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        self.next_slot -= scope.len() as Idx;
+    }
+
+    fn declare(&mut self, ident: &'a str) -> Idx {
+        let slot = self.next_slot;
+        self.scopes.last_mut().unwrap().push((ident, slot));
+        self.next_slot += 1;
+        self.n_locals = self.n_locals.max(self.next_slot);
+        slot
+    }
+
+    fn resolve_local(&self, ident: &str) -> Option<Idx> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find_map(|&(name, slot)| (name == ident).then_some(slot))
+    }
+}
+
+impl<'a> Compiler<'a> {
+    fn function(&mut self, f: &'a FnDecl) -> Function {
+        let mut fc = FnCompiler::new(&f.ident, &f.params);
+        self.stmts(&mut fc, &f.block);
+
+        // Falling off the end is only possible if the last statement doesn't return, as nothing
+        // jumps past it
+        if !matches!(f.block.last(), Some(Stmt::Ret(_))) {
+            match f.type_tok {
+                Token::Void => fc.emit(Op::RetVoid),
+                _ => fc.emit(Op::MissingRet),
+            };
+        }
+
+        fc.finish()
+    }
+
+    fn constant(&mut self, value: Value) -> Idx {
+        // Floats aren't deduplicated, as 0.0 == -0.0
+        let existing = match value {
+            Value::Float(_) => None,
+            _ => self.consts.iter().position(|c| *c == value),
+        };
+
+        existing.unwrap_or_else(|| {
+            self.consts.push(value);
+            self.consts.len() - 1
+        }) as Idx
+    }
+
+    fn block(&mut self, fc: &mut FnCompiler<'a>, block: &'a Block) {
+        fc.push_scope();
+        self.stmts(fc, block);
+        fc.pop_scope();
+    }
+
+    fn stmts(&mut self, fc: &mut FnCompiler<'a>, block: &'a Block) {
+        for stmt in block {
+            self.stmt(fc, stmt);
+        }
+    }
+
+    fn stmt(&mut self, fc: &mut FnCompiler<'a>, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::VarDecl(v) => self.var_decl(fc, v),
+
+            Stmt::Expr(e) => {
+                if e.expr.is_some() {
+                    fc.mark(e.span);
+                    self.expr(fc, &e.expr);
+                    fc.emit(Op::Pop);
+                }
+            }
+
+            Stmt::Ret(r) => {
+                fc.mark(r.span);
+                match &r.expr {
+                    Some(_) => {
+                        self.expr(fc, &r.expr);
+                        fc.emit(Op::Ret);
+                    }
+                    None => {
+                        fc.emit(Op::RetVoid);
+                    }
+                }
+            }
+
+            Stmt::Break(span) => {
+                fc.mark(*span);
+                let jump = fc.emit(Op::Jump(0));
+                fc.breaks
+                    .last_mut()
+                    .expect("`toro` outside a loop made it past analysis")
+                    .push(jump);
+            }
+
+            Stmt::For(f) => {
+                // The init's variable lives in a scope of its own, around the loop
+                fc.push_scope();
+                if let Some(init) = &f.init {
+                    self.var_decl(fc, init);
+                }
+
+                let cond = fc.pc();
+                fc.mark(f.span);
+                let exit = f.cond.expr.as_ref().map(|_| {
+                    self.expr(fc, &f.cond.expr);
+                    fc.emit(Op::JumpIfFalse(0))
+                });
+
+                fc.breaks.push(vec![]);
+                self.block(fc, &f.block);
+
+                if f.updt.is_some() {
+                    fc.mark(f.span);
+                    self.expr(fc, &f.updt);
+                    fc.emit(Op::Pop);
+                }
+                fc.emit(Op::Jump(cond));
+
+                for jump in exit.into_iter().chain(fc.breaks.pop().unwrap()) {
+                    fc.patch(jump);
+                }
+                fc.pop_scope();
+            }
+
+            Stmt::If(i) => {
+                fc.mark(i.span);
+                self.expr(fc, &i.cond);
+                let to_else = fc.emit(Op::JumpIfFalse(0));
+
+                self.block(fc, &i.if_block);
+                let to_end = fc.emit(Op::Jump(0));
+
+                fc.patch(to_else);
+                self.block(fc, &i.else_block);
+                fc.patch(to_end);
+            }
+        }
+    }
+
+    fn var_decl(&mut self, fc: &mut FnCompiler<'a>, v: &'a VarDecl) {
+        fc.mark(v.span);
+        self.expr(fc, &v.expr);
+        let slot = fc.declare(&v.ident);
+        fc.emit(Op::StoreLocal(slot));
+    }
+
+    fn load(&mut self, fc: &mut FnCompiler<'a>, ident: &str) {
+        match fc.resolve_local(ident) {
+            Some(slot) => fc.emit(Op::LoadLocal(slot)),
+            None => fc.emit(Op::LoadGlobal(self.global_ids[ident])),
+        };
+    }
+
+    fn store(&mut self, fc: &mut FnCompiler<'a>, ident: &str) {
+        match fc.resolve_local(ident) {
+            Some(slot) => fc.emit(Op::StoreLocal(slot)),
+            None => fc.emit(Op::StoreGlobal(self.global_ids[ident])),
+        };
+    }
+
+    fn binary(&mut self, fc: &mut FnCompiler<'a>, op: &Token) {
+        fc.emit(Op::from_binary_token(op));
+    }
+
+    fn expr(&mut self, fc: &mut FnCompiler<'a>, expr: &'a Expr) {
+        match expr {
+            Some(e) => self.assign_expr(fc, e),
+            None => {
+                let void = self.constant(Value::Void);
+                fc.emit(Op::Const(void));
+            }
+        }
+    }
+
+    fn assign_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a AssignExpr) {
+        match e {
+            AssignExpr::Bool(b) => self.bool_expr(fc, b),
+            AssignExpr::Assign(lhs, rhs) => {
+                let ident = lhs
+                    .as_ident()
+                    .expect("assignment target should've been checked to be an identifier");
+                self.assign_expr(fc, rhs);
+                fc.emit(Op::Dup);
+                self.store(fc, ident);
+            }
+        }
+    }
+
+    fn bool_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a BoolExpr) {
+        match e {
+            BoolExpr::BitOr(b) => self.bit_or_expr(fc, b),
+            BoolExpr::Bool(lhs, op, rhs) => {
+                self.bool_expr(fc, lhs);
+
+                // Short-circuit
+                let jump = match op {
+                    Token::BooleanAnd => fc.emit(Op::JumpIfFalseOrPop(0)),
+                    _ => fc.emit(Op::JumpIfTrueOrPop(0)),
+                };
+                self.bit_or_expr(fc, rhs);
+                fc.patch(jump);
+            }
+        }
+    }
+
+    fn bit_or_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a BitOrExpr) {
+        match e {
+            BitOrExpr::BitAnd(b) => self.bit_and_expr(fc, b),
+            BitOrExpr::BitOr(lhs, rhs) => {
+                self.bit_or_expr(fc, lhs);
+                self.bit_and_expr(fc, rhs);
+                fc.emit(Op::BitOr);
+            }
+        }
+    }
+
+    fn bit_and_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a BitAndExpr) {
+        match e {
+            BitAndExpr::Comp(c) => self.comp_expr(fc, c),
+            BitAndExpr::BitAnd(lhs, rhs) => {
+                self.bit_and_expr(fc, lhs);
+                self.comp_expr(fc, rhs);
+                fc.emit(Op::BitAnd);
+            }
+        }
+    }
+
+    fn comp_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a CompExpr) {
+        match e {
+            CompExpr::Shift(s) => self.shift_expr(fc, s),
+            CompExpr::Comp(lhs, op, rhs) => {
+                self.comp_expr(fc, lhs);
+                self.shift_expr(fc, rhs);
+                self.binary(fc, op);
+            }
+        }
+    }
+
+    fn shift_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a ShiftExpr) {
+        match e {
+            ShiftExpr::Add(a) => self.add_expr(fc, a),
+            ShiftExpr::Shift(lhs, op, rhs) => {
+                self.shift_expr(fc, lhs);
+                self.add_expr(fc, rhs);
+                self.binary(fc, op);
+            }
+        }
+    }
+
+    fn add_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a AddExpr) {
+        match e {
+            AddExpr::Mul(m) => self.mul_expr(fc, m),
+            AddExpr::Add(lhs, op, rhs) => {
+                self.add_expr(fc, lhs);
+                self.mul_expr(fc, rhs);
+                self.binary(fc, op);
+            }
+        }
+    }
+
+    fn mul_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a MulExpr) {
+        match e {
+            MulExpr::Exp(x) => self.exp_expr(fc, x),
+            MulExpr::Mul(lhs, op, rhs) => {
+                self.mul_expr(fc, lhs);
+                self.exp_expr(fc, rhs);
+                self.binary(fc, op);
+            }
+        }
+    }
+
+    fn exp_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a ExpExpr) {
+        match e {
+            ExpExpr::Unary(u) => self.unary_expr(fc, u),
+            ExpExpr::Exp(lhs, rhs) => {
+                self.unary_expr(fc, lhs);
+                self.exp_expr(fc, rhs);
+                fc.emit(Op::Exp);
+            }
+        }
+    }
+
+    fn unary_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a UnaryExpr) {
+        match e {
+            UnaryExpr::Primary(p) => self.primary_expr(fc, p),
+            UnaryExpr::Unary(op, operand) => {
+                self.unary_expr(fc, operand);
+                match op {
+                    Token::SubOp => fc.emit(Op::Neg),
+                    _ => fc.emit(Op::Not),
+                };
+            }
+        }
+    }
+
+    fn primary_expr(&mut self, fc: &mut FnCompiler<'a>, e: &'a PrimaryExpr) {
+        let value = match e {
+            PrimaryExpr::IntLit(i) => Value::Int(*i),
+            PrimaryExpr::FloatLit(f) => Value::Float(*f),
+            PrimaryExpr::StringLit(s) => Value::String(s.clone()),
+            PrimaryExpr::BoolLit(b) => Value::Bool(*b),
+
+            PrimaryExpr::Ident(ident) => return self.load(fc, ident),
+            PrimaryExpr::Paren(inner) => return self.expr(fc, inner),
+
+            PrimaryExpr::Call(call) => {
+                for arg in &call.args {
+                    self.expr(fc, arg);
+                }
+
+                match self.fn_ids.get(call.ident.as_str()) {
+                    Some(&id) => fc.emit(Op::Call(id)),
+                    None => {
+                        let id = INTRINSICS
+                            .iter()
+                            .position(|i| i.name == call.ident)
+                            .unwrap_or_else(|| unreachable!("undefined `{}`", call.ident));
+                        fc.emit(Op::CallIntrinsic(id as Idx))
+                    }
+                };
+                return;
+            }
+        };
+
+        let id = self.constant(value);
+        fc.emit(Op::Const(id));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::interp::value::Value;
+use crate::lexer::{Span, Token};
+
+/// Index into one of a program's tables, or into a function's code
+pub type Idx = u32;
+
+/// A stack machine instruction. Operands are consumed from, and results pushed onto, the
+/// operand stack; binary ops pop their rhs first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Const(Idx), // push a constant from the pool
+    LoadLocal(Idx),
+    StoreLocal(Idx), // pops
+    LoadGlobal(Idx),
+    StoreGlobal(Idx), // pops
+    Dup,
+    Pop,
+
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    Lt,
+    Gt,
+    Eq,
+    Neg,
+    Not,
+
+    Jump(Idx),
+    JumpIfFalse(Idx), // pops the condition
+    // For `&&`/`||`: jump, leaving the condition on the stack, or pop it and fall through
+    JumpIfFalseOrPop(Idx),
+    JumpIfTrueOrPop(Idx),
+
+    Call(Idx),          // w/ its args on the stack, which become its first locals
+    CallIntrinsic(Idx), // indexes `INTRINSICS`
+    Ret,                // returns the value on top of the stack
+    RetVoid,
+    MissingRet, // a non-khali function ran off its end
+}
+
+impl Op {
+    /// The instruction for a binary operator token
+    pub fn from_binary_token(tok: &Token) -> Op {
+        match tok {
+            Token::AddOp => Op::Add,
+            Token::SubOp => Op::Sub,
+            Token::MulOp => Op::Mul,
+            Token::DivOp => Op::Div,
+            Token::ModOp => Op::Mod,
+            Token::ExpOp => Op::Exp,
+            Token::ShiftLeft => Op::Shl,
+            Token::ShiftRight => Op::Shr,
+            Token::BitwiseAnd => Op::BitAnd,
+            Token::BitwiseOr => Op::BitOr,
+            Token::LessThan => Op::Lt,
+            Token::GreaterThan => Op::Gt,
+            Token::EqualsOp => Op::Eq,
+            _ => unreachable!("{tok:?} isn't an arithmetic operator"),
+        }
+    }
+
+    /// Inverse of `from_binary_token`, for sharing the interpreter's arithmetic
+    pub fn binary_token(self) -> Option<Token> {
+        let tok = match self {
+            Op::Add => Token::AddOp,
+            Op::Sub => Token::SubOp,
+            Op::Mul => Token::MulOp,
+            Op::Div => Token::DivOp,
+            Op::Mod => Token::ModOp,
+            Op::Exp => Token::ExpOp,
+            Op::Shl => Token::ShiftLeft,
+            Op::Shr => Token::ShiftRight,
+            Op::BitAnd => Token::BitwiseAnd,
+            Op::BitOr => Token::BitwiseOr,
+            Op::Lt => Token::LessThan,
+            Op::Gt => Token::GreaterThan,
+            Op::Eq => Token::EqualsOp,
+            _ => return None,
+        };
+
+        Some(tok)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub arity: u32,
+    pub n_locals: u32, // params included
+    pub code: Vec<Op>,
+    pub spans: Vec<(Idx, Span)>, // (pc, span) of each statement's first op, in order of pc
+}
+
+impl Function {
+    /// Span of the statement the op at `pc` belongs to
+    pub fn span_at(&self, pc: usize) -> Span {
+        let i = self
+            .spans
+            .partition_point(|&(start, _)| start as usize <= pc);
+        i.checked_sub(1)
+            .map_or_else(Span::default, |i| self.spans[i].1)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Program {
+    pub consts: Vec<Value>,
+    pub n_globals: u32,
+    pub functions: Vec<Function>,
+    pub init: Idx,          // initialises globals, in order
+    pub entry: Option<Idx>, // `shuru`, if there is one
+}
@@ -0,0 +1,67 @@
+use std::fmt::Write;
+
+use super::core::{Op, Program};
+use crate::semantics::intrinsics::INTRINSICS;
+
+/// Lists every function's instructions, each statement's preceded by the source line it came
+/// from. W/o the source (e.g for a loaded `.nkb`), only line numbers are given.
+pub fn disassemble(program: &Program, src: Option<&str>) -> String {
+    let lines = src.map(|src| src.lines().collect::<Vec<_>>());
+    let mut out = String::new();
+
+    for (id, f) in program.functions.iter().enumerate() {
+        let mut tags = vec![];
+        if id as u32 == program.init {
+            tags.push("init");
+        }
+        if Some(id as u32) == program.entry {
+            tags.push("entry");
+        }
+        let tags = match tags.is_empty() {
+            true => String::new(),
+            false => format!(" [{}]", tags.join(", ")),
+        };
+
+        if id > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(
+            out,
+            "fn #{id} {}{tags}: arity {}, locals {}",
+            f.name, f.arity, f.n_locals
+        );
+
+        let mut spans = f.spans.iter().peekable();
+        let mut last_line = None;
+        for (pc, &op) in f.code.iter().enumerate() {
+            let span = spans.next_if(|&&(start, _)| start as usize <= pc);
+            if let Some((_, span)) = span.filter(|(_, span)| last_line != Some(span.line)) {
+                last_line = Some(span.line);
+                let text = lines
+                    .as_ref()
+                    .and_then(|lines| lines.get(span.line.wrapping_sub(1)))
+                    .map_or(String::new(), |line| format!(": {}", line.trim()));
+                let _ = writeln!(out, "    ; line {}{text}", span.line);
+            }
+
+            let _ = writeln!(out, "    {pc:04}  {}", instruction(program, op));
+        }
+    }
+
+    out
+}
+
+// The op, w/ its operand (if any) resolved to something more readable where possible
+fn instruction(program: &Program, op: Op) -> String {
+    // e.g `Const 3`, rather than `Const(3)`
+    let text = format!("{op:?}").replace('(', " ").replace(')', "");
+
+    let note = match op {
+        Op::Const(i) => format!("{:?}", program.consts[i as usize]),
+        Op::Call(i) => program.functions[i as usize].name.clone(),
+        Op::CallIntrinsic(i) => INTRINSICS[i as usize].name.to_string(),
+        _ => return text,
+    };
+
+    format!("{text:<24}; {note}")
+}
@@ -0,0 +1,404 @@
+//! The `.nkb` file format: a compiled program, loadable w/o the source it came from.
+//!
+//! All integers are little-endian. `u32` lengths prefix every sequence; strings are UTF-8.
+//!
+//! ```text
+//! header     "NKB\0", version: u16 (currently `VERSION`)
+//! globals    n_globals: u32
+//! functions  init: u32, entry: u32 (u32::MAX if there's no entry point)
+//! consts     count: u32, then per constant a tag: u8, followed by its payload:
+//!            0 ginti (i64), 1 asharia (f64 bits), 2 jumla (u32 len + bytes), 3 boli (u8),
+//!            4 khali (nothing)
+//! functions  count: u32, then per function:
+//!            name (u32 len + bytes), arity: u32, n_locals: u32,
+//!            code: count: u32, then per op an opcode: u8, followed by a u32 operand for ops
+//!                  that take one (see `encode_op`)
+//!            line table: count: u32, then per entry pc, start, end, line, col: u32 each,
+//!                  i.e the span of the statement starting at `pc`
+//! ```
+//!
+//! The version is bumped whenever the layout, or the meaning of an opcode, changes. Loading
+//! checks that every index in the file (constants, locals, globals, functions, jump targets)
+//! is in bounds, and that every op finds as many operands on the stack as it pops, however it's
+//! reached. The file doesn't record types, so operands of the wrong type are left to the VM,
+//! which reports them as `RuntimeErrorKind::IllTyped`; either way, a corrupt file is an error
+//! rather than a crash.
+
+use super::core::{Function, Idx, Op, Program};
+use crate::interp::value::Value;
+use crate::lexer::Span;
+use crate::semantics::intrinsics::INTRINSICS;
+
+pub const MAGIC: &[u8; 4] = b"NKB\0";
+pub const VERSION: u16 = 1;
+
+const NO_ENTRY: u32 = u32::MAX;
+
+#[derive(Debug, PartialEq)]
+pub enum NkbError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    TrailingBytes,
+    InvalidConstTag(u8),
+    InvalidOpcode(u8),
+    InvalidUtf8,
+    IndexOutOfBounds, // an operand, or the init/entry function, points nowhere
+    UnbalancedStack(String), // in the named function, an op pops what isn't there
+}
+
+// Opcodes are numbered in order of `Op`'s variants; those w/ an operand are followed by it
+fn encode_op(op: Op) -> (u8, Option<Idx>) {
+    match op {
+        Op::Const(i) => (0, Some(i)),
+        Op::LoadLocal(i) => (1, Some(i)),
+        Op::StoreLocal(i) => (2, Some(i)),
+        Op::LoadGlobal(i) => (3, Some(i)),
+        Op::StoreGlobal(i) => (4, Some(i)),
+        Op::Dup => (5, None),
+        Op::Pop => (6, None),
+        Op::Add => (7, None),
+        Op::Sub => (8, None),
+        Op::Mul => (9, None),
+        Op::Div => (10, None),
+        Op::Mod => (11, None),
+        Op::Exp => (12, None),
+        Op::Shl => (13, None),
+        Op::Shr => (14, None),
+        Op::BitAnd => (15, None),
+        Op::BitOr => (16, None),
+        Op::Lt => (17, None),
+        Op::Gt => (18, None),
+        Op::Eq => (19, None),
+        Op::Neg => (20, None),
+        Op::Not => (21, None),
+        Op::Jump(t) => (22, Some(t)),
+        Op::JumpIfFalse(t) => (23, Some(t)),
+        Op::JumpIfFalseOrPop(t) => (24, Some(t)),
+        Op::JumpIfTrueOrPop(t) => (25, Some(t)),
+        Op::Call(f) => (26, Some(f)),
+        Op::CallIntrinsic(i) => (27, Some(i)),
+        Op::Ret => (28, None),
+        Op::RetVoid => (29, None),
+        Op::MissingRet => (30, None),
+    }
+}
+
+fn decode_op(r: &mut Reader) -> Result<Op, NkbError> {
+    let opcode = r.u8()?;
+    let op = match opcode {
+        0 => Op::Const(r.u32()?),
+        1 => Op::LoadLocal(r.u32()?),
+        2 => Op::StoreLocal(r.u32()?),
+        3 => Op::LoadGlobal(r.u32()?),
+        4 => Op::StoreGlobal(r.u32()?),
+        5 => Op::Dup,
+        6 => Op::Pop,
+        7 => Op::Add,
+        8 => Op::Sub,
+        9 => Op::Mul,
+        10 => Op::Div,
+        11 => Op::Mod,
+        12 => Op::Exp,
+        13 => Op::Shl,
+        14 => Op::Shr,
+        15 => Op::BitAnd,
+        16 => Op::BitOr,
+        17 => Op::Lt,
+        18 => Op::Gt,
+        19 => Op::Eq,
+        20 => Op::Neg,
+        21 => Op::Not,
+        22 => Op::Jump(r.u32()?),
+        23 => Op::JumpIfFalse(r.u32()?),
+        24 => Op::JumpIfFalseOrPop(r.u32()?),
+        25 => Op::JumpIfTrueOrPop(r.u32()?),
+        26 => Op::Call(r.u32()?),
+        27 => Op::CallIntrinsic(r.u32()?),
+        28 => Op::Ret,
+        29 => Op::RetVoid,
+        30 => Op::MissingRet,
+        _ => return Err(NkbError::InvalidOpcode(opcode)),
+    };
+
+    Ok(op)
+}
+
+pub fn serialize(program: &Program) -> Vec<u8> {
+    let mut w = Writer(vec![]);
+
+    w.0.extend_from_slice(MAGIC);
+    w.0.extend_from_slice(&VERSION.to_le_bytes());
+    w.u32(program.n_globals);
+    w.u32(program.init);
+    w.u32(program.entry.unwrap_or(NO_ENTRY));
+
+    w.u32(program.consts.len() as u32);
+    for c in &program.consts {
+        match c {
+            Value::Int(i) => {
+                w.u8(0);
+                w.0.extend_from_slice(&i.to_le_bytes());
+            }
+            Value::Float(x) => {
+                w.u8(1);
+                w.0.extend_from_slice(&x.to_bits().to_le_bytes());
+            }
+            Value::String(s) => {
+                w.u8(2);
+                w.str(s);
+            }
+            Value::Bool(b) => {
+                w.u8(3);
+                w.u8(*b as u8);
+            }
+            Value::Void => w.u8(4),
+        }
+    }
+
+    w.u32(program.functions.len() as u32);
+    for f in &program.functions {
+        w.str(&f.name);
+        w.u32(f.arity);
+        w.u32(f.n_locals);
+
+        w.u32(f.code.len() as u32);
+        for &op in &f.code {
+            let (opcode, operand) = encode_op(op);
+            w.u8(opcode);
+            if let Some(operand) = operand {
+                w.u32(operand);
+            }
+        }
+
+        w.u32(f.spans.len() as u32);
+        for (pc, span) in &f.spans {
+            w.u32(*pc);
+            for n in [span.start, span.end, span.line, span.col] {
+                w.u32(n as u32);
+            }
+        }
+    }
+
+    w.0
+}
+
+pub fn deserialize(bytes: &[u8]) -> Result<Program, NkbError> {
+    let mut r = Reader { bytes, pos: 0 };
+
+    if r.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(NkbError::BadMagic);
+    }
+
+    let version = u16::from_le_bytes(r.take(2)?.try_into().unwrap());
+    if version != VERSION {
+        return Err(NkbError::UnsupportedVersion(version));
+    }
+
+    let n_globals = r.u32()?;
+    let init = r.u32()?;
+    let entry = match r.u32()? {
+        NO_ENTRY => None,
+        entry => Some(entry),
+    };
+
+    let consts = (0..r.u32()?)
+        .map(|_| {
+            let tag = r.u8()?;
+            let value = match tag {
+                0 => Value::Int(r.u64()? as i64),
+                1 => Value::Float(f64::from_bits(r.u64()?)),
+                2 => Value::String(r.str()?),
+                3 => Value::Bool(r.u8()? != 0),
+                4 => Value::Void,
+                _ => return Err(NkbError::InvalidConstTag(tag)),
+            };
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let functions = (0..r.u32()?)
+        .map(|_| {
+            let name = r.str()?;
+            let arity = r.u32()?;
+            let n_locals = r.u32()?;
+            let code = (0..r.u32()?)
+                .map(|_| decode_op(&mut r))
+                .collect::<Result<Vec<_>, _>>()?;
+            let spans = (0..r.u32()?)
+                .map(|_| {
+                    let pc = r.u32()?;
+                    let [start, end, line, col] =
+                        [r.u32()?, r.u32()?, r.u32()?, r.u32()?].map(|n| n as usize);
+                    Ok((
+                        pc,
+                        Span {
+                            start,
+                            end,
+                            line,
+                            col,
+                        },
+                    ))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Function {
+                name,
+                arity,
+                n_locals,
+                code,
+                spans,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if r.pos != bytes.len() {
+        return Err(NkbError::TrailingBytes);
+    }
+
+    let program = Program {
+        consts,
+        n_globals,
+        functions,
+        init,
+        entry,
+    };
+    validate(&program)?;
+    Ok(program)
+}
+
+// Bounds-checks every index in the program
+fn validate(program: &Program) -> Result<(), NkbError> {
+    let n_fns = program.functions.len();
+    let fn_ok = |i: Idx| (i as usize) < n_fns;
+
+    let entry_ok = program
+        .entry
+        .is_none_or(|entry| fn_ok(entry) && program.functions[entry as usize].arity <= 1);
+    let init_ok = fn_ok(program.init) && program.functions[program.init as usize].arity == 0;
+    if !init_ok || !entry_ok {
+        return Err(NkbError::IndexOutOfBounds);
+    }
+
+    for f in &program.functions {
+        let in_bounds = f.arity <= f.n_locals
+            && f.code.last().is_some_and(|op| {
+                matches!(op, Op::Ret | Op::RetVoid | Op::MissingRet | Op::Jump(_))
+            })
+            && f.code.iter().all(|&op| match op {
+                Op::Const(i) => (i as usize) < program.consts.len(),
+                Op::LoadLocal(i) | Op::StoreLocal(i) => i < f.n_locals,
+                Op::LoadGlobal(i) | Op::StoreGlobal(i) => i < program.n_globals,
+                Op::Jump(t)
+                | Op::JumpIfFalse(t)
+                | Op::JumpIfFalseOrPop(t)
+                | Op::JumpIfTrueOrPop(t) => (t as usize) < f.code.len(),
+                Op::Call(i) => fn_ok(i),
+                Op::CallIntrinsic(i) => (i as usize) < INTRINSICS.len(),
+                _ => true,
+            });
+
+        if !in_bounds {
+            return Err(NkbError::IndexOutOfBounds);
+        }
+        if !stack_balanced(program, f) {
+            return Err(NkbError::UnbalancedStack(f.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+// Whether every path through `f` reaches each op w/ the same stack depth, and enough operands
+// for it. Expects `f` to have passed the bounds checks, so that only its last op may be one that
+// doesn't fall through.
+fn stack_balanced(program: &Program, f: &Function) -> bool {
+    let mut depths = vec![None; f.code.len()];
+    let mut pending = vec![(0, 0u32)];
+
+    while let Some((pc, depth)) = pending.pop() {
+        match depths[pc] {
+            Some(seen) if seen == depth => continue,
+            Some(_) => return false,
+            None => depths[pc] = Some(depth),
+        }
+
+        let op = f.code[pc];
+        let (pops, pushes) = match op {
+            Op::Const(_) | Op::LoadLocal(_) | Op::LoadGlobal(_) => (0, 1),
+            Op::StoreLocal(_) | Op::StoreGlobal(_) | Op::Pop | Op::JumpIfFalse(_) => (1, 0),
+            Op::Dup => (1, 2),
+            Op::Neg | Op::Not => (1, 1),
+            Op::Jump(_) | Op::RetVoid | Op::MissingRet => (0, 0),
+            Op::JumpIfFalseOrPop(_) | Op::JumpIfTrueOrPop(_) => (1, 0), // when falling through
+            Op::Call(i) => (program.functions[i as usize].arity, 1),
+            Op::CallIntrinsic(i) => (INTRINSICS[i as usize].params.len() as u32, 1),
+            Op::Ret => (1, 0),
+            _ => (2, 1), // a binary operator
+        };
+        let Some(after) = depth.checked_sub(pops).map(|d| d + pushes) else {
+            return false;
+        };
+
+        match op {
+            Op::Jump(t) => pending.push((t as usize, after)),
+            Op::JumpIfFalse(t) => pending.extend([(t as usize, after), (pc + 1, after)]),
+            Op::JumpIfFalseOrPop(t) | Op::JumpIfTrueOrPop(t) => {
+                pending.extend([(t as usize, depth), (pc + 1, after)]);
+            }
+            Op::Ret | Op::RetVoid | Op::MissingRet => {}
+            _ => pending.push((pc + 1, after)),
+        }
+    }
+
+    true
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, n: u8) {
+        self.0.push(n);
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.0.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.0.extend_from_slice(s.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], NkbError> {
+        let end = self.pos.checked_add(n).ok_or(NkbError::Truncated)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(NkbError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, NkbError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, NkbError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, NkbError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String, NkbError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| NkbError::InvalidUtf8)
+    }
+}
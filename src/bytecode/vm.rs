@@ -0,0 +1,222 @@
+use std::io::{self, BufRead, BufReader, Write};
+
+use super::core::{Idx, Op, Program};
+use crate::interp::{
+    core::{RuntimeError, RuntimeErrorKind, MAX_CALL_DEPTH},
+    intrinsics, ops,
+    value::Value,
+};
+use crate::lexer::{Span, Token};
+use crate::semantics::intrinsics::INTRINSICS;
+
+/// Runs a program compiled to bytecode; see `interp::core::run`, whose behaviour this matches
+pub fn run(program: &Program, args: &[String]) -> Result<i64, RuntimeError> {
+    let mut input = BufReader::new(io::stdin());
+    run_with_io(program, args, &mut input, &mut io::stdout())
+}
+
+/// Like `run`, but w/ the intrinsics reading from `input` and writing to `output`
+pub fn run_with_io(
+    program: &Program,
+    args: &[String],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<i64, RuntimeError> {
+    let mut vm = Vm {
+        program,
+        stack: vec![],
+        globals: vec![Value::Void; program.n_globals as usize],
+        frames: vec![],
+        input,
+        output,
+    };
+
+    let res = vm.run(args.len());
+    vm.output
+        .flush()
+        .map_err(|e| vm.error(RuntimeErrorKind::Io(e.kind())))?;
+    res
+}
+
+struct Frame {
+    func: Idx,
+    pc: usize,
+    base: usize, // index of the frame's first local on the stack
+}
+
+struct Vm<'p, 'io> {
+    program: &'p Program,
+    stack: Vec<Value>, // locals and operands, of every frame
+    globals: Vec<Value>,
+    frames: Vec<Frame>,
+    input: &'io mut dyn BufRead,
+    output: &'io mut dyn Write,
+}
+
+impl Vm<'_, '_> {
+    fn run(&mut self, argc: usize) -> Result<i64, RuntimeError> {
+        self.call(self.program.init, vec![])?;
+
+        let Some(entry) = self.program.entry else {
+            return Err(self.error(RuntimeErrorKind::NoEntryPoint));
+        };
+
+        let args = match self.program.functions[entry as usize].arity {
+            0 => vec![],
+            _ => vec![Value::Int(argc as i64)],
+        };
+
+        match self.call(entry, args)? {
+            Value::Int(code) => Ok(code),
+            _ => Ok(0),
+        }
+    }
+
+    // Calls a function from outside any frame, running until it returns
+    fn call(&mut self, func: Idx, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        self.stack.extend(args);
+        self.push_frame(func)?;
+        self.execute()?;
+        Ok(self.stack.pop().unwrap())
+    }
+
+    // The error, attributed to the statement being executed by the innermost frame
+    fn error(&self, kind: RuntimeErrorKind) -> RuntimeError {
+        let span = match self.frames.last() {
+            Some(frame) => {
+                let f = &self.program.functions[frame.func as usize];
+                f.span_at(frame.pc.saturating_sub(1))
+            }
+            None => Span::default(),
+        };
+
+        RuntimeError { kind, span }
+    }
+
+    // Expects the function's args to be on top of the stack
+    fn push_frame(&mut self, func: Idx) -> Result<(), RuntimeError> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(self.error(RuntimeErrorKind::StackOverflow));
+        }
+
+        let f = &self.program.functions[func as usize];
+        let base = self.stack.len() - f.arity as usize;
+        self.stack.resize(base + f.n_locals as usize, Value::Void);
+        self.frames.push(Frame { func, pc: 0, base });
+        Ok(())
+    }
+
+    // Loading checks that no op pops more than its function has pushed
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("operand stack underflow")
+    }
+
+    fn condition(&self, value: &Value) -> Result<bool, RuntimeError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(self.error(RuntimeErrorKind::IllTyped)),
+        }
+    }
+
+    fn jump(&mut self, target: Idx) {
+        self.frames.last_mut().unwrap().pc = target as usize;
+    }
+
+    // Runs until the frame on top when called returns, leaving its return value on the stack
+    fn execute(&mut self) -> Result<(), RuntimeError> {
+        let depth = self.frames.len();
+
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let base = frame.base;
+            let op = self.program.functions[frame.func as usize].code[frame.pc];
+            frame.pc += 1;
+
+            match op {
+                Op::Const(i) => self.stack.push(self.program.consts[i as usize].clone()),
+                Op::LoadLocal(i) => self.stack.push(self.stack[base + i as usize].clone()),
+                Op::StoreLocal(i) => self.stack[base + i as usize] = self.pop(),
+                Op::LoadGlobal(i) => self.stack.push(self.globals[i as usize].clone()),
+                Op::StoreGlobal(i) => self.globals[i as usize] = self.pop(),
+                Op::Dup => self.stack.push(self.stack.last().unwrap().clone()),
+                Op::Pop => {
+                    self.pop();
+                }
+
+                Op::Neg | Op::Not => {
+                    let tok = match op {
+                        Op::Neg => Token::SubOp,
+                        _ => Token::BooleanNot,
+                    };
+                    let operand = self.pop();
+                    let res = ops::unary(&tok, operand).map_err(|kind| self.error(kind))?;
+                    self.stack.push(res);
+                }
+
+                Op::Jump(t) => self.jump(t),
+                Op::JumpIfFalse(t) => {
+                    let cond = self.pop();
+                    if !self.condition(&cond)? {
+                        self.jump(t);
+                    }
+                }
+                Op::JumpIfFalseOrPop(t) | Op::JumpIfTrueOrPop(t) => {
+                    let jump_on = matches!(op, Op::JumpIfTrueOrPop(_));
+                    if self.condition(self.stack.last().unwrap())? == jump_on {
+                        self.jump(t);
+                    } else {
+                        self.pop();
+                    }
+                }
+
+                Op::Call(func) => self.push_frame(func)?,
+                Op::CallIntrinsic(i) => {
+                    let intrinsic = &INTRINSICS[i as usize];
+                    let args = self
+                        .stack
+                        .split_off(self.stack.len() - intrinsic.params.len());
+                    let well_typed = args
+                        .iter()
+                        .zip(intrinsic.params)
+                        .all(|(arg, &(_, ty))| arg.sym_type() == ty);
+                    if !well_typed {
+                        return Err(self.error(RuntimeErrorKind::IllTyped));
+                    }
+                    let res = intrinsics::call(intrinsic.name, &args, self.input, self.output)
+                        .map_err(|e| self.error(RuntimeErrorKind::Io(e.kind())))?;
+                    self.stack.push(res);
+                }
+
+                Op::Ret | Op::RetVoid => {
+                    let value = match op {
+                        Op::Ret => self.pop(),
+                        _ => Value::Void,
+                    };
+
+                    self.stack.truncate(base);
+                    self.stack.push(value);
+                    self.frames.pop();
+
+                    if self.frames.len() < depth {
+                        return Ok(());
+                    }
+                }
+
+                Op::MissingRet => {
+                    // Reported at the call, like the interpreter does
+                    let frame = self.frames.pop().unwrap();
+                    let name = self.program.functions[frame.func as usize].name.clone();
+                    return Err(self.error(RuntimeErrorKind::MissingReturn(name)));
+                }
+
+                _ => {
+                    let tok = op.binary_token().unwrap();
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let res = ops::binary(&tok, lhs, rhs).map_err(|kind| self.error(kind))?;
+                    self.stack.push(res);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod asm;
+pub mod c;
+pub mod core;
+pub mod isel;
+pub mod llvm;
+pub mod mips;
+pub mod regalloc;
+pub mod riscv64;
+pub mod x86_64;
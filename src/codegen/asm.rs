@@ -0,0 +1,209 @@
+//! Reading assembly, as the simulators share it: comments, labels, sections and data directives
+//! are GNU `as`'s on every target, so only instructions are left to each simulator to decode,
+//! once the labels they may refer to are all known.
+
+use std::collections::HashMap;
+
+/// A line that doesn't assemble, and what's wrong w/ it
+#[derive(Debug, PartialEq)]
+pub struct SyntaxError(pub usize, pub String);
+
+/// The text, decoded, and the data, laid out from the bases `assemble` was given
+pub struct Program<I> {
+    pub text: Vec<I>,
+    pub data: Vec<u8>,
+    pub labels: HashMap<String, u64>,
+}
+
+/// A source line, sans its comment and labels
+pub struct Line<'a> {
+    pub no: usize,
+    pub text: &'a str,
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Text,
+    Data, // `.rodata` and `.bss` too, as they're laid out one after another
+    Ignored,
+}
+
+/// Lays out `asm`'s text, 4 bytes an instruction, from `text_base`, and its data from
+/// `data_base`, then has `instruction` decode each line of the text
+pub fn assemble<I, E: From<SyntaxError>>(
+    asm: &str,
+    text_base: u64,
+    data_base: u64,
+    instruction: impl Fn(&Line, &HashMap<String, u64>) -> Result<I, E>,
+) -> Result<Program<I>, E> {
+    // Labels first, so instructions can refer to those further down
+    let mut labels = HashMap::new();
+    let mut data = vec![];
+    let mut text_lines = vec![];
+    let mut section = Section::Text;
+
+    for (i, raw) in asm.lines().enumerate() {
+        let mut line = strip_comment(raw).trim();
+        let no = i + 1;
+
+        while let Some((label, rest)) = split_label(line) {
+            let addr = match section {
+                Section::Text => text_base + 4 * text_lines.len() as u64,
+                _ => data_base + data.len() as u64,
+            };
+            labels.insert(label.to_string(), addr);
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = split_mnemonic(line);
+        match mnemonic {
+            ".text" => section = Section::Text,
+            ".data" | ".bss" => section = Section::Data,
+            ".section" => {
+                section = match operands.split(',').next().unwrap_or("").trim() {
+                    ".text" => Section::Text,
+                    ".data" | ".rodata" | ".bss" => Section::Data,
+                    _ => Section::Ignored,
+                }
+            }
+            ".globl" | ".global" => {}
+            _ if section == Section::Ignored => {}
+            _ if mnemonic.starts_with('.') && section == Section::Text => {
+                return Err(syntax(no, "data directive in .text").into())
+            }
+            _ if mnemonic.starts_with('.') => directive(no, mnemonic, operands, &mut data)?,
+            _ if section == Section::Text => text_lines.push(Line { no, text: line }),
+            _ => return Err(syntax(no, "instruction outside of .text").into()),
+        }
+    }
+
+    let text = text_lines
+        .iter()
+        .map(|line| instruction(line, &labels))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Program { text, data, labels })
+}
+
+fn syntax(no: usize, msg: &str) -> SyntaxError {
+    SyntaxError(no, msg.to_string())
+}
+
+pub fn split_mnemonic(line: &str) -> (&str, &str) {
+    line.split_once(char::is_whitespace)
+        .map_or((line, ""), |(m, ops)| (m, ops.trim()))
+}
+
+// Comments start w/ a `#` outside of a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_str => escaped = true,
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let (label, rest) = line.split_once(':')?;
+    let is_label = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$');
+    is_label.then_some((label, rest))
+}
+
+fn directive(no: usize, name: &str, operands: &str, data: &mut Vec<u8>) -> Result<(), SyntaxError> {
+    let numbers = || {
+        operands
+            .split(',')
+            .map(|n| parse_int(n.trim()).ok_or_else(|| syntax(no, "expected a number")))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    match name {
+        ".balign" | ".align" | ".p2align" => {
+            let n = numbers()?[0] as usize;
+            let align = if name == ".balign" { n } else { 1 << n };
+            data.resize(data.len().next_multiple_of(align), 0);
+        }
+        ".zero" | ".space" => data.resize(data.len() + numbers()?[0] as usize, 0),
+        ".byte" => data.extend(numbers()?.into_iter().map(|b| b as u8)),
+        ".word" => {
+            for w in numbers()? {
+                data.extend((w as u32).to_le_bytes());
+            }
+        }
+        ".dword" | ".quad" => {
+            for d in numbers()? {
+                data.extend(d.to_le_bytes());
+            }
+        }
+        ".ascii" | ".asciiz" | ".asciz" | ".string" => {
+            data.extend(parse_string(operands).ok_or_else(|| syntax(no, "bad string"))?);
+            if name != ".ascii" {
+                data.push(0);
+            }
+        }
+        _ => return Err(syntax(no, &format!("unknown directive `{name}`"))),
+    }
+
+    Ok(())
+}
+
+// W/ C's escapes, octal ones included
+fn parse_string(s: &str) -> Option<Vec<u8>> {
+    let s = s.strip_prefix('"')?.strip_suffix('"')?.as_bytes();
+    let mut bytes = vec![];
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] != b'\\' {
+            bytes.push(s[i]);
+            i += 1;
+            continue;
+        }
+
+        let digits = s[i + 1..]
+            .iter()
+            .take(3)
+            .take_while(|b| (b'0'..=b'7').contains(b))
+            .count();
+        if digits > 0 {
+            let octal = std::str::from_utf8(&s[i + 1..i + 1 + digits]).ok()?;
+            bytes.push(u8::from_str_radix(octal, 8).ok()?);
+            i += 1 + digits;
+            continue;
+        }
+
+        bytes.push(match *s.get(i + 1)? {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b => b,
+        });
+        i += 2;
+    }
+    Some(bytes)
+}
+
+/// Decimal or `0x` hex, w/ any 64 bits, so that unsigned constants read as they're written
+pub fn parse_int(s: &str) -> Option<i64> {
+    let (neg, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    let n = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()? as i64,
+        None => digits.parse::<u64>().ok()? as i64,
+    };
+    Some(if neg { n.wrapping_neg() } else { n })
+}
@@ -0,0 +1,817 @@
+//! C11 generation: a whole, self-contained translation unit, for running Nuktah wherever there's a
+//! C compiler. It's made from the checked AST rather than the IR, so it reads much like the
+//! source: `duhrao`s become `for`s, `agar`s `if`s, and locals keep their names where C lets them.
+//!
+//! What C leaves undefined or unspecified is pinned down to what the interpreter does. `ginti`
+//! arithmetic goes through the runtime's helpers, which wrap, and report what the interpreter
+//! would, and operands are evaluated left to right: any that could observe, or be observed by,
+//! one to its right is evaluated into a temporary, `nkt_t<n>`, w/ the comma operator, first.
+//! Functions are `fn_<name>`, globals `g_<name>` (initialised in order by `nkt_init`), and locals
+//! that would clash w/ C's keywords, the runtime's names, or a shadowed local are escaped.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::codegen::core::mangle;
+use crate::lexer::Token;
+use crate::parser::ast::core::*;
+use crate::semantics::{
+    core::ENTRY_POINT, intrinsics::find_intrinsic, spaghetti::SymType, utils::token_to_symtype,
+};
+
+/// Included verbatim at the top of each translation, which needs nothing else but libc
+pub const RUNTIME_H: &str = include_str!("runtime.h");
+
+// C's precedence levels, loosest first
+const ASSIGN: u8 = 1;
+const OR: u8 = 2;
+const AND: u8 = 3;
+const BIT_OR: u8 = 4;
+const BIT_AND: u8 = 5;
+const EQUALITY: u8 = 6;
+const RELATIONAL: u8 = 7;
+const ADDITIVE: u8 = 8;
+const MULTIPLICATIVE: u8 = 9;
+const UNARY: u8 = 10;
+const PRIMARY: u8 = 11;
+
+// Names C (or the headers the runtime includes) already gives a meaning to
+const RESERVED: &str = "
+    auto bool break case char const continue default do double else enum errno extern false float
+    for goto if inline int long math_errhandling register restrict return short signed sizeof
+    static stderr stdin stdout struct switch true typedef union unsigned va_list void volatile while
+";
+
+/// Translates a checked program into C
+pub fn generate(ast: &TranslationUnit) -> String {
+    let mut gen = Gen::new(ast);
+    let mut c = RUNTIME_H.to_string();
+
+    let globals: Vec<&VarDecl> = ast
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Var(v) => Some(v),
+            Decl::Fn(_) => None,
+        })
+        .collect();
+    let fns: Vec<&FnDecl> = ast
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Fn(f) => Some(f),
+            Decl::Var(_) => None,
+        })
+        .collect();
+
+    if !globals.is_empty() {
+        c.push('\n');
+    }
+    for v in &globals {
+        let sym_type = token_to_symtype(&v.type_tok, true);
+        let zero = match sym_type {
+            SymType::String => " = \"\"",
+            _ => "",
+        };
+        let decl = declaration(sym_type, &global_name(&v.ident));
+        writeln!(c, "static {decl}{zero};").unwrap();
+    }
+
+    if !fns.is_empty() {
+        c.push('\n');
+    }
+    for f in &fns {
+        writeln!(c, "{};", gen.signature(f)).unwrap();
+    }
+    for f in &fns {
+        c.push('\n');
+        c.push_str(&gen.function(f));
+    }
+
+    if !globals.is_empty() {
+        c.push('\n');
+        c.push_str(&gen.init(&globals));
+    }
+
+    if let Some(entry) = fns.iter().find(|f| f.ident == ENTRY_POINT) {
+        c.push('\n');
+        c.push_str(&main(entry, !globals.is_empty()));
+    }
+
+    c
+}
+
+// `main`, which initialises the globals, and exits w/ what the entry point returns
+fn main(entry: &FnDecl, has_globals: bool) -> String {
+    let mut c = String::new();
+    let call = match entry.params.is_empty() {
+        true => format!("{}()", fn_name(ENTRY_POINT)),
+        false => format!("{}(argc)", fn_name(ENTRY_POINT)),
+    };
+
+    match entry.params.is_empty() {
+        true => c.push_str("int main(void) {\n"),
+        false => c.push_str("int main(int argc, char **argv) {\n\t(void)argv;\n"),
+    }
+    if has_globals {
+        c.push_str("\tnkt_init();\n");
+    }
+    match token_to_symtype(&entry.type_tok, false) {
+        SymType::Int => writeln!(c, "\treturn (int){call};").unwrap(),
+        _ => writeln!(c, "\t{call};\n\treturn 0;").unwrap(),
+    }
+    c.push_str("}\n");
+    c
+}
+
+// An expression's C, and what's needed to place it among others
+#[derive(Clone)]
+struct CExpr {
+    code: String,
+    prec: u8,
+    sym_type: SymType,
+    // Nothing else can change its value, nor it anything else's
+    literal: bool,
+    calls: bool,
+    reads_globals: bool,
+    // The variables it assigns to, by their C names
+    assigns: BTreeSet<String>,
+    // Whether it's worth its own statement, i.e a call or an assignment
+    discardable: bool,
+}
+
+impl CExpr {
+    fn new(code: String, prec: u8, sym_type: SymType) -> CExpr {
+        CExpr {
+            code,
+            prec,
+            sym_type,
+            literal: false,
+            calls: false,
+            reads_globals: false,
+            assigns: BTreeSet::new(),
+            discardable: false,
+        }
+    }
+
+    fn literal(code: String, sym_type: SymType) -> CExpr {
+        CExpr {
+            literal: true,
+            ..CExpr::new(code, PRIMARY, sym_type)
+        }
+    }
+
+    // Its code, parenthesised unless it binds at least as tightly as `prec`
+    fn at(&self, prec: u8) -> String {
+        match self.prec < prec {
+            true => format!("({})", self.code),
+            false => self.code.clone(),
+        }
+    }
+
+    // Whether evaluating `self` before `later` could differ from evaluating it after
+    fn conflicts_with(&self, later: &CExpr) -> bool {
+        let observes = |a: &CExpr, b: &CExpr| {
+            (!a.assigns.is_empty() && !b.literal) || (a.calls && (b.calls || b.reads_globals))
+        };
+        observes(self, later) || observes(later, self)
+    }
+
+    // Takes on the effects of `operands`
+    fn merged(mut self, operands: &[CExpr]) -> CExpr {
+        self.literal = operands.iter().all(|o| o.literal);
+        for o in operands {
+            self.calls |= o.calls;
+            self.reads_globals |= o.reads_globals;
+            self.assigns.extend(o.assigns.iter().cloned());
+        }
+        self
+    }
+}
+
+struct Gen<'a> {
+    // Return types, of the program's functions
+    fns: HashMap<&'a str, SymType>,
+    globals: HashMap<&'a str, SymType>,
+    // Locals in scope, innermost last, by their Nuktah names, w/ their C ones
+    scopes: Vec<Vec<(&'a str, String, SymType)>>,
+    temps: Vec<SymType>,
+    ret_type: SymType,
+    body: String,
+}
+
+impl<'a> Gen<'a> {
+    fn new(ast: &'a TranslationUnit) -> Gen<'a> {
+        let mut fns = HashMap::new();
+        let mut globals = HashMap::new();
+
+        for decl in ast {
+            match decl {
+                Decl::Var(v) => {
+                    globals.insert(v.ident.as_str(), token_to_symtype(&v.type_tok, true));
+                }
+                Decl::Fn(f) => {
+                    fns.insert(f.ident.as_str(), token_to_symtype(&f.type_tok, false));
+                }
+            }
+        }
+
+        Gen {
+            fns,
+            globals,
+            scopes: vec![],
+            temps: vec![],
+            ret_type: SymType::Void,
+            body: String::new(),
+        }
+    }
+
+    // Its prototype, declaring its parameters in scope as a side effect
+    fn signature(&mut self, f: &'a FnDecl) -> String {
+        let ret_type = token_to_symtype(&f.type_tok, false);
+        self.scopes = vec![vec![]];
+
+        let mut params = vec![];
+        for p in &f.params {
+            let sym_type = token_to_symtype(&p.type_tok, true);
+            let name = self.declare(&p.ident, sym_type);
+            params.push(declaration(sym_type, &name));
+        }
+        let params = match params.is_empty() {
+            true => "void".to_string(),
+            false => params.join(", "),
+        };
+
+        let ret_type = declaration(ret_type, "");
+        format!("static {ret_type}{}({params})", fn_name(&f.ident))
+    }
+
+    fn function(&mut self, f: &'a FnDecl) -> String {
+        let signature = self.signature(f);
+        self.ret_type = token_to_symtype(&f.type_tok, false);
+        self.temps.clear();
+        self.body.clear();
+
+        self.block(&f.block, 1);
+        if self.ret_type != SymType::Void && !surely_returns(&f.block) {
+            let name = c_string(&f.ident);
+            writeln!(self.body, "\tnkt_missing_return({name});").unwrap();
+        }
+
+        format!("{signature} {{\n{}{}}}\n", self.temp_decls(), self.body)
+    }
+
+    // `nkt_init`, which initialises the globals in the order they're declared in
+    fn init(&mut self, globals: &[&'a VarDecl]) -> String {
+        self.scopes = vec![];
+        self.temps.clear();
+        self.body.clear();
+
+        for v in globals {
+            let value = self.value(v);
+            writeln!(self.body, "\t{} = {value};", global_name(&v.ident)).unwrap();
+        }
+
+        format!(
+            "static void nkt_init(void) {{\n{}{}}}\n",
+            self.temp_decls(),
+            self.body
+        )
+    }
+
+    fn temp_decls(&self) -> String {
+        let mut decls = String::new();
+        for (i, &sym_type) in self.temps.iter().enumerate() {
+            writeln!(decls, "\t{};", declaration(sym_type, &format!("nkt_t{i}"))).unwrap();
+        }
+        if !decls.is_empty() {
+            decls.push('\n');
+        }
+        decls
+    }
+
+    fn temp(&mut self, sym_type: SymType) -> String {
+        self.temps.push(sym_type);
+        format!("nkt_t{}", self.temps.len() - 1)
+    }
+
+    // Brings a local into scope, named apart from any it shadows
+    fn declare(&mut self, ident: &'a str, sym_type: SymType) -> String {
+        let shadowed = self.scopes.iter().flatten();
+        let depth = shadowed.filter(|(name, ..)| *name == ident).count();
+        let name = local_name(ident, depth);
+
+        let scope = self.scopes.last_mut().expect("a scope to declare in");
+        scope.push((ident, name.clone(), sym_type));
+        name
+    }
+
+    fn line(&mut self, depth: usize, code: &str) {
+        writeln!(self.body, "{}{code}", "\t".repeat(depth)).unwrap();
+    }
+
+    fn block(&mut self, block: &'a Block, depth: usize) {
+        self.scopes.push(vec![]);
+        for stmt in block {
+            self.stmt(stmt, depth);
+        }
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt, depth: usize) {
+        match stmt {
+            Stmt::VarDecl(v) => {
+                let decl = self.var_decl(v);
+                self.line(depth, &format!("{decl};"));
+            }
+
+            Stmt::Expr(ExprStmt { expr: Some(e), .. }) => {
+                let e = self.assign_expr(e);
+                match e.discardable {
+                    true => self.line(depth, &format!("{};", e.code)),
+                    false => self.line(depth, &format!("(void){};", e.at(UNARY))),
+                }
+            }
+            Stmt::Expr(ExprStmt { expr: None, .. }) => {}
+
+            // A `khali` function's `wapsi` may still evaluate something
+            Stmt::Ret(ExprStmt { expr: Some(e), .. }) => {
+                let e = self.assign_expr(e);
+                match self.ret_type {
+                    SymType::Void => {
+                        self.line(depth, &format!("{};", e.code));
+                        self.line(depth, "return;");
+                    }
+                    _ => self.line(depth, &format!("return {};", e.code)),
+                }
+            }
+            Stmt::Ret(ExprStmt { expr: None, .. }) => self.line(depth, "return;"),
+
+            Stmt::Break(_) => self.line(depth, "break;"),
+
+            Stmt::If(s) => {
+                let mut s = s;
+                let cond = self.cond(&s.cond);
+                self.line(depth, &format!("if ({cond}) {{"));
+
+                // `warna { agar .. }` chains
+                loop {
+                    self.block(&s.if_block, depth + 1);
+                    match s.else_block.as_slice() {
+                        [] => break,
+                        [Stmt::If(next)] => {
+                            s = next;
+                            let cond = self.cond(&s.cond);
+                            self.line(depth, &format!("}} else if ({cond}) {{"));
+                        }
+                        _ => {
+                            self.line(depth, "} else {");
+                            self.block(&s.else_block, depth + 1);
+                            break;
+                        }
+                    }
+                }
+                self.line(depth, "}");
+            }
+
+            Stmt::For(s) => {
+                self.scopes.push(vec![]);
+
+                let init = s.init.as_ref().map(|v| self.var_decl(v));
+                let cond = self.cond(&s.cond.expr);
+                let updt = match &s.updt {
+                    Some(e) => format!(" {}", self.assign_expr(e).code),
+                    None => String::new(),
+                };
+                let init = init.unwrap_or_default();
+                let cond = match cond.is_empty() {
+                    true => cond,
+                    false => format!(" {cond}"),
+                };
+
+                self.line(depth, &format!("for ({init};{cond};{updt}) {{"));
+                self.block(&s.block, depth + 1);
+                self.line(depth, "}");
+
+                self.scopes.pop();
+            }
+        }
+    }
+
+    fn cond(&mut self, e: &'a Expr) -> String {
+        match e {
+            Some(e) => self.assign_expr(e).code,
+            None => String::new(),
+        }
+    }
+
+    // Its declaration, sans the `;`, evaluating its value before it's in scope, as Nuktah does
+    fn var_decl(&mut self, v: &'a VarDecl) -> String {
+        let sym_type = token_to_symtype(&v.type_tok, true);
+        let value = self.value(v);
+        let name = self.declare(&v.ident, sym_type);
+        format!("{} = {value}", declaration(sym_type, &name))
+    }
+
+    fn value(&mut self, v: &'a VarDecl) -> String {
+        match &v.expr {
+            Some(e) => self.assign_expr(e).at(ASSIGN),
+            None => match token_to_symtype(&v.type_tok, true) {
+                SymType::Int => "0".to_string(),
+                SymType::Float => "0.0".to_string(),
+                SymType::String => "\"\"".to_string(),
+                _ => "false".to_string(),
+            },
+        }
+    }
+
+    // Joins `operands` into one expression w/ `join`, evaluating any that conflict w/ one further
+    // right into a temporary first
+    fn sequence(
+        &mut self,
+        operands: Vec<CExpr>,
+        sym_type: SymType,
+        join: impl FnOnce(&[CExpr]) -> (String, u8),
+    ) -> CExpr {
+        let mut placed = operands.clone();
+        let mut first = vec![];
+
+        for (i, operand) in operands.iter().enumerate() {
+            if operands[i + 1..]
+                .iter()
+                .any(|later| operand.conflicts_with(later))
+            {
+                let temp = self.temp(operand.sym_type);
+                first.push(format!("{temp} = {}", operand.at(ASSIGN)));
+                placed[i] = CExpr::new(temp, PRIMARY, operand.sym_type);
+            }
+        }
+
+        let (code, prec) = join(&placed);
+        let joined = CExpr::new(code, prec, sym_type);
+        let e = match first.is_empty() {
+            true => joined,
+            false => {
+                let code = format!("({}, {})", first.join(", "), joined.at(ASSIGN));
+                CExpr::new(code, PRIMARY, sym_type)
+            }
+        };
+        e.merged(&operands)
+    }
+
+    fn lookup(&self, ident: &str) -> CExpr {
+        let locals = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev());
+        if let Some((_, name, sym_type)) = locals.clone().find(|(n, ..)| *n == ident) {
+            return CExpr::new(name.clone(), PRIMARY, *sym_type);
+        }
+
+        let sym_type = *self
+            .globals
+            .get(ident)
+            .expect("identifiers to have been resolved");
+        CExpr {
+            reads_globals: true,
+            ..CExpr::new(global_name(ident), PRIMARY, sym_type)
+        }
+    }
+
+    fn assign_expr(&mut self, e: &'a AssignExpr) -> CExpr {
+        let (target, value) = match e {
+            AssignExpr::Bool(b) => return self.bool_expr(b),
+            AssignExpr::Assign(target, value) => (target, value),
+        };
+
+        let ident = target.as_ident().expect("assignments to have been checked");
+        let target = self.lookup(ident);
+        let value = self.assign_expr(value);
+
+        // C leaves `a = (a = ..)` undefined
+        let mut e = match value.assigns.contains(&target.code) {
+            true => {
+                let temp = self.temp(value.sym_type);
+                let code = format!("({temp} = {}, {} = {temp})", value.at(ASSIGN), target.code);
+                CExpr::new(code, PRIMARY, target.sym_type)
+            }
+            false => {
+                let code = format!("{} = {}", target.code, value.at(ASSIGN));
+                CExpr::new(code, ASSIGN, target.sym_type)
+            }
+        }
+        .merged(&[value]);
+
+        e.assigns.insert(target.code);
+        e.discardable = true;
+        e
+    }
+
+    fn bool_expr(&mut self, e: &'a BoolExpr) -> CExpr {
+        let (lhs, op, rhs) = match e {
+            BoolExpr::BitOr(b) => return self.bit_or_expr(b),
+            BoolExpr::Bool(lhs, op, rhs) => (lhs, op, rhs),
+        };
+        let (lhs, rhs) = (self.bool_expr(lhs), self.bit_or_expr(rhs));
+
+        // C short-circuits too, so nothing needs sequencing; mixes are parenthesised for clarity
+        let (prec, op) = match op {
+            Token::BooleanAnd => (AND, "&&"),
+            _ => (OR, "||"),
+        };
+        let lhs_code = match lhs.prec == prec {
+            true => lhs.code.clone(),
+            false => lhs.at(BIT_OR),
+        };
+        let code = format!("{lhs_code} {op} {}", rhs.at(BIT_OR));
+        CExpr::new(code, prec, SymType::Bool).merged(&[lhs, rhs])
+    }
+
+    fn bit_or_expr(&mut self, e: &'a BitOrExpr) -> CExpr {
+        match e {
+            BitOrExpr::BitAnd(b) => self.bit_and_expr(b),
+            BitOrExpr::BitOr(lhs, rhs) => {
+                let (lhs, rhs) = (self.bit_or_expr(lhs), self.bit_and_expr(rhs));
+                self.bitwise(lhs, BIT_OR, "|", rhs)
+            }
+        }
+    }
+
+    fn bit_and_expr(&mut self, e: &'a BitAndExpr) -> CExpr {
+        match e {
+            BitAndExpr::Comp(c) => self.comp_expr(c),
+            BitAndExpr::BitAnd(lhs, rhs) => {
+                let (lhs, rhs) = (self.bit_and_expr(lhs), self.comp_expr(rhs));
+                self.bitwise(lhs, BIT_AND, "&", rhs)
+            }
+        }
+    }
+
+    fn bitwise(&mut self, lhs: CExpr, prec: u8, op: &str, rhs: CExpr) -> CExpr {
+        self.sequence(vec![lhs, rhs], SymType::Int, |ops| {
+            let lhs = match ops[0].prec == prec {
+                true => ops[0].code.clone(),
+                false => ops[0].at(UNARY),
+            };
+            (format!("{lhs} {op} {}", ops[1].at(UNARY)), prec)
+        })
+    }
+
+    fn comp_expr(&mut self, e: &'a CompExpr) -> CExpr {
+        let (lhs, op, rhs) = match e {
+            CompExpr::Shift(s) => return self.shift_expr(s),
+            CompExpr::Comp(lhs, op, rhs) => (lhs, op, rhs),
+        };
+        let (lhs, rhs) = (self.comp_expr(lhs), self.shift_expr(rhs));
+
+        let (prec, op) = match op {
+            Token::LessThan => (RELATIONAL, "<"),
+            Token::GreaterThan => (RELATIONAL, ">"),
+            _ => (EQUALITY, "=="),
+        };
+        let strings = lhs.sym_type == SymType::String;
+
+        // Comparisons of comparisons are parenthesised, for clarity
+        self.sequence(vec![lhs, rhs], SymType::Bool, |ops| match strings {
+            true => {
+                let (lhs, rhs) = (ops[0].at(ASSIGN), ops[1].at(ASSIGN));
+                (format!("nkt_str_cmp({lhs}, {rhs}) {op} 0"), prec)
+            }
+            false => {
+                let (lhs, rhs) = (ops[0].at(ADDITIVE), ops[1].at(ADDITIVE));
+                (format!("{lhs} {op} {rhs}"), prec)
+            }
+        })
+    }
+
+    fn shift_expr(&mut self, e: &'a ShiftExpr) -> CExpr {
+        match e {
+            ShiftExpr::Add(a) => self.add_expr(a),
+            ShiftExpr::Shift(lhs, op, rhs) => {
+                let (lhs, rhs) = (self.shift_expr(lhs), self.add_expr(rhs));
+                self.arith(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn add_expr(&mut self, e: &'a AddExpr) -> CExpr {
+        match e {
+            AddExpr::Mul(m) => self.mul_expr(m),
+            AddExpr::Add(lhs, op, rhs) => {
+                let (lhs, rhs) = (self.add_expr(lhs), self.mul_expr(rhs));
+                self.arith(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn mul_expr(&mut self, e: &'a MulExpr) -> CExpr {
+        match e {
+            MulExpr::Exp(x) => self.exp_expr(x),
+            MulExpr::Mul(lhs, op, rhs) => {
+                let (lhs, rhs) = (self.mul_expr(lhs), self.exp_expr(rhs));
+                self.arith(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn exp_expr(&mut self, e: &'a ExpExpr) -> CExpr {
+        match e {
+            ExpExpr::Unary(u) => self.unary_expr(u),
+            ExpExpr::Exp(lhs, rhs) => {
+                let (lhs, rhs) = (self.unary_expr(lhs), self.exp_expr(rhs));
+                self.arith(lhs, &Token::ExpOp, rhs)
+            }
+        }
+    }
+
+    // `ginti`s w/ the runtime's helpers, and `asharia`s as C has it, bar `%` and `^`
+    fn arith(&mut self, lhs: CExpr, op: &Token, rhs: CExpr) -> CExpr {
+        let sym_type = lhs.sym_type;
+        let helper = match (sym_type, op) {
+            (SymType::Int, Token::AddOp) => Some("nkt_add"),
+            (SymType::Int, Token::SubOp) => Some("nkt_sub"),
+            (SymType::Int, Token::MulOp) => Some("nkt_mul"),
+            (SymType::Int, Token::DivOp) => Some("nkt_div"),
+            (SymType::Int, Token::ModOp) => Some("nkt_mod"),
+            (SymType::Int, Token::ExpOp) => Some("nkt_pow"),
+            (SymType::Int, Token::ShiftLeft) => Some("nkt_shl"),
+            (SymType::Int, Token::ShiftRight) => Some("nkt_shr"),
+            (_, Token::ModOp) => Some("nkt_fmod"),
+            (_, Token::ExpOp) => Some("nkt_fpow"),
+            _ => None,
+        };
+        let (prec, op) = match op {
+            Token::AddOp => (ADDITIVE, "+"),
+            Token::SubOp => (ADDITIVE, "-"),
+            Token::MulOp => (MULTIPLICATIVE, "*"),
+            _ => (MULTIPLICATIVE, "/"),
+        };
+
+        self.sequence(vec![lhs, rhs], sym_type, |ops| match helper {
+            Some(helper) => {
+                let (lhs, rhs) = (ops[0].at(ASSIGN), ops[1].at(ASSIGN));
+                (format!("{helper}({lhs}, {rhs})"), PRIMARY)
+            }
+            None => {
+                let (lhs, rhs) = (ops[0].at(prec), ops[1].at(prec + 1));
+                (format!("{lhs} {op} {rhs}"), prec)
+            }
+        })
+    }
+
+    fn unary_expr(&mut self, e: &'a UnaryExpr) -> CExpr {
+        let (op, operand) = match e {
+            UnaryExpr::Primary(p) => return self.primary_expr(p),
+            UnaryExpr::Unary(op, operand) => (op, operand),
+        };
+        let operand = self.unary_expr(operand);
+
+        // Never `--x`, nor `!!b`
+        let e = match (op, operand.sym_type) {
+            (Token::SubOp, SymType::Int) => {
+                let code = format!("nkt_neg({})", operand.at(ASSIGN));
+                CExpr::new(code, PRIMARY, SymType::Int)
+            }
+            (Token::SubOp, _) => {
+                let code = format!("-{}", operand.at(PRIMARY));
+                CExpr::new(code, UNARY, SymType::Float)
+            }
+            _ => CExpr::new(format!("!{}", operand.at(PRIMARY)), UNARY, SymType::Bool),
+        };
+        e.merged(&[operand])
+    }
+
+    fn primary_expr(&mut self, e: &'a PrimaryExpr) -> CExpr {
+        match e {
+            PrimaryExpr::IntLit(i) => CExpr::literal(i.to_string(), SymType::Int),
+            PrimaryExpr::FloatLit(f) => CExpr::literal(float_lit(*f), SymType::Float),
+            PrimaryExpr::StringLit(s) => CExpr::literal(c_string(s), SymType::String),
+            PrimaryExpr::BoolLit(b) => CExpr::literal(b.to_string(), SymType::Bool),
+            PrimaryExpr::Ident(ident) => self.lookup(ident),
+            PrimaryExpr::Paren(inner) => {
+                let inner = inner.as_ref().as_ref();
+                self.assign_expr(inner.expect("parentheses to hold an expression"))
+            }
+            PrimaryExpr::Call(call) => self.call(call),
+        }
+    }
+
+    fn call(&mut self, call: &'a FnCall) -> CExpr {
+        let (name, ret_type) = match find_intrinsic(&call.ident) {
+            Some(intrinsic) => (format!("nkt_{}", intrinsic.name), intrinsic.ret_type),
+            None => (fn_name(&call.ident), self.fns[call.ident.as_str()]),
+        };
+
+        let mut args = vec![];
+        for arg in &call.args {
+            let arg = arg.as_ref().expect("arguments to be expressions");
+            args.push(self.assign_expr(arg));
+        }
+
+        let mut e = self.sequence(args, ret_type, |args| {
+            let args: Vec<String> = args.iter().map(|arg| arg.at(ASSIGN)).collect();
+            (format!("{name}({})", args.join(", ")), PRIMARY)
+        });
+        e.calls = true;
+        e.discardable = true;
+        e
+    }
+}
+
+// Whether a block can't run off its end
+fn surely_returns(block: &Block) -> bool {
+    match block.last() {
+        Some(Stmt::Ret(_)) => true,
+        Some(Stmt::If(s)) => surely_returns(&s.if_block) && surely_returns(&s.else_block),
+        _ => false,
+    }
+}
+
+// `name` declared as a `sym_type`; w/ no name, just the type, ready to be followed by one
+fn declaration(sym_type: SymType, name: &str) -> String {
+    let c_type = match sym_type {
+        SymType::Int => "int64_t",
+        SymType::Float => "double",
+        SymType::String => "const char *",
+        SymType::Bool => "bool",
+        SymType::Void => "void",
+    };
+
+    match (sym_type, name.is_empty()) {
+        (SymType::String, _) => format!("{c_type}{name}"),
+        (_, true) => format!("{c_type} "),
+        (_, false) => format!("{c_type} {name}"),
+    }
+}
+
+// Identifiers w/ anything but ASCII are mangled, under a prefix of their own
+fn fn_name(name: &str) -> String {
+    match name.is_ascii() {
+        true => format!("fn_{name}"),
+        false => mangle("fnu_", name),
+    }
+}
+
+fn global_name(name: &str) -> String {
+    match name.is_ascii() {
+        true => format!("g_{name}"),
+        false => mangle("gu_", name),
+    }
+}
+
+// A local's name as is, if C has no other use for it, and it shadows nothing; `v<depth>_<name>`
+// otherwise, w/ those of a depth of 0 sans the depth
+fn local_name(name: &str, depth: usize) -> String {
+    match (name.is_ascii(), depth) {
+        (true, 0) if !needs_escaping(name) => name.to_string(),
+        (true, 0) => format!("v_{name}"),
+        (true, depth) => format!("v{depth}_{name}"),
+        (false, 0) => mangle("u_", name),
+        (false, depth) => mangle(&format!("u{depth}_"), name),
+    }
+}
+
+fn needs_escaping(name: &str) -> bool {
+    let prefixed = |prefixes: &[&str]| prefixes.iter().any(|p| name.starts_with(p));
+
+    // Those escaped locals and mangled names are made of
+    let (head, tail) = name.split_at(1);
+    let escaped = (head == "v" || head == "u")
+        && tail
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .starts_with('_');
+
+    RESERVED.split_whitespace().any(|word| word == name)
+        || escaped
+        || prefixed(&["_", "fn_", "fnu_", "g_", "gu_", "nkt_"])
+        // Macros are (almost) all upper case, and typedefs end in `_t`
+        || !name.chars().any(|c| c.is_ascii_lowercase())
+        || prefixed(&["PRI", "SCN", "INT", "UINT"])
+        || name.ends_with("_t")
+}
+
+fn float_lit(f: f64) -> String {
+    match f.is_finite() {
+        true => format!("{f:?}"),
+        false => "HUGE_VAL".to_string(),
+    }
+}
+
+// A C string literal: printable ASCII as is, anything else in octal, and `?`s escaped, lest they
+// make trigraphs
+fn c_string(s: &str) -> String {
+    let mut lit = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' | b'\\' | b'?' => {
+                lit.push('\\');
+                lit.push(b as char);
+            }
+            b'\n' => lit.push_str("\\n"),
+            b'\t' => lit.push_str("\\t"),
+            0x20..=0x7e => lit.push(b as char),
+            _ => write!(lit, "\\{b:03o}").unwrap(),
+        }
+    }
+    lit.push('"');
+    lit
+}
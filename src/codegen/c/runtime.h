@@ -0,0 +1,128 @@
+/*
+ * Nuktah's runtime for C, at the top of every translation `nktc build --target=c` produces.
+ * Integers wrap on overflow, and errors are reported as the interpreter names them, sans the
+ * span, exiting w/ 1, like it does. Plain C11, w/ nothing past the standard library.
+ */
+
+#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static _Noreturn void nkt_fail(const char *fmt, ...) {
+	va_list ap;
+
+	fflush(stdout);
+	fputs("runtime error: ", stderr);
+	va_start(ap, fmt);
+	vfprintf(stderr, fmt, ap);
+	va_end(ap);
+	fputc('\n', stderr);
+	exit(1);
+}
+
+static inline void nkt_likho(const char *s) {
+	puts(s);
+}
+
+static inline void nkt_likho_ginti(int64_t i) {
+	printf("%" PRId64 "\n", i);
+}
+
+/* A line from stdin, sans the newline; empty at EOF */
+static inline const char *nkt_parho(void) {
+	size_t len = 0, cap = 64;
+	char *line = malloc(cap);
+	int c;
+
+	if (!line)
+		nkt_fail("out of memory");
+	while ((c = getchar()) != EOF && c != '\n') {
+		if (len + 1 == cap && !(line = realloc(line, cap *= 2)))
+			nkt_fail("out of memory");
+		line[len++] = (char)c;
+	}
+
+	while (len > 0 && line[len - 1] == '\r')
+		len--;
+	line[len] = '\0';
+	return line;
+}
+
+static inline int nkt_str_cmp(const char *a, const char *b) {
+	return strcmp(a, b);
+}
+
+static inline int64_t nkt_add(int64_t a, int64_t b) {
+	return (int64_t)((uint64_t)a + (uint64_t)b);
+}
+
+static inline int64_t nkt_sub(int64_t a, int64_t b) {
+	return (int64_t)((uint64_t)a - (uint64_t)b);
+}
+
+static inline int64_t nkt_mul(int64_t a, int64_t b) {
+	return (int64_t)((uint64_t)a * (uint64_t)b);
+}
+
+static inline int64_t nkt_neg(int64_t a) {
+	return (int64_t)(0 - (uint64_t)a);
+}
+
+/* `INT64_MIN / -1` wraps */
+static inline int64_t nkt_div(int64_t a, int64_t b) {
+	if (b == 0)
+		nkt_fail("DivisionByZero");
+	return b == -1 ? nkt_neg(a) : a / b;
+}
+
+static inline int64_t nkt_mod(int64_t a, int64_t b) {
+	if (b == 0)
+		nkt_fail("DivisionByZero");
+	return b == -1 ? 0 : a % b;
+}
+
+/* `base ^ exp`, wrapping on overflow */
+static inline int64_t nkt_pow(int64_t base, int64_t exp) {
+	uint64_t acc = 1, b = (uint64_t)base;
+
+	if (exp < 0)
+		nkt_fail("NegativeExponent(%" PRId64 ")", exp);
+
+	while (exp > 0) {
+		if (exp & 1)
+			acc *= b;
+		b *= b;
+		exp >>= 1;
+	}
+	return (int64_t)acc;
+}
+
+static inline int64_t nkt_shl(int64_t a, int64_t amount) {
+	if (amount < 0 || amount > 63)
+		nkt_fail("ShiftOutOfRange(%" PRId64 ")", amount);
+	return (int64_t)((uint64_t)a << amount);
+}
+
+/* Arithmetic, w/o relying on how the compiler shifts negative numbers */
+static inline int64_t nkt_shr(int64_t a, int64_t amount) {
+	if (amount < 0 || amount > 63)
+		nkt_fail("ShiftOutOfRange(%" PRId64 ")", amount);
+	return a < 0 ? ~(~a >> amount) : a >> amount;
+}
+
+static inline double nkt_fmod(double a, double b) {
+	return fmod(a, b);
+}
+
+static inline double nkt_fpow(double a, double b) {
+	return pow(a, b);
+}
+
+static inline _Noreturn void nkt_missing_return(const char *func) {
+	nkt_fail("MissingReturn(\"%s\")", func);
+}
@@ -0,0 +1,147 @@
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::ir::core::{FnId, Module};
+
+/// What the support code for native executables is written in: the intrinsics, the operators
+/// too fiddly to inline, and `main`, which calls the generated `nkt_entry`
+pub const RUNTIME_C: &str = include_str!("runtime.c");
+
+/// A machine `nktc build` can compile for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    X86_64Linux,
+    Riscv64Linux,
+    Mips32,
+    C,
+    LlvmIr,
+}
+
+impl FromStr for Target {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x86_64-linux" => Ok(Target::X86_64Linux),
+            "riscv64-linux" => Ok(Target::Riscv64Linux),
+            "mips32" => Ok(Target::Mips32),
+            "c" => Ok(Target::C),
+            "llvm-ir" => Ok(Target::LlvmIr),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Target {
+    /// The assembler and C compiler that build executables for the target: the system's own for
+    /// x86-64, cross tools otherwise. MIPS32 programs are only ever assembly, for a simulator, and
+    /// C and LLVM ones source, for whichever compiler.
+    pub fn tools(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Target::X86_64Linux => Some(("as", "cc")),
+            Target::Riscv64Linux => Some(("riscv64-linux-gnu-as", "riscv64-linux-gnu-gcc")),
+            Target::Mips32 | Target::C | Target::LlvmIr => None,
+        }
+    }
+}
+
+/// A symbol for `name` that assemblers (and linkers) accept, w/ anything but ASCII letters and
+/// digits escaped as its hex code point between underscores, e.g `nkt_fn_` + `a_b` is
+/// `nkt_fn_a_5f_b`. Symbols never contain a `.`, so labels can be made from them w/ one.
+pub fn mangle(prefix: &str, name: &str) -> String {
+    let mut symbol = prefix.to_string();
+    for c in name.chars() {
+        match c.is_ascii_alphanumeric() {
+            true => symbol.push(c),
+            false => symbol.push_str(&format!("_{:x}_", c as u32)),
+        }
+    }
+    symbol
+}
+
+/// The symbol a function is defined under; the one initialising globals is `nkt_init`
+pub fn fn_symbol(module: &Module, func: FnId) -> String {
+    match func == module.init {
+        true => "nkt_init".to_string(),
+        false => mangle("nkt_fn_", &module.functions[func as usize].name),
+    }
+}
+
+pub fn global_symbol(module: &Module, global: u32) -> String {
+    mangle("nkt_global_", &module.globals[global as usize].name)
+}
+
+/// `s` for GNU `as`'s `.string`: printable ASCII as is, anything else in octal
+pub fn escape_string(s: &str) -> String {
+    let mut escaped = String::new();
+    for b in s.bytes() {
+        match b {
+            b'"' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(b as char);
+            }
+            b' '..=b'~' => escaped.push(b as char),
+            _ => {
+                let _ = write!(escaped, "\\{b:03o}");
+            }
+        }
+    }
+    escaped
+}
+
+/// Assembles `asm` w/ `target`'s assembler, and links it against the runtime w/ its C compiler,
+/// into an executable at `out`
+pub fn build_executable(target: Target, asm: &str, out: &Path) -> io::Result<()> {
+    let Some((assembler, cc)) = target.tools() else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{target:?} has no executables"),
+        ));
+    };
+
+    // Unique per build, as tests build concurrently
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+    let n = BUILDS.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("nktc-{}-{n}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let (asm_path, obj_path, rt_path) = (dir.join("prog.s"), dir.join("prog.o"), dir.join("rt.c"));
+    std::fs::write(&asm_path, asm)?;
+    std::fs::write(&rt_path, RUNTIME_C)?;
+
+    let res = run_tool(
+        Command::new(assembler)
+            .arg("-o")
+            .arg(&obj_path)
+            .arg(&asm_path),
+    )
+    .and_then(|_| {
+        let mut cc = Command::new(cc);
+        cc.arg("-O2")
+            .arg("-o")
+            .arg(out)
+            .arg(&obj_path)
+            .arg(&rt_path)
+            .arg("-lm");
+        run_tool(&mut cc)
+    });
+
+    std::fs::remove_dir_all(&dir)?;
+    res
+}
+
+fn run_tool(cmd: &mut Command) -> io::Result<()> {
+    let output = cmd.output()?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "{:?} failed:\n{}",
+            cmd.get_program(),
+            String::from_utf8_lossy(&output.stderr)
+        ))),
+    }
+}
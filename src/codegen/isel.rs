@@ -0,0 +1,149 @@
+//! Instruction selection, as the native backends share it: a function's blocks are walked in
+//! order, w/ jumps to the next one left out, and each instruction is handed to the hook for its
+//! operands' types. Backends only say what to emit for each hook, into an `Asm`.
+
+use crate::ir::core::{
+    BinOp, BlockId, Callee, Function, GlobalId, Inst, Module, Operand, Reg, Terminator, UnOp,
+};
+use crate::semantics::intrinsics::INTRINSICS;
+use crate::semantics::spaghetti::SymType;
+
+/// The assembly for a function, as it's emitted, w/ labels local to it
+pub struct Asm<'o> {
+    out: &'o mut String,
+    pub sym: String,
+    local: (&'static str, &'static str), // what goes before the symbol, and b/w it and a name
+    n_labels: usize,                     // for those within a block
+}
+
+impl<'o> Asm<'o> {
+    /// `local` is how the assembler wants local labels written: `(".L", ".")` makes them
+    /// `.L<sym>.<name>`
+    pub fn new(out: &'o mut String, sym: String, local: (&'static str, &'static str)) -> Self {
+        Asm {
+            out,
+            sym,
+            local,
+            n_labels: 0,
+        }
+    }
+
+    pub fn emit(&mut self, line: &str) {
+        self.out.push('\t');
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    pub fn label(&mut self, label: &str) {
+        self.out.push_str(label);
+        self.out.push_str(":\n");
+    }
+
+    pub fn local_label(&self, name: &str) -> String {
+        format!("{}{}{}{name}", self.local.0, self.sym, self.local.1)
+    }
+
+    pub fn new_label(&mut self) -> String {
+        self.n_labels += 1;
+        self.local_label(&self.n_labels.to_string())
+    }
+
+    pub fn block_label(&self, id: BlockId) -> String {
+        self.local_label(&format!("bb{}", id.0))
+    }
+}
+
+/// What a backend emits for each instruction, and terminator, of a function out of SSA form
+pub trait Isel<'m> {
+    fn module(&self) -> &'m Module;
+    fn func(&self) -> &'m Function;
+
+    /// Starts the function off, up to its first block
+    fn prologue(&mut self);
+    fn block(&mut self, id: BlockId);
+
+    fn copy(&mut self, dst: Reg, src: &Operand);
+    fn int_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand);
+    fn float_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand);
+    /// Only ever a comparison
+    fn str_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand);
+    fn int_neg(&mut self, dst: Reg, operand: &Operand);
+    fn float_neg(&mut self, dst: Reg, operand: &Operand);
+    fn not(&mut self, dst: Reg, operand: &Operand);
+    fn call(&mut self, dst: Option<Reg>, callee: Callee, ret_type: SymType, args: &[Operand]);
+    fn load_global(&mut self, dst: Reg, global: GlobalId);
+    fn store_global(&mut self, global: GlobalId, src: &Operand);
+
+    fn jump(&mut self, target: BlockId);
+    /// Branches to `target` if `cond` holds, and falls through otherwise
+    fn branch(&mut self, cond: &Operand, target: BlockId);
+    fn ret(&mut self, value: Option<&Operand>);
+    fn missing_ret(&mut self);
+}
+
+/// Selects instructions for the whole of `isel`'s function
+pub fn select<'m>(isel: &mut impl Isel<'m>) {
+    let f = isel.func();
+    isel.prologue();
+
+    for id in f.block_ids() {
+        isel.block(id);
+
+        let block = f.block(id);
+        for inst in &block.insts {
+            self::inst(isel, f, inst);
+        }
+
+        // Falling through to the next block saves a jump
+        let next = BlockId(id.0 + 1);
+        match &block.term {
+            Terminator::Jump(target) if *target == next => {}
+            Terminator::Jump(target) => isel.jump(*target),
+            Terminator::Branch {
+                cond,
+                then_block,
+                else_block,
+            } => {
+                isel.branch(cond, *then_block);
+                if *else_block != next {
+                    isel.jump(*else_block);
+                }
+            }
+            Terminator::Ret(value) => isel.ret(value.as_ref()),
+            Terminator::MissingRet => isel.missing_ret(),
+        }
+    }
+}
+
+fn inst<'m>(isel: &mut impl Isel<'m>, f: &Function, inst: &Inst) {
+    match inst {
+        Inst::Copy { dst, src } => isel.copy(*dst, src),
+
+        Inst::Binary {
+            dst, op, lhs, rhs, ..
+        } => match f.operand_type(lhs) {
+            SymType::Float => isel.float_binary(*dst, *op, lhs, rhs),
+            SymType::String => isel.str_binary(*dst, *op, lhs, rhs),
+            _ => isel.int_binary(*dst, *op, lhs, rhs),
+        },
+
+        Inst::Unary { dst, op, operand } => match (op, f.operand_type(operand)) {
+            (UnOp::Neg, SymType::Float) => isel.float_neg(*dst, operand),
+            (UnOp::Neg, _) => isel.int_neg(*dst, operand),
+            (UnOp::Not, _) => isel.not(*dst, operand),
+        },
+
+        Inst::Call { dst, callee, args } => {
+            let ret_type = match *callee {
+                Callee::Fn(func) => isel.module().functions[func as usize].ret_type,
+                Callee::Intrinsic(i) => INTRINSICS[i as usize].ret_type,
+            };
+            isel.call(*dst, *callee, ret_type, args);
+        }
+
+        Inst::LoadGlobal { dst, global } => isel.load_global(*dst, *global),
+        Inst::StoreGlobal { global, src } => isel.store_global(*global, src),
+
+        Inst::Phi { .. } => unreachable!("phi in `{}`, which isn't out of SSA", f.name),
+    }
+}
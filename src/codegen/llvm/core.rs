@@ -0,0 +1,748 @@
+//! LLVM IR generation: a textual module, runtime included, for `lli` to run, or `llc` and `clang`
+//! to optimise and compile, w/o linking LLVM into the compiler. Like the C backend's translations,
+//! it's made from the checked AST; unlike them, the order operands are evaluated in is simply the
+//! order their instructions are emitted in.
+//!
+//! `ginti`s are `i64`s, `asharia`s `double`s, `boli`s `i1`s, and `jumla`s `ptr`s, pointers being
+//! opaque, as LLVM 15 and later have them (LLVM 14 takes them w/ `-opaque-pointers`). Every variable is an `alloca` in the entry block, loaded
+//! from and stored to as it's used, and `&&` and `||` store their result to one too, so there are
+//! no `phi`s to place: `mem2reg` (or any `-O`) makes SSA of them. Functions are `@fn.<name>`,
+//! globals `@g.<name>` (initialised in order by `@nkt.init`), and locals `%<name>.addr<n>`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::lexer::Token;
+use crate::parser::ast::core::*;
+use crate::semantics::{
+    core::ENTRY_POINT, intrinsics::find_intrinsic, spaghetti::SymType, utils::token_to_symtype,
+};
+
+/// Included verbatim at the top of each module, which needs nothing else but libc
+pub const RUNTIME_LL: &str = include_str!("runtime.ll");
+
+/// Translates a checked program into a textual LLVM module
+pub fn generate(ast: &TranslationUnit) -> String {
+    let mut gen = Gen::new(ast);
+
+    let globals: Vec<&VarDecl> = ast
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Var(v) => Some(v),
+            Decl::Fn(_) => None,
+        })
+        .collect();
+    let fns: Vec<&FnDecl> = ast
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Fn(f) => Some(f),
+            Decl::Var(_) => None,
+        })
+        .collect();
+
+    let mut defs = String::new();
+    for f in &fns {
+        defs.push('\n');
+        defs.push_str(&gen.function(f));
+    }
+    if !globals.is_empty() {
+        defs.push('\n');
+        defs.push_str(&gen.init(&globals));
+    }
+    if let Some(entry) = fns.iter().find(|f| f.ident == ENTRY_POINT) {
+        defs.push('\n');
+        defs.push_str(&main(entry, !globals.is_empty()));
+    }
+
+    // Globals start out zeroed, as in the other backends, until `@nkt.init` runs
+    let mut global_defs = String::new();
+    for v in &globals {
+        let sym_type = token_to_symtype(&v.type_tok, true);
+        let zero = match sym_type {
+            SymType::Int => "0".to_string(),
+            SymType::Float => float_lit(0.0),
+            SymType::String => gen.string(""),
+            _ => "false".to_string(),
+        };
+        let name = global_ident(&format!("g.{}", v.ident));
+        writeln!(
+            global_defs,
+            "{name} = internal global {} {zero}",
+            ty(sym_type)
+        )
+        .unwrap();
+    }
+
+    let mut ll = RUNTIME_LL.to_string();
+    if !gen.strings.is_empty() {
+        ll.push('\n');
+    }
+    for (i, s) in gen.strings.iter().enumerate() {
+        let len = s.len() + 1;
+        let bytes = escape(s.as_bytes());
+        writeln!(
+            ll,
+            "@.str.{i} = private unnamed_addr constant [{len} x i8] c\"{bytes}\\00\""
+        )
+        .unwrap();
+    }
+    if !global_defs.is_empty() {
+        ll.push('\n');
+        ll.push_str(&global_defs);
+    }
+    ll.push_str(&defs);
+    ll
+}
+
+// `@main`, which initialises the globals, and exits w/ what the entry point returns
+fn main(entry: &FnDecl, has_globals: bool) -> String {
+    let mut ll = String::new();
+    let shuru = global_ident(&format!("fn.{ENTRY_POINT}"));
+
+    match entry.params.is_empty() {
+        true => ll.push_str("define i32 @main() {\n"),
+        false => ll.push_str("define i32 @main(i32 %argc, ptr %argv) {\n"),
+    }
+    if has_globals {
+        ll.push_str("  call void @nkt.init()\n");
+    }
+    match entry.params.is_empty() {
+        true => writeln!(ll, "  %code = call i64 {shuru}()").unwrap(),
+        false => {
+            ll.push_str("  %argc.64 = sext i32 %argc to i64\n");
+            writeln!(ll, "  %code = call i64 {shuru}(i64 %argc.64)").unwrap();
+        }
+    }
+    ll.push_str("  %status = trunc i64 %code to i32\n  ret i32 %status\n}\n");
+    ll
+}
+
+// An operand, w/ its type
+struct Val {
+    repr: String,
+    sym_type: SymType,
+}
+
+impl Val {
+    fn new(repr: String, sym_type: SymType) -> Val {
+        Val { repr, sym_type }
+    }
+
+    // Typed, as arguments and operands of `store` are written
+    fn typed(&self) -> String {
+        format!("{} {}", ty(self.sym_type), self.repr)
+    }
+}
+
+struct Gen<'a> {
+    // Return types, of the program's functions
+    fns: HashMap<&'a str, SymType>,
+    globals: HashMap<&'a str, SymType>,
+    // String constants, `@.str.<i>`, w/ where each is
+    strings: Vec<String>,
+    interned: HashMap<String, usize>,
+
+    // Locals in scope, innermost last, by their Nuktah names, w/ their `alloca`s
+    scopes: Vec<Vec<(&'a str, String, SymType)>>,
+    // How many `alloca`s each name has had, so they're unique across a function
+    slots: HashMap<&'a str, usize>,
+    allocas: String,
+    body: String,
+    next_temp: usize,
+    next_label: usize,
+    // Whether the current block has been terminated, so anything more needs a new one
+    terminated: bool,
+    // Where each enclosing loop `toro`s to
+    loop_ends: Vec<String>,
+    ret_type: SymType,
+}
+
+impl<'a> Gen<'a> {
+    fn new(ast: &'a TranslationUnit) -> Gen<'a> {
+        let mut fns = HashMap::new();
+        let mut globals = HashMap::new();
+
+        for decl in ast {
+            match decl {
+                Decl::Var(v) => {
+                    globals.insert(v.ident.as_str(), token_to_symtype(&v.type_tok, true));
+                }
+                Decl::Fn(f) => {
+                    fns.insert(f.ident.as_str(), token_to_symtype(&f.type_tok, false));
+                }
+            }
+        }
+
+        Gen {
+            fns,
+            globals,
+            strings: vec![],
+            interned: HashMap::new(),
+            scopes: vec![],
+            slots: HashMap::new(),
+            allocas: String::new(),
+            body: String::new(),
+            next_temp: 0,
+            next_label: 0,
+            terminated: false,
+            loop_ends: vec![],
+            ret_type: SymType::Void,
+        }
+    }
+
+    fn reset(&mut self, ret_type: SymType) {
+        self.scopes = vec![vec![]];
+        self.slots.clear();
+        self.allocas.clear();
+        self.body.clear();
+        // The entry block is left unlabelled, lest a parameter share its name, so it's `%0`
+        self.next_temp = 1;
+        self.next_label = 0;
+        self.terminated = false;
+        self.ret_type = ret_type;
+    }
+
+    fn function(&mut self, f: &'a FnDecl) -> String {
+        let ret_type = token_to_symtype(&f.type_tok, false);
+        self.reset(ret_type);
+
+        // Parameters are stored to `alloca`s of their own, like any other variable
+        let mut params = vec![];
+        for p in &f.params {
+            let sym_type = token_to_symtype(&p.type_tok, true);
+            let param = Val::new(local_ident(&p.ident), sym_type);
+            params.push(param.typed());
+
+            let slot = self.declare(&p.ident, sym_type);
+            self.inst(format!("store {}, ptr {slot}", param.typed()));
+        }
+
+        self.block(&f.block);
+        if !self.terminated {
+            match ret_type {
+                SymType::Void => self.terminate("ret void".to_string()),
+                _ => {
+                    let name = self.string(&f.ident);
+                    self.inst(format!("call void @nkt.missing_return(ptr {name})"));
+                    self.terminate("unreachable".to_string());
+                }
+            }
+        }
+
+        let name = global_ident(&format!("fn.{}", f.ident));
+        format!(
+            "define internal {} {name}({}) {{\n{}{}}}\n",
+            ty(ret_type),
+            params.join(", "),
+            self.allocas,
+            self.body
+        )
+    }
+
+    // `@nkt.init`, which initialises the globals in the order they're declared in
+    fn init(&mut self, globals: &[&'a VarDecl]) -> String {
+        self.reset(SymType::Void);
+        self.scopes.clear();
+
+        for v in globals {
+            let value = self.value(v);
+            let name = global_ident(&format!("g.{}", v.ident));
+            self.inst(format!("store {}, ptr {name}", value.typed()));
+        }
+        self.terminate("ret void".to_string());
+
+        format!(
+            "define internal void @nkt.init() {{\n{}{}}}\n",
+            self.allocas, self.body
+        )
+    }
+
+    // A pointer to the first byte of `s`, as a constant
+    fn string(&mut self, s: &str) -> String {
+        let i = match self.interned.get(s) {
+            Some(&i) => i,
+            None => {
+                self.strings.push(s.to_string());
+                self.interned.insert(s.to_string(), self.strings.len() - 1);
+                self.strings.len() - 1
+            }
+        };
+
+        format!("@.str.{i}")
+    }
+
+    fn alloca(&mut self, name: &str, sym_type: SymType) -> String {
+        let slot = local_ident(name);
+        writeln!(self.allocas, "  {slot} = alloca {}", ty(sym_type)).unwrap();
+        slot
+    }
+
+    // Brings a local into scope, w/ an `alloca` named apart from any other in the function
+    fn declare(&mut self, ident: &'a str, sym_type: SymType) -> String {
+        let n = self.slots.entry(ident).or_insert(0);
+        let name = match *n {
+            0 => format!("{ident}.addr"),
+            n => format!("{ident}.addr{n}"),
+        };
+        *n += 1;
+
+        let slot = self.alloca(&name, sym_type);
+        let scope = self.scopes.last_mut().expect("a scope to declare in");
+        scope.push((ident, slot.clone(), sym_type));
+        slot
+    }
+
+    fn inst(&mut self, inst: String) {
+        writeln!(self.body, "  {inst}").unwrap();
+    }
+
+    // Emits an instruction that produces a value, returning it
+    fn value_inst(&mut self, inst: String, sym_type: SymType) -> Val {
+        let temp = format!("%{}", self.next_temp);
+        self.next_temp += 1;
+        self.inst(format!("{temp} = {inst}"));
+        Val::new(temp, sym_type)
+    }
+
+    fn terminate(&mut self, inst: String) {
+        self.inst(inst);
+        self.terminated = true;
+    }
+
+    fn fresh_label(&mut self, kind: &str) -> String {
+        self.next_label += 1;
+        format!("{kind}.{}", self.next_label)
+    }
+
+    // Starts a block, falling through to it from the current one if that's still open
+    fn label(&mut self, label: &str) {
+        if !self.terminated {
+            self.inst(format!("br label %{label}"));
+        }
+        writeln!(self.body, "\n{label}:").unwrap();
+        self.terminated = false;
+    }
+
+    fn br(&mut self, label: &str) {
+        if !self.terminated {
+            self.terminate(format!("br label %{label}"));
+        }
+    }
+
+    fn cond_br(&mut self, cond: &Val, then: &str, otherwise: &str) {
+        let cond = &cond.repr;
+        self.terminate(format!("br i1 {cond}, label %{then}, label %{otherwise}"));
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        self.scopes.push(vec![]);
+        for stmt in block {
+            self.stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        // Whatever follows a `wapsi` or `toro` is never reached, but still needs a block
+        if self.terminated {
+            let dead = self.fresh_label("dead");
+            self.label(&dead);
+        }
+
+        match stmt {
+            Stmt::VarDecl(v) => self.var_decl(v),
+
+            Stmt::Expr(ExprStmt { expr: Some(e), .. }) => {
+                self.assign_expr(e);
+            }
+            Stmt::Expr(ExprStmt { expr: None, .. }) => {}
+
+            // A `khali` function's `wapsi` may still evaluate something
+            Stmt::Ret(ExprStmt { expr: Some(e), .. }) => {
+                let value = self.assign_expr(e);
+                match self.ret_type {
+                    SymType::Void => self.terminate("ret void".to_string()),
+                    _ => self.terminate(format!("ret {}", value.typed())),
+                }
+            }
+            Stmt::Ret(ExprStmt { expr: None, .. }) => self.terminate("ret void".to_string()),
+
+            Stmt::Break(_) => {
+                let end = self
+                    .loop_ends
+                    .last()
+                    .expect("`toro` to be in a loop")
+                    .clone();
+                self.br(&end);
+            }
+
+            Stmt::If(s) => {
+                let then = self.fresh_label("if.then");
+                let otherwise = self.fresh_label("if.else");
+                let end = self.fresh_label("if.end");
+
+                let cond = self.cond(&s.cond);
+                match s.else_block.is_empty() {
+                    true => self.cond_br(&cond, &then, &end),
+                    false => self.cond_br(&cond, &then, &otherwise),
+                }
+
+                self.label(&then);
+                self.block(&s.if_block);
+                self.br(&end);
+
+                if !s.else_block.is_empty() {
+                    self.label(&otherwise);
+                    self.block(&s.else_block);
+                    self.br(&end);
+                }
+                self.label(&end);
+            }
+
+            Stmt::For(s) => {
+                let cond = self.fresh_label("for.cond");
+                let body = self.fresh_label("for.body");
+                let updt = self.fresh_label("for.updt");
+                let end = self.fresh_label("for.end");
+
+                self.scopes.push(vec![]);
+                if let Some(init) = &s.init {
+                    self.var_decl(init);
+                }
+
+                self.label(&cond);
+                match &s.cond.expr {
+                    Some(e) => {
+                        let c = self.assign_expr(e);
+                        self.cond_br(&c, &body, &end);
+                    }
+                    None => self.br(&body),
+                }
+
+                self.label(&body);
+                self.loop_ends.push(end.clone());
+                self.block(&s.block);
+                self.loop_ends.pop();
+
+                self.label(&updt);
+                if let Some(e) = &s.updt {
+                    self.assign_expr(e);
+                }
+                self.br(&cond);
+
+                self.label(&end);
+                self.scopes.pop();
+            }
+        }
+    }
+
+    fn cond(&mut self, e: &'a Expr) -> Val {
+        let e = e.as_ref().expect("conditions to be expressions");
+        self.assign_expr(e)
+    }
+
+    // Its value is evaluated before it's in scope, as Nuktah has it
+    fn var_decl(&mut self, v: &'a VarDecl) {
+        let value = self.value(v);
+        let slot = self.declare(&v.ident, value.sym_type);
+        self.inst(format!("store {}, ptr {slot}", value.typed()));
+    }
+
+    fn value(&mut self, v: &'a VarDecl) -> Val {
+        let sym_type = token_to_symtype(&v.type_tok, true);
+        match &v.expr {
+            Some(e) => self.assign_expr(e),
+            None => {
+                let zero = match sym_type {
+                    SymType::Int => "0".to_string(),
+                    SymType::Float => float_lit(0.0),
+                    SymType::String => self.string(""),
+                    _ => "false".to_string(),
+                };
+                Val::new(zero, sym_type)
+            }
+        }
+    }
+
+    // Where a variable lives, be it an `alloca` or a global
+    fn lookup(&self, ident: &str) -> (String, SymType) {
+        let mut locals = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev());
+        if let Some((_, slot, sym_type)) = locals.find(|(n, ..)| *n == ident) {
+            return (slot.clone(), *sym_type);
+        }
+
+        let sym_type = self.globals[ident];
+        (global_ident(&format!("g.{ident}")), sym_type)
+    }
+
+    fn assign_expr(&mut self, e: &'a AssignExpr) -> Val {
+        let (target, value) = match e {
+            AssignExpr::Bool(b) => return self.bool_expr(b),
+            AssignExpr::Assign(target, value) => (target, value),
+        };
+
+        let ident = target.as_ident().expect("assignments to have been checked");
+        let value = self.assign_expr(value);
+        let (slot, _) = self.lookup(ident);
+        self.inst(format!("store {}, ptr {slot}", value.typed()));
+        value
+    }
+
+    // `&&` and `||` short-circuit, storing what they come to, rather than merging it w/ a `phi`
+    fn bool_expr(&mut self, e: &'a BoolExpr) -> Val {
+        let (lhs, op, rhs) = match e {
+            BoolExpr::BitOr(b) => return self.bit_or_expr(b),
+            BoolExpr::Bool(lhs, op, rhs) => (lhs, op, rhs),
+        };
+
+        let rest = self.fresh_label("sc.rhs");
+        let result = self.alloca(&format!("sc.{}", self.next_label), SymType::Bool);
+        let end = self.fresh_label("sc.end");
+
+        let lhs = self.bool_expr(lhs);
+        self.inst(format!("store {}, ptr {result}", lhs.typed()));
+        match op {
+            Token::BooleanAnd => self.cond_br(&lhs, &rest, &end),
+            _ => self.cond_br(&lhs, &end, &rest),
+        }
+
+        self.label(&rest);
+        let rhs = self.bit_or_expr(rhs);
+        self.inst(format!("store {}, ptr {result}", rhs.typed()));
+
+        self.label(&end);
+        self.value_inst(format!("load i1, ptr {result}"), SymType::Bool)
+    }
+
+    fn bit_or_expr(&mut self, e: &'a BitOrExpr) -> Val {
+        match e {
+            BitOrExpr::BitAnd(b) => self.bit_and_expr(b),
+            BitOrExpr::BitOr(lhs, rhs) => {
+                let lhs = self.bit_or_expr(lhs);
+                let rhs = self.bit_and_expr(rhs);
+                self.value_inst(format!("or {}, {}", lhs.typed(), rhs.repr), SymType::Int)
+            }
+        }
+    }
+
+    fn bit_and_expr(&mut self, e: &'a BitAndExpr) -> Val {
+        match e {
+            BitAndExpr::Comp(c) => self.comp_expr(c),
+            BitAndExpr::BitAnd(lhs, rhs) => {
+                let lhs = self.bit_and_expr(lhs);
+                let rhs = self.comp_expr(rhs);
+                self.value_inst(format!("and {}, {}", lhs.typed(), rhs.repr), SymType::Int)
+            }
+        }
+    }
+
+    fn comp_expr(&mut self, e: &'a CompExpr) -> Val {
+        let (lhs, op, rhs) = match e {
+            CompExpr::Shift(s) => return self.shift_expr(s),
+            CompExpr::Comp(lhs, op, rhs) => (lhs, op, rhs),
+        };
+        let lhs = self.comp_expr(lhs);
+        let rhs = self.shift_expr(rhs);
+
+        // `boli`s compare as `sach` > `jhoot`, and NaNs as nothing
+        let (int, float) = match op {
+            Token::LessThan => ("slt", "olt"),
+            Token::GreaterThan => ("sgt", "ogt"),
+            _ => ("eq", "oeq"),
+        };
+        let inst = match lhs.sym_type {
+            SymType::Float => format!("fcmp {float} {}, {}", lhs.typed(), rhs.repr),
+            SymType::Bool => {
+                let pred = int.replace('s', "u");
+                format!("icmp {pred} {}, {}", lhs.typed(), rhs.repr)
+            }
+            SymType::String => {
+                let args = format!("{}, {}", lhs.typed(), rhs.typed());
+                let cmp = self.value_inst(format!("call i32 @strcmp({args})"), SymType::Int);
+                format!("icmp {int} i32 {}, 0", cmp.repr)
+            }
+            _ => format!("icmp {int} {}, {}", lhs.typed(), rhs.repr),
+        };
+        self.value_inst(inst, SymType::Bool)
+    }
+
+    fn shift_expr(&mut self, e: &'a ShiftExpr) -> Val {
+        match e {
+            ShiftExpr::Add(a) => self.add_expr(a),
+            ShiftExpr::Shift(lhs, op, rhs) => {
+                let lhs = self.shift_expr(lhs);
+                let rhs = self.add_expr(rhs);
+                self.arith(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn add_expr(&mut self, e: &'a AddExpr) -> Val {
+        match e {
+            AddExpr::Mul(m) => self.mul_expr(m),
+            AddExpr::Add(lhs, op, rhs) => {
+                let lhs = self.add_expr(lhs);
+                let rhs = self.mul_expr(rhs);
+                self.arith(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn mul_expr(&mut self, e: &'a MulExpr) -> Val {
+        match e {
+            MulExpr::Exp(x) => self.exp_expr(x),
+            MulExpr::Mul(lhs, op, rhs) => {
+                let lhs = self.mul_expr(lhs);
+                let rhs = self.exp_expr(rhs);
+                self.arith(lhs, op, rhs)
+            }
+        }
+    }
+
+    fn exp_expr(&mut self, e: &'a ExpExpr) -> Val {
+        match e {
+            ExpExpr::Unary(u) => self.unary_expr(u),
+            ExpExpr::Exp(lhs, rhs) => {
+                let lhs = self.unary_expr(lhs);
+                let rhs = self.exp_expr(rhs);
+                self.arith(lhs, &Token::ExpOp, rhs)
+            }
+        }
+    }
+
+    // What LLVM leaves undefined goes through the runtime's helpers
+    fn arith(&mut self, lhs: Val, op: &Token, rhs: Val) -> Val {
+        let sym_type = lhs.sym_type;
+        let inst = match (sym_type, op) {
+            (SymType::Int, Token::AddOp) => "add",
+            (SymType::Int, Token::SubOp) => "sub",
+            (SymType::Int, Token::MulOp) => "mul",
+            (SymType::Int, Token::DivOp) => "call i64 @nkt.div",
+            (SymType::Int, Token::ModOp) => "call i64 @nkt.mod",
+            (SymType::Int, Token::ExpOp) => "call i64 @nkt.pow",
+            (SymType::Int, Token::ShiftLeft) => "call i64 @nkt.shl",
+            (SymType::Int, Token::ShiftRight) => "call i64 @nkt.shr",
+            (_, Token::AddOp) => "fadd",
+            (_, Token::SubOp) => "fsub",
+            (_, Token::MulOp) => "fmul",
+            (_, Token::DivOp) => "fdiv",
+            (_, Token::ModOp) => "frem",
+            _ => "call double @llvm.pow.f64",
+        };
+
+        let inst = match inst.starts_with("call") {
+            true => format!("{inst}({}, {})", lhs.typed(), rhs.typed()),
+            false => format!("{inst} {}, {}", lhs.typed(), rhs.repr),
+        };
+        self.value_inst(inst, sym_type)
+    }
+
+    fn unary_expr(&mut self, e: &'a UnaryExpr) -> Val {
+        let (op, operand) = match e {
+            UnaryExpr::Primary(p) => return self.primary_expr(p),
+            UnaryExpr::Unary(op, operand) => (op, operand),
+        };
+        let operand = self.unary_expr(operand);
+
+        let inst = match (op, operand.sym_type) {
+            (Token::SubOp, SymType::Int) => format!("sub i64 0, {}", operand.repr),
+            (Token::SubOp, _) => format!("fneg {}", operand.typed()),
+            _ => format!("xor {}, true", operand.typed()),
+        };
+        self.value_inst(inst, operand.sym_type)
+    }
+
+    fn primary_expr(&mut self, e: &'a PrimaryExpr) -> Val {
+        match e {
+            PrimaryExpr::IntLit(i) => Val::new(i.to_string(), SymType::Int),
+            PrimaryExpr::FloatLit(f) => Val::new(float_lit(*f), SymType::Float),
+            PrimaryExpr::StringLit(s) => Val::new(self.string(s), SymType::String),
+            PrimaryExpr::BoolLit(b) => Val::new(b.to_string(), SymType::Bool),
+            PrimaryExpr::Ident(ident) => {
+                let (slot, sym_type) = self.lookup(ident);
+                let sym = ty(sym_type);
+                self.value_inst(format!("load {sym}, ptr {slot}"), sym_type)
+            }
+            PrimaryExpr::Paren(inner) => {
+                let inner = inner.as_ref().as_ref();
+                self.assign_expr(inner.expect("parentheses to hold an expression"))
+            }
+            PrimaryExpr::Call(call) => self.call(call),
+        }
+    }
+
+    fn call(&mut self, call: &'a FnCall) -> Val {
+        let (name, ret_type) = match find_intrinsic(&call.ident) {
+            Some(intrinsic) => (format!("@nkt.{}", intrinsic.name), intrinsic.ret_type),
+            None => {
+                let name = global_ident(&format!("fn.{}", call.ident));
+                (name, self.fns[call.ident.as_str()])
+            }
+        };
+
+        let mut args = vec![];
+        for arg in &call.args {
+            let arg = arg.as_ref().expect("arguments to be expressions");
+            args.push(self.assign_expr(arg).typed());
+        }
+
+        let inst = format!("call {} {name}({})", ty(ret_type), args.join(", "));
+        match ret_type {
+            SymType::Void => {
+                self.inst(inst);
+                Val::new(String::new(), SymType::Void)
+            }
+            _ => self.value_inst(inst, ret_type),
+        }
+    }
+}
+
+fn ty(sym_type: SymType) -> &'static str {
+    match sym_type {
+        SymType::Int => "i64",
+        SymType::Float => "double",
+        SymType::String => "ptr",
+        SymType::Bool => "i1",
+        SymType::Void => "void",
+    }
+}
+
+// `%name`, quoted if it's more than letters, digits, `.`s and `_`s
+fn local_ident(name: &str) -> String {
+    format!("%{}", quoted(name))
+}
+
+fn global_ident(name: &str) -> String {
+    format!("@{}", quoted(name))
+}
+
+fn quoted(name: &str) -> String {
+    match name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+    {
+        true => name.to_string(),
+        false => format!("\"{}\"", escape(name.as_bytes())),
+    }
+}
+
+// Exactly, as the bits of the `double`
+fn float_lit(f: f64) -> String {
+    format!("0x{:016X}", f.to_bits())
+}
+
+// For a `c"..."` constant, or a quoted name: printable ASCII as is, anything else in hex
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for &b in bytes {
+        match b {
+            0x20..=0x7e if b != b'"' && b != b'\\' => escaped.push(b as char),
+            _ => write!(escaped, "\\{b:02X}").unwrap(),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,239 @@
+; Nuktah's runtime for LLVM, at the top of every module `nktc build --target=llvm-ir` produces.
+; Integers wrap on overflow, as LLVM's arithmetic does w/o `nsw`; what it leaves undefined, i.e
+; dividing by zero, `INT64_MIN / -1` and shifting by 64 bits or more, is checked for here. Errors
+; are reported as the interpreter names them, sans the span, exiting w/ 1, like it does.
+
+declare i32 @puts(ptr)
+declare i32 @printf(ptr, ...)
+declare i32 @dprintf(i32, ptr, ...)
+declare i32 @fflush(ptr)
+declare i32 @getchar()
+declare ptr @malloc(i64)
+declare ptr @realloc(ptr, i64)
+declare i32 @strcmp(ptr, ptr)
+declare void @exit(i32) noreturn
+declare double @llvm.pow.f64(double, double)
+
+@nkt.fmt.ginti = private unnamed_addr constant [6 x i8] c"%lld\0A\00"
+@nkt.fmt.div = private unnamed_addr constant [31 x i8] c"runtime error: DivisionByZero\0A\00"
+@nkt.fmt.shift = private unnamed_addr constant [38 x i8] c"runtime error: ShiftOutOfRange(%lld)\0A\00"
+@nkt.fmt.exp = private unnamed_addr constant [39 x i8] c"runtime error: NegativeExponent(%lld)\0A\00"
+@nkt.fmt.ret = private unnamed_addr constant [36 x i8] c"runtime error: MissingReturn(\22%s\22)\0A\00"
+
+define internal void @nkt.likho(ptr %s) {
+entry:
+  %r = call i32 @puts(ptr %s)
+  ret void
+}
+
+define internal void @nkt.likho_ginti(i64 %i) {
+entry:
+  %r = call i32 (ptr, ...) @printf(ptr @nkt.fmt.ginti, i64 %i)
+  ret void
+}
+
+; A line from stdin, sans the newline; empty at EOF
+define internal ptr @nkt.parho() {
+entry:
+  %len = alloca i64
+  %cap = alloca i64
+  %line = alloca ptr
+  store i64 0, ptr %len
+  store i64 64, ptr %cap
+  %buf = call ptr @malloc(i64 64)
+  store ptr %buf, ptr %line
+  br label %read
+
+read:
+  %c = call i32 @getchar()
+  %eof = icmp eq i32 %c, -1
+  %newline = icmp eq i32 %c, 10
+  %end = or i1 %eof, %newline
+  br i1 %end, label %strip, label %append
+
+append:
+  %n = load i64, ptr %len
+  %size = load i64, ptr %cap
+  %n1 = add i64 %n, 1
+  %full = icmp eq i64 %n1, %size
+  br i1 %full, label %grow, label %store
+
+grow:
+  %old = load ptr, ptr %line
+  %bigger = shl i64 %size, 1
+  store i64 %bigger, ptr %cap
+  %new = call ptr @realloc(ptr %old, i64 %bigger)
+  store ptr %new, ptr %line
+  br label %store
+
+store:
+  %at = load ptr, ptr %line
+  %slot = getelementptr inbounds i8, ptr %at, i64 %n
+  %byte = trunc i32 %c to i8
+  store i8 %byte, ptr %slot
+  store i64 %n1, ptr %len
+  br label %read
+
+strip:
+  %m = load i64, ptr %len
+  %empty = icmp eq i64 %m, 0
+  br i1 %empty, label %done, label %check
+
+check:
+  %s = load ptr, ptr %line
+  %last_at = sub i64 %m, 1
+  %last_slot = getelementptr inbounds i8, ptr %s, i64 %last_at
+  %last = load i8, ptr %last_slot
+  %cr = icmp eq i8 %last, 13
+  br i1 %cr, label %drop, label %done
+
+drop:
+  store i64 %last_at, ptr %len
+  br label %strip
+
+done:
+  %final = load ptr, ptr %line
+  %k = load i64, ptr %len
+  %nul = getelementptr inbounds i8, ptr %final, i64 %k
+  store i8 0, ptr %nul
+  ret ptr %final
+}
+
+; Flushes stdout first, so what was printed before the error still is
+define internal void @nkt.fail(ptr %fmt, i64 %arg) noreturn {
+entry:
+  %f = call i32 @fflush(ptr null)
+  %r = call i32 (i32, ptr, ...) @dprintf(i32 2, ptr %fmt, i64 %arg)
+  call void @exit(i32 1)
+  unreachable
+}
+
+define internal void @nkt.missing_return(ptr %func) noreturn {
+entry:
+  %f = call i32 @fflush(ptr null)
+  %r = call i32 (i32, ptr, ...) @dprintf(i32 2, ptr @nkt.fmt.ret, ptr %func)
+  call void @exit(i32 1)
+  unreachable
+}
+
+; `INT64_MIN / -1` wraps
+define internal i64 @nkt.div(i64 %a, i64 %b) {
+entry:
+  %zero = icmp eq i64 %b, 0
+  br i1 %zero, label %fail, label %nonzero
+
+fail:
+  call void @nkt.fail(ptr @nkt.fmt.div, i64 0)
+  unreachable
+
+nonzero:
+  %minus_one = icmp eq i64 %b, -1
+  br i1 %minus_one, label %negate, label %divide
+
+negate:
+  %neg = sub i64 0, %a
+  ret i64 %neg
+
+divide:
+  %q = sdiv i64 %a, %b
+  ret i64 %q
+}
+
+define internal i64 @nkt.mod(i64 %a, i64 %b) {
+entry:
+  %zero = icmp eq i64 %b, 0
+  br i1 %zero, label %fail, label %nonzero
+
+fail:
+  call void @nkt.fail(ptr @nkt.fmt.div, i64 0)
+  unreachable
+
+nonzero:
+  %minus_one = icmp eq i64 %b, -1
+  br i1 %minus_one, label %none, label %divide
+
+none:
+  ret i64 0
+
+divide:
+  %r = srem i64 %a, %b
+  ret i64 %r
+}
+
+; `base ^ exp`, wrapping on overflow
+define internal i64 @nkt.pow(i64 %base, i64 %exp) {
+entry:
+  %acc = alloca i64
+  %b = alloca i64
+  %e = alloca i64
+  %negative = icmp slt i64 %exp, 0
+  br i1 %negative, label %fail, label %start
+
+fail:
+  call void @nkt.fail(ptr @nkt.fmt.exp, i64 %exp)
+  unreachable
+
+start:
+  store i64 1, ptr %acc
+  store i64 %base, ptr %b
+  store i64 %exp, ptr %e
+  br label %loop
+
+loop:
+  %left = load i64, ptr %e
+  %more = icmp sgt i64 %left, 0
+  br i1 %more, label %body, label %done
+
+body:
+  %bit = and i64 %left, 1
+  %odd = icmp eq i64 %bit, 1
+  br i1 %odd, label %multiply, label %square
+
+multiply:
+  %so_far = load i64, ptr %acc
+  %factor = load i64, ptr %b
+  %product = mul i64 %so_far, %factor
+  store i64 %product, ptr %acc
+  br label %square
+
+square:
+  %x = load i64, ptr %b
+  %squared = mul i64 %x, %x
+  store i64 %squared, ptr %b
+  %halved = ashr i64 %left, 1
+  store i64 %halved, ptr %e
+  br label %loop
+
+done:
+  %result = load i64, ptr %acc
+  ret i64 %result
+}
+
+define internal i64 @nkt.shl(i64 %a, i64 %amount) {
+entry:
+  %in_range = icmp ult i64 %amount, 64
+  br i1 %in_range, label %shift, label %fail
+
+fail:
+  call void @nkt.fail(ptr @nkt.fmt.shift, i64 %amount)
+  unreachable
+
+shift:
+  %r = shl i64 %a, %amount
+  ret i64 %r
+}
+
+; Arithmetic
+define internal i64 @nkt.shr(i64 %a, i64 %amount) {
+entry:
+  %in_range = icmp ult i64 %amount, 64
+  br i1 %in_range, label %shift, label %fail
+
+fail:
+  call void @nkt.fail(ptr @nkt.fmt.shift, i64 %amount)
+  unreachable
+
+shift:
+  %r = ashr i64 %a, %amount
+  ret i64 %r
+}
@@ -0,0 +1,573 @@
+//! MIPS32 code generation, for SPIM and MARS: a whole program, runtime included, that starts at
+//! `main` and does its I/O through their syscalls. Takes a module out of SSA form, and assumes
+//! branches aren't delayed, as is both simulators' default.
+//!
+//! `ginti`s always get a stack slot of 8 bytes, below `$fp`, and take up both of its words, the
+//! low one first, as MIPS32's registers are only 32 bits wide; they're worked on in pairs of
+//! them. `boli`s (as 0 or 1, w/ a zeroed high word) and `jumla`s (as the address of a
+//! NUL-terminated string) are allocated among `$t3`-`$t8` and `$s0`-`$s6`, and `asharia`s among
+//! the even ones of coprocessor 1's from `$f4`, each w/ the odd one after it; those that don't get
+//! one are spilled to a slot too. The callee-saved registers used are saved past the slots.
+//!
+//! Arguments are passed on the stack, 8 bytes each, and the callee's parameters are read from
+//! where they were passed, unless they're allocated a register. Results come back in `$v0` (and
+//! `$v1`), or `$f0`.
+
+use std::fmt::Write;
+
+use crate::codegen::core::{fn_symbol, global_symbol};
+use crate::codegen::isel::{self, Asm, Isel};
+use crate::codegen::regalloc::core::{
+    self as regalloc, Allocation, Allocator, Loc, RegClass, RegFile, Regs,
+};
+use crate::ir::core::{
+    BinOp, BlockId, Callee, Const, FnId, Function, GlobalId, Inst, Module, Operand, Reg, Terminator,
+};
+use crate::semantics::intrinsics::INTRINSICS;
+use crate::semantics::spaghetti::SymType;
+
+/// Routines the generated code calls for what MIPS32 can't do in a few instructions, and for
+/// the intrinsics; appended to every program
+pub const RUNTIME: &str = include_str!("runtime.s");
+
+// Past what instruction selection and the runtime use as temporaries, and `$s7`, which holds argc
+pub const REG_FILE: RegFile = RegFile {
+    int: Regs {
+        caller_saved: &["$t3", "$t4", "$t5", "$t6", "$t7", "$t8"],
+        callee_saved: &["$s0", "$s1", "$s2", "$s3", "$s4", "$s5", "$s6"],
+    },
+    float: Regs {
+        caller_saved: &["$f4", "$f6", "$f8", "$f10", "$f16", "$f18"],
+        callee_saved: &["$f20", "$f22", "$f24", "$f26", "$f28", "$f30"],
+    },
+    class: |t| match t {
+        SymType::Int => None,
+        SymType::Float => Some(RegClass::Float),
+        _ => Some(RegClass::Int),
+    },
+    clobbers,
+};
+
+// Calls, and the operators lowered to calls into the runtime
+fn clobbers(f: &Function, inst: &Inst) -> bool {
+    regalloc::calls_out(f, inst, |op, t| match t {
+        SymType::String => true,
+        SymType::Float => matches!(op, BinOp::Mod | BinOp::Exp),
+        _ => matches!(
+            op,
+            BinOp::Div | BinOp::Mod | BinOp::Exp | BinOp::Shl | BinOp::Shr
+        ),
+    })
+}
+
+// The odd register of the pair an even one of coprocessor 1's starts
+fn odd(even: &str) -> String {
+    let n: usize = even[2..].parse().expect("a float register");
+    format!("$f{}", n + 1)
+}
+
+/// A program for SPIM or MARS, w/ `main` initialising globals, calling `shuru`, and exiting w/
+/// the code it returns
+pub fn generate(module: &Module, allocator: Allocator) -> String {
+    let mut out = String::from("\t.data\n");
+
+    for (id, s) in module.strings.iter().enumerate() {
+        let _ = writeln!(out, "nkt_str{id}:\t{}", string_directive(s));
+    }
+    for (func, f) in module.functions.iter().enumerate() {
+        if f.blocks.iter().any(|b| b.term == Terminator::MissingRet) {
+            let sym = fn_symbol(module, func as FnId);
+            let _ = writeln!(out, "{sym}_name:\t{}", string_directive(&f.name));
+        }
+    }
+    if !module.globals.is_empty() {
+        out.push_str("\t.align 3\n");
+    }
+    for global in 0..module.globals.len() as u32 {
+        let _ = writeln!(out, "{}:\t.space 8", global_symbol(module, global));
+    }
+
+    // MARS starts at the top of `.text`, SPIM at `main`
+    out.push_str("\n\t.text\n\t.globl main\nmain:\n");
+    entry_point(module, &mut out);
+
+    for func in 0..module.functions.len() {
+        out.push('\n');
+        isel::select(&mut FnGen::new(module, func as FnId, allocator, &mut out));
+    }
+
+    out.push('\n');
+    out.push_str(RUNTIME);
+    out
+}
+
+fn entry_point(module: &Module, out: &mut String) {
+    // argc, kept in a register the generated code never touches; the stack is 8-byte aligned
+    // for `asharia`s
+    out.push_str("\tmove $s7, $a0\n\tli $t0, -8\n\tand $sp, $sp, $t0\n");
+    let _ = writeln!(out, "\tjal {}", fn_symbol(module, module.init));
+
+    let Some(entry) = module.entry else {
+        out.push_str("\tli $v0, 10\n\tsyscall\n");
+        return;
+    };
+    let shuru = &module.functions[entry as usize];
+
+    if !shuru.params.is_empty() {
+        out.push_str("\taddiu $sp, $sp, -8\n\tsw $s7, 0($sp)\n\tsw $zero, 4($sp)\n");
+    }
+    let _ = writeln!(out, "\tjal {}", fn_symbol(module, entry));
+    match shuru.ret_type {
+        SymType::Int => out.push_str("\tmove $a0, $v0\n"),
+        _ => out.push_str("\tmove $a0, $zero\n"),
+    }
+    out.push_str("\tli $v0, 17\n\tsyscall\n");
+}
+
+// `.asciiz` if the assemblers can't misread it, bytes otherwise
+fn string_directive(s: &str) -> String {
+    if s.bytes()
+        .all(|b| matches!(b, b' '..=b'~') && b != b'"' && b != b'\\')
+    {
+        return format!(".asciiz \"{s}\"");
+    }
+
+    let bytes = s.bytes().chain([0]).map(|b| b.to_string());
+    format!(".byte {}", bytes.collect::<Vec<_>>().join(", "))
+}
+
+struct FnGen<'m, 'o> {
+    module: &'m Module,
+    f: &'m Function,
+    alloc: Allocation,
+    asm: Asm<'o>,
+}
+
+impl<'m, 'o> FnGen<'m, 'o> {
+    fn new(module: &'m Module, func: FnId, allocator: Allocator, out: &'o mut String) -> Self {
+        let f = &module.functions[func as usize];
+        FnGen {
+            module,
+            f,
+            alloc: regalloc::allocate(f, &REG_FILE, allocator),
+            asm: Asm::new(out, fn_symbol(module, func), ("", "_")),
+        }
+    }
+
+    // The offset from `$fp` of the nth slot
+    fn nth_slot(n: usize) -> i64 {
+        -8 * (n as i64 + 2)
+    }
+
+    // The offset from `$fp` of `reg`'s slot, or, for parameters, of where it was passed, if it
+    // isn't in a register
+    fn slot(&self, reg: Reg) -> Option<i64> {
+        let Loc::Slot(n) = self.alloc.loc(reg) else {
+            return None;
+        };
+        Some(match self.f.params.iter().position(|&p| p == reg) {
+            Some(i) => 8 * i as i64,
+            None => Self::nth_slot(n),
+        })
+    }
+
+    // The register `operand`, a `boli` or `jumla`, is already in, if any, or `scratch`, once
+    // it's loaded
+    fn word_operand(&mut self, operand: &Operand, scratch: &'static str) -> &'static str {
+        if let Operand::Reg(reg) = *operand {
+            if let Loc::Reg(name) = self.alloc.loc(reg) {
+                return name;
+            }
+        }
+        self.load_word(operand, scratch);
+        scratch
+    }
+
+    // Loads both words of `operand` into `lo` and `hi`
+    fn load_pair(&mut self, operand: &Operand, lo: &str, hi: &str) {
+        let bits = match *operand {
+            Operand::Reg(reg) => {
+                match (self.slot(reg), self.alloc.loc(reg), self.f.reg_type(reg)) {
+                    (Some(slot), _, _) => {
+                        self.asm.emit(&format!("lw {lo}, {slot}($fp)"));
+                        self.asm.emit(&format!("lw {hi}, {}($fp)", slot + 4));
+                    }
+                    (None, Loc::Reg(src), SymType::Float) => {
+                        self.asm.emit(&format!("mfc1 {lo}, {src}"));
+                        self.asm.emit(&format!("mfc1 {hi}, {}", odd(src)));
+                    }
+                    (None, Loc::Reg(src), _) => {
+                        self.asm.emit(&format!("move {lo}, {src}"));
+                        self.asm.emit(&format!("move {hi}, $zero"));
+                    }
+                    (None, Loc::Slot(_), _) => unreachable!("a slot w/o an offset"),
+                }
+                return;
+            }
+            Operand::Const(Const::Str(id)) => {
+                self.asm.emit(&format!("la {lo}, nkt_str{id}"));
+                self.asm.emit(&format!("move {hi}, $zero"));
+                return;
+            }
+            Operand::Const(Const::Int(i)) => i as u64,
+            Operand::Const(Const::Float(x)) => x.to_bits(),
+            Operand::Const(Const::Bool(b)) => b as u64,
+        };
+        self.asm.emit(&format!("li {lo}, {}", bits as u32 as i32));
+        self.asm
+            .emit(&format!("li {hi}, {}", (bits >> 32) as u32 as i32));
+    }
+
+    // Loads the low word of `operand`, all a `boli` or `jumla` needs
+    fn load_word(&mut self, operand: &Operand, dst: &str) {
+        match *operand {
+            Operand::Reg(reg) => match (self.slot(reg), self.alloc.loc(reg)) {
+                (Some(slot), _) => self.asm.emit(&format!("lw {dst}, {slot}($fp)")),
+                (None, Loc::Reg(src)) => self.asm.emit(&format!("move {dst}, {src}")),
+                (None, Loc::Slot(_)) => unreachable!("a slot w/o an offset"),
+            },
+            Operand::Const(Const::Str(id)) => self.asm.emit(&format!("la {dst}, nkt_str{id}")),
+            Operand::Const(Const::Bool(b)) => self.asm.emit(&format!("li {dst}, {}", b as i32)),
+            Operand::Const(c) => unreachable!("{c:?} takes up two words"),
+        }
+    }
+
+    // Loads an `asharia` into `$f<n>` (and `$f<n + 1>`)
+    fn load_double(&mut self, operand: &Operand, n: usize) {
+        match *operand {
+            Operand::Reg(reg) => match (self.slot(reg), self.alloc.loc(reg)) {
+                (Some(slot), _) => self.asm.emit(&format!("l.d $f{n}, {slot}($fp)")),
+                (None, Loc::Reg(src)) => self.asm.emit(&format!("mov.d $f{n}, {src}")),
+                (None, Loc::Slot(_)) => unreachable!("a slot w/o an offset"),
+            },
+            _ => {
+                self.load_pair(operand, "$t0", "$t1");
+                self.asm.emit(&format!("mtc1 $t0, $f{n}"));
+                self.asm.emit(&format!("mtc1 $t1, $f{}", n + 1));
+            }
+        }
+    }
+
+    // Stores `lo` and `hi` to `dst`; the high word is dropped for a `boli` or `jumla` in a
+    // register, as it's zero
+    fn store_pair(&mut self, lo: &str, hi: &str, dst: Reg) {
+        match (self.slot(dst), self.alloc.loc(dst), self.f.reg_type(dst)) {
+            (Some(slot), _, _) => {
+                self.asm.emit(&format!("sw {lo}, {slot}($fp)"));
+                self.asm.emit(&format!("sw {hi}, {}($fp)", slot + 4));
+            }
+            (None, Loc::Reg(dst), SymType::Float) => {
+                self.asm.emit(&format!("mtc1 {lo}, {dst}"));
+                self.asm.emit(&format!("mtc1 {hi}, {}", odd(dst)));
+            }
+            (None, Loc::Reg(dst), _) => self.asm.emit(&format!("move {dst}, {lo}")),
+            (None, Loc::Slot(_), _) => unreachable!("a slot w/o an offset"),
+        }
+    }
+
+    fn store_double(&mut self, n: usize, dst: Reg) {
+        match (self.slot(dst), self.alloc.loc(dst)) {
+            (Some(slot), _) => self.asm.emit(&format!("s.d $f{n}, {slot}($fp)")),
+            (None, Loc::Reg(dst)) => self.asm.emit(&format!("mov.d {dst}, $f{n}")),
+            (None, Loc::Slot(_)) => unreachable!("a slot w/o an offset"),
+        }
+    }
+
+    // Saves, or restores, the callee-saved registers the function uses, past its slots
+    fn callee_saved(&mut self, save: bool) {
+        for (k, (class, name)) in self.alloc.callee_saved.clone().into_iter().enumerate() {
+            let slot = Self::nth_slot(self.alloc.n_slots + k);
+            let op = match (class, save) {
+                (RegClass::Int, true) => "sw",
+                (RegClass::Int, false) => "lw",
+                (RegClass::Float, true) => "s.d",
+                (RegClass::Float, false) => "l.d",
+            };
+            self.asm.emit(&format!("{op} {name}, {slot}($fp)"));
+        }
+    }
+}
+
+impl<'m> Isel<'m> for FnGen<'m, '_> {
+    fn module(&self) -> &'m Module {
+        self.module
+    }
+
+    fn func(&self) -> &'m Function {
+        self.f
+    }
+
+    fn prologue(&mut self) {
+        // The return address and the caller's `$fp`, then the slots, then callee-saved registers
+        let frame = 8 * (self.alloc.n_slots + self.alloc.callee_saved.len() + 1);
+
+        let sym = self.asm.sym.clone();
+        self.asm.label(&sym);
+        self.asm.emit(&format!("addiu $sp, $sp, -{frame}"));
+        self.asm.emit(&format!("sw $ra, {}($sp)", frame - 4));
+        self.asm.emit(&format!("sw $fp, {}($sp)", frame - 8));
+        self.asm.emit(&format!("addiu $fp, $sp, {frame}"));
+        self.callee_saved(true);
+
+        // Those given a register are loaded from where they were passed
+        for (i, &param) in self.f.params.iter().enumerate() {
+            match (self.alloc.loc(param), self.f.reg_type(param)) {
+                (Loc::Reg(name), SymType::Float) => {
+                    self.asm.emit(&format!("l.d {name}, {}($fp)", 8 * i));
+                }
+                (Loc::Reg(name), _) => self.asm.emit(&format!("lw {name}, {}($fp)", 8 * i)),
+                (Loc::Slot(_), _) => {}
+            }
+        }
+    }
+
+    fn block(&mut self, id: BlockId) {
+        let label = self.asm.block_label(id);
+        self.asm.label(&label);
+    }
+
+    fn copy(&mut self, dst: Reg, src: &Operand) {
+        // Straight b/w registers of the same class, if they're in them
+        if let Operand::Reg(src) = *src {
+            let float = self.f.reg_type(src) == SymType::Float;
+            match (self.alloc.loc(dst), self.alloc.loc(src)) {
+                (Loc::Reg(d), Loc::Reg(s)) if d == s => return,
+                (Loc::Reg(d), Loc::Reg(s)) if float => {
+                    return self.asm.emit(&format!("mov.d {d}, {s}"));
+                }
+                (Loc::Reg(d), Loc::Reg(s)) => return self.asm.emit(&format!("move {d}, {s}")),
+                _ => {}
+            }
+        }
+
+        self.load_pair(src, "$t0", "$t1");
+        self.store_pair("$t0", "$t1", dst);
+    }
+
+    // W/ the operands in (a0, a1) and (a2, a3), and the result in (v0, v1)
+    fn int_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load_pair(lhs, "$a0", "$a1");
+        self.load_pair(rhs, "$a2", "$a3");
+
+        match op {
+            BinOp::Add => {
+                self.asm.emit("addu $v0, $a0, $a2");
+                self.asm.emit("sltu $t0, $v0, $a0"); // carried into the high word
+                self.asm.emit("addu $v1, $a1, $a3");
+                self.asm.emit("addu $v1, $v1, $t0");
+            }
+            BinOp::Sub => {
+                self.asm.emit("subu $v0, $a0, $a2");
+                self.asm.emit("sltu $t0, $a0, $a2"); // borrowed from the high word
+                self.asm.emit("subu $v1, $a1, $a3");
+                self.asm.emit("subu $v1, $v1, $t0");
+            }
+            // The low words' full product, plus the cross terms' low words
+            BinOp::Mul => {
+                self.asm.emit("multu $a0, $a2");
+                self.asm.emit("mflo $v0");
+                self.asm.emit("mfhi $v1");
+                self.asm.emit("mul $t0, $a0, $a3");
+                self.asm.emit("addu $v1, $v1, $t0");
+                self.asm.emit("mul $t0, $a1, $a2");
+                self.asm.emit("addu $v1, $v1, $t0");
+            }
+            BinOp::BitAnd => {
+                self.asm.emit("and $v0, $a0, $a2");
+                self.asm.emit("and $v1, $a1, $a3");
+            }
+            BinOp::BitOr => {
+                self.asm.emit("or $v0, $a0, $a2");
+                self.asm.emit("or $v1, $a1, $a3");
+            }
+
+            BinOp::Div => self.asm.emit("jal nkt_rt_div"),
+            BinOp::Mod => self.asm.emit("jal nkt_rt_mod"),
+            BinOp::Exp => self.asm.emit("jal nkt_rt_pow"),
+            BinOp::Shl => self.asm.emit("jal nkt_rt_shl"),
+            BinOp::Shr => self.asm.emit("jal nkt_rt_shr"),
+
+            // The high words decide, unless they're equal
+            BinOp::Lt | BinOp::Gt => {
+                let (l, r) = match op {
+                    BinOp::Lt => (("$a0", "$a1"), ("$a2", "$a3")),
+                    _ => (("$a2", "$a3"), ("$a0", "$a1")),
+                };
+                let (same_hi, done) = (self.asm.new_label(), self.asm.new_label());
+                self.asm.emit(&format!("beq {}, {}, {same_hi}", l.1, r.1));
+                self.asm.emit(&format!("slt $v0, {}, {}", l.1, r.1));
+                self.asm.emit(&format!("b {done}"));
+                self.asm.label(&same_hi);
+                self.asm.emit(&format!("sltu $v0, {}, {}", l.0, r.0));
+                self.asm.label(&done);
+                self.asm.emit("move $v1, $zero");
+            }
+            BinOp::Eq => {
+                self.asm.emit("xor $t0, $a0, $a2");
+                self.asm.emit("xor $t1, $a1, $a3");
+                self.asm.emit("or $t0, $t0, $t1");
+                self.asm.emit("sltiu $v0, $t0, 1");
+                self.asm.emit("move $v1, $zero");
+            }
+        }
+
+        self.store_pair("$v0", "$v1", dst);
+    }
+
+    fn float_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load_double(lhs, 12);
+        self.load_double(rhs, 14);
+
+        let cond = match op {
+            BinOp::Add => Some("add.d $f0, $f12, $f14"),
+            BinOp::Sub => Some("sub.d $f0, $f12, $f14"),
+            BinOp::Mul => Some("mul.d $f0, $f12, $f14"),
+            BinOp::Div => Some("div.d $f0, $f12, $f14"),
+            BinOp::Mod => Some("jal nkt_rt_fmod"),
+            BinOp::Exp => Some("jal nkt_rt_pow_d"),
+            _ => None,
+        };
+        if let Some(line) = cond {
+            self.asm.emit(line);
+            self.store_double(0, dst);
+            return;
+        }
+
+        // Comparisons w/ NaN are unordered, which all of these treat as false
+        match op {
+            BinOp::Lt => self.asm.emit("c.lt.d $f12, $f14"),
+            BinOp::Gt => self.asm.emit("c.lt.d $f14, $f12"),
+            BinOp::Eq => self.asm.emit("c.eq.d $f12, $f14"),
+            _ => unreachable!("`{op:?}` on floats"),
+        }
+        let done = self.asm.new_label();
+        self.asm.emit("li $v0, 1");
+        self.asm.emit(&format!("bc1t {done}"));
+        self.asm.emit("li $v0, 0");
+        self.asm.label(&done);
+        self.store_pair("$v0", "$zero", dst);
+    }
+
+    fn str_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load_word(lhs, "$a0");
+        self.load_word(rhs, "$a1");
+        self.asm.emit("jal nkt_rt_str_cmp");
+        match op {
+            BinOp::Lt => self.asm.emit("slt $v0, $v0, $zero"),
+            BinOp::Gt => self.asm.emit("slt $v0, $zero, $v0"),
+            _ => self.asm.emit("sltiu $v0, $v0, 1"),
+        }
+        self.store_pair("$v0", "$zero", dst);
+    }
+
+    fn int_neg(&mut self, dst: Reg, operand: &Operand) {
+        self.load_pair(operand, "$a0", "$a1");
+        self.asm.emit("subu $v0, $zero, $a0");
+        self.asm.emit("sltu $t0, $zero, $v0"); // borrowed from the high word
+        self.asm.emit("subu $v1, $zero, $a1");
+        self.asm.emit("subu $v1, $v1, $t0");
+        self.store_pair("$v0", "$v1", dst);
+    }
+
+    fn float_neg(&mut self, dst: Reg, operand: &Operand) {
+        self.load_double(operand, 0);
+        self.asm.emit("neg.d $f0, $f0");
+        self.store_double(0, dst);
+    }
+
+    fn not(&mut self, dst: Reg, operand: &Operand) {
+        let src = self.word_operand(operand, "$a0");
+        self.asm.emit(&format!("xori $v0, {src}, 1"));
+        self.store_pair("$v0", "$zero", dst);
+    }
+
+    fn call(&mut self, dst: Option<Reg>, callee: Callee, ret_type: SymType, args: &[Operand]) {
+        match callee {
+            // The runtime takes its arguments in registers
+            Callee::Intrinsic(i) => {
+                let intrinsic = &INTRINSICS[i as usize];
+                let mut regs = ["$a0", "$a1", "$a2", "$a3"].into_iter();
+                for arg in args {
+                    match self.f.operand_type(arg) {
+                        SymType::Int => {
+                            let (lo, hi) = (regs.next().unwrap(), regs.next().unwrap());
+                            self.load_pair(arg, lo, hi);
+                        }
+                        _ => self.load_word(arg, regs.next().unwrap()),
+                    }
+                }
+                self.asm.emit(&format!("jal nkt_rt_{}", intrinsic.name));
+            }
+
+            Callee::Fn(func) => {
+                let area = 8 * args.len();
+                if area > 0 {
+                    self.asm.emit(&format!("addiu $sp, $sp, -{area}"));
+                }
+                for (i, arg) in args.iter().enumerate() {
+                    self.load_pair(arg, "$t0", "$t1");
+                    self.asm.emit(&format!("sw $t0, {}($sp)", 8 * i));
+                    self.asm.emit(&format!("sw $t1, {}($sp)", 8 * i + 4));
+                }
+
+                self.asm
+                    .emit(&format!("jal {}", fn_symbol(self.module, func)));
+                if area > 0 {
+                    self.asm.emit(&format!("addiu $sp, $sp, {area}"));
+                }
+            }
+        }
+
+        match (dst, ret_type) {
+            (Some(dst), SymType::Float) => self.store_double(0, dst),
+            (Some(dst), SymType::Int) => self.store_pair("$v0", "$v1", dst),
+            (Some(dst), _) => self.store_pair("$v0", "$zero", dst),
+            (None, _) => {}
+        }
+    }
+
+    fn load_global(&mut self, dst: Reg, global: GlobalId) {
+        let sym = global_symbol(self.module, global);
+        self.asm.emit(&format!("la $t2, {sym}"));
+        self.asm.emit("lw $t0, 0($t2)");
+        self.asm.emit("lw $t1, 4($t2)");
+        self.store_pair("$t0", "$t1", dst);
+    }
+
+    fn store_global(&mut self, global: GlobalId, src: &Operand) {
+        self.load_pair(src, "$t0", "$t1");
+        let sym = global_symbol(self.module, global);
+        self.asm.emit(&format!("la $t2, {sym}"));
+        self.asm.emit("sw $t0, 0($t2)");
+        self.asm.emit("sw $t1, 4($t2)");
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        let label = self.asm.block_label(target);
+        self.asm.emit(&format!("b {label}"));
+    }
+
+    fn branch(&mut self, cond: &Operand, target: BlockId) {
+        let cond = self.word_operand(cond, "$t0");
+        let label = self.asm.block_label(target);
+        self.asm.emit(&format!("bnez {cond}, {label}"));
+    }
+
+    fn ret(&mut self, value: Option<&Operand>) {
+        match value {
+            Some(value) if self.f.ret_type == SymType::Float => self.load_double(value, 0),
+            Some(value) => self.load_pair(value, "$v0", "$v1"),
+            None => {}
+        }
+        self.callee_saved(false);
+        self.asm.emit("lw $ra, -4($fp)");
+        self.asm.emit("move $sp, $fp");
+        self.asm.emit("lw $fp, -8($sp)");
+        self.asm.emit("jr $ra");
+    }
+
+    fn missing_ret(&mut self) {
+        self.asm.emit(&format!("la $a0, {}_name", self.asm.sym));
+        self.asm.emit("j nkt_rt_missing_return");
+    }
+}
@@ -0,0 +1,487 @@
+# Nuktah's runtime for MIPS32, appended to every program the backend emits. `ginti`s are
+# passed in pairs of registers, the low word first: (a0, a1) and (a2, a3), and returned in
+# (v0, v1); `asharia`s in $f12 and $f14, and returned in $f0. Routines only clobber $t*, $a*,
+# $v*, $f0 to $f18, hi and lo.
+
+	.data
+nkt_rt_prefix:	.asciiz "runtime error: "
+nkt_rt_div_msg:	.asciiz "DivisionByZero"
+nkt_rt_shift_msg:	.asciiz "ShiftOutOfRange("
+nkt_rt_exp_msg:	.asciiz "NegativeExponent("
+nkt_rt_ret_msg:	.asciiz "MissingReturn(\""
+nkt_rt_ret_end:	.asciiz "\")"
+nkt_rt_paren:	.asciiz ")"
+
+	.text
+# likho(jumla s)
+nkt_rt_likho:
+	li $v0, 4
+	syscall
+	li $a0, 10
+	li $v0, 11
+	syscall
+	jr $ra
+
+# likho_ginti(ginti i)
+nkt_rt_likho_ginti:
+	addiu $sp, $sp, -8
+	sw $ra, 4($sp)
+	jal nkt_rt_print_i64
+	li $a0, 10
+	li $v0, 11
+	syscall
+	lw $ra, 4($sp)
+	addiu $sp, $sp, 8
+	jr $ra
+
+# parho() -> jumla, w/ lines of up to 1023 bytes
+nkt_rt_parho:
+	li $a0, 1024
+	li $v0, 9
+	syscall
+	move $t0, $v0
+	move $a0, $v0
+	li $a1, 1024
+	li $v0, 8
+	syscall
+	move $t1, $t0
+nkt_rt_parho_end:
+	lbu $t2, 0($t1)
+	beqz $t2, nkt_rt_parho_trim
+	addiu $t1, $t1, 1
+	b nkt_rt_parho_end
+nkt_rt_parho_trim:
+	beq $t1, $t0, nkt_rt_parho_done
+	lbu $t2, -1($t1)
+	li $t3, 10
+	beq $t2, $t3, nkt_rt_parho_cut
+	li $t3, 13
+	beq $t2, $t3, nkt_rt_parho_cut
+	b nkt_rt_parho_done
+nkt_rt_parho_cut:
+	addiu $t1, $t1, -1
+	sb $zero, 0($t1)
+	b nkt_rt_parho_trim
+nkt_rt_parho_done:
+	move $v0, $t0
+	jr $ra
+
+# Prints (a0, a1) in decimal
+nkt_rt_print_i64:
+	addiu $sp, $sp, -32
+	sw $ra, 28($sp)
+	sb $zero, 24($sp)
+	addiu $t7, $sp, 24
+	move $t8, $a1
+	bgez $a1, nkt_rt_print_digits
+	subu $a0, $zero, $a0
+	sltu $t5, $zero, $a0
+	subu $a1, $zero, $a1
+	subu $a1, $a1, $t5
+nkt_rt_print_digits:
+	li $a2, 10
+	move $a3, $zero
+	jal nkt_rt_udivmod
+	addiu $t7, $t7, -1
+	addiu $t0, $t0, 48
+	sb $t0, 0($t7)
+	or $t0, $a0, $a1
+	bnez $t0, nkt_rt_print_digits
+	bgez $t8, nkt_rt_print_str
+	addiu $t7, $t7, -1
+	li $t0, 45
+	sb $t0, 0($t7)
+nkt_rt_print_str:
+	move $a0, $t7
+	li $v0, 4
+	syscall
+	lw $ra, 28($sp)
+	addiu $sp, $sp, 32
+	jr $ra
+
+# Unsigned (a0, a1) / (a2, a3): the quotient in (a0, a1), the remainder in (t0, t1)
+nkt_rt_udivmod:
+	move $t0, $zero
+	move $t1, $zero
+	li $t2, 64
+nkt_rt_udivmod_loop:
+	srl $t4, $t1, 31
+	sll $t1, $t1, 1
+	srl $t3, $t0, 31
+	or $t1, $t1, $t3
+	sll $t0, $t0, 1
+	srl $t3, $a1, 31
+	or $t0, $t0, $t3
+	sll $a1, $a1, 1
+	srl $t3, $a0, 31
+	or $a1, $a1, $t3
+	sll $a0, $a0, 1
+	bnez $t4, nkt_rt_udivmod_sub
+	sltu $t3, $t1, $a3
+	bnez $t3, nkt_rt_udivmod_next
+	bne $t1, $a3, nkt_rt_udivmod_sub
+	sltu $t3, $t0, $a2
+	bnez $t3, nkt_rt_udivmod_next
+nkt_rt_udivmod_sub:
+	sltu $t3, $t0, $a2
+	subu $t0, $t0, $a2
+	subu $t1, $t1, $a3
+	subu $t1, $t1, $t3
+	ori $a0, $a0, 1
+nkt_rt_udivmod_next:
+	addiu $t2, $t2, -1
+	bnez $t2, nkt_rt_udivmod_loop
+	jr $ra
+
+# (a0, a1) / (a2, a3), rounding towards zero, and wrapping on `MIN / -1`
+nkt_rt_div:
+	or $t0, $a2, $a3
+	beqz $t0, nkt_rt_division_by_zero
+	and $t0, $a2, $a3
+	li $t1, -1
+	bne $t0, $t1, nkt_rt_div_signed
+	subu $v0, $zero, $a0
+	sltu $t5, $zero, $v0
+	subu $v1, $zero, $a1
+	subu $v1, $v1, $t5
+	jr $ra
+nkt_rt_div_signed:
+	addiu $sp, $sp, -8
+	sw $ra, 4($sp)
+	xor $t6, $a1, $a3
+	jal nkt_rt_abs_operands
+	jal nkt_rt_udivmod
+	move $v0, $a0
+	move $v1, $a1
+	bgez $t6, nkt_rt_div_done
+	subu $v0, $zero, $v0
+	sltu $t5, $zero, $v0
+	subu $v1, $zero, $v1
+	subu $v1, $v1, $t5
+nkt_rt_div_done:
+	lw $ra, 4($sp)
+	addiu $sp, $sp, 8
+	jr $ra
+
+# (a0, a1) % (a2, a3), w/ the sign of (a0, a1)
+nkt_rt_mod:
+	or $t0, $a2, $a3
+	beqz $t0, nkt_rt_division_by_zero
+	and $t0, $a2, $a3
+	li $t1, -1
+	bne $t0, $t1, nkt_rt_mod_signed
+	move $v0, $zero
+	move $v1, $zero
+	jr $ra
+nkt_rt_mod_signed:
+	addiu $sp, $sp, -8
+	sw $ra, 4($sp)
+	move $t6, $a1
+	jal nkt_rt_abs_operands
+	jal nkt_rt_udivmod
+	move $v0, $t0
+	move $v1, $t1
+	bgez $t6, nkt_rt_mod_done
+	subu $v0, $zero, $v0
+	sltu $t5, $zero, $v0
+	subu $v1, $zero, $v1
+	subu $v1, $v1, $t5
+nkt_rt_mod_done:
+	lw $ra, 4($sp)
+	addiu $sp, $sp, 8
+	jr $ra
+
+# Makes both (a0, a1) and (a2, a3) non-negative, as unsigned numbers
+nkt_rt_abs_operands:
+	bgez $a1, nkt_rt_abs_rhs
+	subu $a0, $zero, $a0
+	sltu $t5, $zero, $a0
+	subu $a1, $zero, $a1
+	subu $a1, $a1, $t5
+nkt_rt_abs_rhs:
+	bgez $a3, nkt_rt_abs_done
+	subu $a2, $zero, $a2
+	sltu $t5, $zero, $a2
+	subu $a3, $zero, $a3
+	subu $a3, $a3, $t5
+nkt_rt_abs_done:
+	jr $ra
+
+# (a0, a1) ^ (a2, a3), wrapping on overflow
+nkt_rt_pow:
+	bltz $a3, nkt_rt_negative_exponent
+	li $v0, 1
+	move $v1, $zero
+nkt_rt_pow_loop:
+	or $t0, $a2, $a3
+	beqz $t0, nkt_rt_pow_done
+	andi $t0, $a2, 1
+	beqz $t0, nkt_rt_pow_square
+	multu $v0, $a0
+	mflo $t1
+	mfhi $t2
+	mul $t3, $v0, $a1
+	addu $t2, $t2, $t3
+	mul $t3, $v1, $a0
+	addu $t2, $t2, $t3
+	move $v0, $t1
+	move $v1, $t2
+nkt_rt_pow_square:
+	multu $a0, $a0
+	mflo $t1
+	mfhi $t2
+	mul $t3, $a0, $a1
+	addu $t2, $t2, $t3
+	addu $t2, $t2, $t3
+	move $a0, $t1
+	move $a1, $t2
+	srl $a2, $a2, 1
+	sll $t3, $a3, 31
+	or $a2, $a2, $t3
+	srl $a3, $a3, 1
+	b nkt_rt_pow_loop
+nkt_rt_pow_done:
+	jr $ra
+
+# (a0, a1) << (a2, a3)
+nkt_rt_shl:
+	bnez $a3, nkt_rt_shift_out_of_range
+	sltiu $t0, $a2, 64
+	beqz $t0, nkt_rt_shift_out_of_range
+	move $v0, $a0
+	move $v1, $a1
+	beqz $a2, nkt_rt_shl_done
+	sltiu $t0, $a2, 32
+	beqz $t0, nkt_rt_shl_words
+	sllv $v1, $a1, $a2
+	subu $t1, $zero, $a2
+	srlv $t1, $a0, $t1
+	or $v1, $v1, $t1
+	sllv $v0, $a0, $a2
+	jr $ra
+nkt_rt_shl_words:
+	addiu $t1, $a2, -32
+	sllv $v1, $a0, $t1
+	move $v0, $zero
+nkt_rt_shl_done:
+	jr $ra
+
+# (a0, a1) >> (a2, a3), arithmetically
+nkt_rt_shr:
+	bnez $a3, nkt_rt_shift_out_of_range
+	sltiu $t0, $a2, 64
+	beqz $t0, nkt_rt_shift_out_of_range
+	move $v0, $a0
+	move $v1, $a1
+	beqz $a2, nkt_rt_shr_done
+	sltiu $t0, $a2, 32
+	beqz $t0, nkt_rt_shr_words
+	srlv $v0, $a0, $a2
+	subu $t1, $zero, $a2
+	sllv $t1, $a1, $t1
+	or $v0, $v0, $t1
+	srav $v1, $a1, $a2
+	jr $ra
+nkt_rt_shr_words:
+	addiu $t1, $a2, -32
+	srav $v0, $a1, $t1
+	sra $v1, $a1, 31
+nkt_rt_shr_done:
+	jr $ra
+
+# Compares the strings at a0 and a1, byte by byte: -1, 0 or 1 in v0
+nkt_rt_str_cmp:
+	lbu $t0, 0($a0)
+	lbu $t1, 0($a1)
+	bne $t0, $t1, nkt_rt_str_cmp_differ
+	beqz $t0, nkt_rt_str_cmp_same
+	addiu $a0, $a0, 1
+	addiu $a1, $a1, 1
+	b nkt_rt_str_cmp
+nkt_rt_str_cmp_differ:
+	sltu $t2, $t0, $t1
+	sltu $t3, $t1, $t0
+	subu $v0, $t3, $t2
+	jr $ra
+nkt_rt_str_cmp_same:
+	move $v0, $zero
+	jr $ra
+
+# fmod($f12, $f14), by subtracting the largest power-of-two multiple of |$f14| that fits, which
+# is exact, until what's left is smaller than it
+nkt_rt_fmod:
+	mtc1 $zero, $f4
+	mtc1 $zero, $f5
+	c.eq.d $f14, $f4
+	bc1t nkt_rt_fmod_nan
+	c.eq.d $f14, $f14
+	bc1f nkt_rt_fmod_nan
+	sub.d $f6, $f12, $f12
+	c.eq.d $f6, $f6
+	bc1f nkt_rt_fmod_nan
+	abs.d $f0, $f12
+	abs.d $f2, $f14
+nkt_rt_fmod_outer:
+	c.le.d $f2, $f0
+	bc1f nkt_rt_fmod_sign
+	mov.d $f6, $f2
+nkt_rt_fmod_inner:
+	add.d $f8, $f6, $f6
+	c.le.d $f8, $f0
+	bc1f nkt_rt_fmod_sub
+	mov.d $f6, $f8
+	b nkt_rt_fmod_inner
+nkt_rt_fmod_sub:
+	sub.d $f0, $f0, $f6
+	b nkt_rt_fmod_outer
+nkt_rt_fmod_sign:
+	c.lt.d $f12, $f4
+	bc1f nkt_rt_fmod_done
+	neg.d $f0, $f0
+nkt_rt_fmod_done:
+	jr $ra
+nkt_rt_fmod_nan:
+	div.d $f0, $f4, $f4
+	jr $ra
+
+# $f12 ^ $f14 (w/ its sign in t7). The whole part of the exponent is raised to by squaring, and the fraction, bit by
+# bit, w/ square roots; exponents of 2^31 or more are only accurate enough to be 0, 1 or inf.
+nkt_rt_pow_d:
+	addiu $sp, $sp, -8
+	sw $ra, 4($sp)
+	c.eq.d $f14, $f14
+	bc1f nkt_rt_pow_d_nan
+	mfc1 $t7, $f15
+	abs.d $f16, $f14
+	li $t0, 0x41e00000
+	mtc1 $zero, $f4
+	mtc1 $t0, $f5
+	c.lt.d $f16, $f4
+	bc1f nkt_rt_pow_d_huge
+	trunc.w.d $f6, $f16
+	mfc1 $t6, $f6
+	cvt.d.w $f6, $f6
+	sub.d $f18, $f16, $f6
+	b nkt_rt_pow_d_whole
+nkt_rt_pow_d_huge:
+	mov.d $f10, $f12
+	mov.d $f12, $f16
+	li $t0, 0x40000000
+	mtc1 $zero, $f14
+	mtc1 $t0, $f15
+	jal nkt_rt_fmod
+	mov.d $f12, $f10
+	li $t6, 0x7ffffffe
+	mtc1 $zero, $f4
+	mtc1 $zero, $f5
+	c.eq.d $f0, $f4
+	bc1t nkt_rt_pow_d_even
+	ori $t6, $t6, 1
+nkt_rt_pow_d_even:
+	mtc1 $zero, $f18
+	mtc1 $zero, $f19
+nkt_rt_pow_d_whole:
+	li $t0, 0x3ff00000
+	mtc1 $zero, $f0
+	mtc1 $t0, $f1
+	mov.d $f2, $f12
+nkt_rt_pow_d_loop:
+	beqz $t6, nkt_rt_pow_d_fraction
+	andi $t0, $t6, 1
+	beqz $t0, nkt_rt_pow_d_square
+	mul.d $f0, $f0, $f2
+nkt_rt_pow_d_square:
+	mul.d $f2, $f2, $f2
+	srl $t6, $t6, 1
+	b nkt_rt_pow_d_loop
+nkt_rt_pow_d_fraction:
+	mtc1 $zero, $f4
+	mtc1 $zero, $f5
+	c.eq.d $f18, $f4
+	bc1t nkt_rt_pow_d_sign
+	li $t0, 0x3ff00000
+	mtc1 $zero, $f8
+	mtc1 $t0, $f9
+	mov.d $f2, $f12
+	li $t1, 52
+nkt_rt_pow_d_bit:
+	beqz $t1, nkt_rt_pow_d_sign
+	addiu $t1, $t1, -1
+	sqrt.d $f2, $f2
+	add.d $f18, $f18, $f18
+	c.le.d $f8, $f18
+	bc1f nkt_rt_pow_d_bit
+	mul.d $f0, $f0, $f2
+	sub.d $f18, $f18, $f8
+	b nkt_rt_pow_d_bit
+nkt_rt_pow_d_sign:
+	bgez $t7, nkt_rt_pow_d_done
+	li $t0, 0x3ff00000
+	mtc1 $zero, $f8
+	mtc1 $t0, $f9
+	div.d $f0, $f8, $f0
+	b nkt_rt_pow_d_done
+nkt_rt_pow_d_nan:
+	mov.d $f0, $f14
+nkt_rt_pow_d_done:
+	lw $ra, 4($sp)
+	addiu $sp, $sp, 8
+	jr $ra
+
+nkt_rt_division_by_zero:
+	jal nkt_rt_error_prefix
+	la $a0, nkt_rt_div_msg
+	li $v0, 4
+	syscall
+	b nkt_rt_fail
+
+# The amount is in (a2, a3)
+nkt_rt_shift_out_of_range:
+	la $t9, nkt_rt_shift_msg
+	b nkt_rt_fail_w_operand
+# The exponent is in (a2, a3)
+nkt_rt_negative_exponent:
+	la $t9, nkt_rt_exp_msg
+nkt_rt_fail_w_operand:
+	move $s0, $a2
+	move $s1, $a3
+	jal nkt_rt_error_prefix
+	move $a0, $t9
+	li $v0, 4
+	syscall
+	move $a0, $s0
+	move $a1, $s1
+	jal nkt_rt_print_i64
+	la $a0, nkt_rt_paren
+	li $v0, 4
+	syscall
+	b nkt_rt_fail
+
+# The function's name is at a0
+nkt_rt_missing_return:
+	move $s0, $a0
+	jal nkt_rt_error_prefix
+	la $a0, nkt_rt_ret_msg
+	li $v0, 4
+	syscall
+	move $a0, $s0
+	li $v0, 4
+	syscall
+	la $a0, nkt_rt_ret_end
+	li $v0, 4
+	syscall
+	b nkt_rt_fail
+
+nkt_rt_error_prefix:
+	la $a0, nkt_rt_prefix
+	li $v0, 4
+	syscall
+	jr $ra
+
+nkt_rt_fail:
+	li $a0, 10
+	li $v0, 11
+	syscall
+	li $a0, 1
+	li $v0, 17
+	syscall
@@ -0,0 +1,587 @@
+//! A simulator for the subset of MIPS32 the backend emits, in SPIM's syntax, w/ the syscalls it
+//! uses, so that programs can be run, and the backend tested, w/o SPIM or MARS. Like them, it
+//! runs the assembly itself: pseudo-instructions are executed as one instruction, and branches
+//! aren't delayed.
+//!
+//! Memory is laid out as in MARS: static data, then the heap `sbrk` grows, from `0x10010000`,
+//! and the stack down from `0x7fffeffc`. `main` is called w/ `argc` in `$a0`.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+
+use crate::codegen::asm::{self, parse_int, split_mnemonic, Line, SyntaxError};
+
+const TEXT_BASE: u32 = 0x0040_0000;
+const DATA_BASE: u32 = 0x1001_0000;
+const STACK_TOP: u32 = 0x7fff_effc;
+const STACK_SIZE: u32 = 8 << 20;
+
+#[derive(Debug, PartialEq)]
+pub enum SimError {
+    Syntax(usize, String), // the line, and what's wrong w/ it
+    UnknownLabel(String),
+    NoMain,
+    BadAddress(u32), // outside of the data, the heap and the stack, or of the text for jumps
+    Unaligned(u32),
+    UnknownSyscall(u32),
+    Io(io::ErrorKind),
+}
+
+impl From<SyntaxError> for SimError {
+    fn from(SyntaxError(no, msg): SyntaxError) -> Self {
+        SimError::Syntax(no, msg)
+    }
+}
+
+/// Runs `asm` until it exits, giving back its exit code
+pub fn run(asm: &str, args: &[String]) -> Result<i32, SimError> {
+    let mut input = BufReader::new(io::stdin());
+    run_with_io(asm, args, &mut input, &mut io::stdout())
+}
+
+/// Like `run`, but w/ syscalls reading from `input` and writing to `output`
+pub fn run_with_io(
+    asm: &str,
+    args: &[String],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<i32, SimError> {
+    let program = asm::assemble(asm, TEXT_BASE.into(), DATA_BASE.into(), instruction)?;
+    let main = *program.labels.get("main").ok_or(SimError::NoMain)? as u32;
+
+    let mut cpu = Cpu {
+        regs: [0; 32],
+        fregs: [0; 32],
+        fcc: false,
+        hi: 0,
+        lo: 0,
+        pc: main,
+        data: program.data,
+        stack: vec![0; STACK_SIZE as usize],
+        input,
+        output,
+        pending: vec![],
+    };
+    cpu.regs[SP as usize] = STACK_TOP;
+    cpu.regs[A0 as usize] = args.len() as u32;
+
+    let res = cpu.run(&program.text);
+    cpu.output.flush().map_err(|e| SimError::Io(e.kind()))?;
+    res
+}
+
+const A0: u8 = 4;
+const A1: u8 = 5;
+const V0: u8 = 2;
+const SP: u8 = 29;
+const RA: u8 = 31;
+
+#[derive(Debug, Clone, Copy)]
+enum AluOp {
+    Addu,
+    Subu,
+    And,
+    Or,
+    Xor,
+    Nor,
+    Slt,
+    Sltu,
+    Sllv,
+    Srlv,
+    Srav,
+    Mul,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Width {
+    Byte,
+    ByteUnsigned,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cond {
+    Always,
+    Eq,
+    Ne,
+    Ltz,
+    Gez,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FpuOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    Abs,
+    Sqrt,
+    Mov,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FCmp {
+    Lt,
+    Le,
+    Eq,
+}
+
+// Registers are numbers, and labels addresses, by the time instructions are executed
+#[derive(Debug, Clone, Copy)]
+enum Instr {
+    Alu(AluOp, u8, u8, u8),     // rd, rs, rt
+    AluImm(AluOp, u8, u8, u32), // rt, rs, and the immediate, extended as the opcode says
+    Li(u8, u32),
+    Mult(bool, u8, u8), // signed?
+    Mfhi(u8),
+    Mflo(u8),
+    Load(Width, u8, u8, i32), // rt, base, offset
+    Store(Width, u8, u8, i32),
+    LoadD(u8, u8, i32), // ft, base, offset
+    StoreD(u8, u8, i32),
+    Branch(Cond, u8, u8, u32), // rs, rt, target
+    Jump(u32, bool),           // link?
+    Jr(u8),
+    Syscall,
+    Fpu(FpuOp, u8, u8, u8), // fd, fs, ft
+    FCmp(FCmp, u8, u8),
+    Bc1(bool, u32),
+    Mtc1(u8, u8), // rt, fs
+    Mfc1(u8, u8),
+    CvtDW(u8, u8), // fd, fs
+    TruncWD(u8, u8),
+}
+
+fn reg(name: &str) -> Option<u8> {
+    const NAMES: [&str; 32] = [
+        "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+        "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp",
+        "fp", "ra",
+    ];
+
+    let name = name.strip_prefix('$')?;
+    match name.parse::<u8>() {
+        Ok(n) if n < 32 => Some(n),
+        Ok(_) => None,
+        Err(_) => NAMES.iter().position(|&n| n == name).map(|n| n as u8),
+    }
+}
+
+fn freg(name: &str) -> Option<u8> {
+    let n = name.strip_prefix("$f")?.parse::<u8>().ok()?;
+    (n < 32).then_some(n)
+}
+
+fn instruction(line: &Line, labels: &HashMap<String, u64>) -> Result<Instr, SimError> {
+    let (mnemonic, operands) = split_mnemonic(line.text);
+    let ops = match operands.is_empty() {
+        true => vec![],
+        false => operands.split(',').map(str::trim).collect::<Vec<_>>(),
+    };
+
+    let err = |msg: &str| SimError::Syntax(line.no, format!("{msg}: `{}`", line.text));
+    let op = |i: usize| ops.get(i).copied().ok_or_else(|| err("missing operand"));
+    let r = |i: usize| op(i).and_then(|o| reg(o).ok_or_else(|| err("expected a register")));
+    let f = |i: usize| op(i).and_then(|o| freg(o).ok_or_else(|| err("expected an FPU register")));
+    let imm =
+        |i: usize| op(i).and_then(|o| parse_int(o).ok_or_else(|| err("expected an immediate")));
+    let label = |i: usize| {
+        op(i).and_then(|o| {
+            labels
+                .get(o)
+                .map(|&addr| addr as u32)
+                .ok_or_else(|| SimError::UnknownLabel(o.to_string()))
+        })
+    };
+    // `offset(base)`
+    let mem = |i: usize| {
+        let o = op(i)?;
+        let (offset, base) = o
+            .strip_suffix(')')
+            .and_then(|o| o.split_once('('))
+            .ok_or_else(|| err("expected `offset(base)`"))?;
+        let offset = match offset {
+            "" => 0,
+            offset => parse_int(offset).ok_or_else(|| err("bad offset"))? as i32,
+        };
+        Ok((reg(base).ok_or_else(|| err("expected a register"))?, offset))
+    };
+
+    let alu = |op| Ok(Instr::Alu(op, r(0)?, r(1)?, r(2)?));
+    let alu_imm = |op| Ok(Instr::AluImm(op, r(0)?, r(1)?, imm(2)? as u32));
+    let load =
+        |width| mem(1).and_then(|(base, offset)| Ok(Instr::Load(width, r(0)?, base, offset)));
+    let store =
+        |width| mem(1).and_then(|(base, offset)| Ok(Instr::Store(width, r(0)?, base, offset)));
+    let fpu = |op| Ok(Instr::Fpu(op, f(0)?, f(1)?, f(2)?));
+    let fpu_unary = |op| Ok(Instr::Fpu(op, f(0)?, f(1)?, 0));
+
+    match mnemonic {
+        "addu" => alu(AluOp::Addu),
+        "subu" => alu(AluOp::Subu),
+        "and" => alu(AluOp::And),
+        "or" => alu(AluOp::Or),
+        "xor" => alu(AluOp::Xor),
+        "nor" => alu(AluOp::Nor),
+        "slt" => alu(AluOp::Slt),
+        "sltu" => alu(AluOp::Sltu),
+        "sllv" => alu(AluOp::Sllv),
+        "srlv" => alu(AluOp::Srlv),
+        "srav" => alu(AluOp::Srav),
+        "mul" => alu(AluOp::Mul),
+
+        "addiu" => alu_imm(AluOp::Addu),
+        "andi" => Ok(Instr::AluImm(
+            AluOp::And,
+            r(0)?,
+            r(1)?,
+            imm(2)? as u32 & 0xffff,
+        )),
+        "ori" => Ok(Instr::AluImm(
+            AluOp::Or,
+            r(0)?,
+            r(1)?,
+            imm(2)? as u32 & 0xffff,
+        )),
+        "xori" => Ok(Instr::AluImm(
+            AluOp::Xor,
+            r(0)?,
+            r(1)?,
+            imm(2)? as u32 & 0xffff,
+        )),
+        "slti" => alu_imm(AluOp::Slt),
+        "sltiu" => alu_imm(AluOp::Sltu),
+        "sll" => alu_imm(AluOp::Sllv),
+        "srl" => alu_imm(AluOp::Srlv),
+        "sra" => alu_imm(AluOp::Srav),
+
+        "li" => Ok(Instr::Li(r(0)?, imm(1)? as u32)),
+        "la" => Ok(Instr::Li(r(0)?, label(1)?)),
+        "move" => Ok(Instr::Alu(AluOp::Addu, r(0)?, r(1)?, 0)),
+
+        "mult" => Ok(Instr::Mult(true, r(0)?, r(1)?)),
+        "multu" => Ok(Instr::Mult(false, r(0)?, r(1)?)),
+        "mfhi" => Ok(Instr::Mfhi(r(0)?)),
+        "mflo" => Ok(Instr::Mflo(r(0)?)),
+
+        "lb" => load(Width::Byte),
+        "lbu" => load(Width::ByteUnsigned),
+        "lw" => load(Width::Word),
+        "sb" => store(Width::Byte),
+        "sw" => store(Width::Word),
+        "l.d" | "ldc1" => mem(1).and_then(|(base, offset)| Ok(Instr::LoadD(f(0)?, base, offset))),
+        "s.d" | "sdc1" => mem(1).and_then(|(base, offset)| Ok(Instr::StoreD(f(0)?, base, offset))),
+
+        "b" => Ok(Instr::Branch(Cond::Always, 0, 0, label(0)?)),
+        "beq" => Ok(Instr::Branch(Cond::Eq, r(0)?, r(1)?, label(2)?)),
+        "bne" => Ok(Instr::Branch(Cond::Ne, r(0)?, r(1)?, label(2)?)),
+        "beqz" => Ok(Instr::Branch(Cond::Eq, r(0)?, 0, label(1)?)),
+        "bnez" => Ok(Instr::Branch(Cond::Ne, r(0)?, 0, label(1)?)),
+        "bltz" => Ok(Instr::Branch(Cond::Ltz, r(0)?, 0, label(1)?)),
+        "bgez" => Ok(Instr::Branch(Cond::Gez, r(0)?, 0, label(1)?)),
+        "j" => Ok(Instr::Jump(label(0)?, false)),
+        "jal" => Ok(Instr::Jump(label(0)?, true)),
+        "jr" => Ok(Instr::Jr(r(0)?)),
+        "syscall" => Ok(Instr::Syscall),
+
+        "add.d" => fpu(FpuOp::Add),
+        "sub.d" => fpu(FpuOp::Sub),
+        "mul.d" => fpu(FpuOp::Mul),
+        "div.d" => fpu(FpuOp::Div),
+        "neg.d" => fpu_unary(FpuOp::Neg),
+        "abs.d" => fpu_unary(FpuOp::Abs),
+        "sqrt.d" => fpu_unary(FpuOp::Sqrt),
+        "mov.d" => fpu_unary(FpuOp::Mov),
+        "c.lt.d" => Ok(Instr::FCmp(FCmp::Lt, f(0)?, f(1)?)),
+        "c.le.d" => Ok(Instr::FCmp(FCmp::Le, f(0)?, f(1)?)),
+        "c.eq.d" => Ok(Instr::FCmp(FCmp::Eq, f(0)?, f(1)?)),
+        "bc1t" => Ok(Instr::Bc1(true, label(0)?)),
+        "bc1f" => Ok(Instr::Bc1(false, label(0)?)),
+        "mtc1" => Ok(Instr::Mtc1(r(0)?, f(1)?)),
+        "mfc1" => Ok(Instr::Mfc1(r(0)?, f(1)?)),
+        "cvt.d.w" => Ok(Instr::CvtDW(f(0)?, f(1)?)),
+        "trunc.w.d" => Ok(Instr::TruncWD(f(0)?, f(1)?)),
+
+        _ => Err(err("unknown instruction")),
+    }
+}
+
+struct Cpu<'io> {
+    regs: [u32; 32],
+    fregs: [u32; 32], // a double in an even register, and the one after it
+    fcc: bool,
+    hi: u32,
+    lo: u32,
+    pc: u32,
+    data: Vec<u8>, // static data, then the heap
+    stack: Vec<u8>,
+    input: &'io mut dyn BufRead,
+    output: &'io mut dyn Write,
+    pending: Vec<u8>, // input read, but not yet by the program
+}
+
+impl Cpu<'_> {
+    fn run(&mut self, text: &[Instr]) -> Result<i32, SimError> {
+        loop {
+            let i = self.pc.wrapping_sub(TEXT_BASE) / 4;
+            let Some(&instr) = self
+                .pc
+                .is_multiple_of(4)
+                .then(|| text.get(i as usize))
+                .flatten()
+            else {
+                return Err(SimError::BadAddress(self.pc));
+            };
+            self.pc += 4;
+
+            if let Some(code) = self.step(instr)? {
+                return Ok(code);
+            }
+        }
+    }
+
+    fn set(&mut self, r: u8, value: u32) {
+        if r != 0 {
+            self.regs[r as usize] = value;
+        }
+    }
+
+    fn double(&self, f: u8) -> f64 {
+        let (lo, hi) = (self.fregs[f as usize], self.fregs[f as usize + 1]);
+        f64::from_bits((hi as u64) << 32 | lo as u64)
+    }
+
+    fn set_double(&mut self, f: u8, x: f64) {
+        let bits = x.to_bits();
+        self.fregs[f as usize] = bits as u32;
+        self.fregs[f as usize + 1] = (bits >> 32) as u32;
+    }
+
+    // Executes an instruction, giving back the exit code if it exits
+    fn step(&mut self, instr: Instr) -> Result<Option<i32>, SimError> {
+        let r = |cpu: &Self, r: u8| cpu.regs[r as usize];
+
+        match instr {
+            Instr::Alu(op, rd, rs, rt) => {
+                let value = alu(op, r(self, rs), r(self, rt));
+                self.set(rd, value);
+            }
+            Instr::AluImm(op, rt, rs, imm) => {
+                let value = alu(op, r(self, rs), imm);
+                self.set(rt, value);
+            }
+            Instr::Li(rd, imm) => self.set(rd, imm),
+
+            Instr::Mult(signed, rs, rt) => {
+                let (a, b) = (r(self, rs), r(self, rt));
+                let product = match signed {
+                    true => (a as i32 as i64).wrapping_mul(b as i32 as i64) as u64,
+                    false => a as u64 * b as u64,
+                };
+                self.hi = (product >> 32) as u32;
+                self.lo = product as u32;
+            }
+            Instr::Mfhi(rd) => self.set(rd, self.hi),
+            Instr::Mflo(rd) => self.set(rd, self.lo),
+
+            Instr::Load(width, rt, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u32);
+                let value = match width {
+                    Width::Byte => self.read(addr, 1)?[0] as i8 as u32,
+                    Width::ByteUnsigned => self.read(addr, 1)?[0] as u32,
+                    Width::Word => u32::from_le_bytes(self.read(addr, 4)?.try_into().unwrap()),
+                };
+                self.set(rt, value);
+            }
+            Instr::Store(width, rt, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u32);
+                let value = r(self, rt);
+                match width {
+                    Width::Word => self.write(addr, &value.to_le_bytes())?,
+                    _ => self.write(addr, &[value as u8])?,
+                }
+            }
+            Instr::LoadD(ft, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u32);
+                let bits = u64::from_le_bytes(self.read(addr, 8)?.try_into().unwrap());
+                self.set_double(ft, f64::from_bits(bits));
+            }
+            Instr::StoreD(ft, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u32);
+                let bits = self.double(ft).to_bits();
+                self.write(addr, &bits.to_le_bytes())?;
+            }
+
+            Instr::Branch(cond, rs, rt, target) => {
+                let (a, b) = (r(self, rs), r(self, rt));
+                let taken = match cond {
+                    Cond::Always => true,
+                    Cond::Eq => a == b,
+                    Cond::Ne => a != b,
+                    Cond::Ltz => (a as i32) < 0,
+                    Cond::Gez => (a as i32) >= 0,
+                };
+                if taken {
+                    self.pc = target;
+                }
+            }
+            Instr::Jump(target, link) => {
+                if link {
+                    self.set(RA, self.pc);
+                }
+                self.pc = target;
+            }
+            Instr::Jr(rs) => self.pc = r(self, rs),
+            Instr::Syscall => return self.syscall(),
+
+            Instr::Fpu(op, fd, fs, ft) => {
+                let (a, b) = (self.double(fs), self.double(ft));
+                let x = match op {
+                    FpuOp::Add => a + b,
+                    FpuOp::Sub => a - b,
+                    FpuOp::Mul => a * b,
+                    FpuOp::Div => a / b,
+                    FpuOp::Neg => -a,
+                    FpuOp::Abs => a.abs(),
+                    FpuOp::Sqrt => a.sqrt(),
+                    FpuOp::Mov => a,
+                };
+                self.set_double(fd, x);
+            }
+            Instr::FCmp(op, fs, ft) => {
+                let (a, b) = (self.double(fs), self.double(ft));
+                self.fcc = match op {
+                    FCmp::Lt => a < b,
+                    FCmp::Le => a <= b,
+                    FCmp::Eq => a == b,
+                };
+            }
+            Instr::Bc1(on, target) => {
+                if self.fcc == on {
+                    self.pc = target;
+                }
+            }
+            Instr::Mtc1(rt, fs) => self.fregs[fs as usize] = r(self, rt),
+            Instr::Mfc1(rt, fs) => self.set(rt, self.fregs[fs as usize]),
+            Instr::CvtDW(fd, fs) => self.set_double(fd, self.fregs[fs as usize] as i32 as f64),
+            Instr::TruncWD(fd, fs) => {
+                // Out of range, or NaN, is the "invalid" result
+                let x = self.double(fs).trunc();
+                let w = match x >= i32::MIN as f64 && x <= i32::MAX as f64 {
+                    true => x as i32,
+                    false => i32::MAX,
+                };
+                self.fregs[fd as usize] = w as u32;
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn syscall(&mut self) -> Result<Option<i32>, SimError> {
+        let (a0, a1) = (self.regs[A0 as usize], self.regs[A1 as usize]);
+        let io = |e: io::Error| SimError::Io(e.kind());
+
+        match self.regs[V0 as usize] {
+            // print_int
+            1 => write!(self.output, "{}", a0 as i32).map_err(io)?,
+            // print_string
+            4 => {
+                let mut s = vec![];
+                let mut addr = a0;
+                loop {
+                    let b = self.read(addr, 1)?[0];
+                    if b == 0 {
+                        break;
+                    }
+                    s.push(b);
+                    addr += 1;
+                }
+                self.output.write_all(&s).map_err(io)?;
+            }
+            // read_string, like `fgets`: at most a1 - 1 bytes, up to (and including) a newline
+            8 => {
+                if self.pending.is_empty() {
+                    self.input
+                        .read_until(b'\n', &mut self.pending)
+                        .map_err(io)?;
+                }
+                let n = self.pending.len().min(a1.saturating_sub(1) as usize);
+                let mut line = self.pending.drain(..n).collect::<Vec<_>>();
+                line.push(0);
+                self.write(a0, &line)?;
+            }
+            // sbrk, 8-byte aligned
+            9 => {
+                let start = self.data.len().next_multiple_of(8);
+                self.data.resize(start + a0 as usize, 0);
+                self.set(V0, DATA_BASE + start as u32);
+            }
+            // exit
+            10 => return Ok(Some(0)),
+            // print_char
+            11 => self.output.write_all(&[a0 as u8]).map_err(io)?,
+            // exit2
+            17 => return Ok(Some(a0 as i32)),
+            n => return Err(SimError::UnknownSyscall(n)),
+        }
+
+        Ok(None)
+    }
+
+    // The memory `addr` refers to, as a segment and an index into it
+    fn segment(&mut self, addr: u32, len: u32) -> Result<(&mut Vec<u8>, usize), SimError> {
+        if !addr.is_multiple_of(len.min(8)) {
+            return Err(SimError::Unaligned(addr));
+        }
+
+        let end = addr.checked_add(len).ok_or(SimError::BadAddress(addr))?;
+        let stack_bottom = STACK_TOP + 4 - STACK_SIZE;
+
+        if addr >= DATA_BASE && end <= DATA_BASE + self.data.len() as u32 {
+            Ok((&mut self.data, (addr - DATA_BASE) as usize))
+        } else if addr >= stack_bottom && end <= STACK_TOP + 4 {
+            Ok((&mut self.stack, (addr - stack_bottom) as usize))
+        } else {
+            Err(SimError::BadAddress(addr))
+        }
+    }
+
+    fn read(&mut self, addr: u32, len: u32) -> Result<&[u8], SimError> {
+        let (segment, i) = self.segment(addr, len)?;
+        Ok(&segment[i..i + len as usize])
+    }
+
+    fn write(&mut self, addr: u32, bytes: &[u8]) -> Result<(), SimError> {
+        // Strings are written byte by byte, so needn't be aligned
+        if bytes.len() != 4 && bytes.len() != 8 {
+            for (i, b) in bytes.iter().enumerate() {
+                let (segment, j) = self.segment(addr + i as u32, 1)?;
+                segment[j] = *b;
+            }
+            return Ok(());
+        }
+
+        let (segment, i) = self.segment(addr, bytes.len() as u32)?;
+        segment[i..i + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+fn alu(op: AluOp, a: u32, b: u32) -> u32 {
+    match op {
+        AluOp::Addu => a.wrapping_add(b),
+        AluOp::Subu => a.wrapping_sub(b),
+        AluOp::And => a & b,
+        AluOp::Or => a | b,
+        AluOp::Xor => a ^ b,
+        AluOp::Nor => !(a | b),
+        AluOp::Slt => ((a as i32) < (b as i32)) as u32,
+        AluOp::Sltu => (a < b) as u32,
+        AluOp::Sllv => a << (b & 31),
+        AluOp::Srlv => a >> (b & 31),
+        AluOp::Srav => ((a as i32) >> (b & 31)) as u32,
+        AluOp::Mul => (a as i32).wrapping_mul(b as i32) as u32,
+    }
+}
@@ -0,0 +1,3 @@
+pub mod colouring;
+pub mod core;
+pub mod linear_scan;
@@ -0,0 +1,84 @@
+//! Graph colouring allocation, after Chaitin & Briggs: registers that are live at once interfere,
+//! and each is coloured w/ a register none of those it interferes w/ got. Those w/ fewer
+//! neighbours than registers they may take always can be, so they're set aside first; when none
+//! are left, the cheapest to spill is, optimistically, in case its neighbours share colours.
+
+use std::collections::BTreeSet;
+
+use crate::ir::core::{Function, Reg};
+
+use super::core::{Allocation, Lifetimes, RegFile};
+
+pub(super) fn allocate(f: &Function, file: &RegFile, lifetimes: &Lifetimes) -> Allocation {
+    let mut alloc = Allocation::new(f);
+    let n_regs = f.reg_types.len();
+
+    // Registers that are used, w/ those they may be coloured w/
+    let mut allowed: Vec<Option<Vec<&'static str>>> = vec![None; n_regs];
+    for reg in (0..n_regs as u32).map(Reg) {
+        if lifetimes.intervals[reg.0 as usize].is_none() {
+            continue;
+        }
+        match (file.class)(f.reg_type(reg)) {
+            Some(class) => {
+                let across_call = lifetimes.across_call[reg.0 as usize];
+                allowed[reg.0 as usize] = Some(file.candidates(class, across_call));
+            }
+            None => alloc.spill(reg),
+        }
+    }
+
+    // Only those of the same class compete for registers
+    let mut neighbours = vec![BTreeSet::new(); n_regs];
+    for &(a, b) in &lifetimes.interferences {
+        let (a, b) = (a.0 as usize, b.0 as usize);
+        let same_class = (file.class)(f.reg_types[a]) == (file.class)(f.reg_types[b]) && a != b;
+        if same_class && allowed[a].is_some() && allowed[b].is_some() {
+            neighbours[a].insert(b);
+            neighbours[b].insert(a);
+        }
+    }
+
+    // Simplify, until the graph's empty
+    let mut remaining: BTreeSet<usize> = (0..n_regs).filter(|&r| allowed[r].is_some()).collect();
+    let mut stack = vec![];
+    while !remaining.is_empty() {
+        let degree = |r: usize| neighbours[r].intersection(&remaining).count();
+        let k = |r: usize| allowed[r].as_ref().map_or(0, Vec::len);
+
+        let trivial = remaining.iter().copied().find(|&r| degree(r) < k(r));
+        let node = trivial.unwrap_or_else(|| {
+            let cost = |r: usize| lifetimes.uses[r] as f64 / degree(r).max(1) as f64;
+            remaining
+                .iter()
+                .copied()
+                .min_by(|&a, &b| cost(a).total_cmp(&cost(b)))
+                .expect("a register left to simplify")
+        });
+
+        remaining.remove(&node);
+        stack.push(node);
+    }
+
+    // Select, in the reverse order they were set aside in
+    let mut colours: Vec<Option<&'static str>> = vec![None; n_regs];
+    while let Some(node) = stack.pop() {
+        let taken: BTreeSet<_> = neighbours[node]
+            .iter()
+            .filter_map(|&n| colours[n])
+            .collect();
+        let allowed = allowed[node]
+            .as_ref()
+            .expect("a register that may be coloured");
+
+        match allowed.iter().find(|name| !taken.contains(*name)) {
+            Some(&name) => {
+                colours[node] = Some(name);
+                alloc.assign(Reg(node as u32), name, file);
+            }
+            None => alloc.spill(Reg(node as u32)),
+        }
+    }
+
+    alloc
+}
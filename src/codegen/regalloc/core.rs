@@ -0,0 +1,263 @@
+//! What register allocators share: the description of a target's registers they're
+//! parameterised by, where they put each of a function's registers, and the lifetimes they
+//! decide that from. Functions must be out of SSA form.
+
+use crate::ir::cfg::Cfg;
+use crate::ir::core::{BinOp, BlockId, Function, Inst, Operand, Reg};
+use crate::ir::live::Liveness;
+use crate::opt::core::OptLevel;
+use crate::semantics::spaghetti::SymType;
+
+use super::{colouring, linear_scan};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegClass {
+    Int,
+    Float,
+}
+
+/// One class of a target's registers: those an allocator may hand out, i.e none that
+/// instruction selection loads operands into, or passes arguments in
+#[derive(Debug)]
+pub struct Regs {
+    pub caller_saved: &'static [&'static str],
+    pub callee_saved: &'static [&'static str],
+}
+
+/// A target's registers, as allocators see them
+#[derive(Debug)]
+pub struct RegFile {
+    pub int: Regs,
+    pub float: Regs,
+    /// The class of register a value of a type fits in, if any does
+    pub class: fn(SymType) -> Option<RegClass>,
+    /// Whether the target's code for an instruction calls out, clobbering caller-saved
+    /// registers. Values live across such an instruction only ever get callee-saved ones.
+    pub clobbers: fn(&Function, &Inst) -> bool,
+}
+
+impl RegFile {
+    pub fn regs(&self, class: RegClass) -> &Regs {
+        match class {
+            RegClass::Int => &self.int,
+            RegClass::Float => &self.float,
+        }
+    }
+
+    /// The registers a value may get, in order of preference: caller-saved ones first, as they
+    /// needn't be saved, unless it's live across a call
+    pub(super) fn candidates(&self, class: RegClass, across_call: bool) -> Vec<&'static str> {
+        let regs = self.regs(class);
+        match across_call {
+            true => regs.callee_saved.to_vec(),
+            false => [regs.caller_saved, regs.callee_saved].concat(),
+        }
+    }
+}
+
+/// Whether the instruction is a call, or an operator `calls_out` says is lowered to one
+pub fn calls_out(f: &Function, inst: &Inst, calls_out: fn(BinOp, SymType) -> bool) -> bool {
+    match inst {
+        Inst::Call { .. } => true,
+        Inst::Binary { op, lhs, .. } => calls_out(*op, f.operand_type(lhs)),
+        _ => false,
+    }
+}
+
+/// Where a register's value is kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loc {
+    Reg(&'static str),
+    Slot(usize), // the nth of the function's stack slots
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Allocator {
+    LinearScan,
+    GraphColouring,
+}
+
+impl Allocator {
+    /// Graph colouring, at `-O2`, for the time it takes; linear scan otherwise
+    pub fn for_level(level: OptLevel) -> Self {
+        match level {
+            OptLevel::O2 => Allocator::GraphColouring,
+            _ => Allocator::LinearScan,
+        }
+    }
+}
+
+/// Where each of a function's registers is kept, and what of the target's the function uses
+#[derive(Debug)]
+pub struct Allocation {
+    locs: Vec<Option<Loc>>, // None for registers that are never used
+    pub n_slots: usize,
+    /// The callee-saved registers handed out, which the function must save and restore
+    pub callee_saved: Vec<(RegClass, &'static str)>,
+}
+
+impl Allocation {
+    pub fn loc(&self, reg: Reg) -> Loc {
+        self.locs[reg.0 as usize].expect("a location for a register that's never used")
+    }
+
+    pub(super) fn new(f: &Function) -> Self {
+        Allocation {
+            locs: vec![None; f.reg_types.len()],
+            n_slots: 0,
+            callee_saved: vec![],
+        }
+    }
+
+    pub(super) fn assign(&mut self, reg: Reg, name: &'static str, file: &RegFile) {
+        self.locs[reg.0 as usize] = Some(Loc::Reg(name));
+
+        let saved = [RegClass::Int, RegClass::Float]
+            .into_iter()
+            .find(|&class| file.regs(class).callee_saved.contains(&name));
+        if let Some(class) = saved {
+            if !self.callee_saved.contains(&(class, name)) {
+                self.callee_saved.push((class, name));
+            }
+        }
+    }
+
+    pub(super) fn spill(&mut self, reg: Reg) {
+        self.locs[reg.0 as usize] = Some(Loc::Slot(self.n_slots));
+        self.n_slots += 1;
+    }
+}
+
+/// Allocates registers for `f`, w/ those in `file`
+pub fn allocate(f: &Function, file: &RegFile, allocator: Allocator) -> Allocation {
+    let lifetimes = Lifetimes::new(f, file);
+    let mut alloc = match allocator {
+        Allocator::LinearScan => linear_scan::allocate(f, file, &lifetimes),
+        Allocator::GraphColouring => colouring::allocate(f, file, &lifetimes),
+    };
+
+    // Saved in the order they're listed in, whichever allocator it was
+    alloc.callee_saved.sort_by_key(|&(class, name)| {
+        let saved = file.regs(class).callee_saved;
+        (
+            class == RegClass::Float,
+            saved.iter().position(|&r| r == name),
+        )
+    });
+    alloc
+}
+
+/// When each register is live, over the function's instructions in the order they're emitted
+#[derive(Debug)]
+pub(super) struct Lifetimes {
+    /// The first and last positions each register is live at, if it's ever used: parameters
+    /// are defined at 0, then each instruction (or terminator) reads its operands at one
+    /// position, and writes its result at the next
+    pub intervals: Vec<Option<(usize, usize)>>,
+    /// Whether each register is live across an instruction that clobbers caller-saved ones
+    pub across_call: Vec<bool>,
+    /// How often each register is read or written, as a spill's cost
+    pub uses: Vec<usize>,
+    /// Pairs of registers that are live at once, by the liveness analysis itself
+    pub interferences: Vec<(Reg, Reg)>,
+}
+
+impl Lifetimes {
+    pub fn new(f: &Function, file: &RegFile) -> Self {
+        let n_regs = f.reg_types.len();
+        let liveness = Liveness::new(f, &Cfg::new(f));
+
+        let mut intervals: Vec<Option<(usize, usize)>> = vec![None; n_regs];
+        let mut extend = |reg: Reg, pos: usize| {
+            let interval = &mut intervals[reg.0 as usize];
+            *interval = Some(match *interval {
+                Some((start, end)) => (start.min(pos), end.max(pos)),
+                None => (pos, pos),
+            });
+        };
+        let mut uses = vec![0; n_regs];
+
+        for &param in &f.params {
+            extend(param, 0);
+        }
+
+        let mut pos = 1;
+        for id in f.block_ids() {
+            let block = f.block(id);
+            let start = pos;
+
+            for inst in &block.insts {
+                for reg in regs(inst.operands()) {
+                    extend(reg, pos);
+                    uses[reg.0 as usize] += 1;
+                }
+                if let Some(dst) = inst.dst() {
+                    extend(dst, pos + 1);
+                    uses[dst.0 as usize] += 1;
+                }
+                pos += 2;
+            }
+            for reg in regs(block.term.operands()) {
+                extend(reg, pos);
+                uses[reg.0 as usize] += 1;
+            }
+            pos += 2;
+
+            // W/o holes, live ranges span the whole of each block they're live through
+            for &reg in liveness.live_in(id) {
+                extend(reg, start);
+            }
+            for &reg in liveness.live_out(id) {
+                extend(reg, pos - 1);
+            }
+        }
+
+        // Backwards through each block, from what's live out of it
+        let mut across_call = vec![false; n_regs];
+        let mut interferences = vec![];
+        for id in f.block_ids() {
+            let block = f.block(id);
+            let mut live = liveness.live_out(id).clone();
+            live.extend(regs(block.term.operands()));
+
+            for inst in block.insts.iter().rev() {
+                let dst = inst.dst();
+                if (file.clobbers)(f, inst) {
+                    for &reg in live.iter().filter(|&&reg| Some(reg) != dst) {
+                        across_call[reg.0 as usize] = true;
+                    }
+                }
+                if let Some(dst) = dst {
+                    live.remove(&dst);
+                    interferences.extend(live.iter().map(|&reg| (dst, reg)));
+                }
+                live.extend(regs(inst.operands()));
+            }
+        }
+
+        // Parameters are all defined on entry, along w/ anything else live into it
+        let mut on_entry = liveness.live_in(BlockId(0)).clone();
+        on_entry.extend(&f.params);
+        for &a in &on_entry {
+            for &b in &on_entry {
+                if a < b {
+                    interferences.push((a, b));
+                }
+            }
+        }
+
+        Lifetimes {
+            intervals,
+            across_call,
+            uses,
+            interferences,
+        }
+    }
+}
+
+fn regs<'a>(operands: Vec<&'a Operand>) -> impl Iterator<Item = Reg> + 'a {
+    operands.into_iter().filter_map(|operand| match operand {
+        Operand::Reg(reg) => Some(*reg),
+        Operand::Const(_) => None,
+    })
+}
@@ -0,0 +1,62 @@
+//! Linear scan allocation, after Poletto & Sarkar: registers' live intervals are walked in order
+//! of where they start, each taking a register that's free at that point. When none is, the
+//! interval that ends last is spilled, whether that's the new one or one already handed a
+//! register, which the new one then takes.
+
+use crate::ir::core::{Function, Reg};
+
+use super::core::{Allocation, Lifetimes, RegFile};
+
+pub(super) fn allocate(f: &Function, file: &RegFile, lifetimes: &Lifetimes) -> Allocation {
+    let mut alloc = Allocation::new(f);
+
+    let mut intervals: Vec<(Reg, usize, usize)> = (0..f.reg_types.len() as u32)
+        .map(Reg)
+        .filter_map(|reg| Some((reg, lifetimes.intervals[reg.0 as usize]?)))
+        .map(|(reg, (start, end))| (reg, start, end))
+        .collect();
+    intervals.sort_by_key(|&(reg, start, _)| (start, reg));
+
+    // Those holding a register, w/ where they end, and which it is
+    let mut active: Vec<(Reg, usize, &'static str)> = vec![];
+
+    for (reg, start, end) in intervals {
+        let Some(class) = (file.class)(f.reg_type(reg)) else {
+            alloc.spill(reg);
+            continue;
+        };
+
+        active.retain(|&(_, active_end, _)| active_end >= start);
+
+        let across_call = lifetimes.across_call[reg.0 as usize];
+        let candidates = file.candidates(class, across_call);
+        let free = candidates
+            .iter()
+            .find(|&&name| active.iter().all(|&(_, _, taken)| taken != name));
+
+        if let Some(&name) = free {
+            alloc.assign(reg, name, file);
+            active.push((reg, end, name));
+            continue;
+        }
+
+        // Only one holding a register this one may take is worth spilling instead
+        let furthest = active
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, name))| candidates.contains(name))
+            .max_by_key(|(_, &(_, active_end, _))| active_end)
+            .map(|(i, &interval)| (i, interval));
+
+        match furthest {
+            Some((i, (spilled, spilled_end, name))) if spilled_end > end => {
+                alloc.spill(spilled);
+                alloc.assign(reg, name, file);
+                active[i] = (reg, end, name);
+            }
+            _ => alloc.spill(reg),
+        }
+    }
+
+    alloc
+}
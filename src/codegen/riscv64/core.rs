@@ -0,0 +1,532 @@
+//! RISC-V (RV64IMD) code generation, for Linux: GNU assembler following the LP64D psABI, so
+//! compiled functions can call, and be called from, C. Takes a module out of SSA form.
+//!
+//! Registers are allocated among `t3`-`t5` and `s1`-`s11`, and `ft0`-`ft11` and `fs0`-`fs11`
+//! for `asharia`s; those that don't get one are spilled to a stack slot of 8 bytes, below `s0`,
+//! past the return address and the caller's `s0`, and the callee-saved registers used are saved
+//! past those. `t0`-`t2` and `fa0`/`fa1` are left as temporaries, and `t6` for addressing slots.
+//! `ginti`s, `boli`s (as 0 or 1) and `jumla`s (as a pointer to a NUL-terminated string) travel in
+//! integer registers, and `asharia`s in float ones, or integer ones once those run out.
+
+use std::fmt::Write;
+
+use crate::codegen::core::{escape_string, fn_symbol, global_symbol};
+use crate::codegen::isel::{self, Asm, Isel};
+use crate::codegen::regalloc::core::{
+    self as regalloc, Allocation, Allocator, Loc, RegClass, RegFile, Regs,
+};
+use crate::ir::core::{
+    BinOp, BlockId, Callee, Const, FnId, Function, GlobalId, Inst, Module, Operand, Reg, Terminator,
+};
+use crate::semantics::intrinsics::INTRINSICS;
+use crate::semantics::spaghetti::SymType;
+
+const ARG_REGS: usize = 8; // a0 to a7, and fa0 to fa7
+
+pub const REG_FILE: RegFile = RegFile {
+    int: Regs {
+        caller_saved: &["t3", "t4", "t5"],
+        callee_saved: &[
+            "s1", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11",
+        ],
+    },
+    float: Regs {
+        caller_saved: &[
+            "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "ft8", "ft9", "ft10", "ft11",
+        ],
+        callee_saved: &[
+            "fs0", "fs1", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9", "fs10", "fs11",
+        ],
+    },
+    class: |t| match t {
+        SymType::Float => Some(RegClass::Float),
+        _ => Some(RegClass::Int),
+    },
+    clobbers,
+};
+
+// Calls, and the operators lowered to them
+fn clobbers(f: &Function, inst: &Inst) -> bool {
+    regalloc::calls_out(f, inst, |op, t| match t {
+        SymType::String => true,
+        SymType::Float => matches!(op, BinOp::Mod | BinOp::Exp),
+        _ => op == BinOp::Exp,
+    })
+}
+
+/// Assembly for `module`, which defines `nkt_entry(argc)`: it initialises globals, then calls
+/// `shuru`. The runtime's `main` calls it in turn.
+pub fn generate(module: &Module, allocator: Allocator) -> String {
+    let mut out = String::from("\t.text\n");
+
+    for func in 0..module.functions.len() {
+        out.push('\n');
+        isel::select(&mut FnGen::new(module, func as FnId, allocator, &mut out));
+    }
+    if let Some(entry) = module.entry {
+        entry_point(module, entry, &mut out);
+    }
+
+    let _ = writeln!(out, "\n\t.section .rodata");
+    for (id, s) in module.strings.iter().enumerate() {
+        let _ = writeln!(out, ".Lstr{id}:\n\t.string \"{}\"", escape_string(s));
+    }
+    // Names, for reporting functions that ran off their end
+    for (func, f) in module.functions.iter().enumerate() {
+        if f.blocks.iter().any(|b| b.term == Terminator::MissingRet) {
+            let sym = fn_symbol(module, func as FnId);
+            let _ = writeln!(
+                out,
+                ".L{sym}.name:\n\t.string \"{}\"",
+                escape_string(&f.name)
+            );
+        }
+    }
+
+    if !module.globals.is_empty() {
+        let _ = writeln!(out, "\n\t.bss\n\t.balign 8");
+    }
+    for global in 0..module.globals.len() as u32 {
+        let _ = writeln!(out, "{}:\n\t.zero 8", global_symbol(module, global));
+    }
+
+    // No executable stack
+    out.push_str("\n\t.section .note.GNU-stack,\"\",@progbits\n");
+    out
+}
+
+fn entry_point(module: &Module, entry: FnId, out: &mut String) {
+    let shuru = &module.functions[entry as usize];
+
+    out.push_str("\n\t.globl nkt_entry\nnkt_entry:\n");
+    out.push_str("\taddi sp, sp, -16\n\tsd ra, 8(sp)\n\tsd a0, 0(sp)\n"); // and argc
+    let _ = writeln!(out, "\tcall {}", fn_symbol(module, module.init));
+    out.push_str("\tld a0, 0(sp)\n");
+    let _ = writeln!(out, "\tcall {}", fn_symbol(module, entry));
+    if shuru.ret_type != SymType::Int {
+        out.push_str("\tli a0, 0\n");
+    }
+    out.push_str("\tld ra, 8(sp)\n\taddi sp, sp, 16\n\tret\n");
+}
+
+// Where each argument of a call (or parameter of a function) is passed
+enum ArgLoc {
+    Int(usize),   // `a<n>`
+    Float(usize), // `fa<n>`
+    Stack(usize), // the nth argument passed on the stack
+}
+
+fn arg_locs(types: impl Iterator<Item = SymType>) -> Vec<ArgLoc> {
+    let (mut ints, mut floats, mut stack) = (0, 0, 0);
+    types
+        .map(|t| match t {
+            SymType::Float if floats < ARG_REGS => {
+                floats += 1;
+                ArgLoc::Float(floats - 1)
+            }
+            // Floats go where integers would, once their own registers run out
+            _ if ints < ARG_REGS => {
+                ints += 1;
+                ArgLoc::Int(ints - 1)
+            }
+            _ => {
+                stack += 1;
+                ArgLoc::Stack(stack - 1)
+            }
+        })
+        .collect()
+}
+
+// Whether `imm` fits in an I-type instruction's 12 bits
+fn fits_imm(imm: i64) -> bool {
+    (-2048..2048).contains(&imm)
+}
+
+struct FnGen<'m, 'o> {
+    module: &'m Module,
+    f: &'m Function,
+    alloc: Allocation,
+    asm: Asm<'o>,
+}
+
+impl<'m, 'o> FnGen<'m, 'o> {
+    fn new(module: &'m Module, func: FnId, allocator: Allocator, out: &'o mut String) -> Self {
+        let f = &module.functions[func as usize];
+        FnGen {
+            module,
+            f,
+            alloc: regalloc::allocate(f, &REG_FILE, allocator),
+            asm: Asm::new(out, fn_symbol(module, func), (".L", ".")),
+        }
+    }
+
+    // The nth slot, as an operand to a load or store; those too far from `s0` for an
+    // immediate offset are addressed through `t6`
+    fn slot(&mut self, n: usize) -> String {
+        let offset = -8 * (n as i64 + 3);
+        if fits_imm(offset) {
+            return format!("{offset}(s0)");
+        }
+
+        self.asm.emit(&format!("li t6, {offset}"));
+        self.asm.emit("add t6, t6, s0");
+        "0(t6)".to_string()
+    }
+
+    // The integer register `operand` is already in, if any, or `scratch`, once it's loaded
+    fn int_operand(&mut self, operand: &Operand, scratch: &'static str) -> &'static str {
+        match *operand {
+            Operand::Reg(reg) if self.f.reg_type(reg) != SymType::Float => {
+                match self.alloc.loc(reg) {
+                    Loc::Reg(name) => name,
+                    Loc::Slot(_) => {
+                        self.load(operand, scratch);
+                        scratch
+                    }
+                }
+            }
+            _ => {
+                self.load(operand, scratch);
+                scratch
+            }
+        }
+    }
+
+    // Loads `operand`'s bits into an integer register
+    fn load(&mut self, operand: &Operand, dst: &str) {
+        let line = match *operand {
+            Operand::Reg(reg) => match (self.alloc.loc(reg), self.f.reg_type(reg)) {
+                (Loc::Reg(src), SymType::Float) => format!("fmv.x.d {dst}, {src}"),
+                (Loc::Reg(src), _) => format!("mv {dst}, {src}"),
+                (Loc::Slot(n), _) => format!("ld {dst}, {}", self.slot(n)),
+            },
+            Operand::Const(Const::Int(i)) => format!("li {dst}, {i}"),
+            Operand::Const(Const::Float(x)) => format!("li {dst}, {}", x.to_bits() as i64),
+            Operand::Const(Const::Bool(b)) => format!("li {dst}, {}", b as i64),
+            Operand::Const(Const::Str(id)) => format!("la {dst}, .Lstr{id}"),
+        };
+        self.asm.emit(&line);
+    }
+
+    fn load_float(&mut self, operand: &Operand, dst: &str) {
+        match operand {
+            Operand::Reg(reg) => match self.alloc.loc(*reg) {
+                Loc::Reg(src) => self.asm.emit(&format!("fmv.d {dst}, {src}")),
+                Loc::Slot(n) => {
+                    let slot = self.slot(n);
+                    self.asm.emit(&format!("fld {dst}, {slot}"));
+                }
+            },
+            Operand::Const(_) => {
+                self.load(operand, "t0");
+                self.asm.emit(&format!("fmv.d.x {dst}, t0"));
+            }
+        }
+    }
+
+    // Stores the bits in integer register `src` to `dst`
+    fn store(&mut self, src: &str, dst: Reg) {
+        let line = match (self.alloc.loc(dst), self.f.reg_type(dst)) {
+            (Loc::Reg(dst), SymType::Float) => format!("fmv.d.x {dst}, {src}"),
+            (Loc::Reg(dst), _) => format!("mv {dst}, {src}"),
+            (Loc::Slot(n), _) => format!("sd {src}, {}", self.slot(n)),
+        };
+        self.asm.emit(&line);
+    }
+
+    fn store_float(&mut self, src: &str, dst: Reg) {
+        let line = match self.alloc.loc(dst) {
+            Loc::Reg(dst) => format!("fmv.d {dst}, {src}"),
+            Loc::Slot(n) => format!("fsd {src}, {}", self.slot(n)),
+        };
+        self.asm.emit(&line);
+    }
+
+    // Saves, or restores, the callee-saved registers the function uses, past its slots
+    fn callee_saved(&mut self, save: bool) {
+        for (k, (class, name)) in self.alloc.callee_saved.clone().into_iter().enumerate() {
+            let slot = self.slot(self.alloc.n_slots + k);
+            let op = match (class, save) {
+                (RegClass::Int, true) => "sd",
+                (RegClass::Int, false) => "ld",
+                (RegClass::Float, true) => "fsd",
+                (RegClass::Float, false) => "fld",
+            };
+            self.asm.emit(&format!("{op} {name}, {slot}"));
+        }
+    }
+
+    // Moves `sp` by `by` bytes, w/ `t0` if that's too far for `addi`
+    fn adjust_sp(&mut self, by: i64) {
+        match fits_imm(by) {
+            true => self.asm.emit(&format!("addi sp, sp, {by}")),
+            false => {
+                self.asm.emit(&format!("li t0, {by}"));
+                self.asm.emit("add sp, sp, t0");
+            }
+        }
+    }
+}
+
+impl<'m> Isel<'m> for FnGen<'m, '_> {
+    fn module(&self) -> &'m Module {
+        self.module
+    }
+
+    fn func(&self) -> &'m Function {
+        self.f
+    }
+
+    fn prologue(&mut self) {
+        let sym = self.asm.sym.clone();
+        self.asm.label(&sym);
+        self.asm.emit("addi sp, sp, -16");
+        self.asm.emit("sd ra, 8(sp)");
+        self.asm.emit("sd s0, 0(sp)");
+        self.asm.emit("addi s0, sp, 16");
+
+        let n_slots = self.alloc.n_slots + self.alloc.callee_saved.len();
+        let slots = (8 * n_slots).next_multiple_of(16) as i64;
+        if slots > 0 {
+            self.adjust_sp(-slots);
+        }
+        self.callee_saved(true);
+
+        let locs = arg_locs(self.f.params.iter().map(|&p| self.f.reg_type(p)));
+        for (&param, loc) in self.f.params.iter().zip(locs) {
+            match loc {
+                ArgLoc::Int(n) => self.store(&format!("a{n}"), param),
+                ArgLoc::Float(n) => self.store_float(&format!("fa{n}"), param),
+                ArgLoc::Stack(n) => {
+                    // Where the caller's `sp` was
+                    self.asm.emit(&format!("ld t0, {}(s0)", 8 * n));
+                    self.store("t0", param);
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, id: BlockId) {
+        let label = self.asm.block_label(id);
+        self.asm.label(&label);
+    }
+
+    fn copy(&mut self, dst: Reg, src: &Operand) {
+        // Straight b/w registers of the same class, if they're in them
+        if let Operand::Reg(src) = *src {
+            let float = self.f.reg_type(src) == SymType::Float;
+            match (self.alloc.loc(dst), self.alloc.loc(src)) {
+                (Loc::Reg(d), Loc::Reg(s)) if d == s => return,
+                (Loc::Reg(d), Loc::Reg(s)) if float => {
+                    return self.asm.emit(&format!("fmv.d {d}, {s}"));
+                }
+                (Loc::Reg(d), Loc::Reg(s)) => return self.asm.emit(&format!("mv {d}, {s}")),
+                _ => {}
+            }
+        }
+
+        self.load(src, "t0");
+        self.store("t0", dst);
+    }
+
+    fn int_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        let l = self.int_operand(lhs, "t0");
+        let r = self.int_operand(rhs, "t1");
+
+        match op {
+            BinOp::Add => self.asm.emit(&format!("add t0, {l}, {r}")),
+            BinOp::Sub => self.asm.emit(&format!("sub t0, {l}, {r}")),
+            BinOp::Mul => self.asm.emit(&format!("mul t0, {l}, {r}")),
+            BinOp::BitAnd => self.asm.emit(&format!("and t0, {l}, {r}")),
+            BinOp::BitOr => self.asm.emit(&format!("or t0, {l}, {r}")),
+
+            BinOp::Lt => self.asm.emit(&format!("slt t0, {l}, {r}")),
+            BinOp::Gt => self.asm.emit(&format!("slt t0, {r}, {l}")),
+            BinOp::Eq => {
+                self.asm.emit(&format!("xor t0, {l}, {r}"));
+                self.asm.emit("seqz t0, t0");
+            }
+
+            // `i64::MIN / -1` wraps, as RISC-V has it, but dividing by zero doesn't trap
+            BinOp::Div | BinOp::Mod => {
+                let nonzero = self.asm.new_label();
+                self.asm.emit(&format!("bnez {r}, {nonzero}"));
+                self.asm.emit("call nkt_rt_division_by_zero");
+                self.asm.label(&nonzero);
+                match op {
+                    BinOp::Div => self.asm.emit(&format!("div t0, {l}, {r}")),
+                    _ => self.asm.emit(&format!("rem t0, {l}, {r}")),
+                }
+            }
+
+            BinOp::Exp => {
+                self.asm.emit(&format!("mv a0, {l}"));
+                self.asm.emit(&format!("mv a1, {r}"));
+                self.asm.emit("call nkt_rt_pow");
+                self.asm.emit("mv t0, a0");
+            }
+
+            // Unsigned, so negative amounts are out of range too
+            BinOp::Shl | BinOp::Shr => {
+                let in_range = self.asm.new_label();
+                self.asm.emit("li t2, 63");
+                self.asm.emit(&format!("bleu {r}, t2, {in_range}"));
+                self.asm.emit(&format!("mv a0, {r}"));
+                self.asm.emit("call nkt_rt_shift_out_of_range");
+                self.asm.label(&in_range);
+
+                match op {
+                    BinOp::Shl => self.asm.emit(&format!("sll t0, {l}, {r}")),
+                    _ => self.asm.emit(&format!("sra t0, {l}, {r}")),
+                }
+            }
+        }
+
+        self.store("t0", dst);
+    }
+
+    fn float_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load_float(lhs, "fa0");
+        self.load_float(rhs, "fa1");
+
+        match op {
+            BinOp::Add => self.asm.emit("fadd.d fa0, fa0, fa1"),
+            BinOp::Sub => self.asm.emit("fsub.d fa0, fa0, fa1"),
+            BinOp::Mul => self.asm.emit("fmul.d fa0, fa0, fa1"),
+            BinOp::Div => self.asm.emit("fdiv.d fa0, fa0, fa1"),
+            BinOp::Mod => self.asm.emit("call fmod"),
+            BinOp::Exp => self.asm.emit("call pow"),
+
+            // Comparisons w/ NaN are false
+            BinOp::Lt | BinOp::Gt | BinOp::Eq => {
+                match op {
+                    BinOp::Lt => self.asm.emit("flt.d t0, fa0, fa1"),
+                    BinOp::Gt => self.asm.emit("flt.d t0, fa1, fa0"),
+                    _ => self.asm.emit("feq.d t0, fa0, fa1"),
+                }
+                self.store("t0", dst);
+                return;
+            }
+
+            _ => unreachable!("`{op:?}` on floats"),
+        }
+
+        self.store_float("fa0", dst);
+    }
+
+    fn str_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load(lhs, "a0");
+        self.load(rhs, "a1");
+        self.asm.emit("call nkt_rt_str_cmp");
+        match op {
+            BinOp::Lt => self.asm.emit("sltz t0, a0"),
+            BinOp::Gt => self.asm.emit("sgtz t0, a0"),
+            _ => self.asm.emit("seqz t0, a0"),
+        }
+        self.store("t0", dst);
+    }
+
+    fn int_neg(&mut self, dst: Reg, operand: &Operand) {
+        let src = self.int_operand(operand, "t0");
+        self.asm.emit(&format!("neg t0, {src}"));
+        self.store("t0", dst);
+    }
+
+    fn float_neg(&mut self, dst: Reg, operand: &Operand) {
+        self.load_float(operand, "fa0");
+        self.asm.emit("fneg.d fa0, fa0");
+        self.store_float("fa0", dst);
+    }
+
+    fn not(&mut self, dst: Reg, operand: &Operand) {
+        let src = self.int_operand(operand, "t0");
+        self.asm.emit(&format!("xori t0, {src}, 1"));
+        self.store("t0", dst);
+    }
+
+    fn call(&mut self, dst: Option<Reg>, callee: Callee, ret_type: SymType, args: &[Operand]) {
+        let target = match callee {
+            Callee::Fn(func) => fn_symbol(self.module, func),
+            Callee::Intrinsic(i) => format!("nkt_rt_{}", INTRINSICS[i as usize].name),
+        };
+
+        let locs = arg_locs(args.iter().map(|arg| self.f.operand_type(arg)));
+        let n_stack = locs
+            .iter()
+            .filter(|loc| matches!(loc, ArgLoc::Stack(_)))
+            .count();
+
+        // `sp` must be 16-byte aligned at the call
+        let area = (8 * n_stack).next_multiple_of(16) as i64;
+        if area > 0 {
+            self.adjust_sp(-area);
+        }
+        for (arg, loc) in args.iter().zip(&locs) {
+            if let ArgLoc::Stack(n) = loc {
+                self.load(arg, "t0");
+                self.asm.emit(&format!("sd t0, {}(sp)", 8 * n));
+            }
+        }
+        for (arg, loc) in args.iter().zip(&locs) {
+            match loc {
+                ArgLoc::Int(n) => self.load(arg, &format!("a{n}")),
+                ArgLoc::Float(n) => self.load_float(arg, &format!("fa{n}")),
+                ArgLoc::Stack(_) => {}
+            }
+        }
+
+        self.asm.emit(&format!("call {target}"));
+        if area > 0 {
+            self.adjust_sp(area);
+        }
+
+        match (dst, ret_type) {
+            (Some(dst), SymType::Float) => self.store_float("fa0", dst),
+            (Some(dst), _) => self.store("a0", dst),
+            (None, _) => {}
+        }
+    }
+
+    fn load_global(&mut self, dst: Reg, global: GlobalId) {
+        let sym = global_symbol(self.module, global);
+        self.asm.emit(&format!("la t1, {sym}"));
+        self.asm.emit("ld t0, 0(t1)");
+        self.store("t0", dst);
+    }
+
+    fn store_global(&mut self, global: GlobalId, src: &Operand) {
+        self.load(src, "t0");
+        let sym = global_symbol(self.module, global);
+        self.asm.emit(&format!("la t1, {sym}"));
+        self.asm.emit("sd t0, 0(t1)");
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        let label = self.asm.block_label(target);
+        self.asm.emit(&format!("j {label}"));
+    }
+
+    fn branch(&mut self, cond: &Operand, target: BlockId) {
+        let cond = self.int_operand(cond, "t0");
+        let label = self.asm.block_label(target);
+        self.asm.emit(&format!("bnez {cond}, {label}"));
+    }
+
+    fn ret(&mut self, value: Option<&Operand>) {
+        match value {
+            Some(value) if self.f.ret_type == SymType::Float => self.load_float(value, "fa0"),
+            Some(value) => self.load(value, "a0"),
+            None => {}
+        }
+        self.callee_saved(false);
+        self.asm.emit("ld ra, -8(s0)");
+        self.asm.emit("mv sp, s0");
+        self.asm.emit("ld s0, -16(sp)");
+        self.asm.emit("ret");
+    }
+
+    fn missing_ret(&mut self) {
+        self.asm.emit(&format!("la a0, .L{}.name", self.asm.sym));
+        self.asm.emit("call nkt_rt_missing_return");
+    }
+}
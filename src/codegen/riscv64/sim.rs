@@ -0,0 +1,650 @@
+//! An interpreter for the subset of RV64IMD the backend emits, in GNU `as`'s syntax, so that
+//! programs can be run, and the backend tested, w/o a RISC-V machine or an emulator. It runs the
+//! assembly itself, pseudo-instructions as one instruction each, but checks immediates fit as
+//! an assembler would.
+//!
+//! The runtime isn't assembled, but stood in for: calls to its functions, and to libm's `fmod`
+//! and `pow`, are carried out by the interpreter, as the C runtime would, and its errors end
+//! the program. It starts at `nkt_entry`, as the runtime's `main` would, w/ `argc` in `a0`.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+
+use crate::codegen::asm::{self, parse_int, split_mnemonic, Line, SyntaxError};
+use crate::interp::{core::RuntimeErrorKind, ops};
+
+const TEXT_BASE: u64 = 0x1_0000;
+const DATA_BASE: u64 = 0x1000_0000;
+const STACK_TOP: u64 = 0x7fff_f000;
+const STACK_SIZE: u64 = 8 << 20;
+const EXIT: u64 = 0; // the return address `nkt_entry` is called w/
+const CLOBBERED: u64 = 0xdead_beef_dead_beef; // what calls out to the host leave in registers
+
+#[derive(Debug, PartialEq)]
+pub enum SimError {
+    Syntax(usize, String), // the line, and what's wrong w/ it
+    UnknownSymbol(String),
+    NoEntry,
+    BadAddress(u64), // outside of the data, the heap and the stack, or of the text for jumps
+    Unaligned(u64),
+    Runtime(RuntimeErrorKind), // one the runtime reported
+}
+
+impl From<SyntaxError> for SimError {
+    fn from(SyntaxError(no, msg): SyntaxError) -> Self {
+        SimError::Syntax(no, msg)
+    }
+}
+
+/// Runs `asm` until `nkt_entry` returns, giving back the exit code `main` would
+pub fn run(asm: &str, args: &[String]) -> Result<i32, SimError> {
+    let mut input = BufReader::new(io::stdin());
+    run_with_io(asm, args, &mut input, &mut io::stdout())
+}
+
+/// Like `run`, but w/ the runtime reading from `input` and writing to `output`
+pub fn run_with_io(
+    asm: &str,
+    args: &[String],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<i32, SimError> {
+    let program = asm::assemble(asm, TEXT_BASE, DATA_BASE, instruction)?;
+    let entry = *program.labels.get("nkt_entry").ok_or(SimError::NoEntry)?;
+
+    let mut cpu = Cpu {
+        regs: [0; 32],
+        fregs: [0; 32],
+        pc: entry,
+        data: program.data,
+        stack: vec![0; STACK_SIZE as usize],
+        input,
+        output,
+    };
+    cpu.regs[SP as usize] = STACK_TOP;
+    cpu.regs[RA as usize] = EXIT;
+    cpu.regs[A0 as usize] = args.len() as u64;
+
+    let res = cpu.run(&program.text);
+    cpu.output
+        .flush()
+        .map_err(|e| SimError::Runtime(RuntimeErrorKind::Io(e.kind())))?;
+    res
+}
+
+const RA: u8 = 1;
+const SP: u8 = 2;
+const A0: u8 = 10;
+const A1: u8 = 11;
+
+#[derive(Debug, Clone, Copy)]
+enum AluOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+    Slt,
+    Sltu,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Width {
+    Byte,
+    ByteUnsigned,
+    Word,
+    Double,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FpuOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Sgnj,
+    Sgnjn,
+    Sgnjx,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FCmp {
+    Eq,
+    Lt,
+    Le,
+}
+
+// What the runtime (and libm) would do, when called
+#[derive(Debug, Clone, Copy)]
+enum Host {
+    Likho,
+    LikhoGinti,
+    Parho,
+    StrCmp,
+    Pow,
+    DivisionByZero,
+    ShiftOutOfRange,
+    MissingReturn,
+    Fmod,
+    PowD,
+}
+
+impl Host {
+    fn from_symbol(sym: &str) -> Option<Host> {
+        let host = match sym {
+            "nkt_rt_likho" => Host::Likho,
+            "nkt_rt_likho_ginti" => Host::LikhoGinti,
+            "nkt_rt_parho" => Host::Parho,
+            "nkt_rt_str_cmp" => Host::StrCmp,
+            "nkt_rt_pow" => Host::Pow,
+            "nkt_rt_division_by_zero" => Host::DivisionByZero,
+            "nkt_rt_shift_out_of_range" => Host::ShiftOutOfRange,
+            "nkt_rt_missing_return" => Host::MissingReturn,
+            "fmod" => Host::Fmod,
+            "pow" => Host::PowD,
+            _ => return None,
+        };
+        Some(host)
+    }
+}
+
+// Registers are numbers, and labels addresses, by the time instructions are executed
+#[derive(Debug, Clone, Copy)]
+enum Instr {
+    Alu(AluOp, u8, u8, u8),     // rd, rs1, rs2
+    AluImm(AluOp, u8, u8, i64), // rd, rs1, imm
+    Li(u8, u64),
+    Load(Width, u8, u8, i64), // rd, base, offset
+    Store(Width, u8, u8, i64),
+    LoadD(u8, u8, i64), // fd, base, offset
+    StoreD(u8, u8, i64),
+    Branch(Cond, u8, u8, u64), // rs1, rs2, target
+    Jal(u8, u64),              // rd, target
+    Jalr(u8, u8, i64),         // rd, rs1, offset
+    Host(Host),
+    Fpu(FpuOp, u8, u8, u8), // fd, fs1, fs2
+    FCmp(FCmp, u8, u8, u8), // rd, fs1, fs2
+    FmvDX(u8, u8),          // fd, rs1
+    FmvXD(u8, u8),          // rd, fs1
+}
+
+fn reg(name: &str) -> Option<u8> {
+    const NAMES: [&str; 32] = [
+        "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ];
+
+    match name {
+        "fp" => Some(8),
+        _ => match name.strip_prefix('x').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n) => (n < 32).then_some(n),
+            None => NAMES.iter().position(|&n| n == name).map(|n| n as u8),
+        },
+    }
+}
+
+fn freg(name: &str) -> Option<u8> {
+    const NAMES: [&str; 32] = [
+        "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+        "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+        "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+    ];
+
+    match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        Some(n) => (n < 32).then_some(n),
+        None => NAMES.iter().position(|&n| n == name).map(|n| n as u8),
+    }
+}
+
+fn instruction(line: &Line, labels: &HashMap<String, u64>) -> Result<Instr, SimError> {
+    let (mnemonic, operands) = split_mnemonic(line.text);
+    let ops = match operands.is_empty() {
+        true => vec![],
+        false => operands.split(',').map(str::trim).collect::<Vec<_>>(),
+    };
+
+    let err = |msg: &str| SimError::Syntax(line.no, format!("{msg}: `{}`", line.text));
+    let op = |i: usize| ops.get(i).copied().ok_or_else(|| err("missing operand"));
+    let r = |i: usize| op(i).and_then(|o| reg(o).ok_or_else(|| err("expected a register")));
+    let f = |i: usize| op(i).and_then(|o| freg(o).ok_or_else(|| err("expected a float register")));
+    let int =
+        |i: usize| op(i).and_then(|o| parse_int(o).ok_or_else(|| err("expected an immediate")));
+    // 12 bits, sign-extended
+    let imm = |i: usize| {
+        int(i).and_then(|imm| match (-2048..2048).contains(&imm) {
+            true => Ok(imm),
+            false => Err(err("immediate out of range")),
+        })
+    };
+    let shamt = |i: usize| {
+        int(i).and_then(|imm| match (0..64).contains(&imm) {
+            true => Ok(imm),
+            false => Err(err("shift amount out of range")),
+        })
+    };
+    let label = |i: usize| {
+        op(i).and_then(|o| {
+            labels
+                .get(o)
+                .copied()
+                .ok_or_else(|| SimError::UnknownSymbol(o.to_string()))
+        })
+    };
+    // `offset(base)`
+    let mem = |i: usize| {
+        let o = op(i)?;
+        let (offset, base) = o
+            .strip_suffix(')')
+            .and_then(|o| o.split_once('('))
+            .ok_or_else(|| err("expected `offset(base)`"))?;
+        let offset = match offset {
+            "" => 0,
+            offset => parse_int(offset).ok_or_else(|| err("bad offset"))?,
+        };
+        if !(-2048..2048).contains(&offset) {
+            return Err(err("offset out of range"));
+        }
+        Ok((reg(base).ok_or_else(|| err("expected a register"))?, offset))
+    };
+
+    let alu = |op| Ok(Instr::Alu(op, r(0)?, r(1)?, r(2)?));
+    let alu_imm = |op| Ok(Instr::AluImm(op, r(0)?, r(1)?, imm(2)?));
+    let shift_imm = |op| Ok(Instr::AluImm(op, r(0)?, r(1)?, shamt(2)?));
+    let load =
+        |width| mem(1).and_then(|(base, offset)| Ok(Instr::Load(width, r(0)?, base, offset)));
+    let store =
+        |width| mem(1).and_then(|(base, offset)| Ok(Instr::Store(width, r(0)?, base, offset)));
+    let branch = |cond| Ok(Instr::Branch(cond, r(0)?, r(1)?, label(2)?));
+    // W/ the operands swapped, e.g `bgt` as `blt`
+    let branch_swapped = |cond| Ok(Instr::Branch(cond, r(1)?, r(0)?, label(2)?));
+    let branch_zero = |cond| Ok(Instr::Branch(cond, r(0)?, 0, label(1)?));
+    let fpu = |op| Ok(Instr::Fpu(op, f(0)?, f(1)?, f(2)?));
+    let fpu_unary = |op| f(1).and_then(|fs| Ok(Instr::Fpu(op, f(0)?, fs, fs)));
+    let fcmp = |op| Ok(Instr::FCmp(op, r(0)?, f(1)?, f(2)?));
+
+    match mnemonic {
+        "add" => alu(AluOp::Add),
+        "sub" => alu(AluOp::Sub),
+        "mul" => alu(AluOp::Mul),
+        "div" => alu(AluOp::Div),
+        "rem" => alu(AluOp::Rem),
+        "and" => alu(AluOp::And),
+        "or" => alu(AluOp::Or),
+        "xor" => alu(AluOp::Xor),
+        "sll" => alu(AluOp::Sll),
+        "srl" => alu(AluOp::Srl),
+        "sra" => alu(AluOp::Sra),
+        "slt" => alu(AluOp::Slt),
+        "sltu" => alu(AluOp::Sltu),
+
+        "addi" => alu_imm(AluOp::Add),
+        "andi" => alu_imm(AluOp::And),
+        "ori" => alu_imm(AluOp::Or),
+        "xori" => alu_imm(AluOp::Xor),
+        "slti" => alu_imm(AluOp::Slt),
+        "sltiu" => alu_imm(AluOp::Sltu),
+        "slli" => shift_imm(AluOp::Sll),
+        "srli" => shift_imm(AluOp::Srl),
+        "srai" => shift_imm(AluOp::Sra),
+
+        "li" => Ok(Instr::Li(r(0)?, int(1)? as u64)),
+        "la" | "lla" => Ok(Instr::Li(r(0)?, label(1)?)),
+        "mv" => Ok(Instr::AluImm(AluOp::Add, r(0)?, r(1)?, 0)),
+        "neg" => Ok(Instr::Alu(AluOp::Sub, r(0)?, 0, r(1)?)),
+        "not" => Ok(Instr::AluImm(AluOp::Xor, r(0)?, r(1)?, -1)),
+        "seqz" => Ok(Instr::AluImm(AluOp::Sltu, r(0)?, r(1)?, 1)),
+        "snez" => Ok(Instr::Alu(AluOp::Sltu, r(0)?, 0, r(1)?)),
+        "sltz" => Ok(Instr::Alu(AluOp::Slt, r(0)?, r(1)?, 0)),
+        "sgtz" => Ok(Instr::Alu(AluOp::Slt, r(0)?, 0, r(1)?)),
+        "nop" => Ok(Instr::AluImm(AluOp::Add, 0, 0, 0)),
+
+        "lb" => load(Width::Byte),
+        "lbu" => load(Width::ByteUnsigned),
+        "lw" => load(Width::Word),
+        "ld" => load(Width::Double),
+        "sb" => store(Width::Byte),
+        "sw" => store(Width::Word),
+        "sd" => store(Width::Double),
+        "fld" => mem(1).and_then(|(base, offset)| Ok(Instr::LoadD(f(0)?, base, offset))),
+        "fsd" => mem(1).and_then(|(base, offset)| Ok(Instr::StoreD(f(0)?, base, offset))),
+
+        "beq" => branch(Cond::Eq),
+        "bne" => branch(Cond::Ne),
+        "blt" => branch(Cond::Lt),
+        "bge" => branch(Cond::Ge),
+        "bltu" => branch(Cond::Ltu),
+        "bgeu" => branch(Cond::Geu),
+        "bgt" => branch_swapped(Cond::Lt),
+        "ble" => branch_swapped(Cond::Ge),
+        "bgtu" => branch_swapped(Cond::Ltu),
+        "bleu" => branch_swapped(Cond::Geu),
+        "beqz" => branch_zero(Cond::Eq),
+        "bnez" => branch_zero(Cond::Ne),
+        "bltz" => branch_zero(Cond::Lt),
+        "bgez" => branch_zero(Cond::Ge),
+
+        "j" => Ok(Instr::Jal(0, label(0)?)),
+        "jal" if ops.len() == 1 => Ok(Instr::Jal(RA, label(0)?)),
+        "jal" => Ok(Instr::Jal(r(0)?, label(1)?)),
+        "jr" => Ok(Instr::Jalr(0, r(0)?, 0)),
+        "jalr" => Ok(Instr::Jalr(RA, r(0)?, 0)),
+        "ret" => Ok(Instr::Jalr(0, RA, 0)),
+        // The runtime's functions, unless they're defined
+        "call" | "tail" => {
+            let rd = if mnemonic == "call" { RA } else { 0 };
+            let sym = op(0)?.trim_end_matches("@plt");
+            match (labels.get(sym), Host::from_symbol(sym)) {
+                (Some(&addr), _) => Ok(Instr::Jal(rd, addr)),
+                (None, Some(host)) if rd == RA => Ok(Instr::Host(host)),
+                _ => Err(SimError::UnknownSymbol(sym.to_string())),
+            }
+        }
+
+        "fadd.d" => fpu(FpuOp::Add),
+        "fsub.d" => fpu(FpuOp::Sub),
+        "fmul.d" => fpu(FpuOp::Mul),
+        "fdiv.d" => fpu(FpuOp::Div),
+        "fsgnj.d" => fpu(FpuOp::Sgnj),
+        "fsgnjn.d" => fpu(FpuOp::Sgnjn),
+        "fsgnjx.d" => fpu(FpuOp::Sgnjx),
+        "fmv.d" => fpu_unary(FpuOp::Sgnj),
+        "fneg.d" => fpu_unary(FpuOp::Sgnjn),
+        "fabs.d" => fpu_unary(FpuOp::Sgnjx),
+        "feq.d" => fcmp(FCmp::Eq),
+        "flt.d" => fcmp(FCmp::Lt),
+        "fle.d" => fcmp(FCmp::Le),
+        "fmv.d.x" => Ok(Instr::FmvDX(f(0)?, r(1)?)),
+        "fmv.x.d" => Ok(Instr::FmvXD(r(0)?, f(1)?)),
+
+        _ => Err(err("unknown instruction")),
+    }
+}
+
+struct Cpu<'io> {
+    regs: [u64; 32],
+    fregs: [u64; 32],
+    pc: u64,
+    data: Vec<u8>, // static data, then the heap
+    stack: Vec<u8>,
+    input: &'io mut dyn BufRead,
+    output: &'io mut dyn Write,
+}
+
+impl Cpu<'_> {
+    fn run(&mut self, text: &[Instr]) -> Result<i32, SimError> {
+        loop {
+            if self.pc == EXIT {
+                return Ok(self.regs[A0 as usize] as i32);
+            }
+
+            let i = self.pc.wrapping_sub(TEXT_BASE) / 4;
+            let Some(&instr) = self
+                .pc
+                .is_multiple_of(4)
+                .then(|| text.get(i as usize))
+                .flatten()
+            else {
+                return Err(SimError::BadAddress(self.pc));
+            };
+            self.pc += 4;
+
+            self.step(instr)?;
+        }
+    }
+
+    fn set(&mut self, r: u8, value: u64) {
+        if r != 0 {
+            self.regs[r as usize] = value;
+        }
+    }
+
+    fn double(&self, f: u8) -> f64 {
+        f64::from_bits(self.fregs[f as usize])
+    }
+
+    fn step(&mut self, instr: Instr) -> Result<(), SimError> {
+        let r = |cpu: &Self, r: u8| cpu.regs[r as usize];
+
+        match instr {
+            Instr::Alu(op, rd, rs1, rs2) => {
+                let value = alu(op, r(self, rs1), r(self, rs2));
+                self.set(rd, value);
+            }
+            Instr::AluImm(op, rd, rs1, imm) => {
+                let value = alu(op, r(self, rs1), imm as u64);
+                self.set(rd, value);
+            }
+            Instr::Li(rd, imm) => self.set(rd, imm),
+
+            Instr::Load(width, rd, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u64);
+                let value = match width {
+                    Width::Byte => self.read(addr, 1)?[0] as i8 as u64,
+                    Width::ByteUnsigned => self.read(addr, 1)?[0] as u64,
+                    Width::Word => {
+                        i32::from_le_bytes(self.read(addr, 4)?.try_into().unwrap()) as u64
+                    }
+                    Width::Double => u64::from_le_bytes(self.read(addr, 8)?.try_into().unwrap()),
+                };
+                self.set(rd, value);
+            }
+            Instr::Store(width, rs, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u64);
+                let bytes = r(self, rs).to_le_bytes();
+                match width {
+                    Width::Byte | Width::ByteUnsigned => self.write(addr, &bytes[..1])?,
+                    Width::Word => self.write(addr, &bytes[..4])?,
+                    Width::Double => self.write(addr, &bytes)?,
+                }
+            }
+            Instr::LoadD(fd, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u64);
+                self.fregs[fd as usize] =
+                    u64::from_le_bytes(self.read(addr, 8)?.try_into().unwrap());
+            }
+            Instr::StoreD(fs, base, offset) => {
+                let addr = r(self, base).wrapping_add(offset as u64);
+                self.write(addr, &self.fregs[fs as usize].to_le_bytes())?;
+            }
+
+            Instr::Branch(cond, rs1, rs2, target) => {
+                let (a, b) = (r(self, rs1), r(self, rs2));
+                let taken = match cond {
+                    Cond::Eq => a == b,
+                    Cond::Ne => a != b,
+                    Cond::Lt => (a as i64) < (b as i64),
+                    Cond::Ge => (a as i64) >= (b as i64),
+                    Cond::Ltu => a < b,
+                    Cond::Geu => a >= b,
+                };
+                if taken {
+                    self.pc = target;
+                }
+            }
+            Instr::Jal(rd, target) => {
+                self.set(rd, self.pc);
+                self.pc = target;
+            }
+            Instr::Jalr(rd, rs1, offset) => {
+                let target = r(self, rs1).wrapping_add(offset as u64);
+                self.set(rd, self.pc);
+                self.pc = target;
+            }
+            Instr::Host(host) => self.host(host)?,
+
+            Instr::Fpu(op, fd, fs1, fs2) => {
+                let (a, b) = (self.double(fs1), self.double(fs2));
+                const SIGN: u64 = 1 << 63;
+                let bits = match op {
+                    FpuOp::Add => (a + b).to_bits(),
+                    FpuOp::Sub => (a - b).to_bits(),
+                    FpuOp::Mul => (a * b).to_bits(),
+                    FpuOp::Div => (a / b).to_bits(),
+                    FpuOp::Sgnj => a.to_bits() & !SIGN | b.to_bits() & SIGN,
+                    FpuOp::Sgnjn => a.to_bits() & !SIGN | !b.to_bits() & SIGN,
+                    FpuOp::Sgnjx => a.to_bits() ^ b.to_bits() & SIGN,
+                };
+                self.fregs[fd as usize] = bits;
+            }
+            Instr::FCmp(op, rd, fs1, fs2) => {
+                let (a, b) = (self.double(fs1), self.double(fs2));
+                let holds = match op {
+                    FCmp::Eq => a == b,
+                    FCmp::Lt => a < b,
+                    FCmp::Le => a <= b,
+                };
+                self.set(rd, holds as u64);
+            }
+            Instr::FmvDX(fd, rs1) => self.fregs[fd as usize] = r(self, rs1),
+            Instr::FmvXD(rd, fs1) => self.set(rd, self.fregs[fs1 as usize]),
+        }
+
+        Ok(())
+    }
+
+    // Calls one of the runtime's functions, w/ its arguments where the psABI puts them
+    fn host(&mut self, host: Host) -> Result<(), SimError> {
+        let (a0, a1) = (self.regs[A0 as usize], self.regs[A1 as usize]);
+        let (fa0, fa1) = (self.double(A0), self.double(A1));
+        let io = |e: io::Error| SimError::Runtime(RuntimeErrorKind::Io(e.kind()));
+
+        match host {
+            Host::Likho => {
+                let mut s = self.c_string(a0)?;
+                s.push(b'\n');
+                self.output.write_all(&s).map_err(io)?;
+            }
+            Host::LikhoGinti => writeln!(self.output, "{}", a0 as i64).map_err(io)?,
+            Host::Parho => {
+                let mut line = vec![];
+                self.input.read_until(b'\n', &mut line).map_err(io)?;
+                while line.last().is_some_and(|&b| b == b'\n' || b == b'\r') {
+                    line.pop();
+                }
+                line.push(0);
+
+                let addr = self.alloc(&line);
+                self.set(A0, addr);
+            }
+            Host::StrCmp => {
+                let (a, b) = (self.c_string(a0)?, self.c_string(a1)?);
+                self.set(A0, a.cmp(&b) as i64 as u64);
+            }
+            Host::Pow => {
+                let res = ops::int_pow(a0 as i64, a1 as i64).map_err(SimError::Runtime)?;
+                self.set(A0, res as u64);
+            }
+            Host::DivisionByZero => {
+                return Err(SimError::Runtime(RuntimeErrorKind::DivisionByZero))
+            }
+            Host::ShiftOutOfRange => {
+                let kind = RuntimeErrorKind::ShiftOutOfRange(a0 as i64);
+                return Err(SimError::Runtime(kind));
+            }
+            Host::MissingReturn => {
+                let name = String::from_utf8_lossy(&self.c_string(a0)?).into_owned();
+                return Err(SimError::Runtime(RuntimeErrorKind::MissingReturn(name)));
+            }
+            Host::Fmod => self.fregs[A0 as usize] = (fa0 % fa1).to_bits(),
+            Host::PowD => self.fregs[A0 as usize] = fa0.powf(fa1).to_bits(),
+        }
+
+        // As C code would, for all the caller knows, leave garbage in caller-saved registers,
+        // past those results come back in
+        for reg in [5, 6, 7, 28, 29, 30, 31].into_iter().chain(A0 + 1..=A0 + 7) {
+            self.set(reg, CLOBBERED);
+        }
+        for freg in (0..8).chain(28..32).chain(A0 + 1..=A0 + 7) {
+            self.fregs[freg as usize] = CLOBBERED;
+        }
+
+        Ok(())
+    }
+
+    // Puts `bytes` on the heap, 8-byte aligned, like `malloc` would
+    fn alloc(&mut self, bytes: &[u8]) -> u64 {
+        let start = self.data.len().next_multiple_of(8);
+        self.data.resize(start, 0);
+        self.data.extend(bytes);
+        DATA_BASE + start as u64
+    }
+
+    fn c_string(&mut self, mut addr: u64) -> Result<Vec<u8>, SimError> {
+        let mut s = vec![];
+        loop {
+            match self.read(addr, 1)?[0] {
+                0 => return Ok(s),
+                b => s.push(b),
+            }
+            addr += 1;
+        }
+    }
+
+    // The memory `addr` refers to, as a segment and an index into it
+    fn segment(&mut self, addr: u64, len: u64) -> Result<(&mut Vec<u8>, usize), SimError> {
+        if !addr.is_multiple_of(len) {
+            return Err(SimError::Unaligned(addr));
+        }
+
+        let end = addr.checked_add(len).ok_or(SimError::BadAddress(addr))?;
+        let stack_bottom = STACK_TOP - STACK_SIZE;
+
+        if addr >= DATA_BASE && end <= DATA_BASE + self.data.len() as u64 {
+            Ok((&mut self.data, (addr - DATA_BASE) as usize))
+        } else if addr >= stack_bottom && end <= STACK_TOP {
+            Ok((&mut self.stack, (addr - stack_bottom) as usize))
+        } else {
+            Err(SimError::BadAddress(addr))
+        }
+    }
+
+    fn read(&mut self, addr: u64, len: u64) -> Result<&[u8], SimError> {
+        let (segment, i) = self.segment(addr, len)?;
+        Ok(&segment[i..i + len as usize])
+    }
+
+    fn write(&mut self, addr: u64, bytes: &[u8]) -> Result<(), SimError> {
+        let (segment, i) = self.segment(addr, bytes.len() as u64)?;
+        segment[i..i + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+fn alu(op: AluOp, a: u64, b: u64) -> u64 {
+    let (sa, sb) = (a as i64, b as i64);
+    match op {
+        AluOp::Add => a.wrapping_add(b),
+        AluOp::Sub => a.wrapping_sub(b),
+        AluOp::Mul => a.wrapping_mul(b),
+        // Neither traps: dividing by zero gives all ones, and the remainder the dividend
+        AluOp::Div if b == 0 => u64::MAX,
+        AluOp::Div => sa.wrapping_div(sb) as u64,
+        AluOp::Rem if b == 0 => a,
+        AluOp::Rem => sa.wrapping_rem(sb) as u64,
+        AluOp::And => a & b,
+        AluOp::Or => a | b,
+        AluOp::Xor => a ^ b,
+        AluOp::Sll => a << (b & 63),
+        AluOp::Srl => a >> (b & 63),
+        AluOp::Sra => (sa >> (b & 63)) as u64,
+        AluOp::Slt => (sa < sb) as u64,
+        AluOp::Sltu => (a < b) as u64,
+    }
+}
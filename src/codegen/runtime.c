@@ -0,0 +1,88 @@
+/*
+ * Nuktah's runtime for native executables, linked into every program `nktc build` produces.
+ * Errors are reported as the interpreter names them, sans the span, and exit w/ 1, like it does.
+ */
+
+#define _POSIX_C_SOURCE 200809L /* getline */
+
+#include <inttypes.h>
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/types.h>
+
+int64_t nkt_entry(int64_t argc);
+
+static _Noreturn void fail(const char *fmt, ...) {
+	va_list ap;
+
+	fflush(stdout);
+	fputs("runtime error: ", stderr);
+	va_start(ap, fmt);
+	vfprintf(stderr, fmt, ap);
+	va_end(ap);
+	fputc('\n', stderr);
+	exit(1);
+}
+
+void nkt_rt_likho(const char *s) {
+	puts(s);
+}
+
+void nkt_rt_likho_ginti(int64_t i) {
+	printf("%" PRId64 "\n", i);
+}
+
+const char *nkt_rt_parho(void) {
+	char *line = NULL;
+	size_t cap = 0;
+	ssize_t len = getline(&line, &cap, stdin);
+
+	if (len < 0)
+		return "";
+
+	while (len > 0 && (line[len - 1] == '\n' || line[len - 1] == '\r'))
+		line[--len] = '\0';
+	return line;
+}
+
+int64_t nkt_rt_str_cmp(const char *a, const char *b) {
+	return strcmp(a, b);
+}
+
+/* `base ^ exp`, wrapping on overflow */
+int64_t nkt_rt_pow(int64_t base, int64_t exp) {
+	uint64_t acc = 1, b = (uint64_t)base;
+
+	if (exp < 0)
+		fail("NegativeExponent(%" PRId64 ")", exp);
+
+	while (exp > 0) {
+		if (exp & 1)
+			acc *= b;
+		b *= b;
+		exp >>= 1;
+	}
+	return (int64_t)acc;
+}
+
+void nkt_rt_division_by_zero(void) {
+	fail("DivisionByZero");
+}
+
+void nkt_rt_shift_out_of_range(int64_t amount) {
+	fail("ShiftOutOfRange(%" PRId64 ")", amount);
+}
+
+void nkt_rt_missing_return(const char *func) {
+	fail("MissingReturn(\"%s\")", func);
+}
+
+int main(int argc, char **argv) {
+	(void)argv;
+	int64_t code = nkt_entry(argc);
+
+	fflush(stdout);
+	return (int)code;
+}
@@ -0,0 +1,537 @@
+//! x86-64 code generation, for Linux: GNU assembler (AT&T syntax) following the System V ABI, so
+//! compiled functions can call, and be called from, C. Takes a module out of SSA form.
+//!
+//! Registers are allocated among `%r10`, `%rbx` and `%r12`-`%r15`, and `%xmm8`-`%xmm15` for
+//! `asharia`s; those that don't get one are spilled to a stack slot of 8 bytes, below `%rbp`, and
+//! the callee-saved registers used are saved past those. Instructions load operands into the
+//! argument registers, `%rax` and `%r11`, and `%xmm0`/`%xmm1`, which are never allocated.
+//! `ginti`s, `boli`s (as 0 or 1) and `jumla`s (as a pointer to a NUL-terminated string) travel in
+//! general purpose registers, and `asharia`s in SSE ones.
+
+use std::fmt::Write;
+
+use super::core::{escape_string, fn_symbol, global_symbol};
+use super::isel::{self, Asm, Isel};
+use super::regalloc::core::{
+    self as regalloc, Allocation, Allocator, Loc, RegClass, RegFile, Regs,
+};
+use crate::ir::core::{
+    BinOp, BlockId, Callee, Const, FnId, Function, GlobalId, Inst, Module, Operand, Reg, Terminator,
+};
+use crate::semantics::intrinsics::INTRINSICS;
+use crate::semantics::spaghetti::SymType;
+
+const INT_ARG_REGS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+const FLOAT_ARG_REGS: usize = 8; // %xmm0 to %xmm7
+
+// No SSE registers are callee-saved
+pub const REG_FILE: RegFile = RegFile {
+    int: Regs {
+        caller_saved: &["%r10"],
+        callee_saved: &["%rbx", "%r12", "%r13", "%r14", "%r15"],
+    },
+    float: Regs {
+        caller_saved: &[
+            "%xmm8", "%xmm9", "%xmm10", "%xmm11", "%xmm12", "%xmm13", "%xmm14", "%xmm15",
+        ],
+        callee_saved: &[],
+    },
+    class: |t| match t {
+        SymType::Float => Some(RegClass::Float),
+        _ => Some(RegClass::Int),
+    },
+    clobbers,
+};
+
+// Calls, and the operators lowered to them
+fn clobbers(f: &Function, inst: &Inst) -> bool {
+    regalloc::calls_out(f, inst, |op, t| match t {
+        SymType::String => true,
+        SymType::Float => matches!(op, BinOp::Mod | BinOp::Exp),
+        _ => op == BinOp::Exp,
+    })
+}
+
+/// Assembly for `module`, which defines `nkt_entry(argc)`: it initialises globals, then calls
+/// `shuru`. The runtime's `main` calls it in turn.
+pub fn generate(module: &Module, allocator: Allocator) -> String {
+    let mut out = String::from("\t.text\n");
+
+    for func in 0..module.functions.len() {
+        out.push('\n');
+        isel::select(&mut FnGen::new(module, func as FnId, allocator, &mut out));
+    }
+    if let Some(entry) = module.entry {
+        entry_point(module, entry, &mut out);
+    }
+
+    let _ = writeln!(out, "\n\t.section .rodata");
+    for (id, s) in module.strings.iter().enumerate() {
+        let _ = writeln!(out, ".Lstr{id}:\n\t.string \"{}\"", escape_string(s));
+    }
+    // Names, for reporting functions that ran off their end
+    for (func, f) in module.functions.iter().enumerate() {
+        if f.blocks.iter().any(|b| b.term == Terminator::MissingRet) {
+            let sym = fn_symbol(module, func as FnId);
+            let _ = writeln!(
+                out,
+                ".L{sym}.name:\n\t.string \"{}\"",
+                escape_string(&f.name)
+            );
+        }
+    }
+
+    if !module.globals.is_empty() {
+        let _ = writeln!(out, "\n\t.bss\n\t.balign 8");
+    }
+    for global in 0..module.globals.len() as u32 {
+        let _ = writeln!(out, "{}:\n\t.zero 8", global_symbol(module, global));
+    }
+
+    // No executable stack
+    out.push_str("\n\t.section .note.GNU-stack,\"\",@progbits\n");
+    out
+}
+
+fn entry_point(module: &Module, entry: FnId, out: &mut String) {
+    let shuru = &module.functions[entry as usize];
+
+    out.push_str("\n\t.globl nkt_entry\nnkt_entry:\n");
+    out.push_str("\tpushq %rbp\n\tmovq %rsp, %rbp\n");
+    out.push_str("\tpushq %rdi\n\tsubq $8, %rsp\n"); // argc, and the stack kept aligned
+    let _ = writeln!(out, "\tcall {}", fn_symbol(module, module.init));
+    out.push_str("\tmovq -8(%rbp), %rdi\n");
+    let _ = writeln!(out, "\tcall {}", fn_symbol(module, entry));
+    if shuru.ret_type != SymType::Int {
+        out.push_str("\txorl %eax, %eax\n");
+    }
+    out.push_str("\tleave\n\tret\n");
+}
+
+// Where each argument of a call (or parameter of a function) is passed
+enum ArgLoc {
+    Int(&'static str),
+    Float(usize), // `%xmm<n>`
+    Stack(usize), // the nth argument passed on the stack
+}
+
+fn arg_locs(types: impl Iterator<Item = SymType>) -> Vec<ArgLoc> {
+    let (mut ints, mut floats, mut stack) = (0, 0, 0);
+    types
+        .map(|t| match t {
+            SymType::Float if floats < FLOAT_ARG_REGS => {
+                floats += 1;
+                ArgLoc::Float(floats - 1)
+            }
+            SymType::Float => {
+                stack += 1;
+                ArgLoc::Stack(stack - 1)
+            }
+            _ if ints < INT_ARG_REGS.len() => {
+                ints += 1;
+                ArgLoc::Int(INT_ARG_REGS[ints - 1])
+            }
+            _ => {
+                stack += 1;
+                ArgLoc::Stack(stack - 1)
+            }
+        })
+        .collect()
+}
+
+struct FnGen<'m, 'o> {
+    module: &'m Module,
+    f: &'m Function,
+    alloc: Allocation,
+    asm: Asm<'o>,
+}
+
+impl<'m, 'o> FnGen<'m, 'o> {
+    fn new(module: &'m Module, func: FnId, allocator: Allocator, out: &'o mut String) -> Self {
+        let f = &module.functions[func as usize];
+        FnGen {
+            module,
+            f,
+            alloc: regalloc::allocate(f, &REG_FILE, allocator),
+            asm: Asm::new(out, fn_symbol(module, func), (".L", ".")),
+        }
+    }
+
+    // The nth slot
+    fn slot(&self, n: usize) -> String {
+        format!("-{}(%rbp)", 8 * (n + 1))
+    }
+
+    // Where `reg` is kept, as an operand
+    fn loc(&self, reg: Reg) -> String {
+        match self.alloc.loc(reg) {
+            Loc::Reg(name) => name.to_string(),
+            Loc::Slot(n) => self.slot(n),
+        }
+    }
+
+    // The general purpose register `operand` is already in, if any, or `scratch`, once it's
+    // loaded
+    fn int_operand(&mut self, operand: &Operand, scratch: &'static str) -> &'static str {
+        if let Operand::Reg(reg) = *operand {
+            if let (Loc::Reg(name), false) =
+                (self.alloc.loc(reg), self.f.reg_type(reg) == SymType::Float)
+            {
+                return name;
+            }
+        }
+        self.load(operand, scratch);
+        scratch
+    }
+
+    // Loads `operand`'s bits into a general purpose register
+    fn load(&mut self, operand: &Operand, dst: &str) {
+        let line = match *operand {
+            Operand::Reg(reg) => format!("movq {}, {dst}", self.loc(reg)),
+            Operand::Const(Const::Int(i)) if i32::try_from(i).is_ok() => {
+                format!("movq ${i}, {dst}")
+            }
+            Operand::Const(Const::Int(i)) => format!("movabsq ${i}, {dst}"),
+            Operand::Const(Const::Float(x)) => format!("movabsq ${}, {dst}", x.to_bits() as i64),
+            Operand::Const(Const::Bool(b)) => format!("movq ${}, {dst}", b as i64),
+            Operand::Const(Const::Str(id)) => format!("leaq .Lstr{id}(%rip), {dst}"),
+        };
+        self.asm.emit(&line);
+    }
+
+    fn load_float(&mut self, operand: &Operand, dst: usize) {
+        match operand {
+            Operand::Reg(reg) => self
+                .asm
+                .emit(&format!("movsd {}, %xmm{dst}", self.loc(*reg))),
+            Operand::Const(_) => {
+                self.load(operand, "%r11");
+                self.asm.emit(&format!("movq %r11, %xmm{dst}"));
+            }
+        }
+    }
+
+    // Stores the bits in general purpose register `src` to `dst`
+    fn store(&mut self, src: &str, dst: Reg) {
+        self.asm.emit(&format!("movq {src}, {}", self.loc(dst)));
+    }
+
+    fn store_float(&mut self, src: usize, dst: Reg) {
+        self.asm
+            .emit(&format!("movsd %xmm{src}, {}", self.loc(dst)));
+    }
+
+    // Saves, or restores, the callee-saved registers the function uses, past its slots; they're
+    // only ever general purpose ones
+    fn callee_saved(&mut self, save: bool) {
+        for (k, (_, name)) in self.alloc.callee_saved.clone().into_iter().enumerate() {
+            let slot = self.slot(self.alloc.n_slots + k);
+            match save {
+                true => self.asm.emit(&format!("movq {name}, {slot}")),
+                false => self.asm.emit(&format!("movq {slot}, {name}")),
+            }
+        }
+    }
+
+    // Sets `dst` to whether the flags say `op`, signed
+    fn set_flag(&mut self, op: BinOp, dst: &str) {
+        let set = match op {
+            BinOp::Lt => "setl",
+            BinOp::Gt => "setg",
+            _ => "sete",
+        };
+        self.asm.emit(&format!("{set} %al"));
+        self.asm.emit(&format!("movzbq %al, {dst}"));
+    }
+}
+
+impl<'m> Isel<'m> for FnGen<'m, '_> {
+    fn module(&self) -> &'m Module {
+        self.module
+    }
+
+    fn func(&self) -> &'m Function {
+        self.f
+    }
+
+    fn prologue(&mut self) {
+        let n_slots = self.alloc.n_slots + self.alloc.callee_saved.len();
+        let frame = (8 * n_slots).next_multiple_of(16);
+
+        let sym = self.asm.sym.clone();
+        self.asm.label(&sym);
+        self.asm.emit("pushq %rbp");
+        self.asm.emit("movq %rsp, %rbp");
+        if frame > 0 {
+            self.asm.emit(&format!("subq ${frame}, %rsp"));
+        }
+        self.callee_saved(true);
+
+        let locs = arg_locs(self.f.params.iter().map(|&p| self.f.reg_type(p)));
+        for (&param, loc) in self.f.params.iter().zip(locs) {
+            match loc {
+                ArgLoc::Int(reg) => self.store(reg, param),
+                ArgLoc::Float(n) => self.store_float(n, param),
+                ArgLoc::Stack(n) => {
+                    // Above the return address, and the caller's `%rbp`
+                    self.asm.emit(&format!("movq {}(%rbp), %rax", 16 + 8 * n));
+                    self.store("%rax", param);
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, id: BlockId) {
+        let label = self.asm.block_label(id);
+        self.asm.label(&label);
+    }
+
+    fn copy(&mut self, dst: Reg, src: &Operand) {
+        // Straight b/w registers of the same class, if they're in them
+        if let Operand::Reg(src) = *src {
+            let float = self.f.reg_type(src) == SymType::Float;
+            match (self.alloc.loc(dst), self.alloc.loc(src)) {
+                (Loc::Reg(d), Loc::Reg(s)) if d == s => return,
+                (Loc::Reg(d), Loc::Reg(s)) if float => {
+                    return self.asm.emit(&format!("movsd {s}, {d}"));
+                }
+                (Loc::Reg(d), Loc::Reg(s)) => return self.asm.emit(&format!("movq {s}, {d}")),
+                _ => {}
+            }
+        }
+
+        self.load(src, "%rax");
+        self.store("%rax", dst);
+    }
+
+    fn int_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load(lhs, "%rax");
+        // Shifts take their amount in `%cl`
+        let r = match op {
+            BinOp::Shl | BinOp::Shr => {
+                self.load(rhs, "%rcx");
+                "%rcx"
+            }
+            _ => self.int_operand(rhs, "%rcx"),
+        };
+
+        match op {
+            BinOp::Add => self.asm.emit(&format!("addq {r}, %rax")),
+            BinOp::Sub => self.asm.emit(&format!("subq {r}, %rax")),
+            BinOp::Mul => self.asm.emit(&format!("imulq {r}, %rax")),
+            BinOp::BitAnd => self.asm.emit(&format!("andq {r}, %rax")),
+            BinOp::BitOr => self.asm.emit(&format!("orq {r}, %rax")),
+
+            BinOp::Lt | BinOp::Gt | BinOp::Eq => {
+                self.asm.emit(&format!("cmpq {r}, %rax"));
+                self.set_flag(op, "%rax");
+            }
+
+            // `idiv` traps on `i64::MIN / -1`, which wraps instead
+            BinOp::Div | BinOp::Mod => {
+                let (nonzero, not_minus_one, done) = (
+                    self.asm.new_label(),
+                    self.asm.new_label(),
+                    self.asm.new_label(),
+                );
+
+                self.asm.emit(&format!("testq {r}, {r}"));
+                self.asm.emit(&format!("jne {nonzero}"));
+                self.asm.emit("call nkt_rt_division_by_zero@PLT");
+                self.asm.label(&nonzero);
+
+                self.asm.emit(&format!("cmpq $-1, {r}"));
+                self.asm.emit(&format!("jne {not_minus_one}"));
+                match op {
+                    BinOp::Div => self.asm.emit("negq %rax"),
+                    _ => self.asm.emit("xorl %eax, %eax"),
+                }
+                self.asm.emit(&format!("jmp {done}"));
+                self.asm.label(&not_minus_one);
+
+                self.asm.emit("cqto");
+                self.asm.emit(&format!("idivq {r}"));
+                if op == BinOp::Mod {
+                    self.asm.emit("movq %rdx, %rax");
+                }
+                self.asm.label(&done);
+            }
+
+            BinOp::Exp => {
+                self.asm.emit("movq %rax, %rdi");
+                self.asm.emit(&format!("movq {r}, %rsi"));
+                self.asm.emit("call nkt_rt_pow@PLT");
+            }
+
+            // Unsigned, so negative amounts are out of range too
+            BinOp::Shl | BinOp::Shr => {
+                let in_range = self.asm.new_label();
+                self.asm.emit("cmpq $63, %rcx");
+                self.asm.emit(&format!("jbe {in_range}"));
+                self.asm.emit("movq %rcx, %rdi");
+                self.asm.emit("call nkt_rt_shift_out_of_range@PLT");
+                self.asm.label(&in_range);
+
+                match op {
+                    BinOp::Shl => self.asm.emit("shlq %cl, %rax"),
+                    _ => self.asm.emit("sarq %cl, %rax"),
+                }
+            }
+        }
+
+        self.store("%rax", dst);
+    }
+
+    fn float_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load_float(lhs, 0);
+        self.load_float(rhs, 1);
+
+        match op {
+            BinOp::Add => self.asm.emit("addsd %xmm1, %xmm0"),
+            BinOp::Sub => self.asm.emit("subsd %xmm1, %xmm0"),
+            BinOp::Mul => self.asm.emit("mulsd %xmm1, %xmm0"),
+            BinOp::Div => self.asm.emit("divsd %xmm1, %xmm0"),
+            BinOp::Mod => self.asm.emit("call fmod@PLT"),
+            BinOp::Exp => self.asm.emit("call pow@PLT"),
+
+            // Comparisons w/ NaN are unordered, which `seta` (and `setnp`) treat as false
+            BinOp::Lt | BinOp::Gt => {
+                match op {
+                    BinOp::Lt => self.asm.emit("ucomisd %xmm0, %xmm1"),
+                    _ => self.asm.emit("ucomisd %xmm1, %xmm0"),
+                }
+                self.asm.emit("seta %al");
+                self.asm.emit("movzbq %al, %rax");
+                self.store("%rax", dst);
+                return;
+            }
+            BinOp::Eq => {
+                self.asm.emit("ucomisd %xmm1, %xmm0");
+                self.asm.emit("sete %al");
+                self.asm.emit("setnp %cl");
+                self.asm.emit("andb %cl, %al");
+                self.asm.emit("movzbq %al, %rax");
+                self.store("%rax", dst);
+                return;
+            }
+
+            _ => unreachable!("`{op:?}` on floats"),
+        }
+
+        self.store_float(0, dst);
+    }
+
+    fn str_binary(&mut self, dst: Reg, op: BinOp, lhs: &Operand, rhs: &Operand) {
+        self.load(lhs, "%rdi");
+        self.load(rhs, "%rsi");
+        self.asm.emit("call nkt_rt_str_cmp@PLT");
+        self.asm.emit("cmpq $0, %rax");
+        self.set_flag(op, "%rax");
+        self.store("%rax", dst);
+    }
+
+    fn int_neg(&mut self, dst: Reg, operand: &Operand) {
+        self.load(operand, "%rax");
+        self.asm.emit("negq %rax");
+        self.store("%rax", dst);
+    }
+
+    fn float_neg(&mut self, dst: Reg, operand: &Operand) {
+        self.load(operand, "%rax");
+        self.asm.emit("btcq $63, %rax"); // the sign bit
+        self.store("%rax", dst);
+    }
+
+    fn not(&mut self, dst: Reg, operand: &Operand) {
+        self.load(operand, "%rax");
+        self.asm.emit("xorq $1, %rax");
+        self.store("%rax", dst);
+    }
+
+    fn call(&mut self, dst: Option<Reg>, callee: Callee, ret_type: SymType, args: &[Operand]) {
+        let target = match callee {
+            Callee::Fn(func) => fn_symbol(self.module, func),
+            Callee::Intrinsic(i) => format!("nkt_rt_{}@PLT", INTRINSICS[i as usize].name),
+        };
+
+        let locs = arg_locs(args.iter().map(|arg| self.f.operand_type(arg)));
+        let n_stack = locs
+            .iter()
+            .filter(|loc| matches!(loc, ArgLoc::Stack(_)))
+            .count();
+
+        // `%rsp` must be 16-byte aligned at the call
+        let pad = n_stack % 2 * 8;
+        if pad > 0 {
+            self.asm.emit("subq $8, %rsp");
+        }
+        for (arg, _) in args
+            .iter()
+            .zip(&locs)
+            .rev()
+            .filter(|(_, loc)| matches!(loc, ArgLoc::Stack(_)))
+        {
+            self.load(arg, "%rax");
+            self.asm.emit("pushq %rax");
+        }
+        for (arg, loc) in args.iter().zip(&locs) {
+            match loc {
+                ArgLoc::Int(reg) => self.load(arg, reg),
+                ArgLoc::Float(n) => self.load_float(arg, *n),
+                ArgLoc::Stack(_) => {}
+            }
+        }
+
+        self.asm.emit(&format!("call {target}"));
+        if n_stack > 0 {
+            self.asm.emit(&format!("addq ${}, %rsp", 8 * n_stack + pad));
+        }
+
+        match (dst, ret_type) {
+            (Some(dst), SymType::Float) => self.store_float(0, dst),
+            (Some(dst), _) => self.store("%rax", dst),
+            (None, _) => {}
+        }
+    }
+
+    fn load_global(&mut self, dst: Reg, global: GlobalId) {
+        let sym = global_symbol(self.module, global);
+        self.asm.emit(&format!("movq {sym}(%rip), %rax"));
+        self.store("%rax", dst);
+    }
+
+    fn store_global(&mut self, global: GlobalId, src: &Operand) {
+        self.load(src, "%rax");
+        let sym = global_symbol(self.module, global);
+        self.asm.emit(&format!("movq %rax, {sym}(%rip)"));
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        let label = self.asm.block_label(target);
+        self.asm.emit(&format!("jmp {label}"));
+    }
+
+    fn branch(&mut self, cond: &Operand, target: BlockId) {
+        let cond = self.int_operand(cond, "%rax");
+        self.asm.emit(&format!("testq {cond}, {cond}"));
+        let label = self.asm.block_label(target);
+        self.asm.emit(&format!("jne {label}"));
+    }
+
+    fn ret(&mut self, value: Option<&Operand>) {
+        match value {
+            Some(value) if self.f.ret_type == SymType::Float => self.load_float(value, 0),
+            Some(value) => self.load(value, "%rax"),
+            None => {}
+        }
+        self.callee_saved(false);
+        self.asm.emit("leave");
+        self.asm.emit("ret");
+    }
+
+    fn missing_ret(&mut self) {
+        self.asm
+            .emit(&format!("leaq .L{}.name(%rip), %rdi", self.asm.sym));
+        self.asm.emit("call nkt_rt_missing_return@PLT");
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use super::dot::{ast_to_dot, cfg_to_dot, scopes_to_dot};
+use super::json::{ast_to_json, symtab_to_json, tokens_to_json};
+use crate::codegen::regalloc::core::Allocator;
+use crate::codegen::x86_64;
+use crate::ir::print::module_to_string;
+use crate::lexer::core::{tokenize_src_code_with_spans, SpannedTokens};
+use crate::parser::ast::print::ast_to_string;
+use crate::semantics::core::{analyse_semantics_partial, hir_to_string};
+use crate::{parse_src, Artifacts};
+
+/// A stage of the compiler whose output can be dumped via `nktc --emit=<stage>`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Emit {
+    Tokens,
+    Ast,
+    Symtab,
+    Hir,
+    Ir,
+    Ssa,
+    Asm,
+    TokensJson,
+    AstJson,
+    SymtabJson,
+    ScopesDot,
+    AstDot,
+    CfgDot,
+}
+
+#[derive(Debug)]
+pub enum EmitError {
+    UnknownStage(String),
+}
+
+impl FromStr for Emit {
+    type Err = EmitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tokens" => Ok(Emit::Tokens),
+            "ast" => Ok(Emit::Ast),
+            "symtab" => Ok(Emit::Symtab),
+            "hir" => Ok(Emit::Hir),
+            "ir" => Ok(Emit::Ir),
+            "ssa" => Ok(Emit::Ssa),
+            "asm" => Ok(Emit::Asm),
+            "tokens-json" => Ok(Emit::TokensJson),
+            "ast-json" => Ok(Emit::AstJson),
+            "symtab-json" => Ok(Emit::SymtabJson),
+            "scopes-dot" => Ok(Emit::ScopesDot),
+            "ast-dot" => Ok(Emit::AstDot),
+            "cfg-dot" => Ok(Emit::CfgDot),
+            _ => Err(EmitError::UnknownStage(s.to_string())),
+        }
+    }
+}
+
+impl Emit {
+    /// Whether this stage is rendered from the IR, and so needs `compile_src_opt`'s artifacts
+    pub fn needs_ir(self) -> bool {
+        matches!(self, Emit::Ir | Emit::Ssa | Emit::Asm | Emit::CfgDot)
+    }
+
+    /// Renders this stage's output as text. `Asm` is x86-64 assembly, w/ registers allocated as
+    /// `nktc build --target=x86_64-linux` would at the same `-O` level.
+    ///
+    /// Panics if the stage `needs_ir`, and `artifacts` weren't lowered.
+    pub fn render(self, artifacts: &Artifacts) -> String {
+        let lowered = || {
+            artifacts
+                .lowered
+                .as_ref()
+                .expect("artifacts to be lowered for the IR's stages")
+        };
+
+        match self {
+            Emit::Tokens => tokens_to_string(&artifacts.tokens),
+            Emit::Ast => ast_to_string(&artifacts.ast),
+            Emit::Symtab => format!("{:#?}\n", artifacts.sym_table),
+            Emit::Hir => hir_to_string(&artifacts.ast, &artifacts.sym_table),
+            Emit::Ir => module_to_string(&lowered().ir),
+            Emit::Ssa => module_to_string(&lowered().ssa),
+            Emit::Asm => x86_64::generate(&lowered().ir, Allocator::for_level(lowered().level)),
+            Emit::TokensJson => tokens_to_json(&artifacts.tokens),
+            Emit::AstJson => ast_to_json(&artifacts.ast),
+            Emit::SymtabJson => symtab_to_json(&artifacts.sym_table),
+            Emit::ScopesDot => scopes_to_dot(&artifacts.sym_table),
+            Emit::AstDot => ast_to_dot(&artifacts.ast),
+            Emit::CfgDot => cfg_to_dot(&lowered().ir),
+        }
+    }
+
+    /// What can still be rendered of this stage when `src_code` fails to compile, if anything.
+    /// Stages up to (and including) the symbol table are available as long as the source parses;
+    /// the symbol table is then as it stood when semantic analysis failed.
+    pub fn render_partial(self, src_code: &str) -> Option<String> {
+        match self {
+            Emit::Tokens | Emit::TokensJson => {
+                let lexed = tokenize_src_code_with_spans(src_code).ok()?;
+                match self {
+                    Emit::Tokens => Some(tokens_to_string(&lexed)),
+                    _ => Some(tokens_to_json(&lexed)),
+                }
+            }
+
+            Emit::Ast => Some(ast_to_string(&parse_src(src_code).ok()?)),
+            Emit::AstJson => Some(ast_to_json(&parse_src(src_code).ok()?)),
+            Emit::AstDot => Some(ast_to_dot(&parse_src(src_code).ok()?)),
+
+            Emit::Symtab | Emit::SymtabJson | Emit::ScopesDot => {
+                let (sym_table, _) = analyse_semantics_partial(&parse_src(src_code).ok()?);
+                match self {
+                    Emit::Symtab => Some(format!("{sym_table:#?}\n")),
+                    Emit::SymtabJson => Some(symtab_to_json(&sym_table)),
+                    _ => Some(scopes_to_dot(&sym_table)),
+                }
+            }
+
+            Emit::Hir | Emit::Ir | Emit::Ssa | Emit::Asm | Emit::CfgDot => None,
+        }
+    }
+}
+
+/// One token per line, preceded by the `line:col` it starts at
+pub fn tokens_to_string(lexed: &SpannedTokens) -> String {
+    let mut out = String::new();
+
+    for (token, span) in lexed.tokens.iter().zip(&lexed.spans) {
+        let _ = writeln!(out, "{}:{}\t{token:?}", span.line, span.col);
+    }
+
+    out
+}
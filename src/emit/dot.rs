@@ -0,0 +1,360 @@
+//! Graphviz exports, e.g for `nktc --emit=scopes-dot src.nkt | dot -Tsvg > scopes.svg`
+
+use std::fmt::Write;
+
+use crate::ir::core::{Module, Terminator};
+use crate::ir::print::{inst_to_string, term_to_string};
+use crate::lexer::Token;
+use crate::parser::ast::core::*;
+use crate::semantics::spaghetti::SpaghettiStack;
+
+/// One record per `ScopeMap`, listing its symbols (and parameter types, for functions), w/ an
+/// edge to each of its children, labelled w/ the child's name if it has one.
+pub fn scopes_to_dot(spaghet: &SpaghettiStack) -> String {
+    let mut g = DotGraph::new("scopes", "shape=record");
+
+    for id in spaghet.scope_ids() {
+        let mut label = format!("#{id} {:?}", spaghet.get_scope_type(id));
+
+        let params = spaghet.get_scope_param_types(id);
+        if !params.is_empty() {
+            let params = params.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            label += &format!("\\lparams: {}", params.join(", "));
+        }
+        label += "\\l|";
+
+        for (ident, info) in spaghet.get_symbols(id) {
+            let kind = if info.is_var() { "" } else { "fn " };
+            label += &format!("{kind}{ident}: {}\\l", info.get_type());
+        }
+
+        g.node_with_id(&format!("s{id}"), &format!("{{{label}}}"));
+    }
+
+    for id in spaghet.scope_ids() {
+        for (child_id, name) in spaghet.get_children(id) {
+            g.edge(&format!("s{id}"), &format!("s{child_id}"), name);
+        }
+    }
+
+    g.finish()
+}
+
+/// One box per basic block, listing its instructions, w/ edges along control flow. Branches'
+/// edges are labelled w/ the outcome they're taken on.
+pub fn cfg_to_dot(module: &Module) -> String {
+    let mut g = DotGraph::new("cfg", "shape=box");
+
+    for (fn_id, f) in module.functions.iter().enumerate() {
+        let node_id = |block: u32| format!("f{fn_id}_bb{}", block);
+
+        for id in f.block_ids() {
+            let block = f.block(id);
+            let mut label = format!("{} bb{}:\\l", escape(&f.name), id.0);
+            for inst in &block.insts {
+                label += &format!("    {}\\l", escape(&inst_to_string(module, f, inst)));
+            }
+            label += &format!("    {}\\l", escape(&term_to_string(module, &block.term)));
+            g.node_with_id(&node_id(id.0), &label);
+
+            match &block.term {
+                Terminator::Branch {
+                    then_block,
+                    else_block,
+                    ..
+                } => {
+                    g.edge(&node_id(id.0), &node_id(then_block.0), Some("sach"));
+                    g.edge(&node_id(id.0), &node_id(else_block.0), Some("jhoot"));
+                }
+                Terminator::Jump(target) => g.edge(&node_id(id.0), &node_id(target.0), None),
+                Terminator::Ret(_) | Terminator::MissingRet => {}
+            }
+        }
+    }
+
+    g.finish()
+}
+
+/// The parse tree, one node per AST node. Precedence levels w/ a single operand are skipped over,
+/// like in `ast_to_string`.
+pub fn ast_to_dot(ast_root: &TranslationUnit) -> String {
+    let mut g = DotGraph::new("ast", "shape=box");
+    let root = g.node("TranslationUnit");
+
+    for decl in ast_root {
+        let child = match decl {
+            Decl::Var(v) => var_decl(&mut g, v),
+            Decl::Fn(f) => fn_decl(&mut g, f),
+        };
+        g.edge(&root, &child, None);
+    }
+
+    g.finish()
+}
+
+fn var_decl(g: &mut DotGraph, v: &VarDecl) -> String {
+    let id = g.node(&format!("VarDecl {} {}", v.type_tok, v.ident));
+    let expr = expr(g, &v.expr);
+    g.edge(&id, &expr, None);
+    id
+}
+
+fn fn_decl(g: &mut DotGraph, f: &FnDecl) -> String {
+    let id = g.node(&format!("FnDecl {} {}", f.type_tok, f.ident));
+
+    for p in &f.params {
+        let param = g.node(&format!("Param {} {}", p.type_tok, p.ident));
+        g.edge(&id, &param, None);
+    }
+
+    let body = block(g, &f.block);
+    g.edge(&id, &body, None);
+    id
+}
+
+fn block(g: &mut DotGraph, stmts: &Block) -> String {
+    let id = g.node("Block");
+
+    for stmt in stmts {
+        let child = match stmt {
+            Stmt::VarDecl(v) => var_decl(g, v),
+
+            Stmt::For(f) => {
+                let for_id = g.node("For");
+                let init = match &f.init {
+                    Some(init) => var_decl(g, init),
+                    None => g.node("<empty>"),
+                };
+                let cond = expr(g, &f.cond.expr);
+                let updt = expr(g, &f.updt);
+                let body = block(g, &f.block);
+
+                g.edge(&for_id, &init, Some("init"));
+                g.edge(&for_id, &cond, Some("cond"));
+                g.edge(&for_id, &updt, Some("updt"));
+                g.edge(&for_id, &body, Some("body"));
+                for_id
+            }
+
+            Stmt::If(i) => {
+                let if_id = g.node("If");
+                let cond = expr(g, &i.cond);
+                let if_block = block(g, &i.if_block);
+                let else_block = block(g, &i.else_block);
+
+                g.edge(&if_id, &cond, Some("cond"));
+                g.edge(&if_id, &if_block, Some("agar"));
+                g.edge(&if_id, &else_block, Some("warna"));
+                if_id
+            }
+
+            Stmt::Ret(r) => unary_node(g, "Ret", &r.expr),
+            Stmt::Expr(e) => unary_node(g, "ExprStmt", &e.expr),
+            Stmt::Break(_) => g.node("Break"),
+        };
+
+        g.edge(&id, &child, None);
+    }
+
+    id
+}
+
+fn unary_node(g: &mut DotGraph, label: &str, e: &Expr) -> String {
+    let id = g.node(label);
+    let child = expr(g, e);
+    g.edge(&id, &child, None);
+    id
+}
+
+fn binary_node(g: &mut DotGraph, label: &str, op: &Token, lhs: String, rhs: String) -> String {
+    let id = g.node(&format!("{label} {op}"));
+    g.edge(&id, &lhs, None);
+    g.edge(&id, &rhs, None);
+    id
+}
+
+fn expr(g: &mut DotGraph, e: &Expr) -> String {
+    match e {
+        Some(e) => assign_expr(g, e),
+        None => g.node("<empty>"),
+    }
+}
+
+fn assign_expr(g: &mut DotGraph, e: &AssignExpr) -> String {
+    match e {
+        AssignExpr::Bool(b) => bool_expr(g, b),
+        AssignExpr::Assign(lhs, rhs) => {
+            let (lhs, rhs) = (bool_expr(g, lhs), assign_expr(g, rhs));
+            binary_node(g, "Assign", &Token::AssignOp, lhs, rhs)
+        }
+    }
+}
+
+fn bool_expr(g: &mut DotGraph, e: &BoolExpr) -> String {
+    match e {
+        BoolExpr::BitOr(b) => bit_or_expr(g, b),
+        BoolExpr::Bool(lhs, op, rhs) => {
+            let (lhs, rhs) = (bool_expr(g, lhs), bit_or_expr(g, rhs));
+            binary_node(g, "Bool", op, lhs, rhs)
+        }
+    }
+}
+
+fn bit_or_expr(g: &mut DotGraph, e: &BitOrExpr) -> String {
+    match e {
+        BitOrExpr::BitAnd(b) => bit_and_expr(g, b),
+        BitOrExpr::BitOr(lhs, rhs) => {
+            let (lhs, rhs) = (bit_or_expr(g, lhs), bit_and_expr(g, rhs));
+            binary_node(g, "BitOr", &Token::BitwiseOr, lhs, rhs)
+        }
+    }
+}
+
+fn bit_and_expr(g: &mut DotGraph, e: &BitAndExpr) -> String {
+    match e {
+        BitAndExpr::Comp(c) => comp_expr(g, c),
+        BitAndExpr::BitAnd(lhs, rhs) => {
+            let (lhs, rhs) = (bit_and_expr(g, lhs), comp_expr(g, rhs));
+            binary_node(g, "BitAnd", &Token::BitwiseAnd, lhs, rhs)
+        }
+    }
+}
+
+fn comp_expr(g: &mut DotGraph, e: &CompExpr) -> String {
+    match e {
+        CompExpr::Shift(s) => shift_expr(g, s),
+        CompExpr::Comp(lhs, op, rhs) => {
+            let (lhs, rhs) = (comp_expr(g, lhs), shift_expr(g, rhs));
+            binary_node(g, "Comp", op, lhs, rhs)
+        }
+    }
+}
+
+fn shift_expr(g: &mut DotGraph, e: &ShiftExpr) -> String {
+    match e {
+        ShiftExpr::Add(a) => add_expr(g, a),
+        ShiftExpr::Shift(lhs, op, rhs) => {
+            let (lhs, rhs) = (shift_expr(g, lhs), add_expr(g, rhs));
+            binary_node(g, "Shift", op, lhs, rhs)
+        }
+    }
+}
+
+fn add_expr(g: &mut DotGraph, e: &AddExpr) -> String {
+    match e {
+        AddExpr::Mul(m) => mul_expr(g, m),
+        AddExpr::Add(lhs, op, rhs) => {
+            let (lhs, rhs) = (add_expr(g, lhs), mul_expr(g, rhs));
+            binary_node(g, "Add", op, lhs, rhs)
+        }
+    }
+}
+
+fn mul_expr(g: &mut DotGraph, e: &MulExpr) -> String {
+    match e {
+        MulExpr::Exp(x) => exp_expr(g, x),
+        MulExpr::Mul(lhs, op, rhs) => {
+            let (lhs, rhs) = (mul_expr(g, lhs), exp_expr(g, rhs));
+            binary_node(g, "Mul", op, lhs, rhs)
+        }
+    }
+}
+
+fn exp_expr(g: &mut DotGraph, e: &ExpExpr) -> String {
+    match e {
+        ExpExpr::Unary(u) => unary_expr(g, u),
+        ExpExpr::Exp(lhs, rhs) => {
+            let (lhs, rhs) = (unary_expr(g, lhs), exp_expr(g, rhs));
+            binary_node(g, "Exp", &Token::ExpOp, lhs, rhs)
+        }
+    }
+}
+
+fn unary_expr(g: &mut DotGraph, e: &UnaryExpr) -> String {
+    match e {
+        UnaryExpr::Primary(p) => primary_expr(g, p),
+        UnaryExpr::Unary(op, operand) => {
+            let id = g.node(&format!("Unary {op}"));
+            let operand = unary_expr(g, operand);
+            g.edge(&id, &operand, None);
+            id
+        }
+    }
+}
+
+fn primary_expr(g: &mut DotGraph, e: &PrimaryExpr) -> String {
+    match e {
+        PrimaryExpr::IntLit(i) => g.node(&format!("IntLit {i}")),
+        PrimaryExpr::FloatLit(f) => g.node(&format!("FloatLit {f:?}")),
+        PrimaryExpr::StringLit(s) => g.node(&format!("StringLit {s:?}")),
+        PrimaryExpr::BoolLit(b) => g.node(&format!("BoolLit {b}")),
+        PrimaryExpr::Ident(ident) => g.node(&format!("Ident {ident}")),
+        PrimaryExpr::Paren(inner) => unary_node(g, "Paren", inner),
+        PrimaryExpr::Call(call) => {
+            let id = g.node(&format!("Call {}", call.ident));
+            for arg in &call.args {
+                let arg = expr(g, arg);
+                g.edge(&id, &arg, None);
+            }
+            id
+        }
+    }
+}
+
+struct DotGraph {
+    out: String,
+    next_id: usize,
+}
+
+impl DotGraph {
+    fn new(name: &str, node_attrs: &str) -> Self {
+        let mut out = format!("digraph {name} {{\n");
+        let _ = writeln!(out, "    node [{node_attrs}, fontname=\"monospace\"];");
+        Self { out, next_id: 0 }
+    }
+
+    // Adds a node w/ a fresh id, returning the id
+    fn node(&mut self, label: &str) -> String {
+        let id = format!("n{}", self.next_id);
+        self.next_id += 1;
+        self.node_with_id(&id, &escape(label));
+        id
+    }
+
+    // `label` is written out as is, so that record labels' structure survives
+    fn node_with_id(&mut self, id: &str, label: &str) {
+        let _ = writeln!(self.out, "    {id} [label=\"{label}\"];");
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>) {
+        match label {
+            Some(label) => {
+                let _ = writeln!(
+                    self.out,
+                    "    {from} -> {to} [label=\"{}\"];",
+                    escape(label)
+                );
+            }
+            None => {
+                let _ = writeln!(self.out, "    {from} -> {to};");
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.out += "}\n";
+        self.out
+    }
+}
+
+// Escapes text for use inside a double-quoted (non-record) label
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if matches!(c, '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,452 @@
+//! JSON export of the compiler's internals, for consumption by tools not written in Rust.
+//!
+//! Every document is an object w/ a `schema_version` (currently `SCHEMA_VERSION`) and a `kind`
+//! of `"tokens"`, `"ast"` or `"symtab"`. The version is bumped whenever a field is removed,
+//! renamed or changes meaning; adding a field doesn't bump it, so consumers should ignore fields
+//! they don't know about.
+//!
+//! Shared shapes:
+//! - span: `{"start", "end", "line", "col"}`; byte offsets (end exclusive), 1-indexed line/col
+//! - type: the type's keyword, e.g `"ginti"`
+//! - float: a number, or `null` if it isn't finite, which JSON has no way of writing
+//!
+//! `tokens`: `{"tokens": [{"kind", "value"?, "span"}]}`, where `kind` is the `Token` variant's
+//! name and `value` is only present for identifiers and literals.
+//!
+//! `ast`: `{"decls": [decl]}`. Every node is an object w/ a `node` field naming its kind:
+//! - `VarDecl {type, ident, init: expr, span}`
+//! - `FnDecl {type, ident, params: [{type, ident, span}], body: [stmt], span}`
+//! - `For {init: VarDecl | null, cond: expr | null, updt: expr | null, body: [stmt], span}`
+//! - `If {cond: expr, then: [stmt], else: [stmt], span, else_span}`
+//! - `Ret {expr: expr | null, span}`, `Expr {expr: expr | null, span}`, `Break {span}`
+//! - expressions: `Assign {lhs, rhs}`, `Binary {op, lhs, rhs}`, `Unary {op, operand}`,
+//!   `Paren {expr}`, `Call {ident, args: [expr], span}`, `Ident {name}` and
+//!   `IntLit`/`FloatLit`/`StringLit`/`BoolLit {value}`. Operators are given as they're written
+//!   in source, e.g `"<<"`. Single-operand precedence levels are collapsed.
+//!
+//! `symtab`: `{"scopes": [{"id", "scope_type", "parent": id | null, "children": [{"id",
+//! "name": string | null}], "symbols": [{"name", "is_var", "type"}], "param_types": [type]}]}`,
+//! w/ scopes in order of id, the root (0) first, and symbols sorted by name. `scope_type` is one
+//! of `Root`, `FnBlock`, `ForBlock`, `IfBlock` or `Intrinsic` (holding an intrinsic's params).
+
+use std::fmt::{self, Write};
+
+use crate::lexer::{core::SpannedTokens, Span, Token};
+use crate::parser::ast::core::*;
+use crate::semantics::spaghetti::SpaghettiStack;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub fn tokens_to_json(lexed: &SpannedTokens) -> String {
+    let tokens = lexed
+        .tokens
+        .iter()
+        .zip(&lexed.spans)
+        .map(|(token, span)| token_json(token, *span))
+        .collect();
+
+    document("tokens", vec![("tokens", Json::Array(tokens))])
+}
+
+pub fn ast_to_json(ast_root: &TranslationUnit) -> String {
+    let decls = ast_root
+        .iter()
+        .map(|decl| match decl {
+            Decl::Var(v) => var_decl_json(v),
+            Decl::Fn(f) => fn_decl_json(f),
+        })
+        .collect();
+
+    document("ast", vec![("decls", Json::Array(decls))])
+}
+
+pub fn symtab_to_json(spaghet: &SpaghettiStack) -> String {
+    let scopes = spaghet
+        .scope_ids()
+        .map(|id| {
+            let children = spaghet
+                .get_children(id)
+                .map(|(child_id, name)| {
+                    object(vec![
+                        ("id", Json::Int(child_id as i64)),
+                        ("name", name.map_or(Json::Null, Json::str)),
+                    ])
+                })
+                .collect();
+
+            let symbols = spaghet
+                .get_symbols(id)
+                .into_iter()
+                .map(|(name, info)| {
+                    object(vec![
+                        ("name", Json::str(name)),
+                        ("is_var", Json::Bool(info.is_var())),
+                        ("type", Json::str(&info.get_type().to_string())),
+                    ])
+                })
+                .collect();
+
+            let param_types = spaghet
+                .get_scope_param_types(id)
+                .iter()
+                .map(|t| Json::str(&t.to_string()))
+                .collect();
+
+            object(vec![
+                ("id", Json::Int(id as i64)),
+                (
+                    "scope_type",
+                    Json::str(&format!("{:?}", spaghet.get_scope_type(id))),
+                ),
+                (
+                    "parent",
+                    spaghet
+                        .get_node_parent_id(id)
+                        .map_or(Json::Null, |p| Json::Int(p as i64)),
+                ),
+                ("children", Json::Array(children)),
+                ("symbols", Json::Array(symbols)),
+                ("param_types", Json::Array(param_types)),
+            ])
+        })
+        .collect();
+
+    document("symtab", vec![("scopes", Json::Array(scopes))])
+}
+
+fn document(kind: &str, fields: Vec<(&str, Json)>) -> String {
+    let mut all = vec![
+        ("schema_version", Json::Int(SCHEMA_VERSION as i64)),
+        ("kind", Json::str(kind)),
+    ];
+    all.extend(fields);
+
+    format!("{}\n", object(all))
+}
+
+fn token_json(token: &Token, span: Span) -> Json {
+    let value = match token {
+        Token::Identifier(s) | Token::StringLit(s) => Some(Json::str(s)),
+        Token::IntLit(i) => Some(Json::Int(*i)),
+        Token::FloatLit(f) => Some(Json::Float(*f)),
+        _ => None,
+    };
+
+    // The Debug repr of a variant w/o data is just its name
+    let kind = match token {
+        Token::Identifier(_) => "Identifier".to_string(),
+        Token::StringLit(_) => "StringLit".to_string(),
+        Token::IntLit(_) => "IntLit".to_string(),
+        Token::FloatLit(_) => "FloatLit".to_string(),
+        _ => format!("{token:?}"),
+    };
+
+    let mut fields = vec![("kind", Json::String(kind))];
+    if let Some(value) = value {
+        fields.push(("value", value));
+    }
+    fields.push(("span", span_json(span)));
+
+    object(fields)
+}
+
+fn span_json(span: Span) -> Json {
+    object(vec![
+        ("start", Json::Int(span.start as i64)),
+        ("end", Json::Int(span.end as i64)),
+        ("line", Json::Int(span.line as i64)),
+        ("col", Json::Int(span.col as i64)),
+    ])
+}
+
+fn node(kind: &str, mut fields: Vec<(&str, Json)>) -> Json {
+    fields.insert(0, ("node", Json::str(kind)));
+    object(fields)
+}
+
+fn var_decl_json(v: &VarDecl) -> Json {
+    node(
+        "VarDecl",
+        vec![
+            ("type", Json::str(&v.type_tok.to_string())),
+            ("ident", Json::str(&v.ident)),
+            ("init", expr_json(&v.expr)),
+            ("span", span_json(v.span)),
+        ],
+    )
+}
+
+fn fn_decl_json(f: &FnDecl) -> Json {
+    let params = f
+        .params
+        .iter()
+        .map(|p| {
+            object(vec![
+                ("type", Json::str(&p.type_tok.to_string())),
+                ("ident", Json::str(&p.ident)),
+                ("span", span_json(p.span)),
+            ])
+        })
+        .collect();
+
+    node(
+        "FnDecl",
+        vec![
+            ("type", Json::str(&f.type_tok.to_string())),
+            ("ident", Json::str(&f.ident)),
+            ("params", Json::Array(params)),
+            ("body", block_json(&f.block)),
+            ("span", span_json(f.span)),
+        ],
+    )
+}
+
+fn block_json(block: &Block) -> Json {
+    Json::Array(block.iter().map(stmt_json).collect())
+}
+
+fn stmt_json(stmt: &Stmt) -> Json {
+    match stmt {
+        Stmt::VarDecl(v) => var_decl_json(v),
+
+        Stmt::For(f) => node(
+            "For",
+            vec![
+                ("init", f.init.as_ref().map_or(Json::Null, var_decl_json)),
+                ("cond", expr_json(&f.cond.expr)),
+                ("updt", expr_json(&f.updt)),
+                ("body", block_json(&f.block)),
+                ("span", span_json(f.span)),
+            ],
+        ),
+
+        Stmt::If(i) => node(
+            "If",
+            vec![
+                ("cond", expr_json(&i.cond)),
+                ("then", block_json(&i.if_block)),
+                ("else", block_json(&i.else_block)),
+                ("span", span_json(i.span)),
+                ("else_span", span_json(i.else_span)),
+            ],
+        ),
+
+        Stmt::Ret(r) => node(
+            "Ret",
+            vec![("expr", expr_json(&r.expr)), ("span", span_json(r.span))],
+        ),
+
+        Stmt::Expr(e) => node(
+            "Expr",
+            vec![("expr", expr_json(&e.expr)), ("span", span_json(e.span))],
+        ),
+
+        Stmt::Break(span) => node("Break", vec![("span", span_json(*span))]),
+    }
+}
+
+fn expr_json(expr: &Expr) -> Json {
+    expr.as_ref().map_or(Json::Null, assign_json)
+}
+
+fn binary(op: &Token, lhs: Json, rhs: Json) -> Json {
+    node(
+        "Binary",
+        vec![
+            ("op", Json::str(&op.to_string())),
+            ("lhs", lhs),
+            ("rhs", rhs),
+        ],
+    )
+}
+
+fn assign_json(e: &AssignExpr) -> Json {
+    match e {
+        AssignExpr::Bool(b) => bool_json(b),
+        AssignExpr::Assign(lhs, rhs) => node(
+            "Assign",
+            vec![("lhs", bool_json(lhs)), ("rhs", assign_json(rhs))],
+        ),
+    }
+}
+
+fn bool_json(e: &BoolExpr) -> Json {
+    match e {
+        BoolExpr::BitOr(b) => bit_or_json(b),
+        BoolExpr::Bool(lhs, op, rhs) => binary(op, bool_json(lhs), bit_or_json(rhs)),
+    }
+}
+
+fn bit_or_json(e: &BitOrExpr) -> Json {
+    match e {
+        BitOrExpr::BitAnd(b) => bit_and_json(b),
+        BitOrExpr::BitOr(lhs, rhs) => {
+            binary(&Token::BitwiseOr, bit_or_json(lhs), bit_and_json(rhs))
+        }
+    }
+}
+
+fn bit_and_json(e: &BitAndExpr) -> Json {
+    match e {
+        BitAndExpr::Comp(c) => comp_json(c),
+        BitAndExpr::BitAnd(lhs, rhs) => {
+            binary(&Token::BitwiseAnd, bit_and_json(lhs), comp_json(rhs))
+        }
+    }
+}
+
+fn comp_json(e: &CompExpr) -> Json {
+    match e {
+        CompExpr::Shift(s) => shift_json(s),
+        CompExpr::Comp(lhs, op, rhs) => binary(op, comp_json(lhs), shift_json(rhs)),
+    }
+}
+
+fn shift_json(e: &ShiftExpr) -> Json {
+    match e {
+        ShiftExpr::Add(a) => add_json(a),
+        ShiftExpr::Shift(lhs, op, rhs) => binary(op, shift_json(lhs), add_json(rhs)),
+    }
+}
+
+fn add_json(e: &AddExpr) -> Json {
+    match e {
+        AddExpr::Mul(m) => mul_json(m),
+        AddExpr::Add(lhs, op, rhs) => binary(op, add_json(lhs), mul_json(rhs)),
+    }
+}
+
+fn mul_json(e: &MulExpr) -> Json {
+    match e {
+        MulExpr::Exp(x) => exp_json(x),
+        MulExpr::Mul(lhs, op, rhs) => binary(op, mul_json(lhs), exp_json(rhs)),
+    }
+}
+
+fn exp_json(e: &ExpExpr) -> Json {
+    match e {
+        ExpExpr::Unary(u) => unary_json(u),
+        ExpExpr::Exp(lhs, rhs) => binary(&Token::ExpOp, unary_json(lhs), exp_json(rhs)),
+    }
+}
+
+fn unary_json(e: &UnaryExpr) -> Json {
+    match e {
+        UnaryExpr::Primary(p) => primary_json(p),
+        UnaryExpr::Unary(op, operand) => node(
+            "Unary",
+            vec![
+                ("op", Json::str(&op.to_string())),
+                ("operand", unary_json(operand)),
+            ],
+        ),
+    }
+}
+
+fn primary_json(e: &PrimaryExpr) -> Json {
+    match e {
+        PrimaryExpr::IntLit(i) => node("IntLit", vec![("value", Json::Int(*i))]),
+        PrimaryExpr::FloatLit(f) => node("FloatLit", vec![("value", Json::Float(*f))]),
+        PrimaryExpr::StringLit(s) => node("StringLit", vec![("value", Json::str(s))]),
+        PrimaryExpr::BoolLit(b) => node("BoolLit", vec![("value", Json::Bool(*b))]),
+        PrimaryExpr::Ident(name) => node("Ident", vec![("name", Json::str(name))]),
+        PrimaryExpr::Paren(expr) => node("Paren", vec![("expr", expr_json(expr))]),
+        PrimaryExpr::Call(call) => node(
+            "Call",
+            vec![
+                ("ident", Json::str(&call.ident)),
+                (
+                    "args",
+                    Json::Array(call.args.iter().map(expr_json).collect()),
+                ),
+                ("span", span_json(call.span)),
+            ],
+        ),
+    }
+}
+
+// Just enough of JSON to write the above out; objects keep their fields in insertion order so the
+// output is stable.
+enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        fields
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
+}
+
+impl Json {
+    fn str(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent + 1);
+
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Int(i) => write!(f, "{i}"),
+            Json::Float(x) if !x.is_finite() => write!(f, "null"),
+            Json::Float(x) => write!(f, "{x:?}"),
+            Json::String(s) => write_json_str(f, s),
+
+            Json::Array(items) if items.is_empty() => write!(f, "[]"),
+            Json::Array(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    f.write_str(&pad)?;
+                    item.write(f, indent + 1)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", "  ".repeat(indent))
+            }
+
+            Json::Object(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Json::Object(fields) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    f.write_str(&pad)?;
+                    write_json_str(f, key)?;
+                    f.write_str(": ")?;
+                    value.write(f, indent + 1)?;
+                    writeln!(f, "{}", if i + 1 < fields.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", "  ".repeat(indent))
+            }
+        }
+    }
+}
+
+fn write_json_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
@@ -0,0 +1,227 @@
+use super::expr::{expr_text, format_expr};
+use crate::lexer::{
+    core::{line_starts, tokenize_src_code_with_spans},
+    Comment, Span,
+};
+use crate::parser::{ast::core::*, core::parse_spanned_token_stream};
+use crate::CompilerError;
+
+/// Reprints Nuktah source in its canonical form: tab indentation, one statement per line, single
+/// spaces around binary operators, and the source's parentheses (as they're part of the AST).
+///
+/// Comments are kept. Those sharing a line w/ the end of a statement trail it; all others are
+/// placed on their own line, before the statement (or closing brace) that follows them. At most
+/// one blank line is kept b/w statements, and only where the source had one.
+pub fn format_src(src_code: &str) -> Result<String, CompilerError> {
+    let lexed = tokenize_src_code_with_spans(src_code)?;
+    let ast_root = parse_spanned_token_stream(&lexed.tokens, &lexed.spans)?;
+
+    let mut f = Formatter::new(src_code, &lexed.comments);
+    f.translation_unit(&ast_root);
+    Ok(f.out)
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+    line_starts: Vec<usize>,
+    comments: &'a [Comment],
+    next_comment: usize,
+    out: String,
+    indent: usize,
+    last_line: Option<usize>, // source line the last item ended on; None at the start of a block
+}
+
+impl<'a> Formatter<'a> {
+    fn new(src: &'a str, comments: &'a [Comment]) -> Self {
+        Self {
+            src,
+            line_starts: line_starts(src),
+            comments,
+            next_comment: 0,
+            out: String::new(),
+            indent: 0,
+            last_line: None,
+        }
+    }
+
+    fn translation_unit(&mut self, unit: &TranslationUnit) {
+        for decl in unit {
+            match decl {
+                Decl::Var(v) => self.var_decl(v),
+                Decl::Fn(f) => self.fn_decl(f),
+            }
+        }
+
+        self.leading_comments(usize::MAX);
+    }
+
+    fn fn_decl(&mut self, f: &FnDecl) {
+        let params = f
+            .params
+            .iter()
+            .map(|p| format!("{} {}", p.type_tok, p.ident))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.begin_item(f.span);
+        self.start_line();
+        self.write(&format!("fn {} {}({params}) ", f.type_tok, f.ident));
+        self.block(&f.block, f.span.end);
+        self.write(" .");
+        self.end_item(f.span);
+    }
+
+    fn var_decl(&mut self, v: &VarDecl) {
+        self.simple(v.span, &var_decl_text(v));
+    }
+
+    // Writes `{`, the block's statements, and its closing `}`, leaving the current line open
+    // for whatever follows the block. `end` is the source offset the block ends before.
+    fn block(&mut self, block: &Block, end: usize) {
+        let has_comments = self
+            .comments
+            .get(self.next_comment)
+            .is_some_and(|c| c.span.start < end);
+
+        if block.is_empty() && !has_comments {
+            self.write("{}");
+            return;
+        }
+
+        self.write("{");
+        self.end_line();
+        self.indent += 1;
+        self.last_line = None;
+
+        for stmt in block {
+            self.stmt(stmt);
+        }
+        self.leading_comments(end);
+
+        self.indent -= 1;
+        self.start_line();
+        self.write("}");
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(v) => self.var_decl(v),
+            Stmt::Expr(e) => self.simple(e.span, &expr_stmt_text(&e.expr)),
+            Stmt::Ret(r) => self.simple(r.span, &format!("wapsi {}", expr_stmt_text(&r.expr))),
+            Stmt::Break(span) => self.simple(*span, "toro"),
+
+            Stmt::For(f) => {
+                let init = f.init.as_ref().map(var_decl_text);
+                let mut header = format!(
+                    "duhrao ({} {}",
+                    init.as_deref().unwrap_or("."),
+                    expr_stmt_text(&f.cond.expr)
+                );
+                if let Some(updt) = &f.updt {
+                    header += &format!(" {}", format_expr(updt));
+                }
+
+                self.begin_item(f.span);
+                self.start_line();
+                self.write(&format!("{header}) "));
+                self.block(&f.block, f.span.end);
+                self.end_item(f.span);
+            }
+
+            Stmt::If(i) => {
+                self.begin_item(i.span);
+                self.start_line();
+                self.write(&format!("agar ({}) ", expr_text(&i.cond)));
+                self.block(&i.if_block, i.else_span.start);
+                self.write(" warna ");
+                self.block(&i.else_block, i.span.end);
+                self.end_item(i.span);
+            }
+        }
+    }
+
+    // An item that fits on a single line. Comments inside it, were it split over several, are
+    // placed before it.
+    fn simple(&mut self, span: Span, text: &str) {
+        self.leading_comments(span.end);
+        self.separate(span.line);
+        self.start_line();
+        self.write(text);
+        self.end_item(span);
+    }
+
+    fn begin_item(&mut self, span: Span) {
+        self.leading_comments(span.start);
+        self.separate(span.line);
+    }
+
+    fn end_item(&mut self, span: Span) {
+        let end_line = self.line_of(span.end.saturating_sub(1));
+
+        // A comment trails the item if nothing but spaces/tabs stand b/w the two
+        if let Some(c) = self.comments.get(self.next_comment) {
+            if c.span.start >= span.end
+                && self.src[span.end..c.span.start]
+                    .chars()
+                    .all(|ch| ch == ' ' || ch == '\t')
+            {
+                self.write(&format!(" {}", c.text));
+                self.next_comment += 1;
+            }
+        }
+
+        self.end_line();
+        self.last_line = Some(end_line);
+    }
+
+    // Emits, on their own lines, all pending comments that start before `pos`
+    fn leading_comments(&mut self, pos: usize) {
+        while let Some(c) = self.comments.get(self.next_comment) {
+            if c.span.start >= pos {
+                break;
+            }
+
+            self.separate(c.span.line);
+            self.start_line();
+            self.write(&c.text);
+            self.end_line();
+            self.last_line = Some(c.span.line);
+            self.next_comment += 1;
+        }
+    }
+
+    // Keeps (at most) one blank line b/w the last item and one starting on `line`, if the source
+    // had any
+    fn separate(&mut self, line: usize) {
+        if self.last_line.is_some_and(|last| line > last + 1) {
+            self.out.push('\n');
+        }
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.line_starts.partition_point(|&ls| ls <= pos)
+    }
+
+    fn start_line(&mut self) {
+        self.out += &"\t".repeat(self.indent);
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out += text;
+    }
+
+    fn end_line(&mut self) {
+        self.out.push('\n');
+    }
+}
+
+fn var_decl_text(v: &VarDecl) -> String {
+    format!("{} {} = {}", v.type_tok, v.ident, expr_stmt_text(&v.expr))
+}
+
+fn expr_stmt_text(expr: &Expr) -> String {
+    match expr {
+        Some(e) => format!("{} .", format_expr(e)),
+        None => ".".to_string(),
+    }
+}
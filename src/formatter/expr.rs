@@ -0,0 +1,210 @@
+use crate::lexer::Token;
+use crate::parser::ast::core::*;
+
+// Binding power of each level of the expression grammar, loosest first. An expression is only
+// wrapped in parentheses if it binds looser than the position it's printed in demands. The
+// source's own parentheses are `Paren` nodes, and reproduced as such, even where redundant: they
+// can matter beyond precedence (`(b) = 3` vs `b = 3`), and dropping them would change the AST.
+//
+// Left-associative operators allow their own level on the left, but not on the right.
+// Right-associative ones (`=` and `^`) are the other way around.
+const ASSIGN: u8 = 1;
+const BOOL: u8 = 2;
+const BIT_OR: u8 = 3;
+const BIT_AND: u8 = 4;
+const COMP: u8 = 5;
+const SHIFT: u8 = 6;
+const ADD: u8 = 7;
+const MUL: u8 = 8;
+const EXP: u8 = 9;
+const UNARY: u8 = 10;
+const PRIMARY: u8 = 11;
+
+/// Formats an expression that doesn't sit inside any other expression
+pub fn format_expr(expr: &AssignExpr) -> String {
+    assign_expr(expr, 0)
+}
+
+fn wrap((text, prec): (String, u8), min_prec: u8) -> String {
+    if prec < min_prec {
+        format!("({text})")
+    } else {
+        text
+    }
+}
+
+fn binary(lhs: String, op: &Token, rhs: String) -> String {
+    format!("{lhs} {op} {rhs}")
+}
+
+fn assign_expr(expr: &AssignExpr, min_prec: u8) -> String {
+    wrap(assign_expr_prec(expr), min_prec)
+}
+
+fn assign_expr_prec(expr: &AssignExpr) -> (String, u8) {
+    match expr {
+        AssignExpr::Bool(e) => bool_expr_prec(e),
+        AssignExpr::Assign(lhs, rhs) => (
+            binary(
+                wrap(bool_expr_prec(lhs), ASSIGN + 1),
+                &Token::AssignOp,
+                assign_expr(rhs, ASSIGN),
+            ),
+            ASSIGN,
+        ),
+    }
+}
+
+fn bool_expr_prec(expr: &BoolExpr) -> (String, u8) {
+    match expr {
+        BoolExpr::BitOr(e) => bit_or_expr_prec(e),
+        BoolExpr::Bool(lhs, op, rhs) => (
+            binary(
+                wrap(bool_expr_prec(lhs), BOOL),
+                op,
+                wrap(bit_or_expr_prec(rhs), BOOL + 1),
+            ),
+            BOOL,
+        ),
+    }
+}
+
+fn bit_or_expr_prec(expr: &BitOrExpr) -> (String, u8) {
+    match expr {
+        BitOrExpr::BitAnd(e) => bit_and_expr_prec(e),
+        BitOrExpr::BitOr(lhs, rhs) => (
+            binary(
+                wrap(bit_or_expr_prec(lhs), BIT_OR),
+                &Token::BitwiseOr,
+                wrap(bit_and_expr_prec(rhs), BIT_OR + 1),
+            ),
+            BIT_OR,
+        ),
+    }
+}
+
+fn bit_and_expr_prec(expr: &BitAndExpr) -> (String, u8) {
+    match expr {
+        BitAndExpr::Comp(e) => comp_expr_prec(e),
+        BitAndExpr::BitAnd(lhs, rhs) => (
+            binary(
+                wrap(bit_and_expr_prec(lhs), BIT_AND),
+                &Token::BitwiseAnd,
+                wrap(comp_expr_prec(rhs), BIT_AND + 1),
+            ),
+            BIT_AND,
+        ),
+    }
+}
+
+fn comp_expr_prec(expr: &CompExpr) -> (String, u8) {
+    match expr {
+        CompExpr::Shift(e) => shift_expr_prec(e),
+        CompExpr::Comp(lhs, op, rhs) => (
+            binary(
+                wrap(comp_expr_prec(lhs), COMP),
+                op,
+                wrap(shift_expr_prec(rhs), COMP + 1),
+            ),
+            COMP,
+        ),
+    }
+}
+
+fn shift_expr_prec(expr: &ShiftExpr) -> (String, u8) {
+    match expr {
+        ShiftExpr::Add(e) => add_expr_prec(e),
+        ShiftExpr::Shift(lhs, op, rhs) => (
+            binary(
+                wrap(shift_expr_prec(lhs), SHIFT),
+                op,
+                wrap(add_expr_prec(rhs), SHIFT + 1),
+            ),
+            SHIFT,
+        ),
+    }
+}
+
+fn add_expr_prec(expr: &AddExpr) -> (String, u8) {
+    match expr {
+        AddExpr::Mul(e) => mul_expr_prec(e),
+        AddExpr::Add(lhs, op, rhs) => (
+            binary(
+                wrap(add_expr_prec(lhs), ADD),
+                op,
+                wrap(mul_expr_prec(rhs), ADD + 1),
+            ),
+            ADD,
+        ),
+    }
+}
+
+fn mul_expr_prec(expr: &MulExpr) -> (String, u8) {
+    match expr {
+        MulExpr::Exp(e) => exp_expr_prec(e),
+        MulExpr::Mul(lhs, op, rhs) => (
+            binary(
+                wrap(mul_expr_prec(lhs), MUL),
+                op,
+                wrap(exp_expr_prec(rhs), MUL + 1),
+            ),
+            MUL,
+        ),
+    }
+}
+
+fn exp_expr_prec(expr: &ExpExpr) -> (String, u8) {
+    match expr {
+        ExpExpr::Unary(e) => unary_expr_prec(e),
+        ExpExpr::Exp(lhs, rhs) => (
+            binary(
+                wrap(unary_expr_prec(lhs), EXP + 1),
+                &Token::ExpOp,
+                wrap(exp_expr_prec(rhs), EXP),
+            ),
+            EXP,
+        ),
+    }
+}
+
+fn unary_expr_prec(expr: &UnaryExpr) -> (String, u8) {
+    match expr {
+        UnaryExpr::Primary(e) => primary_expr_prec(e),
+        UnaryExpr::Unary(op, e) => (format!("{op}{}", wrap(unary_expr_prec(e), UNARY)), UNARY),
+    }
+}
+
+fn primary_expr_prec(expr: &PrimaryExpr) -> (String, u8) {
+    let text = match expr {
+        PrimaryExpr::IntLit(n) => n.to_string(),
+        PrimaryExpr::FloatLit(n) => format_float(*n),
+        PrimaryExpr::StringLit(s) => format!("\"{s}\""),
+        PrimaryExpr::BoolLit(true) => Token::True.to_string(),
+        PrimaryExpr::BoolLit(false) => Token::False.to_string(),
+        PrimaryExpr::Ident(ident) => ident.clone(),
+
+        PrimaryExpr::Paren(nested_e) => format!("({})", expr_text(nested_e)),
+
+        PrimaryExpr::Call(fn_call) => {
+            let args = fn_call.args.iter().map(expr_text).collect::<Vec<_>>();
+            format!("{}({})", fn_call.ident, args.join(", "))
+        }
+    };
+
+    (text, PRIMARY)
+}
+
+/// Formats an expression that may be missing, as `()`'s and empty arguments' are
+pub fn expr_text(expr: &Expr) -> String {
+    expr.as_ref().map(format_expr).unwrap_or_default()
+}
+
+/// Float literals must always contain a `.`, and never an exponent
+fn format_float(n: f64) -> String {
+    let text = n.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        format!("{text}.0")
+    }
+}
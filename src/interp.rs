@@ -0,0 +1,4 @@
+pub mod core;
+pub mod intrinsics;
+pub mod ops;
+pub mod value;
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+
+use super::{intrinsics, ops, value::Value};
+use crate::lexer::{Span, Token};
+use crate::parser::ast::core::*;
+use crate::semantics::core::ENTRY_POINT;
+
+/// How deeply calls may nest before a program fails w/ `StackOverflow`; the same for every way
+/// of running one
+pub const MAX_CALL_DEPTH: usize = 1 << 16;
+
+// Every Nuktah call nests a few dozen of the interpreter's own, so it runs on a thread w/ a stack
+// big enough for most programs to get to `MAX_CALL_DEPTH`. Those w/ bigger frames run out of it
+// first, and fail the same way, once they're within `STACK_RESERVE` of its end: more than any one
+// statement needs, as the parser, on an 8 MiB main thread, doesn't nest them any deeper.
+const STACK_SIZE: usize = 1 << 30;
+const STACK_RESERVE: usize = 16 << 20;
+
+#[derive(Debug, PartialEq)]
+pub enum RuntimeErrorKind {
+    DivisionByZero,
+    ShiftOutOfRange(i64),
+    NegativeExponent(i64),
+    MissingReturn(String), // a non-khali function ran off its end w/o a `wapsi`
+    NoEntryPoint,
+    StackOverflow,
+    IllTyped, // operands of the wrong type, which only bytecode the checker never saw can have
+    Io(io::ErrorKind), // reading or writing on behalf of an intrinsic failed
+}
+
+/// A runtime error, along w/ the span of the statement (or declaration) that caused it
+#[derive(Debug, PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {:?}", self.span.line, self.span.col, self.kind)
+    }
+}
+
+/// Runs a checked program: initialises its globals, in order, then calls `shuru`, returning
+/// whatever it returns as the program's exit code (0 for a khali `shuru`). `args` are the
+/// program's command-line arguments, its own path first; `shuru` may take their count.
+pub fn run(ast_root: &TranslationUnit, args: &[String]) -> Result<i64, RuntimeError> {
+    let mut input = BufReader::new(io::stdin());
+    run_with_io(ast_root, args, &mut input, &mut io::stdout())
+}
+
+/// Like `run`, but w/ the intrinsics reading from `input` and writing to `output`
+pub fn run_with_io(
+    ast_root: &TranslationUnit,
+    args: &[String],
+    input: &mut (dyn BufRead + Send),
+    output: &mut (dyn Write + Send),
+) -> Result<i64, RuntimeError> {
+    std::thread::scope(|s| {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(s, || {
+                let mut interp = Interpreter::new(ast_root, input, output);
+                let res = interp.run(ast_root, args.len());
+                interp
+                    .output
+                    .flush()
+                    .map_err(|e| interp.error(RuntimeErrorKind::Io(e.kind())))?;
+                res
+            })
+            .expect("failed to spawn interpreter thread")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+// What a statement did to the flow of control
+enum Flow {
+    Normal,
+    Break,
+    Return(Value),
+}
+
+type Scope = HashMap<String, Value>;
+
+struct Interpreter<'a, 'io> {
+    fns: HashMap<&'a str, &'a FnDecl>,
+    globals: Scope,
+    frames: Vec<Vec<Scope>>, // per call, the function's scope and the blocks nested within it
+    span: Span,              // of the statement being executed
+    stack_base: usize,       // roughly where the thread's stack starts
+    input: &'io mut (dyn BufRead + Send),
+    output: &'io mut (dyn Write + Send),
+}
+
+impl<'a, 'io> Interpreter<'a, 'io> {
+    fn new(
+        ast_root: &'a TranslationUnit,
+        input: &'io mut (dyn BufRead + Send),
+        output: &'io mut (dyn Write + Send),
+    ) -> Self {
+        let fns = ast_root
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::Fn(f) => Some((f.ident.as_str(), f)),
+                Decl::Var(_) => None,
+            })
+            .collect();
+
+        let base = 0u8;
+        Self {
+            fns,
+            globals: HashMap::new(),
+            frames: vec![],
+            span: Span::default(),
+            stack_base: std::ptr::addr_of!(base) as usize,
+            input,
+            output,
+        }
+    }
+
+    // Initialises globals, in order, then calls the entry point
+    fn run(&mut self, ast_root: &'a TranslationUnit, argc: usize) -> Result<i64, RuntimeError> {
+        for decl in ast_root {
+            if let Decl::Var(v) = decl {
+                self.var_decl(v)?;
+            }
+        }
+
+        let Some(entry) = self.fns.get(ENTRY_POINT).copied() else {
+            return Err(self.error(RuntimeErrorKind::NoEntryPoint));
+        };
+
+        let args = match entry.params.len() {
+            0 => vec![],
+            _ => vec![Value::Int(argc as i64)],
+        };
+
+        match self.call(entry, args)? {
+            Value::Int(code) => Ok(code),
+            _ => Ok(0),
+        }
+    }
+
+    fn error(&self, kind: RuntimeErrorKind) -> RuntimeError {
+        RuntimeError {
+            kind,
+            span: self.span,
+        }
+    }
+
+    fn call(&mut self, f: &'a FnDecl, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let here = 0u8;
+        let stack_used = self.stack_base.abs_diff(std::ptr::addr_of!(here) as usize);
+        if self.frames.len() >= MAX_CALL_DEPTH || stack_used > STACK_SIZE - STACK_RESERVE {
+            return Err(self.error(RuntimeErrorKind::StackOverflow));
+        }
+
+        let params = f.params.iter().map(|p| p.ident.clone()).zip(args).collect();
+        self.frames.push(vec![params]);
+
+        let caller_span = self.span;
+        let flow = self.stmts(&f.block);
+        self.frames.pop();
+        self.span = caller_span;
+
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ if f.type_tok == Token::Void => Ok(Value::Void),
+            _ => Err(self.error(RuntimeErrorKind::MissingReturn(f.ident.clone()))),
+        }
+    }
+
+    fn call_intrinsic(&mut self, name: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        intrinsics::call(name, &args, self.input, self.output)
+            .map_err(|e| self.error(RuntimeErrorKind::Io(e.kind())))
+    }
+
+    // Runs a block in a scope of its own
+    fn block(&mut self, block: &'a Block) -> Result<Flow, RuntimeError> {
+        self.push_scope();
+        let flow = self.stmts(block);
+        self.pop_scope();
+        flow
+    }
+
+    fn stmts(&mut self, block: &'a Block) -> Result<Flow, RuntimeError> {
+        for stmt in block {
+            let flow = self.stmt(stmt)?;
+            if !matches!(flow, Flow::Normal) {
+                return Ok(flow);
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Stmt::VarDecl(v) => self.var_decl(v)?,
+
+            Stmt::Expr(e) => {
+                self.span = e.span;
+                self.expr(&e.expr)?;
+            }
+
+            Stmt::Ret(r) => {
+                self.span = r.span;
+                return Ok(Flow::Return(self.expr(&r.expr)?));
+            }
+
+            Stmt::Break(_) => return Ok(Flow::Break),
+
+            Stmt::For(f) => {
+                // The init's variable lives in a scope of its own, around the loop
+                self.push_scope();
+                let flow = self.for_stmt(f);
+                self.pop_scope();
+
+                if let Flow::Return(value) = flow? {
+                    return Ok(Flow::Return(value));
+                }
+            }
+
+            Stmt::If(i) => {
+                self.span = i.span;
+                let block = match self.expr(&i.cond)?.as_bool() {
+                    true => &i.if_block,
+                    false => &i.else_block,
+                };
+
+                return self.block(block);
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn for_stmt(&mut self, f: &'a ForStmt) -> Result<Flow, RuntimeError> {
+        if let Some(init) = &f.init {
+            self.var_decl(init)?;
+        }
+
+        loop {
+            self.span = f.span;
+            if f.cond.expr.is_some() && !self.expr(&f.cond.expr)?.as_bool() {
+                break;
+            }
+
+            match self.block(&f.block)? {
+                Flow::Break => break,
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+                Flow::Normal => {}
+            }
+
+            self.span = f.span;
+            self.expr(&f.updt)?;
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn var_decl(&mut self, v: &'a VarDecl) -> Result<(), RuntimeError> {
+        self.span = v.span;
+        let value = self.expr(&v.expr)?;
+
+        match self.frames.last_mut() {
+            Some(scopes) => scopes.last_mut().unwrap().insert(v.ident.clone(), value),
+            None => self.globals.insert(v.ident.clone(), value),
+        };
+
+        Ok(())
+    }
+
+    fn push_scope(&mut self) {
+        self.frames.last_mut().unwrap().push(Scope::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.frames.last_mut().unwrap().pop();
+    }
+
+    // The innermost variable called `ident`
+    fn lookup(&mut self, ident: &str) -> &mut Value {
+        let local = self
+            .frames
+            .last_mut()
+            .and_then(|scopes| scopes.iter_mut().rev().find_map(|s| s.get_mut(ident)));
+
+        match local {
+            Some(value) => value,
+            None => self
+                .globals
+                .get_mut(ident)
+                .unwrap_or_else(|| unreachable!("undeclared `{ident}` made it past analysis")),
+        }
+    }
+
+    fn expr(&mut self, expr: &'a Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Some(e) => self.assign_expr(e),
+            None => Ok(Value::Void),
+        }
+    }
+
+    fn binary(&self, op: &Token, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+        ops::binary(op, lhs, rhs).map_err(|kind| self.error(kind))
+    }
+
+    fn assign_expr(&mut self, e: &'a AssignExpr) -> Result<Value, RuntimeError> {
+        match e {
+            AssignExpr::Bool(b) => self.bool_expr(b),
+            AssignExpr::Assign(lhs, rhs) => {
+                let ident = lhs
+                    .as_ident()
+                    .expect("assignment target should've been checked to be an identifier");
+                let value = self.assign_expr(rhs)?;
+                *self.lookup(ident) = value.clone();
+                Ok(value)
+            }
+        }
+    }
+
+    fn bool_expr(&mut self, e: &'a BoolExpr) -> Result<Value, RuntimeError> {
+        match e {
+            BoolExpr::BitOr(b) => self.bit_or_expr(b),
+            BoolExpr::Bool(lhs, op, rhs) => {
+                let lhs = self.bool_expr(lhs)?.as_bool();
+
+                // Short-circuit
+                match (op, lhs) {
+                    (Token::BooleanAnd, false) => Ok(Value::Bool(false)),
+                    (Token::BooleanOr, true) => Ok(Value::Bool(true)),
+                    _ => self.bit_or_expr(rhs),
+                }
+            }
+        }
+    }
+
+    fn bit_or_expr(&mut self, e: &'a BitOrExpr) -> Result<Value, RuntimeError> {
+        match e {
+            BitOrExpr::BitAnd(b) => self.bit_and_expr(b),
+            BitOrExpr::BitOr(lhs, rhs) => {
+                let lhs = self.bit_or_expr(lhs)?;
+                let rhs = self.bit_and_expr(rhs)?;
+                self.binary(&Token::BitwiseOr, lhs, rhs)
+            }
+        }
+    }
+
+    fn bit_and_expr(&mut self, e: &'a BitAndExpr) -> Result<Value, RuntimeError> {
+        match e {
+            BitAndExpr::Comp(c) => self.comp_expr(c),
+            BitAndExpr::BitAnd(lhs, rhs) => {
+                let lhs = self.bit_and_expr(lhs)?;
+                let rhs = self.comp_expr(rhs)?;
+                self.binary(&Token::BitwiseAnd, lhs, rhs)
+            }
+        }
+    }
+
+    fn comp_expr(&mut self, e: &'a CompExpr) -> Result<Value, RuntimeError> {
+        match e {
+            CompExpr::Shift(s) => self.shift_expr(s),
+            CompExpr::Comp(lhs, op, rhs) => {
+                let lhs = self.comp_expr(lhs)?;
+                let rhs = self.shift_expr(rhs)?;
+                self.binary(op, lhs, rhs)
+            }
+        }
+    }
+
+    fn shift_expr(&mut self, e: &'a ShiftExpr) -> Result<Value, RuntimeError> {
+        match e {
+            ShiftExpr::Add(a) => self.add_expr(a),
+            ShiftExpr::Shift(lhs, op, rhs) => {
+                let lhs = self.shift_expr(lhs)?;
+                let rhs = self.add_expr(rhs)?;
+                self.binary(op, lhs, rhs)
+            }
+        }
+    }
+
+    fn add_expr(&mut self, e: &'a AddExpr) -> Result<Value, RuntimeError> {
+        match e {
+            AddExpr::Mul(m) => self.mul_expr(m),
+            AddExpr::Add(lhs, op, rhs) => {
+                let lhs = self.add_expr(lhs)?;
+                let rhs = self.mul_expr(rhs)?;
+                self.binary(op, lhs, rhs)
+            }
+        }
+    }
+
+    fn mul_expr(&mut self, e: &'a MulExpr) -> Result<Value, RuntimeError> {
+        match e {
+            MulExpr::Exp(x) => self.exp_expr(x),
+            MulExpr::Mul(lhs, op, rhs) => {
+                let lhs = self.mul_expr(lhs)?;
+                let rhs = self.exp_expr(rhs)?;
+                self.binary(op, lhs, rhs)
+            }
+        }
+    }
+
+    fn exp_expr(&mut self, e: &'a ExpExpr) -> Result<Value, RuntimeError> {
+        match e {
+            ExpExpr::Unary(u) => self.unary_expr(u),
+            ExpExpr::Exp(lhs, rhs) => {
+                let lhs = self.unary_expr(lhs)?;
+                let rhs = self.exp_expr(rhs)?;
+                self.binary(&Token::ExpOp, lhs, rhs)
+            }
+        }
+    }
+
+    fn unary_expr(&mut self, e: &'a UnaryExpr) -> Result<Value, RuntimeError> {
+        match e {
+            UnaryExpr::Primary(p) => self.primary_expr(p),
+            UnaryExpr::Unary(op, operand) => {
+                let operand = self.unary_expr(operand)?;
+                ops::unary(op, operand).map_err(|kind| self.error(kind))
+            }
+        }
+    }
+
+    fn primary_expr(&mut self, e: &'a PrimaryExpr) -> Result<Value, RuntimeError> {
+        match e {
+            PrimaryExpr::IntLit(i) => Ok(Value::Int(*i)),
+            PrimaryExpr::FloatLit(x) => Ok(Value::Float(*x)),
+            PrimaryExpr::StringLit(s) => Ok(Value::String(s.clone())),
+            PrimaryExpr::BoolLit(b) => Ok(Value::Bool(*b)),
+            PrimaryExpr::Ident(ident) => Ok(self.lookup(ident).clone()),
+            PrimaryExpr::Paren(inner) => self.expr(inner),
+
+            PrimaryExpr::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match self.fns.get(call.ident.as_str()) {
+                    Some(&f) => self.call(f, args),
+                    None => self.call_intrinsic(&call.ident, args),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::io::{self, BufRead, Write};
+
+use super::value::Value;
+
+/// Runs the intrinsic called `name`, shared by the interpreter and the VM. Arguments are assumed
+/// to have been type-checked.
+pub fn call(
+    name: &str,
+    args: &[Value],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> io::Result<Value> {
+    match (name, args) {
+        ("likho", [Value::String(s)]) => writeln!(output, "{s}")?,
+        ("likho_ginti", [Value::Int(i)]) => writeln!(output, "{i}")?,
+        ("parho", []) => {
+            let mut line = String::new();
+            input.read_line(&mut line)?;
+
+            let len = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(len);
+            return Ok(Value::String(line));
+        }
+        _ => unreachable!("ill-typed call to intrinsic `{name}`: {args:?}"),
+    }
+
+    Ok(Value::Void)
+}
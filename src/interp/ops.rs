@@ -0,0 +1,84 @@
+use super::{core::RuntimeErrorKind, value::Value};
+use crate::lexer::Token;
+
+// Nuktah's arithmetic, shared by everything that needs to evaluate an operator. Integers wrap on
+// overflow; shifting by a negative amount or by >= 64 bits, dividing by zero, and raising an
+// integer to a negative power are errors. So are operands of the wrong type, which the checker
+// rules out, but which a tampered `.nkb` file can still smuggle in.
+
+/// Evaluates a binary operator on two operands of the same type. `&&` and `||` are evaluated
+/// eagerly here; short-circuiting is up to the caller.
+pub fn binary(op: &Token, lhs: Value, rhs: Value) -> Result<Value, RuntimeErrorKind> {
+    use Value::*;
+
+    let res = match (op, lhs, rhs) {
+        (Token::BooleanAnd, Bool(a), Bool(b)) => Bool(a && b),
+        (Token::BooleanOr, Bool(a), Bool(b)) => Bool(a || b),
+
+        (Token::BitwiseOr, Int(a), Int(b)) => Int(a | b),
+        (Token::BitwiseAnd, Int(a), Int(b)) => Int(a & b),
+
+        (Token::LessThan, a, b) => Bool(a < b),
+        (Token::GreaterThan, a, b) => Bool(a > b),
+        (Token::EqualsOp, a, b) => Bool(a == b),
+
+        (Token::ShiftLeft | Token::ShiftRight, Int(a), Int(b)) => {
+            if !(0..64).contains(&b) {
+                return Err(RuntimeErrorKind::ShiftOutOfRange(b));
+            }
+            match op {
+                Token::ShiftLeft => Int(a << b),
+                _ => Int(a >> b),
+            }
+        }
+
+        (Token::AddOp, Int(a), Int(b)) => Int(a.wrapping_add(b)),
+        (Token::SubOp, Int(a), Int(b)) => Int(a.wrapping_sub(b)),
+        (Token::MulOp, Int(a), Int(b)) => Int(a.wrapping_mul(b)),
+        (Token::DivOp | Token::ModOp, Int(_), Int(0)) => {
+            return Err(RuntimeErrorKind::DivisionByZero)
+        }
+        (Token::DivOp, Int(a), Int(b)) => Int(a.wrapping_div(b)),
+        (Token::ModOp, Int(a), Int(b)) => Int(a.wrapping_rem(b)),
+        (Token::ExpOp, Int(a), Int(b)) => Int(int_pow(a, b)?),
+
+        (Token::AddOp, Float(a), Float(b)) => Float(a + b),
+        (Token::SubOp, Float(a), Float(b)) => Float(a - b),
+        (Token::MulOp, Float(a), Float(b)) => Float(a * b),
+        (Token::DivOp, Float(a), Float(b)) => Float(a / b),
+        (Token::ModOp, Float(a), Float(b)) => Float(a % b),
+        (Token::ExpOp, Float(a), Float(b)) => Float(a.powf(b)),
+
+        _ => return Err(RuntimeErrorKind::IllTyped),
+    };
+
+    Ok(res)
+}
+
+/// Evaluates a prefix operator. Both `!` and `~` negate a boli.
+pub fn unary(op: &Token, operand: Value) -> Result<Value, RuntimeErrorKind> {
+    match (op, operand) {
+        (Token::SubOp, Value::Int(i)) => Ok(Value::Int(i.wrapping_neg())),
+        (Token::SubOp, Value::Float(x)) => Ok(Value::Float(-x)),
+        (Token::BooleanNot | Token::BitwiseNot, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        _ => Err(RuntimeErrorKind::IllTyped),
+    }
+}
+
+/// `base ^ exp`, wrapping on overflow
+pub fn int_pow(mut base: i64, mut exp: i64) -> Result<i64, RuntimeErrorKind> {
+    if exp < 0 {
+        return Err(RuntimeErrorKind::NegativeExponent(exp));
+    }
+
+    let mut acc: i64 = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = acc.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exp >>= 1;
+    }
+
+    Ok(acc)
+}
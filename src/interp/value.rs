@@ -0,0 +1,54 @@
+use std::fmt;
+
+use crate::semantics::spaghetti::SymType;
+
+/// A runtime value; one variant per Nuktah type
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Void,
+}
+
+impl Value {
+    pub fn sym_type(&self) -> SymType {
+        match self {
+            Value::Int(_) => SymType::Int,
+            Value::Float(_) => SymType::Float,
+            Value::String(_) => SymType::String,
+            Value::Bool(_) => SymType::Bool,
+            Value::Void => SymType::Void,
+        }
+    }
+
+    // The checker guarantees operands' types, so these never fail in a well-typed program
+
+    pub fn as_int(&self) -> i64 {
+        match self {
+            Value::Int(i) => *i,
+            _ => unreachable!("expected a ginti, found {self:?}"),
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            _ => unreachable!("expected a boli, found {self:?}"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{i}"),
+            Value::Float(x) => write!(f, "{x:?}"),
+            Value::String(s) => write!(f, "{s}"),
+            Value::Bool(true) => write!(f, "sach"),
+            Value::Bool(false) => write!(f, "jhoot"),
+            Value::Void => Ok(()),
+        }
+    }
+}
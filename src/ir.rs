@@ -0,0 +1,10 @@
+pub mod callgraph;
+pub mod cfg;
+pub mod core;
+pub mod dom;
+pub mod eval;
+pub mod live;
+pub mod lower;
+pub mod print;
+pub mod ssa;
+pub mod verify;
@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+
+use super::core::{Callee, FnId, Inst, Module};
+
+/// Which functions each function calls (directly; intrinsics aren't included)
+#[derive(Debug)]
+pub struct CallGraph {
+    callees: Vec<BTreeSet<FnId>>, // indexed by `FnId`
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> CallGraph {
+        let callees = module
+            .functions
+            .iter()
+            .map(|f| {
+                f.blocks
+                    .iter()
+                    .flat_map(|b| &b.insts)
+                    .filter_map(|inst| match inst {
+                        Inst::Call {
+                            callee: Callee::Fn(func),
+                            ..
+                        } => Some(*func),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        CallGraph { callees }
+    }
+
+    pub fn callees(&self, func: FnId) -> &BTreeSet<FnId> {
+        &self.callees[func as usize]
+    }
+
+    /// Per function, whether it can be called, transitively, from any of `roots`
+    pub fn reachable_from(&self, roots: &[FnId]) -> Vec<bool> {
+        let mut reachable = vec![false; self.callees.len()];
+        let mut work = roots.to_vec();
+
+        while let Some(func) = work.pop() {
+            if !std::mem::replace(&mut reachable[func as usize], true) {
+                work.extend(self.callees(func));
+            }
+        }
+
+        reachable
+    }
+
+    /// Whether a function can end up calling itself
+    pub fn is_recursive(&self, func: FnId) -> bool {
+        let callees = self.callees(func).iter().copied().collect::<Vec<_>>();
+        self.reachable_from(&callees)[func as usize]
+    }
+
+    /// Every function, each after those it calls (bar the ones it's mutually recursive with)
+    pub fn post_order(&self) -> Vec<FnId> {
+        let mut order = vec![];
+        let mut visited = vec![false; self.callees.len()];
+
+        for root in 0..self.callees.len() as FnId {
+            if std::mem::replace(&mut visited[root as usize], true) {
+                continue;
+            }
+
+            // Each function, w/ the callees it's yet to visit
+            let mut stack = vec![(root, self.callees(root).iter())];
+            while let Some((func, callees)) = stack.last_mut() {
+                match callees.next() {
+                    Some(&callee) if !std::mem::replace(&mut visited[callee as usize], true) => {
+                        stack.push((callee, self.callees(callee).iter()));
+                    }
+                    Some(_) => {}
+                    None => {
+                        order.push(*func);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        order
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::BTreeSet;
+
+use super::core::{Block, BlockId, Function, Inst, Operand, Terminator};
+use super::dom::DomTree;
+
+/// A function's control-flow graph: its blocks, w/ an edge from each to the targets of its
+/// terminator. Branches to the same block twice make for a single edge.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    succs: Vec<Vec<BlockId>>,
+    preds: Vec<Vec<BlockId>>,
+    rpo: Vec<BlockId>,
+    rpo_index: Vec<Option<usize>>, // None for blocks unreachable from the entry
+}
+
+impl Cfg {
+    pub fn new(f: &Function) -> Self {
+        let n_blocks = f.blocks.len();
+        let mut succs = vec![vec![]; n_blocks];
+        let mut preds = vec![vec![]; n_blocks];
+
+        for id in f.block_ids() {
+            for target in f.block(id).term.successors() {
+                if !succs[id.0 as usize].contains(&target) {
+                    succs[id.0 as usize].push(target);
+                    preds[target.0 as usize].push(id);
+                }
+            }
+        }
+
+        // Post-order, iteratively: a block is emitted once all of its successors have been
+        let mut post_order = vec![];
+        let mut visited = vec![false; n_blocks];
+        let mut stack = vec![(BlockId(0), 0)];
+        visited[0] = true;
+
+        while let Some((id, next_succ)) = stack.last_mut() {
+            match succs[id.0 as usize].get(*next_succ) {
+                Some(&succ) => {
+                    *next_succ += 1;
+                    if !visited[succ.0 as usize] {
+                        visited[succ.0 as usize] = true;
+                        stack.push((succ, 0));
+                    }
+                }
+                None => {
+                    post_order.push(*id);
+                    stack.pop();
+                }
+            }
+        }
+
+        let rpo = post_order.into_iter().rev().collect::<Vec<_>>();
+        let mut rpo_index = vec![None; n_blocks];
+        for (i, id) in rpo.iter().enumerate() {
+            rpo_index[id.0 as usize] = Some(i);
+        }
+
+        Self {
+            succs,
+            preds,
+            rpo,
+            rpo_index,
+        }
+    }
+
+    pub fn n_blocks(&self) -> usize {
+        self.succs.len()
+    }
+
+    pub fn succs(&self, id: BlockId) -> &[BlockId] {
+        &self.succs[id.0 as usize]
+    }
+
+    /// Predecessors, reachable or not
+    pub fn preds(&self, id: BlockId) -> &[BlockId] {
+        &self.preds[id.0 as usize]
+    }
+
+    /// The blocks reachable from the entry, in reverse post-order, i.e every block comes before
+    /// its successors, back edges aside
+    pub fn rpo(&self) -> &[BlockId] {
+        &self.rpo
+    }
+
+    pub fn rpo_index(&self, id: BlockId) -> Option<usize> {
+        self.rpo_index[id.0 as usize]
+    }
+
+    pub fn is_reachable(&self, id: BlockId) -> bool {
+        self.rpo_index(id).is_some()
+    }
+}
+
+/// A natural loop: the blocks that can reach one of the back edges into `header` w/o going
+/// through it. Loops sharing a header are merged into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: BlockId,
+    pub latches: Vec<BlockId>,   // sources of the back edges, in order
+    pub body: BTreeSet<BlockId>, // header included
+}
+
+impl Loop {
+    pub fn contains(&self, id: BlockId) -> bool {
+        self.body.contains(&id)
+    }
+
+    /// Edges leaving the loop, as (inside, outside) pairs
+    pub fn exits(&self, cfg: &Cfg) -> Vec<(BlockId, BlockId)> {
+        self.body
+            .iter()
+            .flat_map(|&id| cfg.succs(id).iter().map(move |&succ| (id, succ)))
+            .filter(|(_, succ)| !self.contains(*succ))
+            .collect()
+    }
+
+    /// The block that's the only way into the header from outside the loop, if there's one,
+    /// and it leads nowhere else. Code hoisted out of the loop goes there.
+    pub fn preheader(&self, cfg: &Cfg) -> Option<BlockId> {
+        let mut outside = cfg
+            .preds(self.header)
+            .iter()
+            .filter(|&&p| cfg.is_reachable(p) && !self.contains(p));
+
+        match (outside.next(), outside.next()) {
+            (Some(&p), None) if cfg.succs(p) == [self.header] => Some(p),
+            _ => None,
+        }
+    }
+}
+
+/// The natural loops of a function, outer loops before the ones nested in them
+pub fn natural_loops(cfg: &Cfg, dom: &DomTree) -> Vec<Loop> {
+    let mut loops = vec![];
+
+    for &header in cfg.rpo() {
+        let latches = cfg
+            .preds(header)
+            .iter()
+            .copied()
+            .filter(|&pred| cfg.is_reachable(pred) && dom.dominates(header, pred))
+            .collect::<Vec<_>>();
+        if latches.is_empty() {
+            continue;
+        }
+
+        // Walk backwards from the latches; the header stops the walk, as it dominates them all
+        let mut body = BTreeSet::from([header]);
+        let mut stack = latches.clone();
+        while let Some(id) = stack.pop() {
+            if body.insert(id) {
+                stack.extend(cfg.preds(id).iter().filter(|&&p| cfg.is_reachable(p)));
+            }
+        }
+
+        loops.push(Loop {
+            header,
+            latches,
+            body,
+        });
+    }
+
+    loops
+}
+
+/// Drops the blocks unreachable from the entry, renumbering the rest in order
+pub fn remove_unreachable_blocks(f: &mut Function) {
+    let cfg = Cfg::new(f);
+
+    let mut new_ids = vec![None; f.blocks.len()];
+    let mut next = 0;
+    for id in f.block_ids() {
+        if cfg.is_reachable(id) {
+            new_ids[id.0 as usize] = Some(BlockId(next));
+            next += 1;
+        }
+    }
+
+    let blocks = std::mem::take(&mut f.blocks);
+    f.blocks = blocks
+        .into_iter()
+        .zip(&new_ids)
+        .filter_map(|(block, new_id)| new_id.map(|_| block))
+        .collect();
+
+    for block in &mut f.blocks {
+        for target in block.term.successors_mut() {
+            *target = new_ids[target.0 as usize].unwrap();
+        }
+
+        // Phis lose the values coming in from dropped predecessors
+        for inst in &mut block.insts {
+            if let Inst::Phi { args, .. } = inst {
+                args.retain_mut(|(pred, _)| match new_ids[pred.0 as usize] {
+                    Some(new_id) => {
+                        *pred = new_id;
+                        true
+                    }
+                    None => false,
+                });
+            }
+        }
+    }
+}
+
+/// Gives every loop a preheader (see `Loop::preheader`), splitting the phis at its header
+/// between the two: the preheader's merge the values coming in from outside the loop.
+pub fn insert_preheaders(f: &mut Function) {
+    loop {
+        let cfg = Cfg::new(f);
+        let dom = DomTree::new(&cfg);
+        let loops = natural_loops(&cfg, &dom);
+        let Some(lp) = loops.iter().find(|lp| lp.preheader(&cfg).is_none()) else {
+            break;
+        };
+
+        let header = lp.header;
+        let preheader = BlockId(f.blocks.len() as u32);
+        let outside = cfg
+            .preds(header)
+            .iter()
+            .copied()
+            .filter(|&p| cfg.is_reachable(p) && !lp.contains(p))
+            .collect::<Vec<_>>();
+
+        for &p in &outside {
+            for target in f.blocks[p.0 as usize].term.successors_mut() {
+                if *target == header {
+                    *target = preheader;
+                }
+            }
+        }
+
+        let mut insts = vec![];
+        let n_phis = f
+            .block(header)
+            .insts
+            .iter()
+            .take_while(|inst| inst.is_phi())
+            .count();
+        for i in 0..n_phis {
+            let Inst::Phi { dst, args } = &mut f.blocks[header.0 as usize].insts[i] else {
+                unreachable!()
+            };
+            let dst = *dst;
+            let (from_outside, mut from_inside): (Vec<_>, Vec<_>) =
+                args.drain(..).partition(|(pred, _)| outside.contains(pred));
+
+            // W/ only the one way in, there's nothing to merge
+            let value = match &from_outside[..] {
+                [(_, value)] => *value,
+                _ => {
+                    let merged = f.new_reg(f.reg_type(dst));
+                    insts.push(Inst::Phi {
+                        dst: merged,
+                        args: from_outside,
+                    });
+                    Operand::Reg(merged)
+                }
+            };
+
+            from_inside.insert(0, (preheader, value));
+            let Inst::Phi { args, .. } = &mut f.blocks[header.0 as usize].insts[i] else {
+                unreachable!()
+            };
+            *args = from_inside;
+        }
+
+        f.blocks.push(Block {
+            insts,
+            term: Terminator::Jump(header),
+        });
+    }
+}
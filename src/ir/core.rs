@@ -0,0 +1,327 @@
+use crate::lexer::{Span, Token};
+use crate::semantics::spaghetti::SymType;
+
+// Three-address code: every instruction names at most two operands and (at most) one
+// destination register. Registers are typed, and may be assigned more than once; a variable
+// lives in a single register for its whole life.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Reg(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+/// Index into `Module::functions`
+pub type FnId = u32;
+
+/// Index into `Module::globals`
+pub type GlobalId = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Const {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(u32), // index into `Module::strings`
+}
+
+impl Const {
+    pub fn get_type(&self) -> SymType {
+        match self {
+            Const::Int(_) => SymType::Int,
+            Const::Float(_) => SymType::Float,
+            Const::Bool(_) => SymType::Bool,
+            Const::Str(_) => SymType::String,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Reg(Reg),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    Lt,
+    Gt,
+    Eq,
+}
+
+impl BinOp {
+    pub fn from_token(tok: &Token) -> BinOp {
+        match tok {
+            Token::AddOp => BinOp::Add,
+            Token::SubOp => BinOp::Sub,
+            Token::MulOp => BinOp::Mul,
+            Token::DivOp => BinOp::Div,
+            Token::ModOp => BinOp::Mod,
+            Token::ExpOp => BinOp::Exp,
+            Token::ShiftLeft => BinOp::Shl,
+            Token::ShiftRight => BinOp::Shr,
+            Token::BitwiseAnd => BinOp::BitAnd,
+            Token::BitwiseOr => BinOp::BitOr,
+            Token::LessThan => BinOp::Lt,
+            Token::GreaterThan => BinOp::Gt,
+            Token::EqualsOp => BinOp::Eq,
+            _ => unreachable!("{tok:?} isn't an arithmetic operator"),
+        }
+    }
+
+    /// Inverse of `from_token`, for sharing the interpreter's arithmetic
+    pub fn token(self) -> Token {
+        match self {
+            BinOp::Add => Token::AddOp,
+            BinOp::Sub => Token::SubOp,
+            BinOp::Mul => Token::MulOp,
+            BinOp::Div => Token::DivOp,
+            BinOp::Mod => Token::ModOp,
+            BinOp::Exp => Token::ExpOp,
+            BinOp::Shl => Token::ShiftLeft,
+            BinOp::Shr => Token::ShiftRight,
+            BinOp::BitAnd => Token::BitwiseAnd,
+            BinOp::BitOr => Token::BitwiseOr,
+            BinOp::Lt => Token::LessThan,
+            BinOp::Gt => Token::GreaterThan,
+            BinOp::Eq => Token::EqualsOp,
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        matches!(self, BinOp::Lt | BinOp::Gt | BinOp::Eq)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callee {
+    Fn(FnId),
+    Intrinsic(u32), // index into `INTRINSICS`
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    Copy {
+        dst: Reg,
+        src: Operand,
+    },
+    Binary {
+        dst: Reg,
+        op: BinOp,
+        lhs: Operand,
+        rhs: Operand,
+        span: Span, // of the statement it's from, for reporting its failures
+    },
+    Unary {
+        dst: Reg,
+        op: UnOp,
+        operand: Operand,
+    },
+    Call {
+        dst: Option<Reg>, // None for khali callees
+        callee: Callee,
+        args: Vec<Operand>,
+    },
+    LoadGlobal {
+        dst: Reg,
+        global: GlobalId,
+    },
+    StoreGlobal {
+        global: GlobalId,
+        src: Operand,
+    },
+    // Only in SSA form, and only at the start of a block: the value coming in from `pred`
+    Phi {
+        dst: Reg,
+        args: Vec<(BlockId, Operand)>, // one per predecessor
+    },
+}
+
+impl Inst {
+    pub fn is_phi(&self) -> bool {
+        matches!(self, Inst::Phi { .. })
+    }
+
+    /// The register this instruction assigns to, if any
+    pub fn dst(&self) -> Option<Reg> {
+        match self {
+            Inst::Copy { dst, .. }
+            | Inst::Binary { dst, .. }
+            | Inst::Unary { dst, .. }
+            | Inst::LoadGlobal { dst, .. }
+            | Inst::Phi { dst, .. } => Some(*dst),
+            Inst::Call { dst, .. } => *dst,
+            Inst::StoreGlobal { .. } => None,
+        }
+    }
+
+    pub fn dst_mut(&mut self) -> Option<&mut Reg> {
+        match self {
+            Inst::Copy { dst, .. }
+            | Inst::Binary { dst, .. }
+            | Inst::Unary { dst, .. }
+            | Inst::LoadGlobal { dst, .. }
+            | Inst::Phi { dst, .. } => Some(dst),
+            Inst::Call { dst, .. } => dst.as_mut(),
+            Inst::StoreGlobal { .. } => None,
+        }
+    }
+
+    /// The operands this instruction reads, in order
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Inst::Copy { src, .. } | Inst::StoreGlobal { src, .. } => vec![src],
+            Inst::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Inst::Unary { operand, .. } => vec![operand],
+            Inst::Call { args, .. } => args.iter().collect(),
+            Inst::Phi { args, .. } => args.iter().map(|(_, arg)| arg).collect(),
+            Inst::LoadGlobal { .. } => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Inst::Copy { src, .. } | Inst::StoreGlobal { src, .. } => vec![src],
+            Inst::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Inst::Unary { operand, .. } => vec![operand],
+            Inst::Call { args, .. } => args.iter_mut().collect(),
+            Inst::Phi { args, .. } => args.iter_mut().map(|(_, arg)| arg).collect(),
+            Inst::LoadGlobal { .. } => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        cond: Operand,
+        then_block: BlockId,
+        else_block: BlockId,
+    },
+    Ret(Option<Operand>),
+    MissingRet, // control ran off the end of a non-khali function
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![*then_block, *else_block],
+            Terminator::Ret(_) | Terminator::MissingRet => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![target],
+            Terminator::Branch {
+                then_block,
+                else_block,
+                ..
+            } => vec![then_block, else_block],
+            Terminator::Ret(_) | Terminator::MissingRet => vec![],
+        }
+    }
+
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Terminator::Branch { cond, .. } => vec![cond],
+            Terminator::Ret(Some(value)) => vec![value],
+            _ => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Terminator::Branch { cond, .. } => vec![cond],
+            Terminator::Ret(Some(value)) => vec![value],
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub insts: Vec<Inst>,
+    pub term: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Reg>,
+    pub ret_type: SymType,
+    pub reg_types: Vec<SymType>, // indexed by register
+    pub blocks: Vec<Block>,      // indexed by `BlockId`, the entry first
+}
+
+impl Function {
+    pub fn reg_type(&self, reg: Reg) -> SymType {
+        self.reg_types[reg.0 as usize]
+    }
+
+    pub fn operand_type(&self, operand: &Operand) -> SymType {
+        match operand {
+            Operand::Reg(reg) => self.reg_type(*reg),
+            Operand::Const(c) => c.get_type(),
+        }
+    }
+
+    pub fn new_reg(&mut self, sym_type: SymType) -> Reg {
+        self.reg_types.push(sym_type);
+        Reg(self.reg_types.len() as u32 - 1)
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0 as usize]
+    }
+
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Global {
+    pub name: String,
+    pub sym_type: SymType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub globals: Vec<Global>,
+    pub strings: Vec<String>,
+    pub functions: Vec<Function>,
+    pub init: FnId,          // initialises globals, in order
+    pub entry: Option<FnId>, // `shuru`, if there is one
+}
+
+impl Module {
+    pub fn function_id(&self, name: &str) -> Option<FnId> {
+        self.functions
+            .iter()
+            .position(|f| f.name == name)
+            .map(|id| id as FnId)
+    }
+}
@@ -0,0 +1,125 @@
+use super::cfg::Cfg;
+use super::core::BlockId;
+
+/// The dominator tree of a function's reachable blocks: `a` dominates `b` if every path from the
+/// entry to `b` goes through `a`. Built w/ Cooper, Harvey & Kennedy's "A Simple, Fast Dominance
+/// Algorithm", iterating over the blocks in reverse post-order until nothing changes.
+#[derive(Debug, Clone)]
+pub struct DomTree {
+    idom: Vec<Option<BlockId>>, // None for the entry, and for unreachable blocks
+    children: Vec<Vec<BlockId>>,
+    depth: Vec<usize>,
+}
+
+impl DomTree {
+    pub fn new(cfg: &Cfg) -> Self {
+        let n_blocks = cfg.n_blocks();
+        let entry = BlockId(0);
+
+        // Indices into the reverse post-order, the entry's idom being itself while computing
+        let mut idom: Vec<Option<usize>> = vec![None; n_blocks];
+        idom[0] = Some(0);
+
+        let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while a > b {
+                    a = idom[cfg.rpo()[a].0 as usize].unwrap();
+                }
+                while b > a {
+                    b = idom[cfg.rpo()[b].0 as usize].unwrap();
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for &id in &cfg.rpo()[1..] {
+                let new_idom = cfg
+                    .preds(id)
+                    .iter()
+                    .filter(|&&p| idom[p.0 as usize].is_some())
+                    .map(|&p| cfg.rpo_index(p).unwrap())
+                    .reduce(|a, b| intersect(&idom, a, b));
+
+                if new_idom.is_some() && idom[id.0 as usize] != new_idom {
+                    idom[id.0 as usize] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut tree = Self {
+            idom: vec![None; n_blocks],
+            children: vec![vec![]; n_blocks],
+            depth: vec![0; n_blocks],
+        };
+
+        for &id in cfg.rpo() {
+            if id == entry {
+                continue;
+            }
+
+            let parent = cfg.rpo()[idom[id.0 as usize].unwrap()];
+            tree.idom[id.0 as usize] = Some(parent);
+            tree.children[parent.0 as usize].push(id);
+            tree.depth[id.0 as usize] = tree.depth[parent.0 as usize] + 1;
+        }
+
+        tree
+    }
+
+    /// The immediate dominator, i.e the parent in the tree
+    pub fn idom(&self, id: BlockId) -> Option<BlockId> {
+        self.idom[id.0 as usize]
+    }
+
+    /// The blocks `id` immediately dominates, in reverse post-order
+    pub fn children(&self, id: BlockId) -> &[BlockId] {
+        &self.children[id.0 as usize]
+    }
+
+    /// Whether `a` dominates `b`; every (reachable) block dominates itself
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        let a_depth = self.depth[a.0 as usize];
+        while self.depth[b.0 as usize] > a_depth {
+            b = self.idom(b).unwrap();
+        }
+        a == b
+    }
+
+    /// The blocks in a pre-order walk of the tree, from the entry
+    pub fn pre_order(&self) -> Vec<BlockId> {
+        let mut order = vec![];
+        let mut stack = vec![BlockId(0)];
+
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.children(id).iter().rev());
+        }
+
+        order
+    }
+
+    /// Each block's dominance frontier: the blocks where its dominance ends, i.e those it doesn't
+    /// strictly dominate, but does dominate a predecessor of
+    pub fn frontiers(&self, cfg: &Cfg) -> Vec<Vec<BlockId>> {
+        let mut frontiers = vec![vec![]; cfg.n_blocks()];
+
+        for &id in cfg.rpo() {
+            for &pred in cfg.preds(id).iter().filter(|&&p| cfg.is_reachable(p)) {
+                let mut runner = Some(pred);
+                while let Some(r) = runner.filter(|&r| Some(r) != self.idom(id)) {
+                    if !frontiers[r.0 as usize].contains(&id) {
+                        frontiers[r.0 as usize].push(id);
+                    }
+                    runner = self.idom(r);
+                }
+            }
+        }
+
+        frontiers
+    }
+}
@@ -0,0 +1,253 @@
+use std::io::{self, BufRead, BufReader, Write};
+
+use super::core::{BlockId, Callee, Const, FnId, Inst, Module, Operand, Reg, Terminator, UnOp};
+use crate::interp::{
+    core::{RuntimeError, RuntimeErrorKind, MAX_CALL_DEPTH},
+    intrinsics, ops,
+    value::Value,
+};
+use crate::lexer::{Span, Token};
+use crate::semantics::intrinsics::INTRINSICS;
+
+/// Runs a lowered module, in or out of SSA form, w/ the interpreter's semantics. Only binary
+/// operators carry spans in the IR, so other errors are reported at `Span::default()`. Meant for
+/// checking that lowering, and the passes that follow it, preserve what a program does.
+pub fn run(module: &Module, args: &[String]) -> Result<i64, RuntimeError> {
+    let mut input = BufReader::new(io::stdin());
+    run_with_io(module, args, &mut input, &mut io::stdout())
+}
+
+/// Like `run`, but w/ the intrinsics reading from `input` and writing to `output`
+pub fn run_with_io(
+    module: &Module,
+    args: &[String],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<i64, RuntimeError> {
+    let mut e = Evaluator {
+        module,
+        globals: vec![Value::Void; module.globals.len()],
+        frames: vec![],
+        input,
+        output,
+    };
+
+    let res = e.run(args.len());
+    e.output
+        .flush()
+        .map_err(|err| error(RuntimeErrorKind::Io(err.kind())))?;
+    res
+}
+
+fn error(kind: RuntimeErrorKind) -> RuntimeError {
+    RuntimeError {
+        kind,
+        span: Span::default(),
+    }
+}
+
+struct Frame {
+    func: FnId,
+    regs: Vec<Value>,
+    block: BlockId,
+    pc: usize,
+    ret_dst: Option<Reg>, // where the caller wants the return value
+}
+
+struct Evaluator<'m, 'io> {
+    module: &'m Module,
+    globals: Vec<Value>,
+    frames: Vec<Frame>,
+    input: &'io mut dyn BufRead,
+    output: &'io mut dyn Write,
+}
+
+impl Evaluator<'_, '_> {
+    fn run(&mut self, argc: usize) -> Result<i64, RuntimeError> {
+        self.call(self.module.init, vec![])?;
+
+        let Some(entry) = self.module.entry else {
+            return Err(error(RuntimeErrorKind::NoEntryPoint));
+        };
+
+        let args = match self.module.functions[entry as usize].params.len() {
+            0 => vec![],
+            _ => vec![Value::Int(argc as i64)],
+        };
+
+        match self.call(entry, args)? {
+            Value::Int(code) => Ok(code),
+            _ => Ok(0),
+        }
+    }
+
+    // Calls a function from outside any frame, running until it returns
+    fn call(&mut self, func: FnId, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        self.push_frame(func, args, None)?;
+        self.execute()
+    }
+
+    fn push_frame(
+        &mut self,
+        func: FnId,
+        args: Vec<Value>,
+        ret_dst: Option<Reg>,
+    ) -> Result<(), RuntimeError> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(error(RuntimeErrorKind::StackOverflow));
+        }
+
+        let f = &self.module.functions[func as usize];
+        let mut regs = vec![Value::Void; f.reg_types.len()];
+        for (&p, arg) in f.params.iter().zip(args) {
+            regs[p.0 as usize] = arg;
+        }
+
+        self.frames.push(Frame {
+            func,
+            regs,
+            block: BlockId(0),
+            pc: 0,
+            ret_dst,
+        });
+        Ok(())
+    }
+
+    // Moves to `target`, evaluating its phis all at once, w/ the values coming in from the
+    // block being left
+    fn jump(&mut self, target: BlockId) {
+        let module = self.module;
+        let frame = self.frames.last_mut().unwrap();
+        let insts = &module.functions[frame.func as usize].block(target).insts;
+
+        let mut incoming = vec![];
+        for inst in insts {
+            let Inst::Phi { dst, args } = inst else {
+                break;
+            };
+            let (_, arg) = args
+                .iter()
+                .find(|(pred, _)| *pred == frame.block)
+                .expect("phi w/o an argument for its predecessor");
+            incoming.push((*dst, operand(module, &frame.regs, arg)));
+        }
+
+        frame.pc = incoming.len();
+        frame.block = target;
+        for (dst, value) in incoming {
+            frame.regs[dst.0 as usize] = value;
+        }
+    }
+
+    // Runs until the frame on top when called returns, giving back its return value
+    fn execute(&mut self) -> Result<Value, RuntimeError> {
+        let module = self.module;
+        let depth = self.frames.len();
+
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let f = &module.functions[frame.func as usize];
+            let block = f.block(frame.block);
+
+            let Some(inst) = block.insts.get(frame.pc) else {
+                match &block.term {
+                    Terminator::Jump(target) => self.jump(*target),
+                    Terminator::Branch {
+                        cond,
+                        then_block,
+                        else_block,
+                    } => {
+                        let target = match operand(module, &frame.regs, cond).as_bool() {
+                            true => *then_block,
+                            false => *else_block,
+                        };
+                        self.jump(target);
+                    }
+                    Terminator::Ret(value) => {
+                        let value = value
+                            .as_ref()
+                            .map_or(Value::Void, |v| operand(module, &frame.regs, v));
+                        let frame = self.frames.pop().unwrap();
+
+                        if self.frames.len() < depth {
+                            return Ok(value);
+                        }
+                        if let Some(dst) = frame.ret_dst {
+                            self.frames.last_mut().unwrap().regs[dst.0 as usize] = value;
+                        }
+                    }
+                    Terminator::MissingRet => {
+                        return Err(error(RuntimeErrorKind::MissingReturn(f.name.clone())));
+                    }
+                }
+                continue;
+            };
+            frame.pc += 1;
+
+            let value = match inst {
+                Inst::Copy { src, .. } => operand(module, &frame.regs, src),
+                Inst::Binary {
+                    op, lhs, rhs, span, ..
+                } => {
+                    let (lhs, rhs) = (
+                        operand(module, &frame.regs, lhs),
+                        operand(module, &frame.regs, rhs),
+                    );
+                    ops::binary(&op.token(), lhs, rhs)
+                        .map_err(|kind| RuntimeError { kind, span: *span })?
+                }
+                Inst::Unary { op, operand: o, .. } => {
+                    let tok = match op {
+                        UnOp::Neg => Token::SubOp,
+                        UnOp::Not => Token::BooleanNot,
+                    };
+                    ops::unary(&tok, operand(module, &frame.regs, o)).map_err(error)?
+                }
+                Inst::LoadGlobal { global, .. } => self.globals[*global as usize].clone(),
+                Inst::StoreGlobal { global, src } => {
+                    self.globals[*global as usize] = operand(module, &frame.regs, src);
+                    continue;
+                }
+                Inst::Call { dst, callee, args } => {
+                    let args = args
+                        .iter()
+                        .map(|arg| operand(module, &frame.regs, arg))
+                        .collect();
+                    match *callee {
+                        Callee::Fn(func) => {
+                            self.push_frame(func, args, *dst)?;
+                            continue;
+                        }
+                        Callee::Intrinsic(i) => {
+                            let name = INTRINSICS[i as usize].name;
+                            intrinsics::call(name, &args, self.input, self.output)
+                                .map_err(|err| error(RuntimeErrorKind::Io(err.kind())))?
+                        }
+                    }
+                }
+                Inst::Phi { .. } => unreachable!("phi after the start of a block"),
+            };
+
+            if let Some(dst) = inst.dst() {
+                let frame = self.frames.last_mut().unwrap();
+                frame.regs[dst.0 as usize] = value;
+            }
+        }
+    }
+}
+
+fn operand(module: &Module, regs: &[Value], operand: &Operand) -> Value {
+    match operand {
+        Operand::Reg(reg) => regs[reg.0 as usize].clone(),
+        Operand::Const(c) => const_value(&module.strings, c),
+    }
+}
+
+pub fn const_value(strings: &[String], c: &Const) -> Value {
+    match *c {
+        Const::Int(i) => Value::Int(i),
+        Const::Float(x) => Value::Float(x),
+        Const::Bool(b) => Value::Bool(b),
+        Const::Str(id) => Value::String(strings[id as usize].clone()),
+    }
+}
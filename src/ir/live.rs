@@ -0,0 +1,100 @@
+use std::collections::BTreeSet;
+
+use super::cfg::Cfg;
+use super::core::{BlockId, Function, Inst, Operand, Reg};
+
+/// Which registers hold a value that may still be read, on entry to and exit from each block.
+/// A phi reads its arguments at the end of the corresponding predecessor, so they're live out of
+/// it, but not into the phi's block; the phi's destination is defined at the start of its block.
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    pub live_in: Vec<BTreeSet<Reg>>,
+    pub live_out: Vec<BTreeSet<Reg>>,
+}
+
+impl Liveness {
+    pub fn new(f: &Function, cfg: &Cfg) -> Self {
+        let n_blocks = f.blocks.len();
+
+        // Per block: registers read before being assigned in it, and those assigned in it
+        let mut uses = vec![BTreeSet::new(); n_blocks];
+        let mut defs = vec![BTreeSet::new(); n_blocks];
+        // Per block: registers read by its successors' phis, along the edge from it
+        let mut phi_uses = vec![BTreeSet::new(); n_blocks];
+
+        for id in f.block_ids() {
+            let (uses, defs) = (&mut uses[id.0 as usize], &mut defs[id.0 as usize]);
+            let block = f.block(id);
+
+            for inst in &block.insts {
+                if let Inst::Phi { args, .. } = inst {
+                    for (pred, arg) in args {
+                        if let Operand::Reg(reg) = arg {
+                            phi_uses[pred.0 as usize].insert(*reg);
+                        }
+                    }
+                } else {
+                    for reg in regs(inst.operands()) {
+                        if !defs.contains(&reg) {
+                            uses.insert(reg);
+                        }
+                    }
+                }
+
+                if let Some(dst) = inst.dst() {
+                    defs.insert(dst);
+                }
+            }
+
+            for reg in regs(block.term.operands()) {
+                if !defs.contains(&reg) {
+                    uses.insert(reg);
+                }
+            }
+        }
+
+        let mut live_in = vec![BTreeSet::new(); n_blocks];
+        let mut live_out = vec![BTreeSet::<Reg>::new(); n_blocks];
+
+        // Backwards dataflow, to a fixed point; post-order visits successors first
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for &id in cfg.rpo().iter().rev() {
+                let i = id.0 as usize;
+
+                let mut out = phi_uses[i].clone();
+                for succ in cfg.succs(id) {
+                    out.extend(&live_in[succ.0 as usize]);
+                }
+
+                let mut in_ = uses[i].clone();
+                in_.extend(out.difference(&defs[i]));
+
+                if in_ != live_in[i] || out != live_out[i] {
+                    live_in[i] = in_;
+                    live_out[i] = out;
+                    changed = true;
+                }
+            }
+        }
+
+        Self { live_in, live_out }
+    }
+
+    pub fn live_in(&self, id: BlockId) -> &BTreeSet<Reg> {
+        &self.live_in[id.0 as usize]
+    }
+
+    pub fn live_out(&self, id: BlockId) -> &BTreeSet<Reg> {
+        &self.live_out[id.0 as usize]
+    }
+}
+
+fn regs<'a>(operands: Vec<&'a Operand>) -> impl Iterator<Item = Reg> + 'a {
+    operands.into_iter().filter_map(|operand| match operand {
+        Operand::Reg(reg) => Some(*reg),
+        Operand::Const(_) => None,
+    })
+}
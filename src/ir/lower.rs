@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+
+use super::cfg::remove_unreachable_blocks;
+use super::core::{
+    BinOp, Block as IrBlock, BlockId, Callee, Const, FnId, Function, Global, GlobalId, Inst,
+    Module, Operand, Reg, Terminator, UnOp,
+};
+use crate::lexer::{Span, Token};
+use crate::parser::ast::core::*;
+use crate::semantics::{
+    core::ENTRY_POINT, intrinsics::INTRINSICS, spaghetti::SymType, utils::token_to_symtype,
+};
+
+/// Name of the function that initialises globals
+pub const INIT_FN: &str = "<init>";
+
+/// Lowers a checked program to three-address code. Every variable gets a register of its own;
+/// control flow (loops, `agar`/`warna`, `toro`, and short-circuiting `&&`/`||`) becomes
+/// branches b/w basic blocks. Blocks that can't be reached are dropped.
+pub fn lower(ast_root: &TranslationUnit) -> Module {
+    let mut l = Lowerer {
+        globals: vec![],
+        global_ids: HashMap::new(),
+        fns: HashMap::new(),
+        strings: vec![],
+    };
+
+    let fn_decls = ast_root
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Fn(f) => Some(f),
+            Decl::Var(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (id, f) in fn_decls.iter().enumerate() {
+        l.fns.insert(
+            f.ident.clone(),
+            (id as FnId, token_to_symtype(&f.type_tok, false)),
+        );
+    }
+
+    // Globals are numbered, and initialised, in order of declaration
+    let var_decls = ast_root
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Var(v) => Some(v),
+            Decl::Fn(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (id, v) in var_decls.iter().enumerate() {
+        l.global_ids.insert(v.ident.clone(), id as GlobalId);
+        l.globals.push(Global {
+            name: v.ident.clone(),
+            sym_type: token_to_symtype(&v.type_tok, true),
+        });
+    }
+
+    let mut functions = fn_decls.iter().map(|f| l.function(f)).collect::<Vec<_>>();
+
+    let mut init = FnLowerer::new(INIT_FN, SymType::Void);
+    for (id, v) in var_decls.iter().enumerate() {
+        init.span = v.span;
+        let value = l.expr(&mut init, &v.expr);
+        init.push(Inst::StoreGlobal {
+            global: id as GlobalId,
+            src: value.expect("global initialised w/ a khali value"),
+        });
+    }
+    init.terminate(Terminator::Ret(None));
+    functions.push(init.finish());
+
+    Module {
+        globals: l.globals,
+        strings: l.strings,
+        init: functions.len() as FnId - 1,
+        entry: l.fns.get(ENTRY_POINT).map(|&(id, _)| id),
+        functions,
+    }
+}
+
+struct Lowerer {
+    globals: Vec<Global>,
+    global_ids: HashMap<String, GlobalId>,
+    fns: HashMap<String, (FnId, SymType)>,
+    strings: Vec<String>,
+}
+
+// The function being lowered
+struct FnLowerer {
+    func: Function,
+    curr: BlockId,
+    scopes: Vec<Vec<(String, Reg)>>, // innermost last
+    loop_exits: Vec<BlockId>,        // of the enclosing loops, innermost last
+    span: Span,                      // of the statement being lowered
+}
+
+impl FnLowerer {
+    fn new(name: &str, ret_type: SymType) -> Self {
+        let func = Function {
+            name: name.to_string(),
+            params: vec![],
+            ret_type,
+            reg_types: vec![],
+            blocks: vec![IrBlock {
+                insts: vec![],
+                term: Terminator::MissingRet,
+            }],
+        };
+
+        Self {
+            func,
+            curr: BlockId(0),
+            scopes: vec![vec![]],
+            loop_exits: vec![],
+            span: Span::default(),
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.func.blocks.push(IrBlock {
+            insts: vec![],
+            term: Terminator::MissingRet,
+        });
+        BlockId(self.func.blocks.len() as u32 - 1)
+    }
+
+    fn push(&mut self, inst: Inst) {
+        self.func.blocks[self.curr.0 as usize].insts.push(inst);
+    }
+
+    // Ends the current block. Code that follows (e.g after a `wapsi`) lands in a fresh block,
+    // which nothing jumps to, unless switched away from.
+    fn terminate(&mut self, term: Terminator) {
+        self.func.blocks[self.curr.0 as usize].term = term;
+        self.curr = self.new_block();
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.curr = block;
+    }
+
+    fn declare(&mut self, ident: &str, sym_type: SymType) -> Reg {
+        let reg = self.func.new_reg(sym_type);
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .push((ident.to_string(), reg));
+        reg
+    }
+
+    fn resolve_local(&self, ident: &str) -> Option<Reg> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find_map(|(name, reg)| (name == ident).then_some(*reg))
+    }
+
+    fn finish(mut self) -> Function {
+        remove_unreachable_blocks(&mut self.func);
+        self.func
+    }
+}
+
+impl Lowerer {
+    fn function(&mut self, f: &FnDecl) -> Function {
+        let mut fl = FnLowerer::new(&f.ident, token_to_symtype(&f.type_tok, false));
+
+        for p in &f.params {
+            let reg = fl.declare(&p.ident, token_to_symtype(&p.type_tok, true));
+            fl.func.params.push(reg);
+        }
+
+        self.stmts(&mut fl, &f.block);
+
+        let term = match fl.func.ret_type {
+            SymType::Void => Terminator::Ret(None),
+            _ => Terminator::MissingRet,
+        };
+        fl.terminate(term);
+        fl.finish()
+    }
+
+    fn block(&mut self, fl: &mut FnLowerer, block: &Block) {
+        fl.scopes.push(vec![]);
+        self.stmts(fl, block);
+        fl.scopes.pop();
+    }
+
+    fn stmts(&mut self, fl: &mut FnLowerer, block: &Block) {
+        for stmt in block {
+            self.stmt(fl, stmt);
+        }
+    }
+
+    fn stmt(&mut self, fl: &mut FnLowerer, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl(v) => self.var_decl(fl, v),
+
+            Stmt::Expr(e) => {
+                fl.span = e.span;
+                self.expr(fl, &e.expr);
+            }
+
+            Stmt::Ret(r) => {
+                fl.span = r.span;
+                let value = self.expr(fl, &r.expr);
+                fl.terminate(Terminator::Ret(value));
+            }
+
+            Stmt::Break(_) => {
+                let exit = *fl
+                    .loop_exits
+                    .last()
+                    .expect("`toro` outside a loop made it past analysis");
+                fl.terminate(Terminator::Jump(exit));
+            }
+
+            Stmt::For(f) => {
+                // The init's variable lives in a scope of its own, around the loop
+                fl.scopes.push(vec![]);
+                if let Some(init) = &f.init {
+                    self.var_decl(fl, init);
+                }
+
+                let (cond, body, updt, exit) = (
+                    fl.new_block(),
+                    fl.new_block(),
+                    fl.new_block(),
+                    fl.new_block(),
+                );
+                fl.terminate(Terminator::Jump(cond));
+
+                fl.switch_to(cond);
+                fl.span = f.span;
+                let term = match self.expr(fl, &f.cond.expr) {
+                    Some(c) => Terminator::Branch {
+                        cond: c,
+                        then_block: body,
+                        else_block: exit,
+                    },
+                    None => Terminator::Jump(body),
+                };
+                fl.terminate(term);
+
+                fl.switch_to(body);
+                fl.loop_exits.push(exit);
+                self.block(fl, &f.block);
+                fl.loop_exits.pop();
+                fl.terminate(Terminator::Jump(updt));
+
+                fl.switch_to(updt);
+                fl.span = f.span;
+                self.expr(fl, &f.updt);
+                fl.terminate(Terminator::Jump(cond));
+
+                fl.switch_to(exit);
+                fl.scopes.pop();
+            }
+
+            Stmt::If(i) => {
+                fl.span = i.span;
+                let cond = self.expr(fl, &i.cond).unwrap();
+                let (then_block, else_block, join) =
+                    (fl.new_block(), fl.new_block(), fl.new_block());
+                fl.terminate(Terminator::Branch {
+                    cond,
+                    then_block,
+                    else_block,
+                });
+
+                fl.switch_to(then_block);
+                self.block(fl, &i.if_block);
+                fl.terminate(Terminator::Jump(join));
+
+                fl.switch_to(else_block);
+                self.block(fl, &i.else_block);
+                fl.terminate(Terminator::Jump(join));
+
+                fl.switch_to(join);
+            }
+        }
+    }
+
+    fn var_decl(&mut self, fl: &mut FnLowerer, v: &VarDecl) {
+        fl.span = v.span;
+        let value = self.expr(fl, &v.expr);
+        let reg = fl.declare(&v.ident, token_to_symtype(&v.type_tok, true));
+        fl.push(Inst::Copy {
+            dst: reg,
+            src: value.expect("variable initialised w/ a khali value"),
+        });
+    }
+
+    // Evaluates an expression, returning where its value ended up (None for khali values)
+    fn expr(&mut self, fl: &mut FnLowerer, expr: &Expr) -> Option<Operand> {
+        expr.as_ref().and_then(|e| self.assign_expr(fl, e))
+    }
+
+    // A fresh register holding `lhs op rhs`
+    fn binary(&mut self, fl: &mut FnLowerer, op: BinOp, lhs: Operand, rhs: Operand) -> Operand {
+        let sym_type = match op.is_comparison() {
+            true => SymType::Bool,
+            false => fl.func.operand_type(&lhs),
+        };
+
+        let dst = fl.func.new_reg(sym_type);
+        let span = fl.span;
+        fl.push(Inst::Binary {
+            dst,
+            op,
+            lhs,
+            rhs,
+            span,
+        });
+        Operand::Reg(dst)
+    }
+
+    fn assign_expr(&mut self, fl: &mut FnLowerer, e: &AssignExpr) -> Option<Operand> {
+        match e {
+            AssignExpr::Bool(b) => self.bool_expr(fl, b),
+            AssignExpr::Assign(lhs, rhs) => {
+                let ident = lhs
+                    .as_ident()
+                    .expect("assignment target should've been checked to be an identifier");
+                let value = self.assign_expr(fl, rhs).unwrap();
+
+                match fl.resolve_local(ident) {
+                    Some(dst) => fl.push(Inst::Copy { dst, src: value }),
+                    None => fl.push(Inst::StoreGlobal {
+                        global: self.global_ids[ident],
+                        src: value,
+                    }),
+                }
+
+                Some(value)
+            }
+        }
+    }
+
+    fn bool_expr(&mut self, fl: &mut FnLowerer, e: &BoolExpr) -> Option<Operand> {
+        let (lhs, op, rhs) = match e {
+            BoolExpr::BitOr(b) => return self.bit_or_expr(fl, b),
+            BoolExpr::Bool(lhs, op, rhs) => (lhs, op, rhs),
+        };
+
+        // Short-circuit: the result starts off as the lhs, and is only overwritten w/ the rhs
+        // if the lhs doesn't decide it
+        let lhs = self.bool_expr(fl, lhs).unwrap();
+        let res = fl.func.new_reg(SymType::Bool);
+        fl.push(Inst::Copy { dst: res, src: lhs });
+
+        let (rhs_block, join) = (fl.new_block(), fl.new_block());
+        let (then_block, else_block) = match op {
+            Token::BooleanAnd => (rhs_block, join),
+            _ => (join, rhs_block),
+        };
+        fl.terminate(Terminator::Branch {
+            cond: Operand::Reg(res),
+            then_block,
+            else_block,
+        });
+
+        fl.switch_to(rhs_block);
+        let rhs = self.bit_or_expr(fl, rhs).unwrap();
+        fl.push(Inst::Copy { dst: res, src: rhs });
+        fl.terminate(Terminator::Jump(join));
+
+        fl.switch_to(join);
+        Some(Operand::Reg(res))
+    }
+
+    fn bit_or_expr(&mut self, fl: &mut FnLowerer, e: &BitOrExpr) -> Option<Operand> {
+        match e {
+            BitOrExpr::BitAnd(b) => self.bit_and_expr(fl, b),
+            BitOrExpr::BitOr(lhs, rhs) => {
+                let lhs = self.bit_or_expr(fl, lhs).unwrap();
+                let rhs = self.bit_and_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::BitOr, lhs, rhs))
+            }
+        }
+    }
+
+    fn bit_and_expr(&mut self, fl: &mut FnLowerer, e: &BitAndExpr) -> Option<Operand> {
+        match e {
+            BitAndExpr::Comp(c) => self.comp_expr(fl, c),
+            BitAndExpr::BitAnd(lhs, rhs) => {
+                let lhs = self.bit_and_expr(fl, lhs).unwrap();
+                let rhs = self.comp_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::BitAnd, lhs, rhs))
+            }
+        }
+    }
+
+    fn comp_expr(&mut self, fl: &mut FnLowerer, e: &CompExpr) -> Option<Operand> {
+        match e {
+            CompExpr::Shift(s) => self.shift_expr(fl, s),
+            CompExpr::Comp(lhs, op, rhs) => {
+                let lhs = self.comp_expr(fl, lhs).unwrap();
+                let rhs = self.shift_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::from_token(op), lhs, rhs))
+            }
+        }
+    }
+
+    fn shift_expr(&mut self, fl: &mut FnLowerer, e: &ShiftExpr) -> Option<Operand> {
+        match e {
+            ShiftExpr::Add(a) => self.add_expr(fl, a),
+            ShiftExpr::Shift(lhs, op, rhs) => {
+                let lhs = self.shift_expr(fl, lhs).unwrap();
+                let rhs = self.add_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::from_token(op), lhs, rhs))
+            }
+        }
+    }
+
+    fn add_expr(&mut self, fl: &mut FnLowerer, e: &AddExpr) -> Option<Operand> {
+        match e {
+            AddExpr::Mul(m) => self.mul_expr(fl, m),
+            AddExpr::Add(lhs, op, rhs) => {
+                let lhs = self.add_expr(fl, lhs).unwrap();
+                let rhs = self.mul_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::from_token(op), lhs, rhs))
+            }
+        }
+    }
+
+    fn mul_expr(&mut self, fl: &mut FnLowerer, e: &MulExpr) -> Option<Operand> {
+        match e {
+            MulExpr::Exp(x) => self.exp_expr(fl, x),
+            MulExpr::Mul(lhs, op, rhs) => {
+                let lhs = self.mul_expr(fl, lhs).unwrap();
+                let rhs = self.exp_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::from_token(op), lhs, rhs))
+            }
+        }
+    }
+
+    fn exp_expr(&mut self, fl: &mut FnLowerer, e: &ExpExpr) -> Option<Operand> {
+        match e {
+            ExpExpr::Unary(u) => self.unary_expr(fl, u),
+            ExpExpr::Exp(lhs, rhs) => {
+                let lhs = self.unary_expr(fl, lhs).unwrap();
+                let rhs = self.exp_expr(fl, rhs).unwrap();
+                Some(self.binary(fl, BinOp::Exp, lhs, rhs))
+            }
+        }
+    }
+
+    fn unary_expr(&mut self, fl: &mut FnLowerer, e: &UnaryExpr) -> Option<Operand> {
+        match e {
+            UnaryExpr::Primary(p) => self.primary_expr(fl, p),
+            UnaryExpr::Unary(op, operand) => {
+                let operand = self.unary_expr(fl, operand).unwrap();
+                let op = match op {
+                    Token::SubOp => UnOp::Neg,
+                    _ => UnOp::Not,
+                };
+
+                let dst = fl.func.new_reg(fl.func.operand_type(&operand));
+                fl.push(Inst::Unary { dst, op, operand });
+                Some(Operand::Reg(dst))
+            }
+        }
+    }
+
+    fn primary_expr(&mut self, fl: &mut FnLowerer, e: &PrimaryExpr) -> Option<Operand> {
+        let c = match e {
+            PrimaryExpr::IntLit(i) => Const::Int(*i),
+            PrimaryExpr::FloatLit(f) => Const::Float(*f),
+            PrimaryExpr::BoolLit(b) => Const::Bool(*b),
+            PrimaryExpr::StringLit(s) => {
+                let id = match self.strings.iter().position(|existing| existing == s) {
+                    Some(id) => id,
+                    None => {
+                        self.strings.push(s.clone());
+                        self.strings.len() - 1
+                    }
+                };
+                Const::Str(id as u32)
+            }
+
+            PrimaryExpr::Ident(ident) => {
+                // Reads go through a temporary, so that an assignment later in the same
+                // expression, e.g `a + (a = 5)`, doesn't affect an operand already evaluated
+                if let Some(var) = fl.resolve_local(ident) {
+                    let dst = fl.func.new_reg(fl.func.reg_type(var));
+                    fl.push(Inst::Copy {
+                        dst,
+                        src: Operand::Reg(var),
+                    });
+                    return Some(Operand::Reg(dst));
+                }
+
+                let global = self.global_ids[ident.as_str()];
+                let dst = fl.func.new_reg(self.globals[global as usize].sym_type);
+                fl.push(Inst::LoadGlobal { dst, global });
+                return Some(Operand::Reg(dst));
+            }
+
+            PrimaryExpr::Paren(inner) => return self.expr(fl, inner),
+            PrimaryExpr::Call(call) => return self.call(fl, call),
+        };
+
+        Some(Operand::Const(c))
+    }
+
+    fn call(&mut self, fl: &mut FnLowerer, call: &FnCall) -> Option<Operand> {
+        let args = call
+            .args
+            .iter()
+            .map(|arg| self.expr(fl, arg).unwrap())
+            .collect();
+
+        let (callee, ret_type) = match self.fns.get(&call.ident) {
+            Some(&(id, ret_type)) => (Callee::Fn(id), ret_type),
+            None => {
+                let id = INTRINSICS
+                    .iter()
+                    .position(|i| i.name == call.ident)
+                    .unwrap_or_else(|| unreachable!("undefined `{}`", call.ident));
+                (Callee::Intrinsic(id as u32), INTRINSICS[id].ret_type)
+            }
+        };
+
+        let dst = (ret_type != SymType::Void).then(|| fl.func.new_reg(ret_type));
+        fl.push(Inst::Call { dst, callee, args });
+        dst.map(Operand::Reg)
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Write;
+
+use super::core::*;
+use crate::semantics::intrinsics::INTRINSICS;
+
+/// Textual form of a module, e.g
+///
+/// ```text
+/// global @0 counter: ginti
+///
+/// fn fib(%0: ginti) -> ginti {
+/// bb0:
+///     %1: ginti = copy %0
+///     %2: boli = lt %1, 2
+///     br %2, bb1, bb2
+/// ...
+/// }
+/// ```
+pub fn module_to_string(module: &Module) -> String {
+    let mut out = String::new();
+
+    for (id, g) in module.globals.iter().enumerate() {
+        let _ = writeln!(out, "global @{id} {}: {}", g.name, g.sym_type);
+    }
+    if !module.globals.is_empty() {
+        out.push('\n');
+    }
+
+    for (id, f) in module.functions.iter().enumerate() {
+        if id > 0 {
+            out.push('\n');
+        }
+        out += &function_to_string(module, f);
+    }
+
+    out
+}
+
+pub fn function_to_string(module: &Module, f: &Function) -> String {
+    let params = f
+        .params
+        .iter()
+        .map(|&p| format!("%{}: {}", p.0, f.reg_type(p)))
+        .collect::<Vec<_>>();
+
+    let mut out = format!(
+        "fn {}({}) -> {} {{\n",
+        f.name,
+        params.join(", "),
+        f.ret_type
+    );
+
+    for id in f.block_ids() {
+        let block = f.block(id);
+        let _ = writeln!(out, "bb{}:", id.0);
+
+        for inst in &block.insts {
+            let _ = writeln!(out, "    {}", inst_to_string(module, f, inst));
+        }
+        let _ = writeln!(out, "    {}", term_to_string(module, &block.term));
+    }
+
+    out += "}\n";
+    out
+}
+
+pub fn inst_to_string(module: &Module, f: &Function, inst: &Inst) -> String {
+    let op = |operand: &Operand| operand_to_string(module, operand);
+    let dst = |reg: Reg| format!("%{}: {} = ", reg.0, f.reg_type(reg));
+
+    match inst {
+        Inst::Copy { dst: d, src } => format!("{}copy {}", dst(*d), op(src)),
+        Inst::Binary {
+            dst: d,
+            op: bin_op,
+            lhs,
+            rhs,
+            ..
+        } => format!(
+            "{}{} {}, {}",
+            dst(*d),
+            bin_op_name(*bin_op),
+            op(lhs),
+            op(rhs)
+        ),
+        Inst::Unary {
+            dst: d,
+            op: un_op,
+            operand,
+        } => {
+            let name = match un_op {
+                UnOp::Neg => "neg",
+                UnOp::Not => "not",
+            };
+            format!("{}{name} {}", dst(*d), op(operand))
+        }
+        Inst::Call {
+            dst: d,
+            callee,
+            args,
+        } => {
+            let args = args.iter().map(op).collect::<Vec<_>>();
+            let name = match callee {
+                Callee::Fn(id) => &module.functions[*id as usize].name,
+                Callee::Intrinsic(id) => INTRINSICS[*id as usize].name,
+            };
+            let dst = d.map_or(String::new(), dst);
+            format!("{dst}call {name}({})", args.join(", "))
+        }
+        Inst::LoadGlobal { dst: d, global } => format!(
+            "{}load @{global} {}",
+            dst(*d),
+            module.globals[*global as usize].name
+        ),
+        Inst::Phi { dst: d, args } => {
+            let args = args
+                .iter()
+                .map(|(pred, arg)| format!("[bb{}: {}]", pred.0, op(arg)))
+                .collect::<Vec<_>>();
+            format!("{}phi {}", dst(*d), args.join(", "))
+        }
+        Inst::StoreGlobal { global, src } => format!(
+            "store @{global} {}, {}",
+            module.globals[*global as usize].name,
+            op(src)
+        ),
+    }
+}
+
+pub fn term_to_string(module: &Module, term: &Terminator) -> String {
+    match term {
+        Terminator::Jump(target) => format!("jump bb{}", target.0),
+        Terminator::Branch {
+            cond,
+            then_block,
+            else_block,
+        } => format!(
+            "br {}, bb{}, bb{}",
+            operand_to_string(module, cond),
+            then_block.0,
+            else_block.0
+        ),
+        Terminator::Ret(Some(value)) => format!("ret {}", operand_to_string(module, value)),
+        Terminator::Ret(None) => "ret".to_string(),
+        Terminator::MissingRet => "missing_ret".to_string(),
+    }
+}
+
+pub fn operand_to_string(module: &Module, operand: &Operand) -> String {
+    match operand {
+        Operand::Reg(reg) => format!("%{}", reg.0),
+        Operand::Const(Const::Int(i)) => i.to_string(),
+        Operand::Const(Const::Float(x)) => format!("{x:?}"),
+        Operand::Const(Const::Bool(true)) => "sach".to_string(),
+        Operand::Const(Const::Bool(false)) => "jhoot".to_string(),
+        Operand::Const(Const::Str(id)) => format!("{:?}", module.strings[*id as usize]),
+    }
+}
+
+pub fn bin_op_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "add",
+        BinOp::Sub => "sub",
+        BinOp::Mul => "mul",
+        BinOp::Div => "div",
+        BinOp::Mod => "mod",
+        BinOp::Exp => "exp",
+        BinOp::Shl => "shl",
+        BinOp::Shr => "shr",
+        BinOp::BitAnd => "and",
+        BinOp::BitOr => "or",
+        BinOp::Lt => "lt",
+        BinOp::Gt => "gt",
+        BinOp::Eq => "eq",
+    }
+}
@@ -0,0 +1,191 @@
+//! Static single assignment form: every register is assigned exactly once, and where control
+//! flow merges different assignments of a variable, a phi picks the one coming in from the
+//! predecessor taken.
+//!
+//! Construction follows Cytron et al.: phis are placed on the iterated dominance frontiers of
+//! each register's assignments (where the register is live, so no dead phis are made), and
+//! registers are then renamed in a walk over the dominator tree. The first assignment a register
+//! comes across keeps its number, so code that was already in SSA form reads the same.
+//!
+//! Destruction replaces each phi w/ copies: into a fresh temporary at the end of every
+//! predecessor, and from it into the phi's register at the start of the block. Going through the
+//! temporary keeps phis at the start of the same block from clobbering each other's arguments,
+//! so critical edges need not be split.
+
+use std::collections::BTreeSet;
+
+use super::cfg::{remove_unreachable_blocks, Cfg};
+use super::core::{BlockId, Function, Inst, Module, Operand, Reg};
+use super::dom::DomTree;
+use super::live::Liveness;
+
+pub fn construct(module: &mut Module) {
+    module.functions.iter_mut().for_each(construct_function);
+}
+
+pub fn destruct(module: &mut Module) {
+    module.functions.iter_mut().for_each(destruct_function);
+}
+
+pub fn construct_function(f: &mut Function) {
+    remove_unreachable_blocks(f);
+
+    let cfg = Cfg::new(f);
+    let dom = DomTree::new(&cfg);
+    insert_phis(f, &cfg, &dom);
+    rename(f, &cfg, &dom);
+}
+
+fn insert_phis(f: &mut Function, cfg: &Cfg, dom: &DomTree) {
+    let frontiers = dom.frontiers(cfg);
+    let live = Liveness::new(f, cfg);
+
+    // The blocks each register is assigned in; parameters are assigned on entry
+    let mut def_blocks = vec![BTreeSet::new(); f.reg_types.len()];
+    for &p in &f.params {
+        def_blocks[p.0 as usize].insert(BlockId(0));
+    }
+    for id in f.block_ids() {
+        for dst in f.block(id).insts.iter().filter_map(Inst::dst) {
+            def_blocks[dst.0 as usize].insert(id);
+        }
+    }
+
+    let mut phis = vec![vec![]; f.blocks.len()]; // the registers needing one, per block
+    for (reg, defs) in def_blocks.iter().enumerate() {
+        let reg = Reg(reg as u32);
+        let mut has_phi = BTreeSet::new();
+        let mut work = defs.iter().copied().collect::<Vec<_>>();
+
+        while let Some(id) = work.pop() {
+            for &join in &frontiers[id.0 as usize] {
+                if live.live_in(join).contains(&reg) && has_phi.insert(join) {
+                    phis[join.0 as usize].push(reg);
+                    if !defs.contains(&join) {
+                        work.push(join);
+                    }
+                }
+            }
+        }
+    }
+
+    // Arguments start off as the register itself, and get renamed along w/ their predecessor
+    for (id, regs) in phis.into_iter().enumerate() {
+        let preds = cfg.preds(BlockId(id as u32));
+        let new_phis = regs.into_iter().map(|reg| Inst::Phi {
+            dst: reg,
+            args: preds.iter().map(|&p| (p, Operand::Reg(reg))).collect(),
+        });
+        f.blocks[id].insts.splice(0..0, new_phis);
+    }
+}
+
+enum Visit {
+    Enter(BlockId),
+    Exit(Vec<Reg>), // the (original) registers whose renamings to pop
+}
+
+fn rename(f: &mut Function, cfg: &Cfg, dom: &DomTree) {
+    let n_regs = f.reg_types.len();
+
+    // Per original register, its current name, innermost last
+    let mut names = vec![vec![]; n_regs];
+    let mut claimed = vec![false; n_regs];
+    for &p in &f.params {
+        names[p.0 as usize].push(p);
+        claimed[p.0 as usize] = true;
+    }
+
+    let current = |names: &[Vec<Reg>], operand: &mut Operand| {
+        if let Operand::Reg(reg) = operand {
+            *reg = *names[reg.0 as usize]
+                .last()
+                .expect("register read before being assigned, along some path");
+        }
+    };
+
+    let mut work = vec![Visit::Enter(BlockId(0))];
+    while let Some(visit) = work.pop() {
+        let id = match visit {
+            Visit::Enter(id) => id,
+            Visit::Exit(renamed) => {
+                for reg in renamed {
+                    names[reg.0 as usize].pop();
+                }
+                continue;
+            }
+        };
+
+        let mut renamed = vec![];
+        let block = &mut f.blocks[id.0 as usize];
+
+        for inst in &mut block.insts {
+            if !inst.is_phi() {
+                for operand in inst.operands_mut() {
+                    current(&names, operand);
+                }
+            }
+
+            if let Some(dst) = inst.dst_mut() {
+                let orig = *dst;
+                if std::mem::replace(&mut claimed[orig.0 as usize], true) {
+                    f.reg_types.push(f.reg_types[orig.0 as usize]);
+                    *dst = Reg(f.reg_types.len() as u32 - 1);
+                }
+                names[orig.0 as usize].push(*dst);
+                renamed.push(orig);
+            }
+        }
+
+        for operand in block.term.operands_mut() {
+            current(&names, operand);
+        }
+
+        for &succ in cfg.succs(id) {
+            for inst in &mut f.blocks[succ.0 as usize].insts {
+                let Inst::Phi { args, .. } = inst else {
+                    break;
+                };
+                for (_, arg) in args.iter_mut().filter(|(pred, _)| *pred == id) {
+                    current(&names, arg);
+                }
+            }
+        }
+
+        work.push(Visit::Exit(renamed));
+        work.extend(
+            dom.children(id)
+                .iter()
+                .rev()
+                .map(|&child| Visit::Enter(child)),
+        );
+    }
+}
+
+pub fn destruct_function(f: &mut Function) {
+    for id in f.block_ids() {
+        let insts = &mut f.blocks[id.0 as usize].insts;
+        let n_phis = insts.iter().take_while(|inst| inst.is_phi()).count();
+        let phis = insts.drain(..n_phis).collect::<Vec<_>>();
+
+        let mut copies = vec![];
+        for phi in phis {
+            let Inst::Phi { dst, args } = phi else {
+                unreachable!()
+            };
+
+            let tmp = f.new_reg(f.reg_type(dst));
+            for (pred, arg) in args {
+                f.blocks[pred.0 as usize]
+                    .insts
+                    .push(Inst::Copy { dst: tmp, src: arg });
+            }
+            copies.push(Inst::Copy {
+                dst,
+                src: Operand::Reg(tmp),
+            });
+        }
+
+        f.blocks[id.0 as usize].insts.splice(0..0, copies);
+    }
+}
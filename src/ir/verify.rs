@@ -0,0 +1,348 @@
+use super::cfg::Cfg;
+use super::core::*;
+use super::dom::DomTree;
+use crate::semantics::{intrinsics::INTRINSICS, spaghetti::SymType};
+
+#[derive(Debug, PartialEq)]
+pub enum VerifyErrorKind {
+    NoBlocks,
+    UnknownBlock(BlockId),
+    UnknownReg(Reg),
+    UnknownGlobal(GlobalId),
+    UnknownCallee(Callee),
+    UnknownString(u32),
+    VoidReg(Reg),                   // registers can't hold khali values
+    UnassignedReg(Reg),             // read, but neither a param nor assigned anywhere
+    TypeMismatch(SymType, SymType), // (expected, found)
+    ArgCount(usize, usize),         // (expected, found)
+    CallResult,      // a call's dst doesn't match whether its callee returns anything
+    InvalidOperator, // e.g `neg` on a boli, or `and` on an asharia
+    MultipleAssignment(Reg),
+    MisplacedPhi,         // a phi after a non-phi instruction
+    PhiArgs,              // a phi's args don't name each predecessor exactly once
+    UseNotDominated(Reg), // a read that some path reaches w/o going through the assignment
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VerifyError {
+    pub func: String,
+    pub block: Option<BlockId>,
+    pub kind: VerifyErrorKind,
+}
+
+/// Checks a module's well-formedness: that every block, register, global and callee referred to
+/// exists, that registers are assigned before (somewhere) being read, and that every instruction
+/// is well-typed. Meant to be run after lowering and after every pass, to catch their bugs early.
+pub fn verify(module: &Module) -> Result<(), VerifyError> {
+    for id in std::iter::once(module.init).chain(module.entry) {
+        if !fn_exists_in(module, id) {
+            return Err(VerifyError {
+                func: String::new(),
+                block: None,
+                kind: VerifyErrorKind::UnknownCallee(Callee::Fn(id)),
+            });
+        }
+    }
+
+    for f in &module.functions {
+        verify_function(module, f).map_err(|(block, kind)| VerifyError {
+            func: f.name.clone(),
+            block,
+            kind,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Checks, on top of `verify`, that a module is in SSA form: every register is assigned exactly
+/// once (parameters by the call), phis only come at the start of blocks and name each predecessor
+/// once, and every read is dominated by the assignment it reads.
+pub fn verify_ssa(module: &Module) -> Result<(), VerifyError> {
+    verify(module)?;
+
+    for f in &module.functions {
+        verify_function_ssa(f).map_err(|(block, kind)| VerifyError {
+            func: f.name.clone(),
+            block,
+            kind,
+        })?;
+    }
+
+    Ok(())
+}
+
+type FnResult = Result<(), (Option<BlockId>, VerifyErrorKind)>;
+
+fn verify_function(module: &Module, f: &Function) -> FnResult {
+    use VerifyErrorKind::*;
+
+    if f.blocks.is_empty() {
+        return Err((None, NoBlocks));
+    }
+
+    let n_regs = f.reg_types.len();
+    let mut assigned = vec![false; n_regs];
+
+    for &p in &f.params {
+        if p.0 as usize >= n_regs {
+            return Err((None, UnknownReg(p)));
+        }
+        assigned[p.0 as usize] = true;
+    }
+
+    for block in &f.blocks {
+        for inst in &block.insts {
+            if let Some(dst) = inst.dst() {
+                if dst.0 as usize >= n_regs {
+                    return Err((None, UnknownReg(dst)));
+                }
+                assigned[dst.0 as usize] = true;
+            }
+        }
+    }
+
+    for (reg, &sym_type) in f.reg_types.iter().enumerate() {
+        if sym_type == SymType::Void {
+            return Err((None, VoidReg(Reg(reg as u32))));
+        }
+    }
+
+    for id in f.block_ids() {
+        let block = f.block(id);
+        let at = |kind| Err((Some(id), kind));
+
+        let check_operand = |operand: &Operand| match *operand {
+            Operand::Reg(reg) if reg.0 as usize >= n_regs => at(UnknownReg(reg)),
+            Operand::Reg(reg) if !assigned[reg.0 as usize] => at(UnassignedReg(reg)),
+            Operand::Const(Const::Str(s)) if s as usize >= module.strings.len() => {
+                at(UnknownString(s))
+            }
+            _ => Ok(()),
+        };
+
+        let expect = |expected: SymType, found: SymType| match expected == found {
+            true => Ok(()),
+            false => at(TypeMismatch(expected, found)),
+        };
+
+        for operand in block
+            .insts
+            .iter()
+            .flat_map(Inst::operands)
+            .chain(block.term.operands())
+        {
+            check_operand(operand)?;
+        }
+
+        for inst in &block.insts {
+            match inst {
+                Inst::Copy { dst, src } => expect(f.reg_type(*dst), f.operand_type(src))?,
+
+                Inst::Binary {
+                    dst, op, lhs, rhs, ..
+                } => {
+                    let operand_type = f.operand_type(lhs);
+                    expect(operand_type, f.operand_type(rhs))?;
+
+                    let valid = match op {
+                        BinOp::Lt | BinOp::Gt | BinOp::Eq => true,
+                        BinOp::Add
+                        | BinOp::Sub
+                        | BinOp::Mul
+                        | BinOp::Div
+                        | BinOp::Mod
+                        | BinOp::Exp => {
+                            matches!(operand_type, SymType::Int | SymType::Float)
+                        }
+                        BinOp::Shl | BinOp::Shr | BinOp::BitAnd | BinOp::BitOr => {
+                            operand_type == SymType::Int
+                        }
+                    };
+                    if !valid {
+                        return at(InvalidOperator);
+                    }
+
+                    let res_type = match op.is_comparison() {
+                        true => SymType::Bool,
+                        false => operand_type,
+                    };
+                    expect(f.reg_type(*dst), res_type)?;
+                }
+
+                Inst::Unary { dst, op, operand } => {
+                    let operand_type = f.operand_type(operand);
+                    let valid = match op {
+                        UnOp::Neg => matches!(operand_type, SymType::Int | SymType::Float),
+                        UnOp::Not => operand_type == SymType::Bool,
+                    };
+                    if !valid {
+                        return at(InvalidOperator);
+                    }
+                    expect(f.reg_type(*dst), operand_type)?;
+                }
+
+                Inst::Call { dst, callee, args } => {
+                    let (param_types, ret_type) = match *callee {
+                        Callee::Fn(id) if fn_exists_in(module, id) => {
+                            let callee = &module.functions[id as usize];
+                            let params = callee.params.iter().map(|&p| callee.reg_type(p));
+                            (params.collect::<Vec<_>>(), callee.ret_type)
+                        }
+                        Callee::Intrinsic(id) if (id as usize) < INTRINSICS.len() => {
+                            let intrinsic = &INTRINSICS[id as usize];
+                            let params = intrinsic.params.iter().map(|&(_, t)| t);
+                            (params.collect(), intrinsic.ret_type)
+                        }
+                        _ => return at(UnknownCallee(*callee)),
+                    };
+
+                    if param_types.len() != args.len() {
+                        return at(ArgCount(param_types.len(), args.len()));
+                    }
+                    for (&param_type, arg) in param_types.iter().zip(args) {
+                        expect(param_type, f.operand_type(arg))?;
+                    }
+
+                    match dst {
+                        Some(dst) if ret_type != SymType::Void => {
+                            expect(ret_type, f.reg_type(*dst))?
+                        }
+                        None if ret_type == SymType::Void => {}
+                        _ => return at(CallResult),
+                    }
+                }
+
+                Inst::LoadGlobal { dst, global } => {
+                    let Some(g) = module.globals.get(*global as usize) else {
+                        return at(UnknownGlobal(*global));
+                    };
+                    expect(g.sym_type, f.reg_type(*dst))?;
+                }
+
+                Inst::Phi { dst, args } => {
+                    for (pred, arg) in args {
+                        if pred.0 as usize >= f.blocks.len() {
+                            return at(UnknownBlock(*pred));
+                        }
+                        expect(f.reg_type(*dst), f.operand_type(arg))?;
+                    }
+                }
+
+                Inst::StoreGlobal { global, src } => {
+                    let Some(g) = module.globals.get(*global as usize) else {
+                        return at(UnknownGlobal(*global));
+                    };
+                    expect(g.sym_type, f.operand_type(src))?;
+                }
+            }
+        }
+
+        for target in block.term.successors() {
+            if target.0 as usize >= f.blocks.len() {
+                return at(UnknownBlock(target));
+            }
+        }
+
+        match &block.term {
+            Terminator::Branch { cond, .. } => expect(SymType::Bool, f.operand_type(cond))?,
+            Terminator::Ret(Some(value)) => expect(f.ret_type, f.operand_type(value))?,
+            Terminator::Ret(None) => expect(f.ret_type, SymType::Void)?,
+            Terminator::Jump(_) | Terminator::MissingRet => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn fn_exists_in(module: &Module, id: FnId) -> bool {
+    (id as usize) < module.functions.len()
+}
+
+// Where a register is assigned: the block, and the index of the instruction in it (None for
+// parameters, which are assigned before the entry's first instruction)
+type DefSite = (BlockId, Option<usize>);
+
+fn verify_function_ssa(f: &Function) -> FnResult {
+    use VerifyErrorKind::*;
+
+    let cfg = Cfg::new(f);
+    let dom = DomTree::new(&cfg);
+
+    let mut defs: Vec<Option<DefSite>> = vec![None; f.reg_types.len()];
+    let mut define = |reg: Reg, site: DefSite| match defs[reg.0 as usize].replace(site) {
+        Some(_) => Err((Some(site.0), MultipleAssignment(reg))),
+        None => Ok(()),
+    };
+
+    for &p in &f.params {
+        define(p, (BlockId(0), None))?;
+    }
+    for id in f.block_ids() {
+        for (i, inst) in f.block(id).insts.iter().enumerate() {
+            if let Some(dst) = inst.dst() {
+                define(dst, (id, Some(i)))?;
+            }
+        }
+    }
+
+    // Whether the value of `reg` is available right before instruction `i` of block `id`
+    let available = |reg: Reg, id: BlockId, i: usize| {
+        let (def_block, def_i) = defs[reg.0 as usize].unwrap();
+        match def_block == id {
+            true => def_i.is_none_or(|def_i| def_i < i),
+            false => dom.dominates(def_block, id),
+        }
+    };
+
+    for &id in cfg.rpo() {
+        let block = f.block(id);
+        let at = |kind| Err((Some(id), kind));
+
+        let n_phis = block.insts.iter().take_while(|inst| inst.is_phi()).count();
+        if block.insts[n_phis..].iter().any(Inst::is_phi) {
+            return at(MisplacedPhi);
+        }
+
+        for (i, inst) in block.insts.iter().enumerate() {
+            if let Inst::Phi { args, .. } = inst {
+                let mut arg_preds = args.iter().map(|(pred, _)| *pred).collect::<Vec<_>>();
+                arg_preds.sort();
+                let mut preds = cfg.preds(id).to_vec();
+                preds.sort();
+                if arg_preds != preds {
+                    return at(PhiArgs);
+                }
+
+                // An argument is read at the end of its predecessor
+                for (pred, arg) in args {
+                    if let Operand::Reg(reg) = arg {
+                        let end = f.block(*pred).insts.len();
+                        if cfg.is_reachable(*pred) && !available(*reg, *pred, end) {
+                            return at(UseNotDominated(*reg));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            for operand in inst.operands() {
+                if let Operand::Reg(reg) = operand {
+                    if !available(*reg, id, i) {
+                        return at(UseNotDominated(*reg));
+                    }
+                }
+            }
+        }
+
+        for operand in block.term.operands() {
+            if let Operand::Reg(reg) = operand {
+                if !available(*reg, id, block.insts.len()) {
+                    return at(UseNotDominated(*reg));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,3 +1,3 @@
 pub mod core;
 pub mod token;
-pub use self::token::Token;
+pub use self::token::{Comment, Span, Token, Trivia, TriviaKind, TriviaToken};
@@ -1,4 +1,4 @@
-use super::Token;
+use super::{Comment, Span, Token, Trivia, TriviaKind, TriviaToken};
 
 const DELIM: &str = " \r\n\t\"\'\\&|;=(){}[]<>+-*/%^`!`.:~,$";
 
@@ -8,31 +8,65 @@ pub enum LexerError {
     InvalidIdentifier(String),
 }
 
+/// Tokens produced by a tokenization pass that also tracks where each of them came from.
+/// `spans[i]` is the span of `tokens[i]`.
+#[derive(Debug)]
+pub struct SpannedTokens {
+    pub tokens: Vec<Token>,
+    pub spans: Vec<Span>,
+    pub comments: Vec<Comment>,
+}
+
 pub fn tokenize_src_code(src: &str) -> Result<Vec<Token>, LexerError> {
+    Ok(tokenize_src_code_with_spans(src)?.tokens)
+}
+
+pub fn tokenize_src_code_with_spans(src: &str) -> Result<SpannedTokens, LexerError> {
     let mut token_list: Vec<Token> = Vec::new();
+    let mut span_list: Vec<Span> = Vec::new();
+    let mut comments: Vec<Comment> = Vec::new();
     let mut idx = 0;
     let mut quotes_started = false;
     let mut comment_started = false;
 
     while idx < src.len() {
+        let start = idx;
         let word = strtok(src, DELIM, &mut idx);
         let mut t = identify_token(word, quotes_started, comment_started)?;
+        let mut span = Span {
+            start,
+            end: idx,
+            ..Default::default()
+        };
 
         // if comment started, ignore all tokens until newline
         if comment_started {
             match t {
-                Token::Newline => comment_started = false,
+                Token::Newline => {
+                    comment_started = false;
+                    close_comment(src, comments.last_mut().unwrap(), start);
+                }
                 _ => continue,
             }
         }
 
         if t == Token::Comment {
             comment_started = true;
+            comments.push(Comment {
+                text: String::new(),
+                span,
+            });
             continue;
         }
 
         if !token_list.is_empty() {
-            consolidate_tokens(&mut token_list, &mut t, quotes_started);
+            consolidate_tokens(
+                &mut token_list,
+                &mut span_list,
+                &mut t,
+                &mut span,
+                quotes_started,
+            );
         }
 
         if t == Token::Quotes {
@@ -40,10 +74,137 @@ pub fn tokenize_src_code(src: &str) -> Result<Vec<Token>, LexerError> {
         }
 
         token_list.push(t);
+        span_list.push(span);
+    }
+
+    if comment_started {
+        close_comment(src, comments.last_mut().unwrap(), src.len());
+    }
+
+    let (tokens, mut spans): (Vec<Token>, Vec<Span>) = token_list
+        .into_iter()
+        .zip(span_list)
+        .filter(|(t, _)| ![Token::Whitespace, Token::Newline].contains(t))
+        .unzip();
+
+    let line_starts = line_starts(src);
+    for span in spans
+        .iter_mut()
+        .chain(comments.iter_mut().map(|c| &mut c.span))
+    {
+        locate_span(src, &line_starts, span);
     }
 
-    token_list.retain(|t| ![Token::Whitespace, Token::Newline].contains(t));
-    Ok(token_list)
+    Ok(SpannedTokens {
+        tokens,
+        spans,
+        comments,
+    })
+}
+
+/// Tokens that, along w/ their trivia, account for every byte of the source they came from
+#[derive(Debug)]
+pub struct LosslessTokens {
+    pub tokens: Vec<TriviaToken>,
+    pub eof_trivia: Vec<Trivia>, // whatever follows the last token's trailing trivia
+}
+
+impl LosslessTokens {
+    /// Reconstructs the source, byte-for-byte
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        for token in &self.tokens {
+            token.write_full_text(&mut out);
+        }
+        for trivia in &self.eof_trivia {
+            out.push_str(&trivia.text);
+        }
+        out
+    }
+}
+
+/// Tokenizes w/o throwing anything away: whitespace, newlines and comments are kept as trivia
+/// attached to the tokens around them.
+pub fn tokenize_src_code_lossless(src: &str) -> Result<LosslessTokens, LexerError> {
+    let spanned = tokenize_src_code_with_spans(src)?;
+    let mut tokens: Vec<TriviaToken> = Vec::with_capacity(spanned.tokens.len());
+    let mut gap_start = 0;
+
+    for (token, span) in spanned.tokens.into_iter().zip(spanned.spans) {
+        let mut gap = split_trivia(&src[gap_start..span.start]);
+
+        if let Some(prev) = tokens.last_mut() {
+            let trailing_len = gap
+                .iter()
+                .position(|t| t.kind == TriviaKind::Newline)
+                .map_or(gap.len(), |i| i + 1);
+            prev.trailing = gap.drain(..trailing_len).collect();
+        }
+
+        tokens.push(TriviaToken {
+            token,
+            text: src[span.start..span.end].to_string(),
+            span,
+            leading: gap,
+            trailing: vec![],
+        });
+        gap_start = span.end;
+    }
+
+    let mut eof_trivia = split_trivia(&src[gap_start..]);
+    if let Some(last) = tokens.last_mut() {
+        let trailing_len = eof_trivia
+            .iter()
+            .position(|t| t.kind == TriviaKind::Newline)
+            .map_or(eof_trivia.len(), |i| i + 1);
+        last.trailing = eof_trivia.drain(..trailing_len).collect();
+    }
+
+    Ok(LosslessTokens { tokens, eof_trivia })
+}
+
+/// Splits the text b/w two tokens into runs of whitespace, single newlines, and comments
+fn split_trivia(gap: &str) -> Vec<Trivia> {
+    let mut trivia = vec![];
+    let mut rest = gap;
+
+    while let Some(first) = rest.chars().next() {
+        let (kind, len) = match first {
+            '\n' => (TriviaKind::Newline, 1),
+            '$' => (TriviaKind::Comment, rest.find('\n').unwrap_or(rest.len())),
+            _ => (
+                TriviaKind::Whitespace,
+                rest.find(['\n', '$']).unwrap_or(rest.len()),
+            ),
+        };
+
+        trivia.push(Trivia {
+            kind,
+            text: rest[..len].to_string(),
+        });
+        rest = &rest[len..];
+    }
+
+    trivia
+}
+
+fn close_comment(src: &str, comment: &mut Comment, end: usize) {
+    comment.text = src[comment.span.start..end].trim_end().to_string();
+    comment.span.end = comment.span.start + comment.text.len();
+}
+
+/// Byte offsets at which each line of `src` begins
+pub fn line_starts(src: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// Fills in the line & column of a span, whose byte offsets are already known
+fn locate_span(src: &str, line_starts: &[usize], span: &mut Span) {
+    let line_idx = line_starts.partition_point(|&ls| ls <= span.start) - 1;
+    span.line = line_idx + 1;
+    span.col = src[line_starts[line_idx]..span.start].chars().count() + 1;
 }
 
 fn strtok<'a>(src: &'a str, delims: &str, idx: &mut usize) -> &'a str {
@@ -158,23 +319,36 @@ fn identify_token(
     }
 }
 
-fn consolidate_tokens(token_list: &mut Vec<Token>, curr_token: &mut Token, quotes_started: bool) {
+fn consolidate_tokens(
+    token_list: &mut Vec<Token>,
+    span_list: &mut Vec<Span>,
+    curr_token: &mut Token,
+    curr_span: &mut Span,
+    quotes_started: bool,
+) {
     let last_token = token_list.last().unwrap();
 
+    // Whenever a token is popped to be merged w/ the current one, the current one's span grows
+    // to start where the popped one did
+    let mut pop = |token_list: &mut Vec<Token>| {
+        token_list.pop();
+        curr_span.start = span_list.pop().unwrap().start;
+    };
+
     // This has got to be some of the nastiest shit I've ever written...
 
     // Combine string literals
     if let Token::StringLit(last_str) = last_token.clone() {
         // if the current token is a string literal, combine them
         if let Token::StringLit(curr_str) = curr_token {
-            token_list.pop();
+            pop(token_list);
             *curr_token = Token::StringLit(last_str + curr_str);
             return;
         }
 
         // if the current token is a quote, and the last string literal ends with a backslash, combine them
         if *curr_token == Token::Quotes && last_str.ends_with('\\') {
-            token_list.pop();
+            pop(token_list);
             *curr_token = Token::StringLit(last_str + "\"");
             return;
         }
@@ -183,8 +357,8 @@ fn consolidate_tokens(token_list: &mut Vec<Token>, curr_token: &mut Token, quote
     // Form float literal from two integers and decimal
     if let [.., Token::IntLit(int_part), Token::Dot] = token_list[..] {
         if let Token::IntLit(frac_part) = *curr_token {
-            token_list.pop();
-            token_list.pop();
+            pop(token_list);
+            pop(token_list);
             let f = format!("{int_part}.{frac_part}");
             *curr_token = Token::FloatLit(f.parse::<f64>().unwrap());
             return;
@@ -210,7 +384,7 @@ fn consolidate_tokens(token_list: &mut Vec<Token>, curr_token: &mut Token, quote
         //  qs -  ws => NO POP
 
         if *curr_token != Token::Whitespace || !quotes_started {
-            token_list.pop();
+            pop(token_list);
         }
 
         *curr_token = match *curr_token {
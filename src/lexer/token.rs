@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     // duhrao, agar, warna, wapsi, dhancha, toro
@@ -68,3 +70,135 @@ pub enum Token {
     ShiftLeft,
     ShiftRight,
 }
+
+/// Byte range of a lexeme within the source, along w/ the (1-indexed) line and column it starts at
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    /// Smallest span covering both `self` and `other`, assuming `self` comes first
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+/// A `$` comment, running till the end of its line. `text` includes the leading `$`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Token {
+    /// Writes the token the way it would appear in source code
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lexeme = match self {
+            Token::For => "duhrao",
+            Token::If => "agar",
+            Token::Else => "warna",
+            Token::Return => "wapsi",
+            Token::Struct => "dhancha",
+            Token::Break => "toro",
+
+            Token::Int => "ginti",
+            Token::String => "jumla",
+            Token::Float => "asharia",
+            Token::Bool => "boli",
+            Token::Void => "khali",
+            Token::Function => "fn",
+            Token::True => "sach",
+            Token::False => "jhoot",
+
+            Token::ParenL => "(",
+            Token::ParenR => ")",
+            Token::BraceL => "{",
+            Token::BraceR => "}",
+            Token::BracketL => "[",
+            Token::BracketR => "]",
+            Token::Backtick => "`",
+            Token::Quotes => "\"",
+            Token::Quote => "'",
+
+            Token::Whitespace => " ",
+            Token::Newline => "\n",
+            Token::Colon => ":",
+            Token::Semicolon => ";",
+            Token::Comment => "$",
+
+            Token::Identifier(s) | Token::StringLit(s) => return write!(f, "{s}"),
+            Token::IntLit(n) => return write!(f, "{n}"),
+            Token::FloatLit(n) => return write!(f, "{n}"),
+
+            Token::AssignOp => "=",
+            Token::AddOp => "+",
+            Token::SubOp => "-",
+            Token::MulOp => "*",
+            Token::DivOp => "/",
+            Token::ModOp => "%",
+            Token::ExpOp => "^",
+            Token::EqualsOp => "==",
+
+            Token::Dot => ".",
+            Token::Comma => ",",
+            Token::BooleanNot => "!",
+            Token::BitwiseAnd => "&",
+            Token::BitwiseOr => "|",
+            Token::BooleanAnd => "&&",
+            Token::BooleanOr => "||",
+            Token::BitwiseNot => "~",
+            Token::LessThan => "<",
+            Token::GreaterThan => ">",
+            Token::ShiftLeft => "<<",
+            Token::ShiftRight => ">>",
+        };
+
+        write!(f, "{lexeme}")
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment,
+}
+
+/// Source text that carries no meaning for the parser
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+/// A token that remembers its exact source text, and the trivia surrounding it. A token's
+/// trailing trivia runs up to (and including) the first newline after it; everything after that
+/// leads the next token.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TriviaToken {
+    pub token: Token,
+    pub text: String,
+    pub span: Span,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl TriviaToken {
+    /// Writes the token back out exactly as it appeared in the source, trivia included
+    pub fn write_full_text(&self, out: &mut String) {
+        for trivia in &self.leading {
+            out.push_str(&trivia.text);
+        }
+        out.push_str(&self.text);
+        for trivia in &self.trailing {
+            out.push_str(&trivia.text);
+        }
+    }
+}
@@ -1,5 +1,12 @@
+pub mod bytecode;
+pub mod codegen;
+pub mod emit;
+pub mod formatter;
+pub mod interp;
+pub mod ir;
 pub mod lexer;
 pub mod macros;
+pub mod opt;
 pub mod parser;
 pub mod semantics;
 
@@ -8,21 +15,90 @@ pub enum CompilerError {
     TokenizationErr(lexer::core::LexerError),
     ParseErr(parser::core::ParseError),
     SemanticErr(semantics::core::SemanticError),
+    OptErr(opt::core::OptError),
 }
 
 convert_across_err!(lexer::core::LexerError, CompilerError, TokenizationErr);
 convert_across_err!(parser::core::ParseError, CompilerError, ParseErr);
 convert_across_err!(semantics::core::SemanticError, CompilerError, SemanticErr);
+convert_across_err!(opt::core::OptError, CompilerError, OptErr);
 
-pub fn compile_src(src_code: &str) -> Result<(), CompilerError> {
-    let tokens = lexer::core::tokenize_src_code(src_code)?;
-    // println!("Tokens:\n{:?}\n", tokens);
+/// Tokenizes and parses `src_code`, stopping short of semantic analysis.
+pub fn parse_src(src_code: &str) -> Result<parser::ast::core::TranslationUnit, CompilerError> {
+    let lexed = lexer::core::tokenize_src_code_with_spans(src_code)?;
+    Ok(parser::core::parse_spanned_token_stream(
+        &lexed.tokens,
+        &lexed.spans,
+    )?)
+}
+
+/// Like `parse_src`, but also builds a concrete syntax tree that keeps every byte of the source,
+/// whitespace and comments included.
+pub fn parse_src_lossless(
+    src_code: &str,
+) -> Result<(parser::ast::core::TranslationUnit, parser::cst::Cst), CompilerError> {
+    let lexed = lexer::core::tokenize_src_code_lossless(src_code)?;
+    Ok(parser::core::parse_lossless_token_stream(&lexed)?)
+}
+
+/// Everything the compiler produced on its way through the source, for inspection via `--emit`
+#[derive(Debug)]
+pub struct Artifacts {
+    pub tokens: lexer::core::SpannedTokens,
+    pub ast: parser::ast::core::TranslationUnit,
+    pub sym_table: semantics::spaghetti::SpaghettiStack,
+    pub lowered: Option<Lowered>, // only by `compile_src_opt`
+}
+
+/// The IR, for the stages and targets that are generated from it
+#[derive(Debug)]
+pub struct Lowered {
+    pub ir: ir::core::Module,  // optimised, then out of SSA form
+    pub ssa: ir::core::Module, // optimised
+    pub level: opt::core::OptLevel,
+}
+
+/// Analyses `src_code`, w/o lowering it to IR: what interpreting it, or compiling it from its
+/// AST, needs. Operators sure to fail are only found once lowered, so this doesn't report them.
+pub fn compile_src(
+    src_code: &str,
+    kind: semantics::core::SrcKind,
+) -> Result<Artifacts, CompilerError> {
+    let lexed = lexer::core::tokenize_src_code_with_spans(src_code)?;
+    let ast_root = parser::core::parse_spanned_token_stream(&lexed.tokens, &lexed.spans)?;
+    let sym_table = semantics::core::analyse_semantics(&ast_root, kind)?;
+
+    Ok(Artifacts {
+        tokens: lexed,
+        ast: ast_root,
+        sym_table,
+        lowered: None,
+    })
+}
+
+/// Like `compile_src`, but also lowers to IR, optimised at `level`. At `O0`, `ir` is exactly as
+/// lowered.
+pub fn compile_src_opt(
+    src_code: &str,
+    kind: semantics::core::SrcKind,
+    level: opt::core::OptLevel,
+) -> Result<Artifacts, CompilerError> {
+    let mut artifacts = compile_src(src_code, kind)?;
 
-    let ast_root = parser::core::parse_token_stream(&tokens)?;
-    // println!("AST:\n{:#?}\n", ast_root);
+    let lowered = ir::lower::lower(&artifacts.ast);
+    let mut ssa = lowered.clone();
+    ir::ssa::construct(&mut ssa);
+    opt::core::optimise(&mut ssa, level)?;
 
-    let sym_table = semantics::core::analyse_semantics(&ast_root)?;
-    println!("Symbol Table:\n{sym_table:#?}\n");
+    let ir = match level {
+        opt::core::OptLevel::O0 => lowered,
+        _ => {
+            let mut ir = ssa.clone();
+            ir::ssa::destruct(&mut ir);
+            ir
+        }
+    };
 
-    Ok(())
+    artifacts.lowered = Some(Lowered { ir, ssa, level });
+    Ok(artifacts)
 }
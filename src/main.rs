@@ -1,25 +1,370 @@
+use std::io::Write;
 use std::time::Instant;
 
-use nuktah::compile_src;
+use nuktah::{
+    bytecode,
+    codegen::{self, core::Target, regalloc::core::Allocator},
+    compile_src, compile_src_opt,
+    emit::core::Emit,
+    formatter::core::format_src,
+    interp,
+    opt::core::OptLevel,
+    semantics::core::SrcKind,
+    CompilerError,
+};
+
+const USAGE: &str = "\
+Usage: nktc [--lib] [-O[<level>]] [--emit=<stage>[,<stage>...] [-o <file>]]... <src.nkt>
+       nktc run [--vm] <src.nkt | prog.nkb> [<arg>...]
+       nktc build --target=<target> [-O[<level>]] [-o <file>] <src.nkt>
+       nktc disasm <src.nkt | prog.nkb>
+       nktc fmt [--check] <src.nkt>...
+
+Stages: tokens, ast, symtab, hir, ir, ssa, asm (x86-64), tokens-json, ast-json, symtab-json,
+        scopes-dot, ast-dot, cfg-dot
+`-o` sends the output of the `--emit` right before it (naming a single stage) to a file, rather
+than stdout.
+`-O<level>` optimises the IR: 0 (the default) not at all, 1 w/ constant folding and dead code
+elimination, 2 also inlining small functions and optimising loops; native targets allocate
+registers by linear scan, or at 2 by graph colouring. `-O` alone is `-O1`. `nktc build` only
+takes it for the targets compiled from the IR: x86_64-linux, riscv64-linux and mips32.
+`--vm` runs the program on the bytecode VM, rather than walking its AST; `.nkb` files always are.
+
+Targets: bytecode (written to <src>.nkb, unless `-o` says otherwise), x86_64-linux (an executable,
+         <src> sans its extension, assembled and linked w/ the system's `as` and `cc`),
+         riscv64-linux (likewise, w/ `riscv64-linux-gnu-as` and `riscv64-linux-gnu-gcc`), mips32
+         (assembly for SPIM or MARS, written to <src>.s), c (self-contained C11, written to
+         <src>.c), llvm-ir (a textual LLVM module, for `lli`, `llc` or `clang`, written to <src>.ll)
+`--lib` skips checking for an entry point, i.e `fn ginti shuru()` or `fn ginti shuru(ginti argc)`.";
+
+// A stage to dump, and where to (stdout if None)
+struct EmitRequest {
+    stage: Emit,
+    out: Option<String>,
+}
 
 fn main() -> std::io::Result<()> {
-    let args = std::env::args().collect::<Vec<String>>();
-    if args.len() != 2 {
-        eprintln!("Usage: nktc <src.nkt>");
-        std::process::exit(1);
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    match args.first().map(String::as_str) {
+        Some("fmt") => return run_fmt(&args[1..]),
+        Some("run") => return run_program(&args[1..]),
+        Some("build") => return run_build(&args[1..]),
+        Some("disasm") => return run_disasm(&args[1..]),
+        _ => {}
     }
 
-    let src_code = std::fs::read_to_string(&args[1])?;
+    let (emits, kind, level, path) = parse_args(&args).unwrap_or_else(|| exit_with_usage());
+    let src_code = std::fs::read_to_string(path)?;
 
     let start = Instant::now();
-    let res = compile_src(&src_code);
+    let res = match emits.iter().any(|req| req.stage.needs_ir()) {
+        true => compile_src_opt(&src_code, kind, level),
+        false => compile_src(&src_code, kind),
+    };
     let duration = start.elapsed();
 
-    if let Err(e) = res {
-        eprintln!("{e:?}");
+    let artifacts = match res {
+        Ok(artifacts) => artifacts,
+        Err(e) => {
+            // Stages that precede the failing one can still be dumped
+            for req in &emits {
+                if let Some(text) = req.stage.render_partial(&src_code) {
+                    write_output(req, &text)?;
+                }
+            }
+
+            exit_with_compile_error(path, e);
+        }
+    };
+
+    for req in &emits {
+        write_output(req, &req.stage.render(&artifacts))?;
+    }
+
+    if emits.is_empty() {
+        println!("Built in {} seconds.", duration.as_secs_f64());
+    }
+
+    Ok(())
+}
+
+fn parse_args(args: &[String]) -> Option<(Vec<EmitRequest>, SrcKind, OptLevel, &String)> {
+    let mut emits: Vec<EmitRequest> = vec![];
+    let mut kind = SrcKind::Program;
+    let mut level = OptLevel::O0;
+    let mut last_emit_len = 0; // no. of stages named by the latest `--emit`
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if let Some(stages) = arg.strip_prefix("--emit=") {
+            let stages = stages
+                .split(',')
+                .map(str::parse::<Emit>)
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?;
+
+            last_emit_len = stages.len();
+            emits.extend(
+                stages
+                    .into_iter()
+                    .map(|stage| EmitRequest { stage, out: None }),
+            );
+        } else if arg == "-o" {
+            let last = emits.last_mut().filter(|req| req.out.is_none())?;
+            if last_emit_len != 1 {
+                return None;
+            }
+            last.out = Some(args.next()?.clone());
+        } else if arg == "--lib" {
+            kind = SrcKind::Library;
+        } else if arg == "-O" {
+            level = OptLevel::O1;
+        } else if let Some(l) = arg.strip_prefix("-O") {
+            level = l.parse().ok()?;
+        } else if arg.starts_with('-') || path.replace(arg).is_some() {
+            return None;
+        }
+    }
+
+    Some((emits, kind, level, path?))
+}
+
+fn write_output(req: &EmitRequest, text: &str) -> std::io::Result<()> {
+    match &req.out {
+        Some(path) => std::fs::write(path, text),
+        None => std::io::stdout().write_all(text.as_bytes()),
+    }
+}
+
+/// Interprets a program, exiting w/ the code its `shuru` returns. Arguments after the path are
+/// the program's own.
+fn run_program(mut args: &[String]) -> std::io::Result<()> {
+    let vm = args.first().is_some_and(|arg| arg == "--vm");
+    if vm {
+        args = &args[1..];
+    }
+
+    let Some(path) = args.first() else {
+        exit_with_usage();
+    };
+    if path.starts_with('-') {
+        exit_with_usage();
+    }
+
+    let res = match read_src_or_bytecode(path)? {
+        Input::Bytecode(program) => bytecode::vm::run(&program, args),
+        Input::Src(src_code) => {
+            let artifacts = compile_or_exit(path, &src_code);
+            match vm {
+                true => bytecode::vm::run(&bytecode::compiler::compile(&artifacts.ast), args),
+                false => interp::core::run(&artifacts.ast, args),
+            }
+        }
+    };
+
+    match res {
+        Ok(code) => std::process::exit(code as i32),
+        Err(e) => {
+            eprintln!("{path}:{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Compiles a program ahead of time, to a file that can be run later
+fn run_build(args: &[String]) -> std::io::Result<()> {
+    let mut target = None;
+    let mut level = None;
+    let mut out = None;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if let Some(t) = arg.strip_prefix("--target=") {
+            target = Some(t);
+        } else if arg == "-o" && out.is_none() {
+            out = Some(args.next().unwrap_or_else(|| exit_with_usage()).clone());
+        } else if arg == "-O" {
+            level = Some(OptLevel::O1);
+        } else if let Some(l) = arg.strip_prefix("-O") {
+            level = Some(l.parse().unwrap_or_else(|_| exit_with_usage()));
+        } else if arg.starts_with('-') || path.replace(arg).is_some() {
+            exit_with_usage();
+        }
+    }
+
+    let (Some(target), Some(path)) = (target, path) else {
+        exit_with_usage();
+    };
+
+    // The others are generated from the AST, which nothing optimises
+    if level.is_some() && matches!(target, "bytecode" | "c" | "llvm-ir") {
+        eprintln!("`-O` only applies to the x86_64-linux, riscv64-linux and mips32 targets");
+        std::process::exit(1);
+    }
+    let level = level.unwrap_or(OptLevel::O0);
+
+    if target == "bytecode" {
+        let src_code = std::fs::read_to_string(path)?;
+        let artifacts = compile_or_exit(path, &src_code);
+        let program = bytecode::compiler::compile(&artifacts.ast);
+
+        let out = out.unwrap_or_else(|| {
+            let path = std::path::Path::new(path).with_extension("nkb");
+            path.to_string_lossy().into_owned()
+        });
+        return std::fs::write(out, bytecode::nkb::serialize(&program));
+    }
+
+    let Ok(target) = target.parse::<Target>() else {
+        exit_with_usage();
+    };
+
+    let src_code = std::fs::read_to_string(path)?;
+    let artifacts = match target {
+        Target::C | Target::LlvmIr => compile_or_exit(path, &src_code),
+        _ => compile_src_opt(&src_code, SrcKind::Program, level)
+            .unwrap_or_else(|e| exit_with_compile_error(path, e)),
+    };
+    let ir = || {
+        &artifacts
+            .lowered
+            .as_ref()
+            .expect("IR targets to be lowered")
+            .ir
+    };
+    let allocator = Allocator::for_level(level);
+
+    // Those that only ever produce source, for some other tool
+    let src_out = match target {
+        Target::Mips32 => Some(("s", codegen::mips::core::generate(ir(), allocator))),
+        Target::C => Some(("c", codegen::c::core::generate(&artifacts.ast))),
+        Target::LlvmIr => Some(("ll", codegen::llvm::core::generate(&artifacts.ast))),
+        _ => None,
+    };
+    if let Some((extension, generated)) = src_out {
+        let out = out.unwrap_or_else(|| {
+            let path = std::path::Path::new(path).with_extension(extension);
+            path.to_string_lossy().into_owned()
+        });
+        return std::fs::write(out, generated);
+    }
+
+    // Never the source itself, even if it has no extension
+    let out = out.unwrap_or_else(|| match std::path::Path::new(path).with_extension("") {
+        stem if stem.as_os_str() == path.as_str() => format!("{path}.out"),
+        stem => stem.to_string_lossy().into_owned(),
+    });
+    let asm = match target {
+        Target::Riscv64Linux => codegen::riscv64::core::generate(ir(), allocator),
+        _ => codegen::x86_64::generate(ir(), allocator),
+    };
+    codegen::core::build_executable(target, &asm, std::path::Path::new(&out))
+}
+
+/// Prints a program's bytecode, compiling it first if given source
+fn run_disasm(args: &[String]) -> std::io::Result<()> {
+    let [path] = args else {
+        exit_with_usage();
+    };
+    if path.starts_with('-') {
+        exit_with_usage();
+    }
+
+    let listing = match read_src_or_bytecode(path)? {
+        Input::Bytecode(program) => bytecode::disasm::disassemble(&program, None),
+        Input::Src(src_code) => {
+            let artifacts = compile_or_exit(path, &src_code);
+            let program = bytecode::compiler::compile(&artifacts.ast);
+            bytecode::disasm::disassemble(&program, Some(&src_code))
+        }
+    };
+
+    std::io::stdout().write_all(listing.as_bytes())
+}
+
+enum Input {
+    Src(String),
+    Bytecode(bytecode::core::Program),
+}
+
+// Tells `.nkb` files apart from source by their magic number, exiting if they fail to load
+fn read_src_or_bytecode(path: &str) -> std::io::Result<Input> {
+    let bytes = std::fs::read(path)?;
+
+    if !bytes.starts_with(bytecode::nkb::MAGIC) {
+        let src_code = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        return Ok(Input::Src(src_code));
+    }
+
+    match bytecode::nkb::deserialize(&bytes) {
+        Ok(program) => Ok(Input::Bytecode(program)),
+        Err(e) => {
+            eprintln!("{path}: {e:?}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn compile_or_exit(path: &str, src_code: &str) -> nuktah::Artifacts {
+    compile_src(src_code, SrcKind::Program).unwrap_or_else(|e| exit_with_compile_error(path, e))
+}
+
+// Operators found to be sure to fail are reported where they are in the source
+fn exit_with_compile_error(path: &str, e: CompilerError) -> ! {
+    match e {
+        CompilerError::OptErr(e) => eprintln!("{path}:{e}"),
+        e => eprintln!("{e:?}"),
+    }
+    std::process::exit(1);
+}
+
+/// Formats files in place; w/ `--check`, only reports the ones that aren't formatted, and exits
+/// w/ a non-zero code if there are any.
+fn run_fmt(args: &[String]) -> std::io::Result<()> {
+    let (check, paths): (Vec<&String>, Vec<&String>) =
+        args.iter().partition(|arg| arg.as_str() == "--check");
+
+    if paths.is_empty() || paths.iter().any(|path| path.starts_with('-')) {
+        exit_with_usage();
+    }
+
+    let mut failed = false;
+
+    for path in paths {
+        let src_code = std::fs::read_to_string(path)?;
+        let formatted = match format_src(&src_code) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{path}: {e:?}");
+                failed = true;
+                continue;
+            }
+        };
+
+        if formatted == src_code {
+            continue;
+        }
+
+        if check.is_empty() {
+            std::fs::write(path, formatted)?;
+        } else {
+            println!("{path} is not formatted");
+            failed = true;
+        }
+    }
+
+    if failed {
         std::process::exit(1);
     }
 
-    println!("Built in {} seconds.", duration.as_secs_f64());
     Ok(())
 }
+
+fn exit_with_usage() -> ! {
+    eprintln!("{USAGE}");
+    std::process::exit(1);
+}
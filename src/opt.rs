@@ -0,0 +1,6 @@
+pub mod constfold;
+pub mod core;
+pub mod dce;
+pub mod inline;
+pub mod licm;
+pub mod strength;
@@ -0,0 +1,363 @@
+//! Constant folding and propagation, over SSA. Registers whose value is known at compile time
+//! (because it's computed from constants alone, or because every argument to a phi is the same
+//! constant) are replaced by that constant wherever they're read, and the instructions computing
+//! them are dropped. Globals that are only ever assigned a constant, by their declaration, are
+//! propagated too.
+//!
+//! Branches are folded along the way: code behind a constant condition that's never met isn't
+//! looked at, so doesn't keep its phis' other arguments from being constant. Operators that would
+//! fail at runtime, e.g dividing by a constant zero, are left for the program to fail on, if it
+//! ever gets to them; `check` finds those it's sure to.
+
+use std::collections::HashSet;
+
+use super::core::OptError;
+use crate::interp::{core::RuntimeErrorKind, ops, value::Value};
+use crate::ir::{
+    cfg::Cfg,
+    core::{BinOp, BlockId, Const, Function, Inst, Module, Operand, Reg, Terminator, UnOp},
+    eval::const_value,
+};
+
+pub fn run(module: &mut Module) -> Result<(), OptError> {
+    let Module {
+        functions, strings, ..
+    } = module;
+
+    for f in functions.iter_mut() {
+        fold_function(f, strings);
+    }
+
+    let globals = constant_globals(module);
+    if globals.iter().all(Option::is_none) {
+        return Ok(());
+    }
+
+    let Module {
+        functions, strings, ..
+    } = module;
+
+    for f in functions.iter_mut() {
+        for inst in f.blocks.iter_mut().flat_map(|b| &mut b.insts) {
+            if let Inst::LoadGlobal { dst, global } = *inst {
+                if let Some(c) = globals[global as usize] {
+                    *inst = Inst::Copy {
+                        dst,
+                        src: Operand::Const(c),
+                    };
+                }
+            }
+        }
+        fold_function(f, strings);
+    }
+
+    Ok(())
+}
+
+/// Finds an operator that fails on constants, in code that runs whenever its function is called,
+/// in a module in SSA form that's yet to be optimised. Code that only might run, e.g behind a
+/// branch on an argument, isn't, and neither is what only becomes constant once optimised, e.g by
+/// inlining, so what's an error doesn't depend on how hard the module's optimised. Globals are
+/// taken to vary.
+pub fn check(module: &Module) -> Result<(), OptError> {
+    for f in &module.functions {
+        let facts = analyse(f, &module.strings);
+
+        for &id in facts.cfg.rpo() {
+            if !facts.always_reached(f, id) {
+                continue;
+            }
+
+            for inst in &f.block(id).insts {
+                if let Inst::Binary {
+                    op, lhs, rhs, span, ..
+                } = inst
+                {
+                    if let Err(kind) = fold_binary(&module.strings, &facts.values, *op, lhs, rhs) {
+                        return Err(OptError::from_runtime_error(&f.name, kind, *span));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// What's known of a register's value: optimistically nothing at first (`Unknown`), narrowed to
+// one constant, or to none at all (`Varying`) as the function's instructions are looked at
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lattice {
+    Unknown,
+    Const(Const),
+    Varying,
+}
+
+impl Lattice {
+    fn meet(self, other: Lattice) -> Lattice {
+        match (self, other) {
+            (Lattice::Unknown, l) | (l, Lattice::Unknown) => l,
+            (Lattice::Const(a), Lattice::Const(b)) if same(a, b) => self,
+            _ => Lattice::Varying,
+        }
+    }
+
+    // `==`, but w/ NaN the same as itself, so folding `0.0 / 0.0` still settles
+    fn same(self, other: Lattice) -> bool {
+        match (self, other) {
+            (Lattice::Const(a), Lattice::Const(b)) => same(a, b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+// What's known of a function once its values have settled
+struct Facts {
+    cfg: Cfg,
+    values: Vec<Lattice>,               // per register
+    edges: HashSet<(BlockId, BlockId)>, // that control may take; the entry's is implied
+}
+
+impl Facts {
+    fn runs(&self, id: BlockId) -> bool {
+        id == BlockId(0)
+            || self
+                .cfg
+                .preds(id)
+                .iter()
+                .any(|&pred| self.edges.contains(&(pred, id)))
+    }
+
+    // Whether control can leave the function, having entered it, w/o going through `avoiding`
+    fn leaves(&self, f: &Function, avoiding: Option<BlockId>) -> bool {
+        let mut seen = vec![false; f.blocks.len()];
+        let mut stack = vec![BlockId(0)];
+
+        while let Some(id) = stack.pop() {
+            if Some(id) == avoiding || std::mem::replace(&mut seen[id.0 as usize], true) {
+                continue;
+            }
+
+            let mut taken = self
+                .cfg
+                .succs(id)
+                .iter()
+                .filter(|&&succ| self.edges.contains(&(id, succ)));
+            match taken.next() {
+                None => return true,
+                Some(&succ) => stack.extend(std::iter::once(succ).chain(taken.copied())),
+            }
+        }
+
+        false
+    }
+
+    // Whether `id` runs on every call that returns (or fails w/ a missing return), i.e whether
+    // every way out of the function goes through it. Loops are assumed to end, and code that
+    // can't get out at all isn't counted on.
+    fn always_reached(&self, f: &Function, id: BlockId) -> bool {
+        self.runs(id) && self.leaves(f, None) && !self.leaves(f, Some(id))
+    }
+}
+
+fn value_of(values: &[Lattice], operand: &Operand) -> Lattice {
+    match operand {
+        Operand::Const(c) => Lattice::Const(*c),
+        Operand::Reg(reg) => values[reg.0 as usize],
+    }
+}
+
+// `lhs op rhs`, as far as it's known, or the error evaluating it fails w/
+fn fold_binary(
+    strings: &[String],
+    values: &[Lattice],
+    op: BinOp,
+    lhs: &Operand,
+    rhs: &Operand,
+) -> Result<Lattice, RuntimeErrorKind> {
+    match (value_of(values, lhs), value_of(values, rhs)) {
+        (Lattice::Const(l), Lattice::Const(r)) => {
+            let (l, r) = (const_value(strings, &l), const_value(strings, &r));
+            ops::binary(&op.token(), l, r).map(|v| Lattice::Const(value_to_const(v)))
+        }
+        (Lattice::Varying, _) | (_, Lattice::Varying) => Ok(Lattice::Varying),
+        _ => Ok(Lattice::Unknown),
+    }
+}
+
+fn analyse(f: &Function, strings: &[String]) -> Facts {
+    let mut facts = Facts {
+        cfg: Cfg::new(f),
+        values: vec![Lattice::Unknown; f.reg_types.len()],
+        edges: HashSet::new(),
+    };
+    for &p in &f.params {
+        facts.values[p.0 as usize] = Lattice::Varying;
+    }
+
+    // Values only ever go from unknown to constant to varying, and edges only get taken, so this
+    // settles. Code that never runs is folded afterwards, for `dce` to remove, w/ its phis taking
+    // every argument; nothing it computes is read by code that does run.
+    for live in [true, false] {
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for &id in facts.cfg.rpo() {
+                if facts.runs(id) != live {
+                    continue;
+                }
+
+                for inst in &f.block(id).insts {
+                    let Some(dst) = inst.dst() else {
+                        continue;
+                    };
+
+                    let values = &facts.values;
+                    let value = match inst {
+                        Inst::Copy { src, .. } => value_of(values, src),
+
+                        Inst::Unary { op, operand, .. } => match value_of(values, operand) {
+                            Lattice::Const(c) => Lattice::Const(fold_unary(*op, c)),
+                            l => l,
+                        },
+
+                        // Evaluating w/ what's only assumed constant can fail where the real
+                        // values wouldn't, and operators that do fail aren't folded, so failing
+                        // just makes the result vary
+                        Inst::Binary { op, lhs, rhs, .. } => {
+                            fold_binary(strings, values, *op, lhs, rhs).unwrap_or(Lattice::Varying)
+                        }
+
+                        Inst::Phi { args, .. } => args
+                            .iter()
+                            .filter(|(pred, _)| !live || facts.edges.contains(&(*pred, id)))
+                            .map(|(_, arg)| value_of(values, arg))
+                            .fold(Lattice::Unknown, Lattice::meet),
+
+                        Inst::Call { .. } | Inst::LoadGlobal { .. } | Inst::StoreGlobal { .. } => {
+                            Lattice::Varying
+                        }
+                    };
+
+                    let old = facts.values[dst.0 as usize];
+                    let new = old.meet(value);
+                    if !new.same(old) {
+                        facts.values[dst.0 as usize] = new;
+                        changed = true;
+                    }
+                }
+
+                if !live {
+                    continue;
+                }
+
+                let taken = match &f.block(id).term {
+                    Terminator::Branch {
+                        cond,
+                        then_block,
+                        else_block,
+                    } => match value_of(&facts.values, cond) {
+                        Lattice::Unknown => vec![],
+                        Lattice::Const(Const::Bool(true)) => vec![*then_block],
+                        Lattice::Const(Const::Bool(false)) => vec![*else_block],
+                        _ => vec![*then_block, *else_block],
+                    },
+                    term => term.successors(),
+                };
+                for succ in taken {
+                    changed |= facts.edges.insert((id, succ));
+                }
+            }
+        }
+    }
+
+    facts
+}
+
+fn fold_function(f: &mut Function, strings: &[String]) {
+    let Facts { values, .. } = analyse(f, strings);
+
+    let known = |reg: Reg| match values[reg.0 as usize] {
+        Lattice::Const(c) => Some(c),
+        _ => None,
+    };
+
+    for block in &mut f.blocks {
+        // Whatever's left of the instructions computing known values is pure, and now unread
+        block
+            .insts
+            .retain(|inst| inst.dst().is_none_or(|dst| known(dst).is_none()));
+
+        let operands = block.insts.iter_mut().flat_map(Inst::operands_mut);
+        for operand in operands.chain(block.term.operands_mut()) {
+            if let Operand::Reg(reg) = operand {
+                if let Some(c) = known(*reg) {
+                    *operand = Operand::Const(c);
+                }
+            }
+        }
+    }
+}
+
+fn fold_unary(op: UnOp, c: Const) -> Const {
+    match (op, c) {
+        (UnOp::Neg, Const::Int(i)) => Const::Int(i.wrapping_neg()),
+        (UnOp::Neg, Const::Float(x)) => Const::Float(-x),
+        (UnOp::Not, Const::Bool(b)) => Const::Bool(!b),
+        (op, c) => unreachable!("ill-typed operand to {op:?}: {c:?}"),
+    }
+}
+
+// No operator produces a jumla, so every result fits in a `Const` w/o new strings
+fn value_to_const(value: Value) -> Const {
+    match value {
+        Value::Int(i) => Const::Int(i),
+        Value::Float(x) => Const::Float(x),
+        Value::Bool(b) => Const::Bool(b),
+        Value::String(_) | Value::Void => unreachable!("operator produced {value:?}"),
+    }
+}
+
+// Floats are compared by their bits, so that e.g 0.0 and -0.0 aren't merged
+fn same(a: Const, b: Const) -> bool {
+    match (a, b) {
+        (Const::Float(a), Const::Float(b)) => a.to_bits() == b.to_bits(),
+        (a, b) => a == b,
+    }
+}
+
+/// Per global, the constant it always holds, if it does: when it's only assigned once, by its
+/// declaration, to a constant. Functions called while initialising globals could read one
+/// before it's assigned, so only those assigned before any call are considered.
+fn constant_globals(module: &Module) -> Vec<Option<Const>> {
+    let mut n_stores = vec![0; module.globals.len()];
+    for inst in module
+        .functions
+        .iter()
+        .flat_map(|f| &f.blocks)
+        .flat_map(|b| &b.insts)
+    {
+        if let Inst::StoreGlobal { global, .. } = inst {
+            n_stores[*global as usize] += 1;
+        }
+    }
+
+    let init = &module.functions[module.init as usize];
+    let mut consts = vec![None; module.globals.len()];
+
+    for &id in Cfg::new(init).rpo() {
+        for inst in &init.block(id).insts {
+            match inst {
+                Inst::Call { .. } => return consts,
+                Inst::StoreGlobal {
+                    global,
+                    src: Operand::Const(c),
+                } if n_stores[*global as usize] == 1 => consts[*global as usize] = Some(*c),
+                _ => {}
+            }
+        }
+    }
+
+    consts
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{constfold, dce, inline, licm, strength};
+use crate::interp::core::RuntimeErrorKind;
+use crate::ir::{core::Module, verify::verify_ssa};
+use crate::lexer::Span;
+
+/// How hard `nktc -O<level>` tries
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    #[default]
+    O0, // none at all
+    O1,
+    O2,
+}
+
+impl FromStr for OptLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Operators that are sure to fail at runtime, as `constfold::check` finds them; each names the
+/// function it was found in, and the span of the statement it's in
+#[derive(Debug, PartialEq)]
+pub enum OptError {
+    ConstDivisionByZero(String, Span),
+    ConstShiftOutOfRange(String, i64, Span),
+    ConstNegativeExponent(String, i64, Span),
+}
+
+impl OptError {
+    pub fn from_runtime_error(func: &str, kind: RuntimeErrorKind, span: Span) -> Self {
+        let func = func.to_string();
+        match kind {
+            RuntimeErrorKind::DivisionByZero => OptError::ConstDivisionByZero(func, span),
+            RuntimeErrorKind::ShiftOutOfRange(n) => OptError::ConstShiftOutOfRange(func, n, span),
+            RuntimeErrorKind::NegativeExponent(n) => OptError::ConstNegativeExponent(func, n, span),
+            kind => unreachable!("{kind:?} can't come of evaluating an operator"),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            OptError::ConstDivisionByZero(_, span)
+            | OptError::ConstShiftOutOfRange(_, _, span)
+            | OptError::ConstNegativeExponent(_, _, span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for OptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{}:{}: ", span.line, span.col)?;
+        match self {
+            OptError::ConstDivisionByZero(func, _) => write!(f, "division by zero, in `{func}`"),
+            OptError::ConstShiftOutOfRange(func, n, _) => {
+                write!(f, "shift by {n}, out of range, in `{func}`")
+            }
+            OptError::ConstNegativeExponent(func, n, _) => {
+                write!(f, "negative exponent {n}, in `{func}`")
+            }
+        }
+    }
+}
+
+type Pass = fn(&mut Module) -> Result<(), OptError>;
+
+// In the order they run, w/ the level each is enabled from
+const PASSES: &[(&str, OptLevel, Pass)] = &[
+    ("constfold", OptLevel::O1, constfold::run),
+    ("dce", OptLevel::O1, dce::run),
+    ("inline", OptLevel::O2, inline::run),
+    // Inlined bodies often fold further, w/ their arguments known
+    ("constfold", OptLevel::O2, constfold::run),
+    ("dce", OptLevel::O2, dce::run),
+    ("licm", OptLevel::O2, licm::run),
+    ("strength", OptLevel::O2, strength::run),
+    ("dce", OptLevel::O2, dce::run),
+];
+
+/// Runs the passes enabled at `level` over a module in SSA form, once it's been checked for
+/// operators sure to fail, the same way at every level. In debug builds, the module is verified
+/// after every pass, to pin down whichever one breaks it.
+pub fn optimise(module: &mut Module, level: OptLevel) -> Result<(), OptError> {
+    constfold::check(module)?;
+
+    for (name, min_level, pass) in PASSES {
+        if level < *min_level {
+            continue;
+        }
+
+        pass(module)?;
+        if cfg!(debug_assertions) {
+            if let Err(e) = verify_ssa(module) {
+                panic!("`{name}` left the IR malformed: {e:?}");
+            }
+        }
+    }
+
+    Ok(())
+}
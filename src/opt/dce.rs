@@ -0,0 +1,338 @@
+//! Dead code elimination, over SSA. Branches on constant conditions become jumps, leaving the
+//! untaken side (along w/ code after a `wapsi`/`toro`) unreachable, and so removed; phis left w/
+//! a single value, and copies, are replaced by what they copy, and blocks that can only follow
+//! one another are merged. Instructions whose results are never read are then removed, unless
+//! they've side effects, or could fail at runtime.
+//!
+//! Functions that can't be called, transitively, from `shuru` or while initialising globals are
+//! removed too. Libraries have no entry point, so keep all of theirs.
+
+use super::core::OptError;
+use crate::ir::{
+    callgraph::CallGraph,
+    cfg::{remove_unreachable_blocks, Cfg},
+    core::{
+        BinOp, Block, BlockId, Callee, Const, FnId, Function, Inst, Module, Operand, Terminator,
+    },
+};
+use crate::semantics::spaghetti::SymType;
+
+pub fn run(module: &mut Module) -> Result<(), OptError> {
+    for f in &mut module.functions {
+        fold_branches(f);
+        remove_unreachable_blocks(f);
+        simplify_phis(f);
+        propagate_copies(f);
+        remove_dead_insts(f);
+        skip_empty_blocks(f);
+        merge_blocks(f);
+        remove_dead_insts(f);
+    }
+
+    remove_unused_functions(module);
+    Ok(())
+}
+
+fn fold_branches(f: &mut Function) {
+    for id in f.block_ids() {
+        let Terminator::Branch {
+            cond: Operand::Const(Const::Bool(cond)),
+            then_block,
+            else_block,
+        } = f.block(id).term
+        else {
+            continue;
+        };
+
+        let (taken, untaken) = match cond {
+            true => (then_block, else_block),
+            false => (else_block, then_block),
+        };
+
+        f.blocks[id.0 as usize].term = Terminator::Jump(taken);
+        if untaken != taken {
+            remove_phi_args(&mut f.blocks[untaken.0 as usize], id);
+        }
+    }
+}
+
+fn remove_phi_args(block: &mut Block, pred: BlockId) {
+    for inst in block.insts.iter_mut().take_while(|inst| inst.is_phi()) {
+        if let Inst::Phi { args, .. } = inst {
+            args.retain(|(p, _)| *p != pred);
+        }
+    }
+}
+
+// Phis whose arguments are all the same (as in blocks w/ a single predecessor) are just copies
+fn simplify_phis(f: &mut Function) {
+    for block in &mut f.blocks {
+        let n_phis = block.insts.iter().take_while(|inst| inst.is_phi()).count();
+        let (mut phis, mut copies) = (vec![], vec![]);
+
+        for phi in block.insts.drain(..n_phis) {
+            let Inst::Phi { dst, args } = &phi else {
+                unreachable!()
+            };
+
+            let first = args.first().map(|(_, arg)| *arg);
+            match first.filter(|first| args.iter().all(|(_, arg)| arg == first)) {
+                Some(src) => copies.push(Inst::Copy { dst: *dst, src }),
+                None => phis.push(phi),
+            }
+        }
+
+        // Phis must stay first; the copies can follow them, as phis read their arguments on the
+        // way into the block
+        phis.append(&mut copies);
+        block.insts.splice(0..0, phis);
+    }
+}
+
+// In SSA form, a copy's source dominates everything its destination does, so reading the
+// source in its place is always fine. The copies themselves are left for `remove_dead_insts`.
+fn propagate_copies(f: &mut Function) {
+    let mut copied = vec![None; f.reg_types.len()];
+    for inst in f.blocks.iter().flat_map(|b| &b.insts) {
+        if let Inst::Copy { dst, src } = inst {
+            copied[dst.0 as usize] = Some(*src);
+        }
+    }
+
+    let resolve = |mut operand: Operand| {
+        while let Operand::Reg(reg) = operand {
+            match copied[reg.0 as usize] {
+                Some(src) => operand = src,
+                None => break,
+            }
+        }
+        operand
+    };
+
+    for block in &mut f.blocks {
+        let operands = block.insts.iter_mut().flat_map(Inst::operands_mut);
+        for operand in operands.chain(block.term.operands_mut()) {
+            *operand = resolve(*operand);
+        }
+    }
+}
+
+// Has whatever jumps to an empty block (that only jumps on) jump to where it does instead. Blocks
+// w/ phis aren't jumped to directly, as the phis would need new arguments.
+fn skip_empty_blocks(f: &mut Function) {
+    let forward = |f: &Function, mut target: BlockId| {
+        let mut seen = vec![false; f.blocks.len()];
+        while let Block {
+            insts,
+            term: Terminator::Jump(next),
+        } = f.block(target)
+        {
+            let next_has_phis = f.block(*next).insts.first().is_some_and(Inst::is_phi);
+            if target == BlockId(0) || !insts.is_empty() || next_has_phis {
+                break;
+            }
+            if std::mem::replace(&mut seen[target.0 as usize], true) {
+                break; // an empty infinite loop
+            }
+            target = *next;
+        }
+        target
+    };
+
+    for id in f.block_ids() {
+        let targets = f
+            .block(id)
+            .term
+            .successors()
+            .into_iter()
+            .map(|target| forward(f, target))
+            .collect::<Vec<_>>();
+
+        let term = &mut f.blocks[id.0 as usize].term;
+        for (target, new) in term.successors_mut().into_iter().zip(targets) {
+            *target = new;
+        }
+
+        if let Terminator::Branch {
+            then_block,
+            else_block,
+            ..
+        } = *term
+        {
+            if then_block == else_block {
+                *term = Terminator::Jump(then_block);
+            }
+        }
+    }
+
+    remove_unreachable_blocks(f);
+}
+
+// Appends each block that's only ever jumped to from one other onto that one
+fn merge_blocks(f: &mut Function) {
+    loop {
+        let cfg = Cfg::new(f);
+        let candidate = f.block_ids().find_map(|a| match f.block(a).term {
+            Terminator::Jump(b) if b != a && b != BlockId(0) && cfg.preds(b) == [a] => Some((a, b)),
+            _ => None,
+        });
+
+        let Some((a, b)) = candidate else {
+            break;
+        };
+
+        // Left empty, and w/o successors, for `remove_unreachable_blocks` to drop
+        let merged = std::mem::replace(
+            &mut f.blocks[b.0 as usize],
+            Block {
+                insts: vec![],
+                term: Terminator::MissingRet,
+            },
+        );
+
+        // W/ a single predecessor, any phis just copy their one argument
+        let insts = merged.insts.into_iter().map(|inst| match inst {
+            Inst::Phi { dst, args } => Inst::Copy {
+                dst,
+                src: args[0].1,
+            },
+            inst => inst,
+        });
+
+        let block = &mut f.blocks[a.0 as usize];
+        block.insts.extend(insts);
+        block.term = merged.term;
+
+        for succ in f.blocks[a.0 as usize].term.successors() {
+            for inst in &mut f.blocks[succ.0 as usize].insts {
+                let Inst::Phi { args, .. } = inst else {
+                    break;
+                };
+                for (pred, _) in args.iter_mut().filter(|(pred, _)| *pred == b) {
+                    *pred = a;
+                }
+            }
+        }
+    }
+
+    remove_unreachable_blocks(f);
+}
+
+fn remove_dead_insts(f: &mut Function) {
+    let mut live = vec![false; f.reg_types.len()];
+    let mut defs = vec![None; f.reg_types.len()];
+    let mut work = vec![];
+
+    let mut mark = |operand: &Operand, work: &mut Vec<_>| {
+        if let Operand::Reg(reg) = operand {
+            if !std::mem::replace(&mut live[reg.0 as usize], true) {
+                work.push(*reg);
+            }
+        }
+    };
+
+    // Whatever's read by something that has to stay is live, as is whatever that reads...
+    for block in &f.blocks {
+        for inst in &block.insts {
+            if let Some(dst) = inst.dst() {
+                defs[dst.0 as usize] = Some(inst);
+            }
+            if !is_removable(f, inst) {
+                inst.operands().into_iter().for_each(|o| mark(o, &mut work));
+            }
+        }
+        block
+            .term
+            .operands()
+            .into_iter()
+            .for_each(|o| mark(o, &mut work));
+    }
+
+    while let Some(reg) = work.pop() {
+        if let Some(def) = defs[reg.0 as usize] {
+            def.operands().into_iter().for_each(|o| mark(o, &mut work));
+        }
+    }
+
+    let keep = f
+        .blocks
+        .iter()
+        .map(|b| {
+            b.insts
+                .iter()
+                .map(|inst| {
+                    !is_removable(f, inst) || inst.dst().is_some_and(|d| live[d.0 as usize])
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for (block, keep) in f.blocks.iter_mut().zip(keep) {
+        let mut keep = keep.into_iter();
+        block.insts.retain(|_| keep.next().unwrap());
+    }
+}
+
+// Whether an instruction can go if its result isn't read: it's no side effects, and can't fail
+pub(super) fn is_removable(f: &Function, inst: &Inst) -> bool {
+    match inst {
+        Inst::Copy { .. } | Inst::Unary { .. } | Inst::LoadGlobal { .. } | Inst::Phi { .. } => true,
+        Inst::Binary { op, lhs, rhs, .. } => !may_fail(*op, f.operand_type(lhs), rhs),
+        Inst::Call { .. } | Inst::StoreGlobal { .. } => false,
+    }
+}
+
+fn may_fail(op: BinOp, operand_type: SymType, rhs: &Operand) -> bool {
+    let int_rhs = match (operand_type, rhs) {
+        (SymType::Int, Operand::Const(Const::Int(i))) => Some(*i),
+        (SymType::Int, _) => None,
+        _ => return false, // floats never fail
+    };
+
+    match op {
+        BinOp::Div | BinOp::Mod => int_rhs.is_none_or(|i| i == 0),
+        BinOp::Shl | BinOp::Shr => int_rhs.is_none_or(|i| !(0..64).contains(&i)),
+        BinOp::Exp => int_rhs.is_none_or(|i| i < 0),
+        _ => false,
+    }
+}
+
+fn remove_unused_functions(module: &mut Module) {
+    let Some(entry) = module.entry else {
+        return;
+    };
+
+    let reachable = CallGraph::new(module).reachable_from(&[module.init, entry]);
+
+    let mut new_ids = vec![None; module.functions.len()];
+    let kept = (0..module.functions.len()).filter(|&func| reachable[func]);
+    for (new_id, func) in kept.enumerate() {
+        new_ids[func] = Some(new_id as FnId);
+    }
+
+    let functions = std::mem::take(&mut module.functions);
+    module.functions = functions
+        .into_iter()
+        .zip(&reachable)
+        .filter_map(|(f, reachable)| reachable.then_some(f))
+        .collect();
+
+    let new_id = |func: FnId| new_ids[func as usize].expect("call to a removed function");
+    for inst in module
+        .functions
+        .iter_mut()
+        .flat_map(|f| &mut f.blocks)
+        .flat_map(|b| &mut b.insts)
+    {
+        if let Inst::Call {
+            callee: Callee::Fn(func),
+            ..
+        } = inst
+        {
+            *func = new_id(*func);
+        }
+    }
+
+    module.init = new_id(module.init);
+    module.entry = Some(new_id(entry));
+}
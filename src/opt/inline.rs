@@ -0,0 +1,165 @@
+//! Inlining, over SSA. Calls to functions no bigger than a threshold are replaced by the callee's
+//! body: its blocks are copied into the caller, w/ fresh registers standing in for its own (so
+//! its locals can't clash w/ the caller's), its parameters replaced by the arguments, and each
+//! `wapsi` jumping to the rest of the caller's block, where a phi picks up the returned value.
+//!
+//! Functions are visited callees first, so what's inlined has already had its own calls inlined.
+//! Recursive functions (including mutually recursive ones) are never inlined, as there'd be no
+//! end to it; calls they make to others still are.
+
+use super::core::OptError;
+use crate::ir::{
+    callgraph::CallGraph,
+    core::{Block, BlockId, Callee, FnId, Function, Inst, Module, Operand, Terminator},
+};
+
+/// The most instructions (terminators included) a function can have and still be inlined
+pub const DEFAULT_THRESHOLD: usize = 24;
+
+pub fn run(module: &mut Module) -> Result<(), OptError> {
+    run_with_threshold(module, DEFAULT_THRESHOLD)
+}
+
+pub fn run_with_threshold(module: &mut Module, threshold: usize) -> Result<(), OptError> {
+    let graph = CallGraph::new(module);
+    let inlinable = (0..module.functions.len() as FnId)
+        .map(|func| !graph.is_recursive(func) && can_inline(&module.functions[func as usize]))
+        .collect::<Vec<_>>();
+
+    for caller in graph.post_order() {
+        let mut id = BlockId(0);
+
+        while (id.0 as usize) < module.functions[caller as usize].blocks.len() {
+            let call = module.functions[caller as usize]
+                .block(id)
+                .insts
+                .iter()
+                .enumerate()
+                .find_map(|(i, inst)| match inst {
+                    Inst::Call {
+                        callee: Callee::Fn(callee),
+                        ..
+                    } if *callee != caller
+                        && inlinable[*callee as usize]
+                        && size(&module.functions[*callee as usize]) <= threshold =>
+                    {
+                        Some((i, *callee))
+                    }
+                    _ => None,
+                });
+
+            match call {
+                Some((i, callee)) => {
+                    let callee = module.functions[callee as usize].clone();
+                    inline_call(&mut module.functions[caller as usize], id, i, &callee);
+                }
+                None => id.0 += 1,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn size(f: &Function) -> usize {
+    f.blocks.iter().map(|b| b.insts.len() + 1).sum()
+}
+
+// Callees that can run off their end would report doing so under the caller's name, and ones
+// whose entry is jumped back to would need phis for the call's block
+fn can_inline(f: &Function) -> bool {
+    f.blocks
+        .iter()
+        .all(|b| b.term != Terminator::MissingRet && !b.term.successors().contains(&BlockId(0)))
+}
+
+// Inlines the call that's the `i`th instruction of `block`
+fn inline_call(f: &mut Function, block: BlockId, i: usize, callee: &Function) {
+    let insts = &mut f.blocks[block.0 as usize].insts;
+    let rest = insts.split_off(i + 1);
+    let Some(Inst::Call { dst, args, .. }) = insts.pop() else {
+        unreachable!("not a call")
+    };
+
+    // The rest of the block, after the call, moves to a new one that the callee returns to
+    let cont = BlockId(f.blocks.len() as u32);
+    let term = std::mem::replace(
+        &mut f.blocks[block.0 as usize].term,
+        Terminator::Jump(BlockId(cont.0 + 1)),
+    );
+    for succ in term.successors() {
+        rename_phi_pred(&mut f.blocks[succ.0 as usize], block, cont);
+    }
+    f.blocks.push(Block { insts: rest, term });
+
+    // Parameters are read as the arguments, and every other register gets a fresh one
+    let mut regs = vec![None; callee.reg_types.len()];
+    for (p, arg) in callee.params.iter().zip(args) {
+        regs[p.0 as usize] = Some(arg);
+    }
+    let regs = regs
+        .into_iter()
+        .zip(&callee.reg_types)
+        .map(|(arg, &t)| arg.unwrap_or_else(|| Operand::Reg(f.new_reg(t))))
+        .collect::<Vec<_>>();
+
+    let offset = cont.0 + 1;
+    let mut returns = vec![];
+
+    for (id, b) in callee.blocks.iter().enumerate() {
+        let mut b = b.clone();
+        let new_id = BlockId(id as u32 + offset);
+
+        for inst in &mut b.insts {
+            if let Some(dst) = inst.dst_mut() {
+                let Operand::Reg(reg) = regs[dst.0 as usize] else {
+                    unreachable!("assignment to a parameter")
+                };
+                *dst = reg;
+            }
+            for operand in inst.operands_mut() {
+                rename(&regs, operand);
+            }
+            if let Inst::Phi { args, .. } = inst {
+                for (pred, _) in args {
+                    pred.0 += offset;
+                }
+            }
+        }
+
+        for operand in b.term.operands_mut() {
+            rename(&regs, operand);
+        }
+        for target in b.term.successors_mut() {
+            target.0 += offset;
+        }
+
+        if let Terminator::Ret(value) = b.term {
+            returns.extend(value.map(|v| (new_id, v)));
+            b.term = Terminator::Jump(cont);
+        }
+
+        f.blocks.push(b);
+    }
+
+    if let Some(dst) = dst {
+        let phi = Inst::Phi { dst, args: returns };
+        f.blocks[cont.0 as usize].insts.insert(0, phi);
+    }
+}
+
+fn rename(regs: &[Operand], operand: &mut Operand) {
+    if let Operand::Reg(reg) = operand {
+        *operand = regs[reg.0 as usize];
+    }
+}
+
+fn rename_phi_pred(block: &mut Block, from: BlockId, to: BlockId) {
+    for inst in block.insts.iter_mut().take_while(|inst| inst.is_phi()) {
+        if let Inst::Phi { args, .. } = inst {
+            for (pred, _) in args.iter_mut().filter(|(pred, _)| *pred == from) {
+                *pred = to;
+            }
+        }
+    }
+}
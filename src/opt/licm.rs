@@ -0,0 +1,78 @@
+//! Loop-invariant code motion, over SSA. Instructions in a loop that compute the same value on
+//! every iteration, as everything they read is computed outside it, are hoisted into its
+//! preheader, so they're run once instead. Inner loops are done first, so what's hoisted out of
+//! one can be hoisted further, out of the loops around it.
+//!
+//! Only instructions that `dce` could remove are hoisted: the loop might not run at all, and
+//! then they mustn't have run either. Reading a global is hoisted only out of loops that don't
+//! store to it, or make any calls.
+
+use super::{core::OptError, dce::is_removable};
+use crate::ir::{
+    cfg::{insert_preheaders, natural_loops, Cfg, Loop},
+    core::{BlockId, Function, Inst, Module, Operand},
+    dom::DomTree,
+};
+
+pub fn run(module: &mut Module) -> Result<(), OptError> {
+    for f in &mut module.functions {
+        insert_preheaders(f);
+        hoist(f);
+    }
+
+    Ok(())
+}
+
+fn hoist(f: &mut Function) {
+    let cfg = Cfg::new(f);
+    let dom = DomTree::new(&cfg);
+
+    // Parameters are defined before any loop
+    let mut def_blocks = vec![None; f.reg_types.len()];
+    for id in f.block_ids() {
+        for dst in f.block(id).insts.iter().filter_map(Inst::dst) {
+            def_blocks[dst.0 as usize] = Some(id);
+        }
+    }
+
+    for lp in natural_loops(&cfg, &dom).iter().rev() {
+        let preheader = lp.preheader(&cfg).expect("loop w/o a preheader");
+        let body = cfg.rpo().iter().copied().filter(|&id| lp.contains(id));
+
+        for id in body.collect::<Vec<_>>() {
+            let mut i = 0;
+            while i < f.block(id).insts.len() {
+                let inst = &f.block(id).insts[i];
+                if !is_invariant(f, lp, &def_blocks, inst) {
+                    i += 1;
+                    continue;
+                }
+
+                let inst = f.blocks[id.0 as usize].insts.remove(i);
+                def_blocks[inst.dst().unwrap().0 as usize] = Some(preheader);
+                f.blocks[preheader.0 as usize].insts.push(inst);
+            }
+        }
+    }
+}
+
+// Blocks are visited in reverse post-order, so an instruction's operands, if they're invariant,
+// have been hoisted by the time it's looked at
+fn is_invariant(f: &Function, lp: &Loop, def_blocks: &[Option<BlockId>], inst: &Inst) -> bool {
+    let outside = |operand: &Operand| match operand {
+        Operand::Const(_) => true,
+        Operand::Reg(reg) => def_blocks[reg.0 as usize].is_none_or(|id| !lp.contains(id)),
+    };
+
+    match inst {
+        Inst::Phi { .. } => false,
+        Inst::LoadGlobal { global, .. } => !lp.body.iter().any(|&id| {
+            f.block(id).insts.iter().any(|inst| match inst {
+                Inst::Call { .. } => true,
+                Inst::StoreGlobal { global: g, .. } => g == global,
+                _ => false,
+            })
+        }),
+        inst => is_removable(f, inst) && inst.operands().into_iter().all(outside),
+    }
+}
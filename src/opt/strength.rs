@@ -0,0 +1,207 @@
+//! Induction variable strength reduction, over SSA. A loop's basic induction variables are the
+//! phis at its header stepped by a constant on every iteration, e.g `i` in
+//! `duhrao (ginti i = 0 . i < n . i = i + 1)`. Multiplying one by a constant, as in `i * 4`,
+//! gives a value that's stepped by a constant too, so the multiplication is replaced by an
+//! accumulator of its own: started at `init * 4` in the preheader, and bumped by `step * 4`
+//! alongside `i`. Integers wrap, so the two always agree.
+
+use std::collections::HashMap;
+
+use super::core::OptError;
+use crate::ir::{
+    cfg::{insert_preheaders, natural_loops, Cfg, Loop},
+    core::{BinOp, BlockId, Const, Function, Inst, Module, Operand, Reg},
+    dom::DomTree,
+};
+use crate::lexer::Span;
+use crate::semantics::spaghetti::SymType;
+
+pub fn run(module: &mut Module) -> Result<(), OptError> {
+    for f in &mut module.functions {
+        insert_preheaders(f);
+
+        let cfg = Cfg::new(f);
+        let dom = DomTree::new(&cfg);
+        for lp in natural_loops(&cfg, &dom) {
+            reduce(f, &cfg, &lp);
+        }
+    }
+
+    Ok(())
+}
+
+// A basic induction variable: `reg = phi [preheader: init], [latch: next]`, where
+// `next = reg + step`
+struct Induction {
+    reg: Reg,
+    init: Operand,
+    next: Reg,
+    step: i64,
+}
+
+fn reduce(f: &mut Function, cfg: &Cfg, lp: &Loop) {
+    let Some(preheader) = lp.preheader(cfg) else {
+        return;
+    };
+    let [latch] = lp.latches[..] else {
+        return;
+    };
+
+    let ivs = f
+        .block(lp.header)
+        .insts
+        .iter()
+        .take_while(|inst| inst.is_phi())
+        .filter_map(|phi| induction(f, phi, preheader, latch))
+        .collect::<Vec<_>>();
+
+    // The multiplications to replace, found before any accumulators move instructions around
+    let mut muls = vec![];
+    for id in cfg.rpo().iter().copied().filter(|&id| lp.contains(id)) {
+        for inst in &f.block(id).insts {
+            let Inst::Binary {
+                dst,
+                op: BinOp::Mul,
+                lhs,
+                rhs,
+                ..
+            } = *inst
+            else {
+                continue;
+            };
+
+            if let (Operand::Reg(reg), Operand::Const(Const::Int(factor)))
+            | (Operand::Const(Const::Int(factor)), Operand::Reg(reg)) = (lhs, rhs)
+            {
+                if let Some(iv) = ivs.iter().position(|iv| iv.reg == reg) {
+                    muls.push((id, dst, iv, factor));
+                }
+            }
+        }
+    }
+
+    // One accumulator per variable and factor, however many times they're multiplied
+    let mut accs = HashMap::new();
+
+    for (id, dst, iv, factor) in muls {
+        let acc = *accs
+            .entry((iv, factor))
+            .or_insert_with(|| new_accumulator(f, lp.header, preheader, latch, &ivs[iv], factor));
+
+        let insts = &mut f.blocks[id.0 as usize].insts;
+        let mul = insts
+            .iter_mut()
+            .find(|inst| inst.dst() == Some(dst))
+            .unwrap();
+        *mul = Inst::Copy {
+            dst,
+            src: Operand::Reg(acc),
+        };
+    }
+}
+
+fn induction(f: &Function, phi: &Inst, preheader: BlockId, latch: BlockId) -> Option<Induction> {
+    let Inst::Phi { dst: reg, args } = phi else {
+        return None;
+    };
+    if f.reg_type(*reg) != SymType::Int {
+        return None;
+    }
+
+    let arg = |block| {
+        args.iter()
+            .find(|(pred, _)| *pred == block)
+            .map(|(_, arg)| *arg)
+    };
+    let (init, Operand::Reg(next)) = (arg(preheader)?, arg(latch)?) else {
+        return None;
+    };
+
+    let step = f
+        .blocks
+        .iter()
+        .flat_map(|b| &b.insts)
+        .find_map(|inst| match *inst {
+            Inst::Binary {
+                dst, op, lhs, rhs, ..
+            } if dst == next => match (op, lhs, rhs) {
+                (BinOp::Add, Operand::Reg(r), Operand::Const(Const::Int(c)))
+                | (BinOp::Add, Operand::Const(Const::Int(c)), Operand::Reg(r))
+                    if r == *reg =>
+                {
+                    Some(c)
+                }
+                (BinOp::Sub, Operand::Reg(r), Operand::Const(Const::Int(c))) if r == *reg => {
+                    Some(c.wrapping_neg())
+                }
+                _ => None,
+            },
+            _ => None,
+        })?;
+
+    Some(Induction {
+        reg: *reg,
+        init,
+        next,
+        step,
+    })
+}
+
+// Adds `acc = phi [preheader: init * factor], [latch: acc + step * factor]` to the loop,
+// bumping it right where the variable is. Integer `*` and `+` can't fail, so have no span.
+fn new_accumulator(
+    f: &mut Function,
+    header: BlockId,
+    preheader: BlockId,
+    latch: BlockId,
+    iv: &Induction,
+    factor: i64,
+) -> Reg {
+    let acc = f.new_reg(SymType::Int);
+    let acc_next = f.new_reg(SymType::Int);
+
+    let start = match iv.init {
+        Operand::Const(Const::Int(init)) => Operand::Const(Const::Int(init.wrapping_mul(factor))),
+        init => {
+            let start = f.new_reg(SymType::Int);
+            f.blocks[preheader.0 as usize].insts.push(Inst::Binary {
+                dst: start,
+                op: BinOp::Mul,
+                lhs: init,
+                rhs: Operand::Const(Const::Int(factor)),
+                span: Span::default(),
+            });
+            Operand::Reg(start)
+        }
+    };
+
+    let (id, i) = f
+        .block_ids()
+        .find_map(|id| {
+            let i = f
+                .block(id)
+                .insts
+                .iter()
+                .position(|inst| inst.dst() == Some(iv.next))?;
+            Some((id, i))
+        })
+        .unwrap();
+    f.blocks[id.0 as usize].insts.insert(
+        i + 1,
+        Inst::Binary {
+            dst: acc_next,
+            op: BinOp::Add,
+            lhs: Operand::Reg(acc),
+            rhs: Operand::Const(Const::Int(iv.step.wrapping_mul(factor))),
+            span: Span::default(),
+        },
+    );
+
+    let phi = Inst::Phi {
+        dst: acc,
+        args: vec![(preheader, start), (latch, Operand::Reg(acc_next))],
+    };
+    f.blocks[header.0 as usize].insts.insert(0, phi);
+
+    acc
+}
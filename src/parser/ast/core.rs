@@ -1,24 +1,23 @@
-use crate::lexer::Token;
+use crate::lexer::{Span, Token};
 
 pub type TranslationUnit = DeclList;
 
 pub type DeclList = Vec<Decl>;
 
-#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Decl {
     Var(VarDecl),
     Fn(FnDecl),
 }
 
-#[derive(Debug)]
 pub struct VarDecl {
     pub type_tok: Type,
     pub ident: String, // Identifier,
     // AssignOp,
     pub expr: Expr,
+    pub span: Span,
 }
 
-#[derive(Debug)]
 pub struct FnDecl {
     // Fn
     pub type_tok: Type,
@@ -28,29 +27,29 @@ pub struct FnDecl {
     // ParenR,
     pub block: Block,
     // Dot
+    pub span: Span,
 }
 
 pub type Type = Token; // {Int,String,Float,Bool}
 
-#[derive(Debug)]
 pub struct Param {
     pub type_tok: Type,
     pub ident: String, // Identifier
+    pub span: Span,
 }
 
 pub type Block = Vec<Stmt>;
 
-#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Stmt {
     For(ForStmt),     // duhrao (..) {}
     If(IfStmt),       // agar (a == 10) {} warna {}
     Ret(RetStmt),     // wapsi a .
     VarDecl(VarDecl), // ginti a = 10 .
     Expr(ExprStmt),   // 10 * 10
-    Break,            // toro
+    Break(Span),      // toro
 }
 
-#[derive(Debug)]
 pub struct ForStmt {
     // For
     // ParenL
@@ -59,9 +58,9 @@ pub struct ForStmt {
     pub updt: Expr,
     // ParenR
     pub block: Block,
+    pub span: Span,
 }
 
-#[derive(Debug)]
 pub struct IfStmt {
     // If
     // ParenL
@@ -70,14 +69,16 @@ pub struct IfStmt {
     pub if_block: Block,
     // Else
     pub else_block: Block,
+    pub span: Span,
+    pub else_span: Span, // of the `warna` keyword
 }
 
 pub type RetStmt = ExprStmt;
 
-#[derive(Debug)]
 pub struct ExprStmt {
     pub expr: Expr,
     // Dot
+    pub span: Span,
 }
 
 pub type Expr = Option<AssignExpr>;
@@ -196,12 +197,35 @@ pub enum PrimaryExpr {
     Call(FnCall),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct FnCall {
     pub ident: String, // Identifier
     // ParenL
     pub args: FnArgs,
     // ParenR
+    pub span: Span,
 }
 
 pub type FnArgs = Vec<Expr>;
+
+impl BoolExpr {
+    /// The identifier this expression boils down to, if it's nothing more than one (possibly
+    /// parenthesised), i.e if it can be assigned to.
+    pub fn as_ident(&self) -> Option<&str> {
+        let BoolExpr::BitOr(BitOrExpr::BitAnd(BitAndExpr::Comp(CompExpr::Shift(ShiftExpr::Add(
+            AddExpr::Mul(MulExpr::Exp(ExpExpr::Unary(UnaryExpr::Primary(primary)))),
+        ))))) = self
+        else {
+            return None;
+        };
+
+        match primary {
+            PrimaryExpr::Ident(ident) => Some(ident),
+            PrimaryExpr::Paren(inner) => match inner.as_ref() {
+                Some(AssignExpr::Bool(b)) => b.as_ident(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
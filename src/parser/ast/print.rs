@@ -3,17 +3,163 @@ use std::fmt;
 use crate::lexer::Token;
 use crate::parser::ast::core::*;
 
+// Every node is printed on its own line, prefixed by a newline and indented by its depth. Leaves
+// fit on a single line e.g `IntLit(3)`, whereas nodes w/ children print a header like
+// `Add(AddOp`, their children one level deeper, and finally a closing `)` on its own line.
+
+/// Renders the whole tree, e.g for `nktc --emit=ast`
+pub fn ast_to_string(unit: &TranslationUnit) -> String {
+    let mut out = String::new();
+    let _ = fmt::write(&mut out, format_args!("{}", TreePrinter(unit)));
+    out
+}
+
+struct TreePrinter<'a>(&'a TranslationUnit);
+
+impl fmt::Display for TreePrinter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TranslationUnit(")?;
+        for decl in self.0 {
+            decl.fmt_with_indent(f, 1)?;
+        }
+        writeln!(f, "\n)")
+    }
+}
+
+fn indent_str(indent: usize) -> String {
+    " ".repeat(indent * 4)
+}
+
+fn fmt_block(block: &Block, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+    let indent_str = indent_str(indent);
+    write!(f, "\n{indent_str}Block(")?;
+    for stmt in block {
+        stmt.fmt_with_indent(f, indent + 1)?;
+    }
+    write!(f, "\n{indent_str})")
+}
+
+fn fmt_option_assign_expr(
+    expr: &Option<AssignExpr>,
+    f: &mut fmt::Formatter,
+    indent: usize,
+) -> fmt::Result {
+    let indent_str = indent_str(indent);
+    match expr {
+        Some(e) => e.fmt_with_indent(f, indent),
+        None => write!(f, "\n{indent_str}<empty>"),
+    }
+}
+
+impl Decl {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        match self {
+            Decl::Var(v) => v.fmt_with_indent(f, indent),
+            Decl::Fn(func) => func.fmt_with_indent(f, indent),
+        }
+    }
+}
+
+impl VarDecl {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(
+            f,
+            "\n{indent_str}VarDecl({:?} {:?}",
+            self.type_tok, self.ident
+        )?;
+        fmt_option_assign_expr(&self.expr, f, indent + 1)?;
+        write!(f, "\n{indent_str})")
+    }
+}
+
+impl FnDecl {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(
+            f,
+            "\n{indent_str}FnDecl({:?} {:?}",
+            self.type_tok, self.ident
+        )?;
+        for param in &self.params {
+            param.fmt_with_indent(f, indent + 1)?;
+        }
+        fmt_block(&self.block, f, indent + 1)?;
+        write!(f, "\n{indent_str})")
+    }
+}
+
+impl Param {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(
+            f,
+            "\n{indent_str}Param({:?} {:?})",
+            self.type_tok, self.ident
+        )
+    }
+}
+
+impl Stmt {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        match self {
+            Stmt::For(for_stmt) => for_stmt.fmt_with_indent(f, indent),
+            Stmt::If(if_stmt) => if_stmt.fmt_with_indent(f, indent),
+            Stmt::Ret(ret_stmt) => {
+                write!(f, "\n{indent_str}Ret(")?;
+                fmt_option_assign_expr(&ret_stmt.expr, f, indent + 1)?;
+                write!(f, "\n{indent_str})")
+            }
+            Stmt::VarDecl(v) => v.fmt_with_indent(f, indent),
+            Stmt::Expr(expr_stmt) => expr_stmt.fmt_with_indent(f, indent),
+            Stmt::Break(_) => write!(f, "\n{indent_str}Break"),
+        }
+    }
+}
+
+impl ForStmt {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(f, "\n{indent_str}For(")?;
+        match &self.init {
+            Some(init) => init.fmt_with_indent(f, indent + 1)?,
+            None => write!(f, "\n{}<empty>", self::indent_str(indent + 1))?,
+        }
+        fmt_option_assign_expr(&self.cond.expr, f, indent + 1)?;
+        fmt_option_assign_expr(&self.updt, f, indent + 1)?;
+        fmt_block(&self.block, f, indent + 1)?;
+        write!(f, "\n{indent_str})")
+    }
+}
+
+impl IfStmt {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(f, "\n{indent_str}If(")?;
+        fmt_option_assign_expr(&self.cond, f, indent + 1)?;
+        fmt_block(&self.if_block, f, indent + 1)?;
+        fmt_block(&self.else_block, f, indent + 1)?;
+        write!(f, "\n{indent_str})")
+    }
+}
+
+impl ExprStmt {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(f, "\n{indent_str}ExprStmt(")?;
+        fmt_option_assign_expr(&self.expr, f, indent + 1)?;
+        write!(f, "\n{indent_str})")
+    }
+}
+
 impl AssignExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             AssignExpr::Bool(e) => e.fmt_with_indent(f, indent),
             AssignExpr::Assign(lhs, rhs) => {
-                write!(f, "\n{indent_str}Assign({:#?})", Token::AssignOp)?;
+                write!(f, "\n{indent_str}Assign({:#?}", Token::AssignOp)?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -23,16 +169,12 @@ impl AssignExpr {
 }
 
 impl BoolExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             BoolExpr::BitOr(e) => e.fmt_with_indent(f, indent),
             BoolExpr::Bool(lhs, op, rhs) => {
-                write!(f, "\n{indent_str}Bool({op:#?})")?;
+                write!(f, "\n{indent_str}Bool({op:#?}")?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -42,16 +184,12 @@ impl BoolExpr {
 }
 
 impl BitOrExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             BitOrExpr::BitAnd(e) => e.fmt_with_indent(f, indent),
             BitOrExpr::BitOr(lhs, rhs) => {
-                write!(f, "\n{indent_str}BitOr({:#?})", Token::BitwiseOr)?;
+                write!(f, "\n{indent_str}BitOr({:#?}", Token::BitwiseOr)?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -61,16 +199,12 @@ impl BitOrExpr {
 }
 
 impl BitAndExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             BitAndExpr::Comp(e) => e.fmt_with_indent(f, indent),
             BitAndExpr::BitAnd(lhs, rhs) => {
-                write!(f, "\n{indent_str}BitAnd({:#?})", Token::BitwiseAnd)?;
+                write!(f, "\n{indent_str}BitAnd({:#?}", Token::BitwiseAnd)?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -80,16 +214,12 @@ impl BitAndExpr {
 }
 
 impl CompExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             CompExpr::Shift(e) => e.fmt_with_indent(f, indent),
             CompExpr::Comp(lhs, op, rhs) => {
-                write!(f, "\n{indent_str}Comp({op:#?})")?;
+                write!(f, "\n{indent_str}Comp({op:#?}")?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -99,16 +229,12 @@ impl CompExpr {
 }
 
 impl ShiftExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             ShiftExpr::Add(e) => e.fmt_with_indent(f, indent),
             ShiftExpr::Shift(lhs, op, rhs) => {
-                write!(f, "\n{indent_str}Shift({op:#?})")?;
+                write!(f, "\n{indent_str}Shift({op:#?}")?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -118,17 +244,12 @@ impl ShiftExpr {
 }
 
 impl AddExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
-
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             AddExpr::Mul(e) => e.fmt_with_indent(f, indent),
             AddExpr::Add(lhs, op, rhs) => {
-                write!(f, "\n{indent_str}::Add({op:#?})")?;
+                write!(f, "\n{indent_str}Add({op:#?}")?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -138,17 +259,12 @@ impl AddExpr {
 }
 
 impl MulExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
-
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             MulExpr::Exp(e) => e.fmt_with_indent(f, indent),
             MulExpr::Mul(lhs, op, rhs) => {
-                write!(f, "\n{indent_str}::Mul({op:#?})")?;
+                write!(f, "\n{indent_str}Mul({op:#?}")?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -158,16 +274,12 @@ impl MulExpr {
 }
 
 impl ExpExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             ExpExpr::Unary(e) => e.fmt_with_indent(f, indent),
             ExpExpr::Exp(lhs, rhs) => {
-                write!(f, "\n{indent_str}Exp({:#?})", Token::ExpOp)?;
+                write!(f, "\n{indent_str}Exp({:#?}", Token::ExpOp)?;
                 lhs.fmt_with_indent(f, indent + 1)?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
@@ -177,16 +289,12 @@ impl ExpExpr {
 }
 
 impl UnaryExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
             UnaryExpr::Primary(e) => e.fmt_with_indent(f, indent),
             UnaryExpr::Unary(op, rhs) => {
-                write!(f, "\n{indent_str}Unary({op:#?})")?;
+                write!(f, "\n{indent_str}Unary({op:#?}")?;
                 rhs.fmt_with_indent(f, indent + 1)?;
                 write!(f, "\n{indent_str})")
             }
@@ -194,95 +302,68 @@ impl UnaryExpr {
     }
 }
 
-fn fmt_option_assign_expr(
-    expr: &Option<AssignExpr>,
-    f: &mut fmt::Formatter,
-    indent: usize,
-) -> fmt::Result {
-    let indent_str = " ".repeat(indent * 4);
-    match expr {
-        Some(e) => e.fmt_with_indent(f, indent),
-        None => write!(f, "\n{indent_str}<empty>"),
-    }
-}
-
 impl PrimaryExpr {
-    fn fmt_with_indent(
-        &self,
-        f: &mut fmt::Formatter,
-        indent: usize,
-    ) -> Result<(), std::fmt::Error> {
-        let indent_str = " ".repeat(indent * 4);
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
         match self {
-            PrimaryExpr::IntLit(e) => write!(f, "\n{indent_str}{e:?}"),
-            PrimaryExpr::FloatLit(e) => write!(f, "\n{indent_str}{e:?}"),
-            PrimaryExpr::StringLit(e) => write!(f, "\n{indent_str}{e:?}"),
-            PrimaryExpr::BoolLit(e) => write!(f, "\n{indent_str}{e:?}"),
-            PrimaryExpr::Ident(e) => write!(f, "\n{indent_str}{e:?}"),
-            PrimaryExpr::Paren(e) => fmt_option_assign_expr(e, f, indent + 1),
-            PrimaryExpr::Call(fn_call) => {
-                write!(f, "\n{indent_str}Call({fn_call:#?})")
+            PrimaryExpr::IntLit(e) => write!(f, "\n{indent_str}IntLit({e:?})"),
+            PrimaryExpr::FloatLit(e) => write!(f, "\n{indent_str}FloatLit({e:?})"),
+            PrimaryExpr::StringLit(e) => write!(f, "\n{indent_str}StringLit({e:?})"),
+            PrimaryExpr::BoolLit(e) => write!(f, "\n{indent_str}BoolLit({e:?})"),
+            PrimaryExpr::Ident(e) => write!(f, "\n{indent_str}Ident({e:?})"),
+            PrimaryExpr::Paren(e) => {
+                write!(f, "\n{indent_str}Paren(")?;
+                fmt_option_assign_expr(e, f, indent + 1)?;
+                write!(f, "\n{indent_str})")
             }
+            PrimaryExpr::Call(fn_call) => fn_call.fmt_with_indent(f, indent),
         }
     }
 }
 
-impl fmt::Debug for AssignExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for BoolExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for BitOrExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for BitAndExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for CompExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for ShiftExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for AddExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
-
-impl fmt::Debug for MulExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
+impl FnCall {
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let indent_str = indent_str(indent);
+        write!(f, "\n{indent_str}Call({:?}", self.ident)?;
+        for arg in &self.args {
+            fmt_option_assign_expr(arg, f, indent + 1)?;
+        }
+        write!(f, "\n{indent_str})")
     }
 }
 
-impl fmt::Debug for ExpExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
+// `Debug` output of any node is its subtree, starting at indentation level 0
+macro_rules! impl_debug_via_indent {
+    ($($node:ty),* $(,)?) => {
+        $(
+            impl fmt::Debug for $node {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    self.fmt_with_indent(f, 0)
+                }
+            }
+        )*
+    };
 }
 
-impl fmt::Debug for UnaryExpr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), std::fmt::Error> {
-        self.fmt_with_indent(f, 1)
-    }
-}
+impl_debug_via_indent!(
+    Decl,
+    VarDecl,
+    FnDecl,
+    Param,
+    Stmt,
+    ForStmt,
+    IfStmt,
+    ExprStmt,
+    AssignExpr,
+    BoolExpr,
+    BitOrExpr,
+    BitAndExpr,
+    CompExpr,
+    ShiftExpr,
+    AddExpr,
+    MulExpr,
+    ExpExpr,
+    UnaryExpr,
+    PrimaryExpr,
+    FnCall,
+);
@@ -0,0 +1,569 @@
+use crate::lexer::Span;
+use crate::parser::ast::core::*;
+
+// Each `visit_*` method defaults to the matching `walk_*` function, which recurses into the
+// node's children. Implementors override the methods for the nodes they care about, and call
+// the `walk_*` function themselves if they still want the children to be visited.
+
+pub trait Visitor<'ast> {
+    fn visit_translation_unit(&mut self, unit: &'ast TranslationUnit) {
+        walk_translation_unit(self, unit)
+    }
+
+    fn visit_decl(&mut self, decl: &'ast Decl) {
+        walk_decl(self, decl)
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &'ast VarDecl) {
+        walk_var_decl(self, var_decl)
+    }
+
+    fn visit_fn_decl(&mut self, fn_decl: &'ast FnDecl) {
+        walk_fn_decl(self, fn_decl)
+    }
+
+    fn visit_param(&mut self, _param: &'ast Param) {}
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_for_stmt(&mut self, for_stmt: &'ast ForStmt) {
+        walk_for_stmt(self, for_stmt)
+    }
+
+    fn visit_if_stmt(&mut self, if_stmt: &'ast IfStmt) {
+        walk_if_stmt(self, if_stmt)
+    }
+
+    fn visit_ret_stmt(&mut self, ret_stmt: &'ast RetStmt) {
+        walk_expr_stmt(self, ret_stmt)
+    }
+
+    fn visit_expr_stmt(&mut self, expr_stmt: &'ast ExprStmt) {
+        walk_expr_stmt(self, expr_stmt)
+    }
+
+    fn visit_break(&mut self, _span: &'ast Span) {}
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_assign_expr(&mut self, expr: &'ast AssignExpr) {
+        walk_assign_expr(self, expr)
+    }
+
+    fn visit_bool_expr(&mut self, expr: &'ast BoolExpr) {
+        walk_bool_expr(self, expr)
+    }
+
+    fn visit_bit_or_expr(&mut self, expr: &'ast BitOrExpr) {
+        walk_bit_or_expr(self, expr)
+    }
+
+    fn visit_bit_and_expr(&mut self, expr: &'ast BitAndExpr) {
+        walk_bit_and_expr(self, expr)
+    }
+
+    fn visit_comp_expr(&mut self, expr: &'ast CompExpr) {
+        walk_comp_expr(self, expr)
+    }
+
+    fn visit_shift_expr(&mut self, expr: &'ast ShiftExpr) {
+        walk_shift_expr(self, expr)
+    }
+
+    fn visit_add_expr(&mut self, expr: &'ast AddExpr) {
+        walk_add_expr(self, expr)
+    }
+
+    fn visit_mul_expr(&mut self, expr: &'ast MulExpr) {
+        walk_mul_expr(self, expr)
+    }
+
+    fn visit_exp_expr(&mut self, expr: &'ast ExpExpr) {
+        walk_exp_expr(self, expr)
+    }
+
+    fn visit_unary_expr(&mut self, expr: &'ast UnaryExpr) {
+        walk_unary_expr(self, expr)
+    }
+
+    fn visit_primary_expr(&mut self, expr: &'ast PrimaryExpr) {
+        walk_primary_expr(self, expr)
+    }
+
+    fn visit_fn_call(&mut self, fn_call: &'ast FnCall) {
+        walk_fn_call(self, fn_call)
+    }
+}
+
+pub fn walk_translation_unit<'ast, V: Visitor<'ast> + ?Sized>(
+    v: &mut V,
+    unit: &'ast TranslationUnit,
+) {
+    for decl in unit {
+        v.visit_decl(decl);
+    }
+}
+
+pub fn walk_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, decl: &'ast Decl) {
+    match decl {
+        Decl::Var(var_decl) => v.visit_var_decl(var_decl),
+        Decl::Fn(fn_decl) => v.visit_fn_decl(fn_decl),
+    }
+}
+
+pub fn walk_var_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, var_decl: &'ast VarDecl) {
+    v.visit_expr(&var_decl.expr);
+}
+
+pub fn walk_fn_decl<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, fn_decl: &'ast FnDecl) {
+    for param in &fn_decl.params {
+        v.visit_param(param);
+    }
+    v.visit_block(&fn_decl.block);
+}
+
+pub fn walk_block<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, block: &'ast Block) {
+    for stmt in block {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, stmt: &'ast Stmt) {
+    match stmt {
+        Stmt::For(for_stmt) => v.visit_for_stmt(for_stmt),
+        Stmt::If(if_stmt) => v.visit_if_stmt(if_stmt),
+        Stmt::Ret(ret_stmt) => v.visit_ret_stmt(ret_stmt),
+        Stmt::VarDecl(var_decl) => v.visit_var_decl(var_decl),
+        Stmt::Expr(expr_stmt) => v.visit_expr_stmt(expr_stmt),
+        Stmt::Break(span) => v.visit_break(span),
+    }
+}
+
+pub fn walk_for_stmt<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, for_stmt: &'ast ForStmt) {
+    if let Some(init) = &for_stmt.init {
+        v.visit_var_decl(init);
+    }
+    v.visit_expr_stmt(&for_stmt.cond);
+    v.visit_expr(&for_stmt.updt);
+    v.visit_block(&for_stmt.block);
+}
+
+pub fn walk_if_stmt<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, if_stmt: &'ast IfStmt) {
+    v.visit_expr(&if_stmt.cond);
+    v.visit_block(&if_stmt.if_block);
+    v.visit_block(&if_stmt.else_block);
+}
+
+pub fn walk_expr_stmt<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr_stmt: &'ast ExprStmt) {
+    v.visit_expr(&expr_stmt.expr);
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast Expr) {
+    if let Some(assign_e) = expr {
+        v.visit_assign_expr(assign_e);
+    }
+}
+
+pub fn walk_assign_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast AssignExpr) {
+    match expr {
+        AssignExpr::Bool(bool_e) => v.visit_bool_expr(bool_e),
+        AssignExpr::Assign(bool_e, assign_e) => {
+            v.visit_bool_expr(bool_e);
+            v.visit_assign_expr(assign_e);
+        }
+    }
+}
+
+pub fn walk_bool_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast BoolExpr) {
+    match expr {
+        BoolExpr::BitOr(bit_or_e) => v.visit_bit_or_expr(bit_or_e),
+        BoolExpr::Bool(bool_e, _, bit_or_e) => {
+            v.visit_bool_expr(bool_e);
+            v.visit_bit_or_expr(bit_or_e);
+        }
+    }
+}
+
+pub fn walk_bit_or_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast BitOrExpr) {
+    match expr {
+        BitOrExpr::BitAnd(bit_and_e) => v.visit_bit_and_expr(bit_and_e),
+        BitOrExpr::BitOr(bit_or_e, bit_and_e) => {
+            v.visit_bit_or_expr(bit_or_e);
+            v.visit_bit_and_expr(bit_and_e);
+        }
+    }
+}
+
+pub fn walk_bit_and_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast BitAndExpr) {
+    match expr {
+        BitAndExpr::Comp(comp_e) => v.visit_comp_expr(comp_e),
+        BitAndExpr::BitAnd(bit_and_e, comp_e) => {
+            v.visit_bit_and_expr(bit_and_e);
+            v.visit_comp_expr(comp_e);
+        }
+    }
+}
+
+pub fn walk_comp_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast CompExpr) {
+    match expr {
+        CompExpr::Shift(shift_e) => v.visit_shift_expr(shift_e),
+        CompExpr::Comp(comp_e, _, shift_e) => {
+            v.visit_comp_expr(comp_e);
+            v.visit_shift_expr(shift_e);
+        }
+    }
+}
+
+pub fn walk_shift_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast ShiftExpr) {
+    match expr {
+        ShiftExpr::Add(add_e) => v.visit_add_expr(add_e),
+        ShiftExpr::Shift(shift_e, _, add_e) => {
+            v.visit_shift_expr(shift_e);
+            v.visit_add_expr(add_e);
+        }
+    }
+}
+
+pub fn walk_add_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast AddExpr) {
+    match expr {
+        AddExpr::Mul(mul_e) => v.visit_mul_expr(mul_e),
+        AddExpr::Add(add_e, _, mul_e) => {
+            v.visit_add_expr(add_e);
+            v.visit_mul_expr(mul_e);
+        }
+    }
+}
+
+pub fn walk_mul_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast MulExpr) {
+    match expr {
+        MulExpr::Exp(exp_e) => v.visit_exp_expr(exp_e),
+        MulExpr::Mul(mul_e, _, exp_e) => {
+            v.visit_mul_expr(mul_e);
+            v.visit_exp_expr(exp_e);
+        }
+    }
+}
+
+pub fn walk_exp_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast ExpExpr) {
+    match expr {
+        ExpExpr::Unary(unary_e) => v.visit_unary_expr(unary_e),
+        ExpExpr::Exp(unary_e, exp_e) => {
+            v.visit_unary_expr(unary_e);
+            v.visit_exp_expr(exp_e);
+        }
+    }
+}
+
+pub fn walk_unary_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast UnaryExpr) {
+    match expr {
+        UnaryExpr::Primary(primary_e) => v.visit_primary_expr(primary_e),
+        UnaryExpr::Unary(_, unary_e) => v.visit_unary_expr(unary_e),
+    }
+}
+
+pub fn walk_primary_expr<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, expr: &'ast PrimaryExpr) {
+    match expr {
+        PrimaryExpr::Paren(nested_e) => v.visit_expr(nested_e),
+        PrimaryExpr::Call(fn_call) => v.visit_fn_call(fn_call),
+        PrimaryExpr::IntLit(_)
+        | PrimaryExpr::FloatLit(_)
+        | PrimaryExpr::StringLit(_)
+        | PrimaryExpr::BoolLit(_)
+        | PrimaryExpr::Ident(_) => {}
+    }
+}
+
+pub fn walk_fn_call<'ast, V: Visitor<'ast> + ?Sized>(v: &mut V, fn_call: &'ast FnCall) {
+    for arg in &fn_call.args {
+        v.visit_expr(arg);
+    }
+}
+
+// Mutable counterpart of `Visitor`, for passes that rewrite the tree in place.
+
+pub trait VisitorMut {
+    fn visit_translation_unit_mut(&mut self, unit: &mut TranslationUnit) {
+        walk_translation_unit_mut(self, unit)
+    }
+
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        walk_decl_mut(self, decl)
+    }
+
+    fn visit_var_decl_mut(&mut self, var_decl: &mut VarDecl) {
+        walk_var_decl_mut(self, var_decl)
+    }
+
+    fn visit_fn_decl_mut(&mut self, fn_decl: &mut FnDecl) {
+        walk_fn_decl_mut(self, fn_decl)
+    }
+
+    fn visit_param_mut(&mut self, _param: &mut Param) {}
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_for_stmt_mut(&mut self, for_stmt: &mut ForStmt) {
+        walk_for_stmt_mut(self, for_stmt)
+    }
+
+    fn visit_if_stmt_mut(&mut self, if_stmt: &mut IfStmt) {
+        walk_if_stmt_mut(self, if_stmt)
+    }
+
+    fn visit_ret_stmt_mut(&mut self, ret_stmt: &mut RetStmt) {
+        walk_expr_stmt_mut(self, ret_stmt)
+    }
+
+    fn visit_expr_stmt_mut(&mut self, expr_stmt: &mut ExprStmt) {
+        walk_expr_stmt_mut(self, expr_stmt)
+    }
+
+    fn visit_break_mut(&mut self, _span: &mut Span) {}
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_assign_expr_mut(&mut self, expr: &mut AssignExpr) {
+        walk_assign_expr_mut(self, expr)
+    }
+
+    fn visit_bool_expr_mut(&mut self, expr: &mut BoolExpr) {
+        walk_bool_expr_mut(self, expr)
+    }
+
+    fn visit_bit_or_expr_mut(&mut self, expr: &mut BitOrExpr) {
+        walk_bit_or_expr_mut(self, expr)
+    }
+
+    fn visit_bit_and_expr_mut(&mut self, expr: &mut BitAndExpr) {
+        walk_bit_and_expr_mut(self, expr)
+    }
+
+    fn visit_comp_expr_mut(&mut self, expr: &mut CompExpr) {
+        walk_comp_expr_mut(self, expr)
+    }
+
+    fn visit_shift_expr_mut(&mut self, expr: &mut ShiftExpr) {
+        walk_shift_expr_mut(self, expr)
+    }
+
+    fn visit_add_expr_mut(&mut self, expr: &mut AddExpr) {
+        walk_add_expr_mut(self, expr)
+    }
+
+    fn visit_mul_expr_mut(&mut self, expr: &mut MulExpr) {
+        walk_mul_expr_mut(self, expr)
+    }
+
+    fn visit_exp_expr_mut(&mut self, expr: &mut ExpExpr) {
+        walk_exp_expr_mut(self, expr)
+    }
+
+    fn visit_unary_expr_mut(&mut self, expr: &mut UnaryExpr) {
+        walk_unary_expr_mut(self, expr)
+    }
+
+    fn visit_primary_expr_mut(&mut self, expr: &mut PrimaryExpr) {
+        walk_primary_expr_mut(self, expr)
+    }
+
+    fn visit_fn_call_mut(&mut self, fn_call: &mut FnCall) {
+        walk_fn_call_mut(self, fn_call)
+    }
+}
+
+pub fn walk_translation_unit_mut<V: VisitorMut + ?Sized>(v: &mut V, unit: &mut TranslationUnit) {
+    for decl in unit {
+        v.visit_decl_mut(decl);
+    }
+}
+
+pub fn walk_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut Decl) {
+    match decl {
+        Decl::Var(var_decl) => v.visit_var_decl_mut(var_decl),
+        Decl::Fn(fn_decl) => v.visit_fn_decl_mut(fn_decl),
+    }
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, var_decl: &mut VarDecl) {
+    v.visit_expr_mut(&mut var_decl.expr);
+}
+
+pub fn walk_fn_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, fn_decl: &mut FnDecl) {
+    for param in &mut fn_decl.params {
+        v.visit_param_mut(param);
+    }
+    v.visit_block_mut(&mut fn_decl.block);
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(v: &mut V, block: &mut Block) {
+    for stmt in block {
+        v.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    match stmt {
+        Stmt::For(for_stmt) => v.visit_for_stmt_mut(for_stmt),
+        Stmt::If(if_stmt) => v.visit_if_stmt_mut(if_stmt),
+        Stmt::Ret(ret_stmt) => v.visit_ret_stmt_mut(ret_stmt),
+        Stmt::VarDecl(var_decl) => v.visit_var_decl_mut(var_decl),
+        Stmt::Expr(expr_stmt) => v.visit_expr_stmt_mut(expr_stmt),
+        Stmt::Break(span) => v.visit_break_mut(span),
+    }
+}
+
+pub fn walk_for_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, for_stmt: &mut ForStmt) {
+    if let Some(init) = &mut for_stmt.init {
+        v.visit_var_decl_mut(init);
+    }
+    v.visit_expr_stmt_mut(&mut for_stmt.cond);
+    v.visit_expr_mut(&mut for_stmt.updt);
+    v.visit_block_mut(&mut for_stmt.block);
+}
+
+pub fn walk_if_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, if_stmt: &mut IfStmt) {
+    v.visit_expr_mut(&mut if_stmt.cond);
+    v.visit_block_mut(&mut if_stmt.if_block);
+    v.visit_block_mut(&mut if_stmt.else_block);
+}
+
+pub fn walk_expr_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, expr_stmt: &mut ExprStmt) {
+    v.visit_expr_mut(&mut expr_stmt.expr);
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let Some(assign_e) = expr {
+        v.visit_assign_expr_mut(assign_e);
+    }
+}
+
+pub fn walk_assign_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut AssignExpr) {
+    match expr {
+        AssignExpr::Bool(bool_e) => v.visit_bool_expr_mut(bool_e),
+        AssignExpr::Assign(bool_e, assign_e) => {
+            v.visit_bool_expr_mut(bool_e);
+            v.visit_assign_expr_mut(assign_e);
+        }
+    }
+}
+
+pub fn walk_bool_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut BoolExpr) {
+    match expr {
+        BoolExpr::BitOr(bit_or_e) => v.visit_bit_or_expr_mut(bit_or_e),
+        BoolExpr::Bool(bool_e, _, bit_or_e) => {
+            v.visit_bool_expr_mut(bool_e);
+            v.visit_bit_or_expr_mut(bit_or_e);
+        }
+    }
+}
+
+pub fn walk_bit_or_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut BitOrExpr) {
+    match expr {
+        BitOrExpr::BitAnd(bit_and_e) => v.visit_bit_and_expr_mut(bit_and_e),
+        BitOrExpr::BitOr(bit_or_e, bit_and_e) => {
+            v.visit_bit_or_expr_mut(bit_or_e);
+            v.visit_bit_and_expr_mut(bit_and_e);
+        }
+    }
+}
+
+pub fn walk_bit_and_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut BitAndExpr) {
+    match expr {
+        BitAndExpr::Comp(comp_e) => v.visit_comp_expr_mut(comp_e),
+        BitAndExpr::BitAnd(bit_and_e, comp_e) => {
+            v.visit_bit_and_expr_mut(bit_and_e);
+            v.visit_comp_expr_mut(comp_e);
+        }
+    }
+}
+
+pub fn walk_comp_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut CompExpr) {
+    match expr {
+        CompExpr::Shift(shift_e) => v.visit_shift_expr_mut(shift_e),
+        CompExpr::Comp(comp_e, _, shift_e) => {
+            v.visit_comp_expr_mut(comp_e);
+            v.visit_shift_expr_mut(shift_e);
+        }
+    }
+}
+
+pub fn walk_shift_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut ShiftExpr) {
+    match expr {
+        ShiftExpr::Add(add_e) => v.visit_add_expr_mut(add_e),
+        ShiftExpr::Shift(shift_e, _, add_e) => {
+            v.visit_shift_expr_mut(shift_e);
+            v.visit_add_expr_mut(add_e);
+        }
+    }
+}
+
+pub fn walk_add_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut AddExpr) {
+    match expr {
+        AddExpr::Mul(mul_e) => v.visit_mul_expr_mut(mul_e),
+        AddExpr::Add(add_e, _, mul_e) => {
+            v.visit_add_expr_mut(add_e);
+            v.visit_mul_expr_mut(mul_e);
+        }
+    }
+}
+
+pub fn walk_mul_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut MulExpr) {
+    match expr {
+        MulExpr::Exp(exp_e) => v.visit_exp_expr_mut(exp_e),
+        MulExpr::Mul(mul_e, _, exp_e) => {
+            v.visit_mul_expr_mut(mul_e);
+            v.visit_exp_expr_mut(exp_e);
+        }
+    }
+}
+
+pub fn walk_exp_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut ExpExpr) {
+    match expr {
+        ExpExpr::Unary(unary_e) => v.visit_unary_expr_mut(unary_e),
+        ExpExpr::Exp(unary_e, exp_e) => {
+            v.visit_unary_expr_mut(unary_e);
+            v.visit_exp_expr_mut(exp_e);
+        }
+    }
+}
+
+pub fn walk_unary_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut UnaryExpr) {
+    match expr {
+        UnaryExpr::Primary(primary_e) => v.visit_primary_expr_mut(primary_e),
+        UnaryExpr::Unary(_, unary_e) => v.visit_unary_expr_mut(unary_e),
+    }
+}
+
+pub fn walk_primary_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut PrimaryExpr) {
+    match expr {
+        PrimaryExpr::Paren(nested_e) => v.visit_expr_mut(nested_e),
+        PrimaryExpr::Call(fn_call) => v.visit_fn_call_mut(fn_call),
+        PrimaryExpr::IntLit(_)
+        | PrimaryExpr::FloatLit(_)
+        | PrimaryExpr::StringLit(_)
+        | PrimaryExpr::BoolLit(_)
+        | PrimaryExpr::Ident(_) => {}
+    }
+}
+
+pub fn walk_fn_call_mut<V: VisitorMut + ?Sized>(v: &mut V, fn_call: &mut FnCall) {
+    for arg in &mut fn_call.args {
+        v.visit_expr_mut(arg);
+    }
+}
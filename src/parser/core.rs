@@ -1,5 +1,6 @@
-use crate::lexer::Token;
+use crate::lexer::{core::LosslessTokens, Span, Token};
 use crate::parser::ast;
+use crate::parser::cst::{Checkpoint, Cst, CstBuilder, SyntaxKind};
 
 #[derive(Debug)]
 pub enum ParseError {
@@ -18,21 +19,89 @@ pub enum ParseError {
 const PRIMITIVE_TYPES: [Token; 4] = [Token::Int, Token::String, Token::Float, Token::Bool];
 
 pub fn parse_token_stream(tokens: &Vec<Token>) -> Result<ast::core::TranslationUnit, ParseError> {
-    let mut p = Parser::new(tokens);
+    let mut p = Parser::new(tokens, &[]);
     p.parse_translation_unit()
 }
 
+/// Same as `parse_token_stream`, but also records where each node came from. `spans[i]` must be
+/// the span of `tokens[i]`.
+pub fn parse_spanned_token_stream(
+    tokens: &Vec<Token>,
+    spans: &[Span],
+) -> Result<ast::core::TranslationUnit, ParseError> {
+    let mut p = Parser::new(tokens, spans);
+    p.parse_translation_unit()
+}
+
+/// Parses a lossless token stream into both the AST and a concrete syntax tree that reproduces
+/// the source exactly.
+pub fn parse_lossless_token_stream(
+    lexed: &LosslessTokens,
+) -> Result<(ast::core::TranslationUnit, Cst), ParseError> {
+    let tokens: Vec<Token> = lexed.tokens.iter().map(|t| t.token.clone()).collect();
+    let spans: Vec<Span> = lexed.tokens.iter().map(|t| t.span).collect();
+
+    let mut p = Parser::new(&tokens, &spans);
+    p.cst = Some(CstBuilder::new(&lexed.tokens));
+    let ast_root = p.parse_translation_unit()?;
+    let cst = p.cst.unwrap().finish(lexed.eof_trivia.clone());
+
+    Ok((ast_root, cst))
+}
+
 struct Parser<'a> {
     pos: usize,
     token_stream: &'a Vec<Token>,
+    spans: &'a [Span],
+    cst: Option<CstBuilder<'a>>, // only built when parsing losslessly
 }
 
 impl<'a> Parser<'a> {
-    fn new(tokens: &'a Vec<Token>) -> Self {
+    fn new(tokens: &'a Vec<Token>, spans: &'a [Span]) -> Self {
         Self {
             pos: 0,
             token_stream: tokens,
+            spans,
+            cst: None,
+        }
+    }
+
+    // The following are no-ops unless a CST is being built.
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        if let Some(cst) = &mut self.cst {
+            cst.start_node(kind);
+        }
+    }
+
+    fn finish_node(&mut self) {
+        if let Some(cst) = &mut self.cst {
+            cst.finish_node();
+        }
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint> {
+        self.cst.as_ref().map(CstBuilder::checkpoint)
+    }
+
+    fn start_node_at(&mut self, checkpoint: &Option<Checkpoint>, kind: SyntaxKind) {
+        if let (Some(cst), Some(cp)) = (&mut self.cst, checkpoint) {
+            cst.start_node_at(cp, kind);
+        }
+    }
+
+    // Span of the token at `pos`; a default one if the token stream came without spans.
+    fn span_at(&self, pos: usize) -> Span {
+        self.spans.get(pos).copied().unwrap_or_default()
+    }
+
+    // Span covering every token consumed since `start_pos`.
+    fn span_since(&self, start_pos: usize) -> Span {
+        if self.pos <= start_pos {
+            return self.span_at(start_pos);
         }
+
+        self.span_at(start_pos).to(self.span_at(self.pos - 1))
     }
 
     // Returns the current token without consuming it.
@@ -45,6 +114,9 @@ impl<'a> Parser<'a> {
     }
 
     fn advance(&mut self) {
+        if let Some(cst) = &mut self.cst {
+            cst.token(self.pos);
+        }
         self.pos += 1;
     }
 
@@ -128,7 +200,10 @@ impl<'a> Parser<'a> {
 
     // translation-unit -> decl-list
     fn parse_translation_unit(&mut self) -> Result<ast::core::TranslationUnit, ParseError> {
-        self.parse_decl_list()
+        self.start_node(SyntaxKind::TranslationUnit);
+        let decls = self.parse_decl_list()?;
+        self.finish_node();
+        Ok(decls)
     }
 
     // decl-list -> decl | decl • decl-list
@@ -154,6 +229,8 @@ impl<'a> Parser<'a> {
 
     // fn-decl -> T_FUNC • fn-type • T_IDENTIFIER • T_PAREN_L • params • T_PAREN_R • block • T_DOT
     fn parse_fn_decl(&mut self) -> Result<ast::core::FnDecl, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::FnDecl);
         self.consume(Token::Function)?;
         let type_token: Token;
         if let Some(Token::Void) = self.peek() {
@@ -163,26 +240,33 @@ impl<'a> Parser<'a> {
             type_token = self.consume_prim_type_tok()?;
         }
         let ident = self.consume_identifier()?;
+        self.start_node(SyntaxKind::ParamList);
         self.consume(Token::ParenL)?;
         let params = self.parse_params()?;
         self.consume(Token::ParenR)?;
+        self.finish_node();
         let block = self.parse_block()?;
         self.consume(Token::Dot)?;
+        self.finish_node();
 
         Ok(ast::core::FnDecl {
             type_tok: type_token,
             ident,
             params,
             block,
+            span: self.span_since(start),
         })
     }
 
     // var-decl -> type • T_IDENTIFIER • T_ASSIGN • expr-stmt
     fn parse_var_decl(&mut self) -> Result<ast::core::VarDecl, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::VarDecl);
         let type_token = self.consume_prim_type_tok()?;
         let ident = self.consume_identifier()?;
         self.consume(Token::AssignOp)?;
         let expr_stmt = self.parse_expr_stmt()?;
+        self.finish_node();
 
         if expr_stmt.expr.is_none() {
             return Err(ParseError::ExpectedExpr);
@@ -192,6 +276,7 @@ impl<'a> Parser<'a> {
             type_tok: type_token,
             ident,
             expr: expr_stmt.expr,
+            span: self.span_since(start),
         })
     }
 
@@ -215,20 +300,26 @@ impl<'a> Parser<'a> {
 
     // param -> type • T_IDENTIFIER
     fn parse_param(&mut self) -> Result<ast::core::Param, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::Param);
         let type_token = self.consume_prim_type_tok()?;
         let ident = self.consume_identifier()?;
+        self.finish_node();
 
         Ok(ast::core::Param {
             type_tok: type_token,
             ident,
+            span: self.span_since(start),
         })
     }
 
     // block -> T_BRACE_L • stmts • T_BRACE_R
     fn parse_block(&mut self) -> Result<ast::core::Block, ParseError> {
+        self.start_node(SyntaxKind::Block);
         self.consume(Token::BraceL)?;
         let stmts = self.parse_stmts()?;
         self.consume(Token::BraceR)?;
+        self.finish_node();
 
         Ok(stmts)
     }
@@ -247,8 +338,10 @@ impl<'a> Parser<'a> {
                     stmts.push(ast::core::Stmt::VarDecl(self.parse_var_decl()?))
                 }
                 Token::Break => {
-                    stmts.push(ast::core::Stmt::Break);
+                    stmts.push(ast::core::Stmt::Break(self.span_at(self.pos)));
+                    self.start_node(SyntaxKind::BreakStmt);
                     self.advance();
+                    self.finish_node();
                 }
                 Token::BraceR => break, // end of encapsulating block...
                 _ => stmts.push(ast::core::Stmt::Expr(self.parse_expr_stmt()?)),
@@ -260,6 +353,8 @@ impl<'a> Parser<'a> {
 
     // for-stmt -> T_FOR • T_PAREN_L • var-decl • expr-stmt • expr • T_PAREN_R • block
     fn parse_for_stmt(&mut self) -> Result<ast::core::ForStmt, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::ForStmt);
         self.consume(Token::For)?;
         self.consume(Token::ParenL)?;
 
@@ -283,48 +378,73 @@ impl<'a> Parser<'a> {
             self.consume(Token::ParenR)?;
         }
 
+        let block = self.parse_block()?;
+        self.finish_node();
+
         Ok(ast::core::ForStmt {
             init,
             cond,
             updt,
-            block: self.parse_block()?,
+            block,
+            span: self.span_since(start),
         })
     }
 
     // if-stmt -> T_IF • T_PAREN_L • expr • T_PAREN_R • block • T_ELSE • block
     fn parse_if_stmt(&mut self) -> Result<ast::core::IfStmt, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::IfStmt);
         self.consume(Token::If)?;
         self.consume(Token::ParenL)?;
         let cond = self.parse_expr()?;
         self.consume(Token::ParenR)?;
         let if_block = self.parse_block()?;
+        let else_span = self.span_at(self.pos);
         self.consume(Token::Else)?;
         let else_block = self.parse_block()?;
+        self.finish_node();
 
         Ok(ast::core::IfStmt {
             cond,
             if_block,
             else_block,
+            span: self.span_since(start),
+            else_span,
         })
     }
 
     // ret-stmt -> T_RET • expr • T_DOT
     fn parse_ret_stmt(&mut self) -> Result<ast::core::RetStmt, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::RetStmt);
         self.consume(Token::Return)?;
-        self.parse_expr_stmt()
+        let mut ret_stmt = self.parse_expr_stmt()?;
+        self.finish_node();
+        ret_stmt.span = self.span_since(start);
+        Ok(ret_stmt)
     }
 
     // expr-stmt -> expr • T_DOT | T_DOT
     fn parse_expr_stmt(&mut self) -> Result<ast::core::ExprStmt, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::ExprStmt);
         if let Some(Token::Dot) = self.peek() {
             self.advance();
-            return Ok(ast::core::ExprStmt { expr: None });
+            self.finish_node();
+            return Ok(ast::core::ExprStmt {
+                expr: None,
+                span: self.span_since(start),
+            });
         }
 
         let expr = self.parse_expr()?;
         self.consume(Token::Dot)?;
+        self.finish_node();
 
-        Ok(ast::core::ExprStmt { expr })
+        Ok(ast::core::ExprStmt {
+            expr,
+            span: self.span_since(start),
+        })
     }
 
     // expr -> assign-expr
@@ -337,6 +457,7 @@ impl<'a> Parser<'a> {
 
     // assign-expr -> bool-expr | bool-expr • T_ASSIGN • assign-expr
     fn parse_assign_expr(&mut self) -> Result<ast::core::AssignExpr, ParseError> {
+        let cp = self.checkpoint();
         let left = self.parse_bool_expr()?;
         let ret: ast::core::AssignExpr;
 
@@ -345,8 +466,10 @@ impl<'a> Parser<'a> {
         // comprise the right tree
 
         if let Some(Token::AssignOp) = self.peek() {
+            self.start_node_at(&cp, SyntaxKind::AssignExpr);
             self.advance();
             let right = self.parse_assign_expr()?;
+            self.finish_node();
             ret = ast::core::AssignExpr::Assign(left, Box::new(right));
         } else {
             ret = ast::core::AssignExpr::Bool(left);
@@ -358,11 +481,14 @@ impl<'a> Parser<'a> {
     // bool-expr -> bitwise-or-expr | bool-expr • bool_op • bitwise-or-expr
     // bool-op -> T_BOOLEANOR | T_BOOLEANAND
     fn parse_bool_expr(&mut self) -> Result<ast::core::BoolExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::BoolExpr::BitOr(self.parse_bitwise_or_expr()?);
 
         while let Some(bool_op @ (Token::BooleanOr | Token::BooleanAnd)) = self.peek().cloned() {
+            self.start_node_at(&cp, SyntaxKind::BoolExpr);
             self.advance();
             let right = self.parse_bitwise_or_expr()?;
+            self.finish_node();
             left = ast::core::BoolExpr::Bool(
                 Box::new(left),
                 bool_op, // && or ||
@@ -375,11 +501,14 @@ impl<'a> Parser<'a> {
 
     // bitwise-or-expr -> bitwise-and-expr | bitwise-or-expr • T_BITWISE_OR • bitwise-and-expr
     fn parse_bitwise_or_expr(&mut self) -> Result<ast::core::BitOrExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::BitOrExpr::BitAnd(self.parse_bitwise_and_expr()?);
 
         while let Some(Token::BitwiseOr) = self.peek() {
+            self.start_node_at(&cp, SyntaxKind::BitOrExpr);
             self.advance();
             let right = self.parse_bitwise_and_expr()?;
+            self.finish_node();
             left = ast::core::BitOrExpr::BitOr(Box::new(left), right)
         }
 
@@ -388,11 +517,14 @@ impl<'a> Parser<'a> {
 
     // bitwise-and-expr -> comp-expr | bitwise-and-expr • T_BITWISEAND • comp-expr
     fn parse_bitwise_and_expr(&mut self) -> Result<ast::core::BitAndExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::BitAndExpr::Comp(self.parse_comp_expr()?);
 
         while let Some(Token::BitwiseAnd) = self.peek().cloned() {
+            self.start_node_at(&cp, SyntaxKind::BitAndExpr);
             self.advance();
             let right = self.parse_comp_expr()?;
+            self.finish_node();
             left = ast::core::BitAndExpr::BitAnd(Box::new(left), right)
         }
 
@@ -402,13 +534,16 @@ impl<'a> Parser<'a> {
     // comp-expr -> shift-expr | comp-expr • comp_op • shift-expr
     // comp-op -> T_LESSTHAN, T_GREATERTHAN, T_EQUALSOP
     fn parse_comp_expr(&mut self) -> Result<ast::core::CompExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::CompExpr::Shift(self.parse_shift_expr()?);
 
         while let Some(comp_op @ (Token::LessThan | Token::GreaterThan | Token::EqualsOp)) =
             self.peek().cloned()
         {
+            self.start_node_at(&cp, SyntaxKind::CompExpr);
             self.advance();
             let right = self.parse_shift_expr()?;
+            self.finish_node();
             left = ast::core::CompExpr::Comp(
                 Box::new(left),
                 comp_op, // < or > or ==
@@ -422,11 +557,14 @@ impl<'a> Parser<'a> {
     // shift-expr -> add-expr | shift-expr • shift-op • add-expr
     // shift-op -> T_SHIFTLEFT | T_SHIFTRIGHT
     fn parse_shift_expr(&mut self) -> Result<ast::core::ShiftExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::ShiftExpr::Add(self.parse_add_expr()?);
 
         while let Some(shift_op @ (Token::ShiftLeft | Token::ShiftRight)) = self.peek().cloned() {
+            self.start_node_at(&cp, SyntaxKind::ShiftExpr);
             self.advance();
             let right = self.parse_add_expr()?;
+            self.finish_node();
             left = ast::core::ShiftExpr::Shift(
                 Box::new(left),
                 shift_op, // << or >>
@@ -440,11 +578,14 @@ impl<'a> Parser<'a> {
     // add-expr -> mul-expr | add-expr • add-op • mul-expr
     // add-op -> T_ADDOP | T_SUBOP
     fn parse_add_expr(&mut self) -> Result<ast::core::AddExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::AddExpr::Mul(self.parse_mul_expr()?);
 
         while let Some(add_op @ (Token::AddOp | Token::SubOp)) = self.peek().cloned() {
+            self.start_node_at(&cp, SyntaxKind::AddExpr);
             self.advance();
             let right = self.parse_mul_expr()?;
+            self.finish_node();
             left = ast::core::AddExpr::Add(
                 Box::new(left),
                 add_op, // + or -
@@ -458,12 +599,15 @@ impl<'a> Parser<'a> {
     // mul-expr -> exp-expr | mul-expr • mul-op • exp-expr
     // mul-op -> T_MULOP | T_DIVOP | T_MODOP
     fn parse_mul_expr(&mut self) -> Result<ast::core::MulExpr, ParseError> {
+        let cp = self.checkpoint();
         let mut left = ast::core::MulExpr::Exp(self.parse_exp_expr()?);
 
         while let Some(mul_op @ (Token::MulOp | Token::DivOp | Token::ModOp)) = self.peek().cloned()
         {
+            self.start_node_at(&cp, SyntaxKind::MulExpr);
             self.advance();
             let right = self.parse_exp_expr()?;
+            self.finish_node();
             left = ast::core::MulExpr::Mul(
                 Box::new(left),
                 mul_op, // * or / or %
@@ -476,12 +620,15 @@ impl<'a> Parser<'a> {
 
     // exp-expr -> unary-expr | unary-expr • T_EXPOP • exp-expr
     fn parse_exp_expr(&mut self) -> Result<ast::core::ExpExpr, ParseError> {
+        let cp = self.checkpoint();
         let left = self.parse_unary_expr()?;
         let ret: ast::core::ExpExpr;
 
         if let Some(Token::ExpOp) = self.peek() {
+            self.start_node_at(&cp, SyntaxKind::ExpExpr);
             self.advance();
             let right = self.parse_exp_expr()?;
+            self.finish_node();
             ret = ast::core::ExpExpr::Exp(left, Box::new(right));
         } else {
             ret = ast::core::ExpExpr::Unary(left);
@@ -495,8 +642,10 @@ impl<'a> Parser<'a> {
     fn parse_unary_expr(&mut self) -> Result<ast::core::UnaryExpr, ParseError> {
         match self.peek().cloned() {
             Some(t @ (Token::SubOp | Token::BooleanNot | Token::BitwiseNot)) => {
+                self.start_node(SyntaxKind::UnaryExpr);
                 self.advance();
                 let right = self.parse_unary_expr()?;
+                self.finish_node();
                 Ok(ast::core::UnaryExpr::Unary(t, Box::new(right)))
             }
             _ => Ok(ast::core::UnaryExpr::Primary(self.parse_primary_expr()?)),
@@ -516,41 +665,55 @@ impl<'a> Parser<'a> {
                     return Ok(ast::core::PrimaryExpr::Call(self.parse_fn_call()?));
                 }
 
+                self.start_node(SyntaxKind::NameRef);
                 let ident = self.consume_identifier()?;
+                self.finish_node();
                 Ok(ast::core::PrimaryExpr::Ident(ident))
             }
 
             Token::IntLit(_) => {
+                self.start_node(SyntaxKind::Literal);
                 let i_lit = self.consume_intlit()?;
+                self.finish_node();
                 Ok(ast::core::PrimaryExpr::IntLit(i_lit))
             }
 
             Token::FloatLit(_) => {
+                self.start_node(SyntaxKind::Literal);
                 let f_lit = self.consume_floatlit()?;
+                self.finish_node();
                 Ok(ast::core::PrimaryExpr::FloatLit(f_lit))
             }
 
             Token::True => {
+                self.start_node(SyntaxKind::Literal);
                 self.advance();
+                self.finish_node();
                 Ok(ast::core::PrimaryExpr::BoolLit(true))
             }
 
             Token::False => {
+                self.start_node(SyntaxKind::Literal);
                 self.advance();
+                self.finish_node();
                 Ok(ast::core::PrimaryExpr::BoolLit(false))
             }
 
             Token::Quotes => {
+                self.start_node(SyntaxKind::Literal);
                 self.consume(Token::Quotes)?;
                 let str = self.consume_stringlit()?;
                 self.consume(Token::Quotes)?;
+                self.finish_node();
                 Ok(ast::core::PrimaryExpr::StringLit(str))
             }
 
             Token::ParenL => {
+                self.start_node(SyntaxKind::ParenExpr);
                 self.consume(Token::ParenL)?;
                 let expr = self.parse_expr()?;
                 self.consume(Token::ParenR)?;
+                self.finish_node();
 
                 Ok(ast::core::PrimaryExpr::Paren(Box::new(expr)))
             }
@@ -561,12 +724,21 @@ impl<'a> Parser<'a> {
 
     // fn-call -> T_IDENTIFIER • T_PAREN_L • fn-args • T_PAREN_R
     fn parse_fn_call(&mut self) -> Result<ast::core::FnCall, ParseError> {
+        let start = self.pos;
+        self.start_node(SyntaxKind::CallExpr);
         let ident = self.consume_identifier()?;
+        self.start_node(SyntaxKind::ArgList);
         self.consume(Token::ParenL)?;
         let args = self.parse_fn_args()?;
         self.consume(Token::ParenR)?;
+        self.finish_node();
+        self.finish_node();
 
-        Ok(ast::core::FnCall { ident, args })
+        Ok(ast::core::FnCall {
+            ident,
+            args,
+            span: self.span_since(start),
+        })
     }
 
     // fn-args -> expr | expr • T_COMMA • fn-args | EPSILON
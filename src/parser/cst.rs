@@ -0,0 +1,216 @@
+use std::fmt;
+
+use crate::lexer::{Trivia, TriviaToken};
+
+/// What a `SyntaxNode` represents. Nodes mirror the grammar's productions; a binary expression
+/// node is only created when an operator is actually present, so `1 .` is an `ExprStmt` holding a
+/// `Literal` rather than ten nested single-child nodes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SyntaxKind {
+    TranslationUnit,
+    FnDecl,
+    ParamList,
+    Param,
+    VarDecl,
+    Block,
+    ForStmt,
+    IfStmt,
+    RetStmt,
+    ExprStmt,
+    BreakStmt,
+    AssignExpr,
+    BoolExpr,
+    BitOrExpr,
+    BitAndExpr,
+    CompExpr,
+    ShiftExpr,
+    AddExpr,
+    MulExpr,
+    ExpExpr,
+    UnaryExpr,
+    ParenExpr,
+    CallExpr,
+    ArgList,
+    NameRef,
+    Literal,
+}
+
+#[derive(PartialEq, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(TriviaToken),
+}
+
+#[derive(PartialEq, Clone)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+/// A concrete syntax tree: every token of the source, trivia included, hung off the node of the
+/// production that consumed it.
+#[derive(PartialEq, Clone)]
+pub struct Cst {
+    pub root: SyntaxNode,
+    pub eof_trivia: Vec<Trivia>,
+}
+
+impl Cst {
+    /// The source this tree was built from, byte-for-byte
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.root.write_text(&mut out);
+        for trivia in &self.eof_trivia {
+            out.push_str(&trivia.text);
+        }
+        out
+    }
+}
+
+impl SyntaxNode {
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out);
+        out
+    }
+
+    pub fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(n) => n.write_text(out),
+                SyntaxElement::Token(t) => t.write_full_text(out),
+            }
+        }
+    }
+
+    /// Child nodes, skipping over tokens
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|c| match c {
+            SyntaxElement::Node(n) => Some(n),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// All tokens under this node, in source order
+    pub fn tokens(&self) -> Vec<&TriviaToken> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a TriviaToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(n) => n.collect_tokens(tokens),
+                SyntaxElement::Token(t) => tokens.push(t),
+            }
+        }
+    }
+
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        writeln!(f, "{}{:?}", "    ".repeat(indent), self.kind)?;
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(n) => n.fmt_with_indent(f, indent + 1)?,
+                SyntaxElement::Token(t) => {
+                    writeln!(f, "{}{:?} {:?}", "    ".repeat(indent + 1), t.token, t.text)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_indent(f, 0)
+    }
+}
+
+impl fmt::Debug for SyntaxElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(n) => n.fmt_with_indent(f, 0),
+            SyntaxElement::Token(t) => write!(f, "{:?} {:?}", t.token, t.text),
+        }
+    }
+}
+
+impl fmt::Debug for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.root.fmt_with_indent(f, 0)
+    }
+}
+
+/// Assembles a `Cst` alongside the parser. Nodes are opened/closed as productions start/finish,
+/// and every token the parser consumes is appended to the innermost open node.
+///
+/// Left-recursive productions don't know they're a node until they see their operator, by which
+/// point the left operand has already been built; a `checkpoint` taken beforehand lets the node be
+/// opened retroactively, around it.
+pub(crate) struct CstBuilder<'a> {
+    tokens: &'a [TriviaToken],
+    stack: Vec<SyntaxNode>,
+}
+
+pub(crate) struct Checkpoint(usize);
+
+impl<'a> CstBuilder<'a> {
+    pub fn new(tokens: &'a [TriviaToken]) -> Self {
+        Self {
+            tokens,
+            stack: vec![],
+        }
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.stack.push(SyntaxNode {
+            kind,
+            children: vec![],
+        });
+    }
+
+    pub fn finish_node(&mut self) {
+        let node = self.stack.pop().expect("no open node to finish");
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(SyntaxElement::Node(node)),
+            None => self.stack.push(node), // root stays put until `finish`
+        }
+    }
+
+    // Appends the token at `idx` of the token stream to the current node
+    pub fn token(&mut self, idx: usize) {
+        let token = self.tokens[idx].clone();
+        self.stack
+            .last_mut()
+            .expect("token outside of any node")
+            .children
+            .push(SyntaxElement::Token(token));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.stack.last().map_or(0, |n| n.children.len()))
+    }
+
+    // Opens a node that adopts everything the current node gained since `checkpoint`
+    pub fn start_node_at(&mut self, checkpoint: &Checkpoint, kind: SyntaxKind) {
+        let parent = self
+            .stack
+            .last_mut()
+            .expect("checkpoint outside of any node");
+        let children = parent.children.split_off(checkpoint.0);
+        self.stack.push(SyntaxNode { kind, children });
+    }
+
+    pub fn finish(mut self, eof_trivia: Vec<Trivia>) -> Cst {
+        assert_eq!(self.stack.len(), 1, "unbalanced start/finish_node calls");
+
+        Cst {
+            root: self.stack.pop().unwrap(),
+            eof_trivia,
+        }
+    }
+}
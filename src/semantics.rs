@@ -1,7 +1,9 @@
 pub mod core;
+pub mod intrinsics;
 pub mod spaghetti;
 
+mod entry;
 mod errors;
 mod scope;
 mod typchk;
-mod utils;
+pub(crate) mod utils;
@@ -1,5 +1,6 @@
 use super::{
-    errors::{ScopeError, TypeChkError},
+    entry,
+    errors::{EntryPointError, ScopeError, TypeChkError},
     scope,
     spaghetti::SpaghettiStack,
     typchk,
@@ -7,19 +8,54 @@ use super::{
 use crate::convert_across_err;
 use crate::parser::ast;
 
+pub use super::typchk::hir::hir_to_string;
+
 #[derive(Debug)]
 pub enum SemanticError {
     ScopeErr(ScopeError),
     TypeChkErr(TypeChkError),
+    EntryPointErr(EntryPointError),
 }
 
 convert_across_err!(ScopeError, SemanticError, ScopeErr);
 convert_across_err!(TypeChkError, SemanticError, TypeChkErr);
+convert_across_err!(EntryPointError, SemanticError, EntryPointErr);
+
+/// Name of the function a program's execution starts from
+pub const ENTRY_POINT: &str = "shuru";
+
+/// Whether a source file is a program, which must define an entry point, or a library
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SrcKind {
+    #[default]
+    Program,
+    Library,
+}
 
 pub fn analyse_semantics(
     ast_root: &ast::core::TranslationUnit,
+    kind: SrcKind,
 ) -> Result<SpaghettiStack, SemanticError> {
     let symbol_table = scope::core::analyse_scope(ast_root)?;
     typchk::core::check_types(ast_root, &symbol_table)?;
+
+    if kind == SrcKind::Program {
+        entry::check_entry_point(&symbol_table)?;
+    }
+
     Ok(symbol_table)
 }
+
+/// Builds as much of the symbol table as possible, returning it alongside the first error, if any.
+/// Useful for inspecting the scope tree of a program that fails analysis.
+pub fn analyse_semantics_partial(
+    ast_root: &ast::core::TranslationUnit,
+) -> (SpaghettiStack, Result<(), SemanticError>) {
+    let (symbol_table, res) = scope::core::analyse_scope_partial(ast_root);
+    if let Err(e) = res {
+        return (symbol_table, Err(e.into()));
+    }
+
+    let res = typchk::core::check_types(ast_root, &symbol_table).map_err(SemanticError::from);
+    (symbol_table, res)
+}
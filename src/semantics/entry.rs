@@ -0,0 +1,30 @@
+use super::{
+    core::ENTRY_POINT,
+    errors::EntryPointError,
+    spaghetti::{SpaghettiStack, SymType, ROOT_ID},
+};
+
+/// Ensures the root scope defines the entry point, as either `fn ginti shuru()` or
+/// `fn ginti shuru(ginti argc)`, the latter receiving the no. of command-line arguments (the
+/// program's own path included). A khali `shuru` is allowed too, and exits w/ 0.
+pub fn check_entry_point(spaghet: &SpaghettiStack) -> Result<(), EntryPointError> {
+    let Some(info) = spaghet.get_ident_info(ROOT_ID, ENTRY_POINT) else {
+        return Err(EntryPointError::Missing);
+    };
+
+    if info.is_var() {
+        return Err(EntryPointError::NotAFunction);
+    }
+
+    let ret_type = info.get_type();
+    if !matches!(ret_type, SymType::Int | SymType::Void) {
+        return Err(EntryPointError::InvalidReturnType(ret_type));
+    }
+
+    let params = spaghet.get_fn_param_types(ENTRY_POINT);
+    if !matches!(params.as_slice(), [] | [SymType::Int]) {
+        return Err(EntryPointError::InvalidParams(params.clone()));
+    }
+
+    Ok(())
+}
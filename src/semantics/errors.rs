@@ -1,9 +1,12 @@
+use super::spaghetti::SymType;
+
 #[derive(Debug)]
 pub enum ScopeError {
     UndeclaredVariableAccessed,
     UndefinedFunctionCalled,
     VariableRedefinition,
     FunctionPrototypeRedefinition,
+    IntrinsicRedefinition,
 }
 
 #[derive(Debug)]
@@ -23,4 +26,13 @@ pub enum TypeChkError {
     AttemptedAddOpOnNonNumeric,
     AttemptedExponentiationOfNonNumeric,
     ReturnStmtNotFound,
+    InvalidAssignmentTarget,
+}
+
+#[derive(Debug)]
+pub enum EntryPointError {
+    Missing,
+    NotAFunction,
+    InvalidReturnType(SymType),
+    InvalidParams(Vec<SymType>),
 }
@@ -0,0 +1,35 @@
+//! Functions every program can call w/o defining them. Scope analysis seeds these into the root
+//! scope, so calls to them are checked like any other; each backend provides the implementations.
+
+use super::spaghetti::SymType;
+
+pub struct Intrinsic {
+    pub name: &'static str,
+    pub params: &'static [(&'static str, SymType)],
+    pub ret_type: SymType,
+}
+
+pub const INTRINSICS: &[Intrinsic] = &[
+    // Prints a string, followed by a newline
+    Intrinsic {
+        name: "likho",
+        params: &[("s", SymType::String)],
+        ret_type: SymType::Void,
+    },
+    // Prints an integer, followed by a newline
+    Intrinsic {
+        name: "likho_ginti",
+        params: &[("i", SymType::Int)],
+        ret_type: SymType::Void,
+    },
+    // Reads a line from stdin, sans the newline; empty at EOF
+    Intrinsic {
+        name: "parho",
+        params: &[],
+        ret_type: SymType::String,
+    },
+];
+
+pub fn find_intrinsic(name: &str) -> Option<&'static Intrinsic> {
+    INTRINSICS.iter().find(|i| i.name == name)
+}
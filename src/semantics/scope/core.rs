@@ -2,30 +2,64 @@ use super::recurse::check_for_undeclared_ident;
 use crate::parser::ast::core::*;
 use crate::semantics::{
     errors::ScopeError,
-    spaghetti::{Id, ScopeType, SpaghettiStack, SymInfo},
+    intrinsics::{find_intrinsic, INTRINSICS},
+    spaghetti::{Id, ScopeType, SpaghettiStack, SymInfo, ROOT_ID},
     utils::{find_info_in_table, token_to_symtype},
 };
 
 /// Traverses AST, generating a symbol table (spaghetti stack) as it goes.
 pub fn analyse_scope(ast_root: &TranslationUnit) -> Result<SpaghettiStack, ScopeError> {
+    let (spaghet, res) = analyse_scope_partial(ast_root);
+    res.map(|_| spaghet)
+}
+
+/// Same as `analyse_scope`, but hands back the symbol table even if analysis fails, as it stood
+/// at the point of failure.
+pub fn analyse_scope_partial(
+    ast_root: &TranslationUnit,
+) -> (SpaghettiStack, Result<(), ScopeError>) {
     let mut spaghet: SpaghettiStack = Default::default();
+    let res = populate_root_scope(&mut spaghet, ast_root);
+    (spaghet, res)
+}
+
+fn populate_root_scope(
+    spaghet: &mut SpaghettiStack,
+    ast_root: &TranslationUnit,
+) -> Result<(), ScopeError> {
     let root_id = spaghet.create_scope_map(None, ScopeType::Root);
+    seed_intrinsics(spaghet, root_id);
 
     for decl in ast_root {
         match decl {
             Decl::Var(v) => {
-                insert_var_to_scope(&mut spaghet, root_id, v)?;
+                insert_var_to_scope(spaghet, root_id, v)?;
             }
 
             Decl::Fn(f) => {
-                insert_fn_to_scope(&mut spaghet, root_id, f)?;
-                let fn_table_id = generate_function_scope(&mut spaghet, root_id, f)?;
-                spaghet.add_child(root_id, fn_table_id, Some(f.ident.clone()));
+                insert_fn_to_scope(spaghet, root_id, f)?;
+                generate_function_scope(spaghet, root_id, f)?;
             }
         }
     }
 
-    Ok(spaghet)
+    Ok(())
+}
+
+// Declares every intrinsic in the root scope, w/ a scope of its own holding its params, just like
+// a user-defined function's
+fn seed_intrinsics(spaghet: &mut SpaghettiStack, root_id: Id) {
+    for intrinsic in INTRINSICS {
+        let info = SymInfo::new(false, intrinsic.ret_type);
+        spaghet.insert_ident_in_node(root_id, intrinsic.name, info, false);
+
+        let table_id = spaghet.create_scope_map(Some(root_id), ScopeType::Intrinsic);
+        spaghet.add_child(root_id, table_id, Some(intrinsic.name.to_string()));
+
+        for &(ident, sym_type) in intrinsic.params {
+            spaghet.insert_ident_in_node(table_id, ident, SymInfo::new(true, sym_type), true);
+        }
+    }
 }
 
 /// Analyse a function for scope discrepancies, populating a new symbol table for it
@@ -36,6 +70,7 @@ fn generate_function_scope(
     fn_node: &FnDecl,
 ) -> Result<Id, ScopeError> {
     let fn_table_id = spaghet.create_scope_map(Some(parent_id), ScopeType::FnBlock);
+    spaghet.add_child(parent_id, fn_table_id, Some(fn_node.ident.clone()));
 
     for param in fn_node.params.iter() {
         let sym_type = token_to_symtype(&param.type_tok, true);
@@ -60,14 +95,11 @@ fn analyse_block_scope(
     for stmt in block {
         match stmt {
             Stmt::For(f) => {
-                let for_table_id = generate_for_scope(spaghet, node_id, f)?;
-                spaghet.add_child(node_id, for_table_id, None);
+                generate_for_scope(spaghet, node_id, f)?;
             }
 
             Stmt::If(i) => {
-                let if_table_ids = generate_if_scope(spaghet, node_id, i)?;
-                spaghet.add_child(node_id, if_table_ids.0, None);
-                spaghet.add_child(node_id, if_table_ids.1, None);
+                generate_if_scope(spaghet, node_id, i)?;
             }
 
             Stmt::Expr(es) | Stmt::Ret(es) => {
@@ -78,7 +110,7 @@ fn analyse_block_scope(
                 insert_var_to_scope(spaghet, node_id, v)?;
             }
 
-            Stmt::Break => {} // ignore
+            Stmt::Break(_) => {} // ignore
         }
     }
 
@@ -91,9 +123,10 @@ fn generate_for_scope(
     for_node: &ForStmt,
 ) -> Result<Id, ScopeError> {
     let for_table_id = spaghet.create_scope_map(Some(parent_id), ScopeType::ForBlock);
+    spaghet.add_child(parent_id, for_table_id, None);
 
-    if for_node.init.is_some() {
-        insert_var_to_scope(spaghet, for_table_id, for_node.init.as_ref().unwrap())?;
+    if let Some(init) = &for_node.init {
+        insert_var_to_scope(spaghet, for_table_id, init)?;
     }
 
     // New variables could have been allocated in this for's scope
@@ -112,6 +145,8 @@ fn generate_if_scope(
 ) -> Result<(Id, Id), ScopeError> {
     let if_table_id = spaghet.create_scope_map(Some(parent_id), ScopeType::IfBlock);
     let else_table_id = spaghet.create_scope_map(Some(parent_id), ScopeType::IfBlock);
+    spaghet.add_child(parent_id, if_table_id, None);
+    spaghet.add_child(parent_id, else_table_id, None);
 
     // New variables can not be declared in this if's condition
     check_for_undeclared_ident(spaghet, parent_id, &if_node.cond)?;
@@ -127,6 +162,11 @@ fn insert_var_to_scope(
     node_id: Id,
     v: &VarDecl,
 ) -> Result<(), ScopeError> {
+    // Globals share the root scope w/ intrinsics
+    if node_id == ROOT_ID && find_intrinsic(&v.ident).is_some() {
+        return Err(ScopeError::IntrinsicRedefinition);
+    }
+
     if find_info_in_table(spaghet, node_id, &v.ident, true).is_some() {
         return Err(ScopeError::VariableRedefinition);
     }
@@ -143,6 +183,10 @@ fn insert_fn_to_scope(
     node_id: Id,
     f: &FnDecl,
 ) -> Result<(), ScopeError> {
+    if find_intrinsic(&f.ident).is_some() {
+        return Err(ScopeError::IntrinsicRedefinition);
+    }
+
     if find_info_in_table(spaghet, node_id, &f.ident, false).is_some() {
         return Err(ScopeError::VariableRedefinition);
     }
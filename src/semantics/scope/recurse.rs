@@ -1,4 +1,5 @@
 use crate::parser::ast::core::*;
+use crate::parser::ast::visit::{self, Visitor};
 use crate::semantics::utils::find_info_in_table;
 use crate::semantics::{
     errors::ScopeError,
@@ -12,174 +13,52 @@ pub fn check_for_undeclared_ident(
     node_id: Id,
     expr: &Expr,
 ) -> Result<(), ScopeError> {
-    let Some(assign_e) = expr else {
-        return Ok(());
+    let mut checker = UndeclaredIdentChecker {
+        spaghet,
+        node_id,
+        err: None,
     };
 
-    match assign_e {
-        AssignExpr::Bool(bool_e) => check_bool_expr(spaghet, node_id, bool_e)?,
-        AssignExpr::Assign(bool_e, nested_a) => {
-            check_bool_expr(spaghet, node_id, bool_e)?;
-            // What the literal FUCK
-            let new_expr = &Some(nested_a.as_ref().clone());
-            check_for_undeclared_ident(spaghet, node_id, new_expr)?;
-        }
-    }
-
-    Ok(())
-}
-
-fn check_bool_expr(
-    spaghet: &SpaghettiStack,
-    node_id: Id,
-    expr: &BoolExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        BoolExpr::BitOr(bit_or_e) => check_bit_or_expr(spaghet, node_id, bit_or_e)?,
-        BoolExpr::Bool(bool_e, _, bit_or_e) => {
-            check_bool_expr(spaghet, node_id, bool_e)?;
-            check_bit_or_expr(spaghet, node_id, bit_or_e)?;
-        }
-    }
-    Ok(())
-}
-
-fn check_bit_or_expr(
-    spaghet: &SpaghettiStack,
-    node_id: Id,
-    expr: &BitOrExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        BitOrExpr::BitAnd(bit_and_e) => check_bit_and_expr(spaghet, node_id, bit_and_e)?,
-        BitOrExpr::BitOr(bit_or_e, bit_and_e) => {
-            check_bit_or_expr(spaghet, node_id, bit_or_e)?;
-            check_bit_and_expr(spaghet, node_id, bit_and_e)?;
-        }
-    }
-    Ok(())
-}
-
-fn check_bit_and_expr(
-    spaghet: &SpaghettiStack,
-    node_id: Id,
-    expr: &BitAndExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        BitAndExpr::Comp(comp_e) => check_comp_expr(spaghet, node_id, comp_e)?,
-        BitAndExpr::BitAnd(bit_and_e, comp_e) => {
-            check_bit_and_expr(spaghet, node_id, bit_and_e)?;
-            check_comp_expr(spaghet, node_id, comp_e)?;
-        }
-    }
-    Ok(())
-}
+    checker.visit_expr(expr);
 
-fn check_comp_expr(
-    spaghet: &SpaghettiStack,
-    node_id: Id,
-    expr: &CompExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        CompExpr::Shift(shift_e) => check_shift_expr(spaghet, node_id, shift_e)?,
-        CompExpr::Comp(comp_e, _, shift_e) => {
-            check_comp_expr(spaghet, node_id, comp_e)?;
-            check_shift_expr(spaghet, node_id, shift_e)?;
-        }
+    match checker.err {
+        Some(e) => Err(e),
+        None => Ok(()),
     }
-    Ok(())
 }
 
-fn check_shift_expr(
-    spaghet: &SpaghettiStack,
+struct UndeclaredIdentChecker<'a> {
+    spaghet: &'a SpaghettiStack,
     node_id: Id,
-    expr: &ShiftExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        ShiftExpr::Add(add_e) => check_add_expr(spaghet, node_id, add_e)?,
-        ShiftExpr::Shift(shift_e, _, add_e) => {
-            check_shift_expr(spaghet, node_id, shift_e)?;
-            check_add_expr(spaghet, node_id, add_e)?;
-        }
-    }
-    Ok(())
-}
-
-fn check_add_expr(spaghet: &SpaghettiStack, node_id: Id, expr: &AddExpr) -> Result<(), ScopeError> {
-    match expr {
-        AddExpr::Mul(mul) => check_mul_expr(spaghet, node_id, mul)?,
-        AddExpr::Add(add_e, _, mul_e) => {
-            check_add_expr(spaghet, node_id, add_e)?;
-            check_mul_expr(spaghet, node_id, mul_e)?;
-        }
-    }
-    Ok(())
+    err: Option<ScopeError>,
 }
 
-fn check_mul_expr(spaghet: &SpaghettiStack, node_id: Id, expr: &MulExpr) -> Result<(), ScopeError> {
-    match expr {
-        MulExpr::Exp(exp_e) => check_exp_expr(spaghet, node_id, exp_e)?,
-        MulExpr::Mul(mul_e, _, exp_e) => {
-            check_mul_expr(spaghet, node_id, mul_e)?;
-            check_exp_expr(spaghet, node_id, exp_e)?;
+impl Visitor<'_> for UndeclaredIdentChecker<'_> {
+    fn visit_primary_expr(&mut self, expr: &PrimaryExpr) {
+        // Only the first error is reported
+        if self.err.is_some() {
+            return;
         }
-    }
-    Ok(())
-}
-
-fn check_exp_expr(spaghet: &SpaghettiStack, node_id: Id, expr: &ExpExpr) -> Result<(), ScopeError> {
-    match expr {
-        ExpExpr::Unary(unary_e) => check_unary_expr(spaghet, node_id, unary_e)?,
-        ExpExpr::Exp(unary_e, exp_e) => {
-            check_unary_expr(spaghet, node_id, unary_e)?;
-            check_exp_expr(spaghet, node_id, exp_e)?;
-        }
-    }
-    Ok(())
-}
-
-fn check_unary_expr(
-    spaghet: &SpaghettiStack,
-    node_id: Id,
-    expr: &UnaryExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        UnaryExpr::Primary(primary_e) => check_primary_expr(spaghet, node_id, primary_e)?,
-        UnaryExpr::Unary(_, unary_e) => check_unary_expr(spaghet, node_id, unary_e)?,
-    }
-    Ok(())
-}
 
-fn check_primary_expr(
-    spaghet: &SpaghettiStack,
-    node_id: Id,
-    expr: &PrimaryExpr,
-) -> Result<(), ScopeError> {
-    match expr {
-        PrimaryExpr::Ident(ident) => {
-            if find_info_in_table(spaghet, node_id, ident, true).is_none() {
-                return Err(ScopeError::UndeclaredVariableAccessed);
+        match expr {
+            PrimaryExpr::Ident(ident) => {
+                if find_info_in_table(self.spaghet, self.node_id, ident, true).is_none() {
+                    self.err = Some(ScopeError::UndeclaredVariableAccessed);
+                }
             }
-        }
 
-        PrimaryExpr::Paren(nested_e) => check_for_undeclared_ident(spaghet, node_id, nested_e)?,
+            PrimaryExpr::Call(fn_call) => {
+                if find_info_in_table(self.spaghet, self.node_id, &fn_call.ident, false).is_none() {
+                    self.err = Some(ScopeError::UndefinedFunctionCalled);
+                    return;
+                }
 
-        PrimaryExpr::Call(fn_call) => {
-            if find_info_in_table(spaghet, node_id, &fn_call.ident, false).is_none() {
-                return Err(ScopeError::UndefinedFunctionCalled);
+                // Check all arguments
+                visit::walk_fn_call(self, fn_call);
             }
 
-            // Check all arguments
-            for arg in &fn_call.args {
-                check_for_undeclared_ident(spaghet, node_id, arg)?;
-            }
+            // Parentheses are recursed into, other literals don't need checking
+            _ => visit::walk_primary_expr(self, expr),
         }
-
-        // Other literals don't need checking
-        PrimaryExpr::IntLit(_)
-        | PrimaryExpr::FloatLit(_)
-        | PrimaryExpr::StringLit(_)
-        | PrimaryExpr::BoolLit(_) => {}
     }
-
-    Ok(())
 }
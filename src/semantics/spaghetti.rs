@@ -1,5 +1,5 @@
 use std::collections::{BTreeMap, HashMap};
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 
 // Symbol 'information' usually comprises one of either two things
 // 1. Type information -> simple; less traversal
@@ -17,6 +17,21 @@ pub enum SymType {
     Void,
 }
 
+impl fmt::Display for SymType {
+    /// Writes the type's keyword
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match self {
+            SymType::Int => "ginti",
+            SymType::String => "jumla",
+            SymType::Float => "asharia",
+            SymType::Bool => "boli",
+            SymType::Void => "khali",
+        };
+
+        write!(f, "{keyword}")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SymInfo {
     is_var: bool,
@@ -43,10 +58,14 @@ pub enum ScopeType {
     FnBlock,
     ForBlock,
     IfBlock,
+    Intrinsic, // holds an intrinsic's params; never has children
 }
 
 pub type Id = usize;
 
+/// Id of the root scope, which holds globals and functions
+pub const ROOT_ID: Id = 0;
+
 #[derive(Debug, Clone)]
 struct ChildInfo {
     id: Id,
@@ -133,7 +152,10 @@ impl SpaghettiStack {
 
         if is_param {
             assert!(
-                scope_map.scope_type == ScopeType::FnBlock,
+                matches!(
+                    scope_map.scope_type,
+                    ScopeType::FnBlock | ScopeType::Intrinsic
+                ),
                 "attempted to insert parameter in non-function scope"
             )
         }
@@ -199,10 +221,48 @@ impl SpaghettiStack {
             .clone()
     }
 
+    /// Ids of all scopes, in order of creation (i.e the root's first)
+    pub fn scope_ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.scopes.keys().copied()
+    }
+
+    /// A scope's children, in order of creation, along w/ the names they were given (if any)
+    pub fn get_children(&self, node_id: Id) -> impl Iterator<Item = (Id, Option<&str>)> {
+        self.scopes
+            .get(&node_id)
+            .expect("id should point to valid ScopeMap")
+            .children
+            .iter()
+            .map(|child| (child.id, child.name.as_deref()))
+    }
+
+    /// A scope's symbols, sorted by name
+    pub fn get_symbols(&self, node_id: Id) -> Vec<(&str, &SymInfo)> {
+        let mut symbols = self
+            .scopes
+            .get(&node_id)
+            .expect("id should point to valid ScopeMap")
+            .symbols
+            .iter()
+            .map(|(ident, info)| (ident.as_str(), info))
+            .collect::<Vec<_>>();
+
+        symbols.sort_by_key(|&(ident, _)| ident);
+        symbols
+    }
+
+    /// Types of the parameters of the function whose scope this is; empty for other scopes
+    pub fn get_scope_param_types(&self, node_id: Id) -> &Vec<SymType> {
+        self.scopes
+            .get(&node_id)
+            .expect("id should point to valid ScopeMap")
+            .get_param_types()
+    }
+
     pub fn get_fn_param_types(&self, ident: &str) -> &Vec<SymType> {
         let child_id = self
             .scopes
-            .get(&0)
+            .get(&ROOT_ID)
             .expect("id should point to valid ScopeMap")
             .children
             .iter()
@@ -3,22 +3,20 @@ use crate::{
     parser::ast::core::*,
     semantics::{
         errors::TypeChkError,
-        spaghetti::{Id, ScopeType, SpaghettiStack, SymType},
+        spaghetti::{Id, ScopeType, SpaghettiStack, SymType, ROOT_ID},
         utils::token_to_symtype,
     },
 };
 
-const ROOT_ID: Id = 0;
-
 // How many nested scopes of diff. types have we encountered within a given scope?
-struct ScopeTypeCounter {
-    _fn: usize,
-    _for: usize,
-    _if: usize,
+pub(super) struct ScopeTypeCounter {
+    pub _fn: usize,
+    pub _for: usize,
+    pub _if: usize,
 }
 
 impl ScopeTypeCounter {
-    fn new() -> ScopeTypeCounter {
+    pub fn new() -> ScopeTypeCounter {
         ScopeTypeCounter {
             _fn: 0,
             _for: 0,
@@ -133,7 +131,7 @@ fn check_block(
 
             Stmt::VarDecl(v) => check_var_decl(spaghet, v, node_id)?,
 
-            Stmt::Break => {
+            Stmt::Break(_) => {
                 // Iterate up to the top and check if we're in a for loop at any point in time
                 // FIXME: only iterate till the first function scope
                 let mut curr_id: Option<Id> = Some(node_id);
@@ -148,14 +146,13 @@ fn check_block(
 
             Stmt::Expr(e) => {
                 let _ = get_expr_type(spaghet, &e.expr, node_id)?;
-                return Ok(()); // CHECK(unnecessary/incorrect ret): I don't think this should be here...?
             }
         }
     }
     Ok(())
 }
 
-fn get_nth_child_of_type(
+pub(super) fn get_nth_child_of_type(
     symbol_table: &SpaghettiStack,
     node_id: Id,
     ctr: usize,
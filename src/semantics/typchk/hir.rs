@@ -0,0 +1,148 @@
+use std::fmt::Write;
+
+use super::{
+    core::{get_nth_child_of_type, ScopeTypeCounter},
+    recurse::get_expr_type,
+};
+use crate::{
+    formatter::expr::format_expr,
+    parser::ast::core::*,
+    semantics::{
+        spaghetti::{Id, ScopeType, SpaghettiStack, ROOT_ID},
+        utils::token_to_symtype,
+    },
+};
+
+/// Renders the program as the semantic passes see it: every block tagged w/ the id of the
+/// `ScopeMap` it was resolved to, and every expression w/ the type the checker inferred for it.
+pub fn hir_to_string(ast_root: &TranslationUnit, spaghet: &SpaghettiStack) -> String {
+    let mut p = HirPrinter {
+        spaghet,
+        out: String::new(),
+        indent: 0,
+    };
+
+    p.line(&format!("root [scope {ROOT_ID}]"));
+    p.indent += 1;
+
+    let mut ctr = ScopeTypeCounter::new();
+    for decl in ast_root {
+        match decl {
+            Decl::Var(v) => p.var_decl("var", v, ROOT_ID),
+            Decl::Fn(f) => {
+                ctr._fn += 1;
+                let node_id = get_nth_child_of_type(spaghet, ROOT_ID, ctr._fn, ScopeType::FnBlock);
+                p.fn_decl(f, node_id);
+            }
+        }
+    }
+
+    p.out
+}
+
+struct HirPrinter<'a> {
+    spaghet: &'a SpaghettiStack,
+    out: String,
+    indent: usize,
+}
+
+impl HirPrinter<'_> {
+    fn fn_decl(&mut self, f: &FnDecl, node_id: Id) {
+        let params = f
+            .params
+            .iter()
+            .map(|p| format!("{}: {}", p.ident, p.type_tok))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.line(&format!(
+            "fn {}({params}) -> {} [scope {node_id}]",
+            f.ident, f.type_tok
+        ));
+        self.block(&f.block, node_id);
+    }
+
+    fn var_decl(&mut self, label: &str, v: &VarDecl, node_id: Id) {
+        let ty = token_to_symtype(&v.type_tok, true);
+        let init = self.typed_expr(&v.expr, node_id);
+        self.line(&format!("{label} {}: {ty} = {init}", v.ident));
+    }
+
+    fn block(&mut self, block: &Block, node_id: Id) {
+        self.indent += 1;
+
+        let mut ctr = ScopeTypeCounter::new();
+        for stmt in block {
+            match stmt {
+                Stmt::VarDecl(v) => self.var_decl("var", v, node_id),
+                Stmt::Expr(e) => {
+                    let expr = self.typed_expr(&e.expr, node_id);
+                    self.line(&format!("expr {expr}"));
+                }
+                Stmt::Ret(r) => {
+                    let expr = self.typed_expr(&r.expr, node_id);
+                    self.line(&format!("wapsi {expr}"));
+                }
+                Stmt::Break(_) => self.line("toro"),
+
+                Stmt::For(f) => {
+                    ctr._for += 1;
+                    let for_id =
+                        get_nth_child_of_type(self.spaghet, node_id, ctr._for, ScopeType::ForBlock);
+
+                    self.line(&format!("duhrao [scope {for_id}]"));
+                    self.indent += 1;
+                    match &f.init {
+                        Some(init) => self.var_decl("init", init, for_id),
+                        None => self.line("init <empty>"),
+                    }
+                    let cond = self.typed_expr(&f.cond.expr, for_id);
+                    self.line(&format!("cond {cond}"));
+                    let updt = self.typed_expr(&f.updt, for_id);
+                    self.line(&format!("updt {updt}"));
+                    self.indent -= 1;
+                    self.block(&f.block, for_id);
+                }
+
+                Stmt::If(i) => {
+                    let cond = self.typed_expr(&i.cond, node_id);
+                    self.line(&format!("agar {cond}"));
+
+                    for (label, block) in [("sach", &i.if_block), ("warna", &i.else_block)] {
+                        ctr._if += 1;
+                        let if_id = get_nth_child_of_type(
+                            self.spaghet,
+                            node_id,
+                            ctr._if,
+                            ScopeType::IfBlock,
+                        );
+
+                        self.indent += 1;
+                        self.line(&format!("{label} [scope {if_id}]"));
+                        self.block(block, if_id);
+                        self.indent -= 1;
+                    }
+                }
+            }
+        }
+
+        self.indent -= 1;
+    }
+
+    // `<expr> : <type>`; expressions the checker never got to (and would've rejected) are marked
+    // as such rather than aborting the dump
+    fn typed_expr(&self, expr: &Expr, node_id: Id) -> String {
+        let Some(e) = expr else {
+            return "<empty>".to_string();
+        };
+
+        match get_expr_type(self.spaghet, expr, node_id) {
+            Ok(ty) => format!("{} : {}", format_expr(e), ty),
+            Err(err) => format!("{} : <{err:?}>", format_expr(e)),
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{}{text}", "    ".repeat(self.indent));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     lexer::Token,
-    parser::ast::core::*,
+    parser::ast::{
+        core::*,
+        visit::{self, Visitor},
+    },
     semantics::{
         errors::TypeChkError,
         spaghetti::{Id, SpaghettiStack, SymInfo, SymType},
@@ -14,230 +17,268 @@ pub fn get_expr_type(
     expr: &Expr,
     node_id: Id,
 ) -> Result<SymType, TypeChkError> {
-    let Some(assign_expr) = expr else {
-        return Ok(SymType::Void);
+    let mut checker = TypeChecker {
+        spaghet,
+        node_id,
+        types: vec![],
+        err: None,
     };
 
-    match assign_expr {
-        AssignExpr::Bool(bool_e) => get_bool_expr_type(spaghet, bool_e, node_id),
-
-        AssignExpr::Assign(bool_e, nested_assign) => {
-            let lhs_type = get_bool_expr_type(spaghet, bool_e, node_id)?;
-            let new_expr = &Some(nested_assign.as_ref().clone());
-            let rhs_type = get_expr_type(spaghet, new_expr, node_id)?;
-
-            // NOTE: type conversion rules would apply here, had I chosen to support them in
-            // Nuktah. Probably via a call to another function that validates if the RHS type can
-            // safely be converted to the LHS type.
+    checker.visit_expr(expr);
 
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
-
-            Ok(lhs_type)
-        }
+    match checker.err {
+        Some(e) => Err(e),
+        None => Ok(checker
+            .types
+            .pop()
+            .expect("the expression to have been typed")),
     }
 }
 
-fn get_bool_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &BoolExpr,
+// Types expressions bottom-up: each one visited leaves its type on `types`, for the operation
+// it's an operand of to take. Operands are visited in the order they were always checked in, so
+// that the same error is reported when there are several.
+struct TypeChecker<'a> {
+    spaghet: &'a SpaghettiStack,
     node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        BoolExpr::BitOr(bit_or_e) => get_bit_or_expr_type(spaghet, bit_or_e, node_id),
-
-        BoolExpr::Bool(bool_e, _, bit_or_e) => {
-            if get_bool_expr_type(spaghet, bool_e, node_id)? != SymType::Bool
-                || get_bit_or_expr_type(spaghet, bit_or_e, node_id)? != SymType::Bool
-            {
-                return Err(TypeChkError::AttemptedBoolOpOnNonBools);
-            }
+    types: Vec<SymType>,
+    err: Option<TypeChkError>, // only the first is reported; nothing is typed after it
+}
+
+impl TypeChecker<'_> {
+    fn push(&mut self, res: Result<SymType, TypeChkError>) {
+        if self.err.is_some() {
+            return;
+        }
 
-            Ok(SymType::Bool)
+        match res {
+            Ok(sym_type) => self.types.push(sym_type),
+            Err(e) => self.err = Some(e),
         }
     }
-}
 
-fn get_bit_or_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &BitOrExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        BitOrExpr::BitAnd(bit_and_e) => get_bit_and_expr_type(spaghet, bit_and_e, node_id),
-        BitOrExpr::BitOr(bit_or_e, bit_and_e) => {
-            let lhs_type = get_bit_and_expr_type(spaghet, bit_and_e, node_id)?;
-            let rhs_type = get_bit_or_expr_type(spaghet, bit_or_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
+    // The type of the expression just visited, unless checking has already failed
+    fn take(&mut self) -> Option<SymType> {
+        match self.err {
+            Some(_) => None,
+            None => self.types.pop(),
+        }
+    }
 
-            if ![SymType::Int, SymType::Float].contains(&lhs_type) {
-                return Err(TypeChkError::AttemptedBitOpOnNonNumeric);
-            }
+    // Types an operation on the expression just visited
+    fn unary(&mut self, rule: impl FnOnce(SymType) -> Result<SymType, TypeChkError>) {
+        if let Some(operand) = self.take() {
+            self.push(rule(operand));
+        }
+    }
 
-            Ok(lhs_type)
+    // Types an operation on the two expressions just visited, the first of them as `lhs`
+    fn binary(&mut self, rule: impl FnOnce(SymType, SymType) -> Result<SymType, TypeChkError>) {
+        if let (Some(rhs), Some(lhs)) = (self.take(), self.take()) {
+            self.push(rule(lhs, rhs));
         }
     }
 }
 
-fn get_bit_and_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &BitAndExpr,
-    node_id: Id,
+// Both operands of an arithmetic/bitwise operator are of the same type, which must be one of
+// `allowed`
+fn same_type_of(
+    lhs_type: SymType,
+    rhs_type: SymType,
+    allowed: &[SymType],
+    err: TypeChkError,
 ) -> Result<SymType, TypeChkError> {
-    match expr {
-        BitAndExpr::Comp(comp_e) => get_comp_expr_type(spaghet, comp_e, node_id),
-        BitAndExpr::BitAnd(bit_and_e, comp_e) => {
-            let lhs_type = get_comp_expr_type(spaghet, comp_e, node_id)?;
-            let rhs_type = get_bit_and_expr_type(spaghet, bit_and_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
+    if lhs_type != rhs_type {
+        return Err(TypeChkError::ExpressionTypeMismatch);
+    }
 
-            if ![SymType::Int, SymType::Float].contains(&lhs_type) {
-                return Err(TypeChkError::AttemptedBitOpOnNonNumeric);
-            }
+    if !allowed.contains(&lhs_type) {
+        return Err(err);
+    }
 
-            Ok(lhs_type)
-        }
+    Ok(lhs_type)
+}
+
+fn bool_operand(operand_type: SymType) -> Result<SymType, TypeChkError> {
+    match operand_type {
+        SymType::Bool => Ok(SymType::Bool),
+        _ => Err(TypeChkError::AttemptedBoolOpOnNonBools),
     }
 }
 
-fn get_comp_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &CompExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        CompExpr::Shift(shift_e) => get_shift_expr_type(spaghet, shift_e, node_id),
-        CompExpr::Comp(comp_e, _, shift_e) => {
-            let lhs_type = get_shift_expr_type(spaghet, shift_e, node_id)?;
-            let rhs_type = get_comp_expr_type(spaghet, comp_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
+const NUMERIC: [SymType; 2] = [SymType::Int, SymType::Float];
 
-            // Comparison always produces a bool
-            Ok(SymType::Bool)
+impl Visitor<'_> for TypeChecker<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Some(assign_e) => self.visit_assign_expr(assign_e),
+            None => self.push(Ok(SymType::Void)),
         }
     }
-}
 
-fn get_shift_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &ShiftExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        ShiftExpr::Add(add_e) => get_add_expr_type(spaghet, add_e, node_id),
-        ShiftExpr::Shift(shift_e, _, add_e) => {
-            let lhs_type = get_add_expr_type(spaghet, add_e, node_id)?;
-            let rhs_type = get_shift_expr_type(spaghet, shift_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
-
-            if lhs_type != SymType::Int {
-                return Err(TypeChkError::AttemptedShiftOnNonInt);
-            }
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        let AssignExpr::Assign(bool_e, nested_assign) = expr else {
+            return visit::walk_assign_expr(self, expr);
+        };
 
-            Ok(lhs_type)
+        // Only a variable has somewhere to store the value; `1 = 2` would check otherwise
+        if bool_e.as_ident().is_none() {
+            return self.push(Err(TypeChkError::InvalidAssignmentTarget));
         }
+
+        self.visit_bool_expr(bool_e);
+        self.visit_assign_expr(nested_assign);
+
+        // NOTE: type conversion rules would apply here, had I chosen to support them in
+        // Nuktah. Probably via a call to another function that validates if the RHS type can
+        // safely be converted to the LHS type.
+        self.binary(|lhs_type, rhs_type| match lhs_type == rhs_type {
+            true => Ok(lhs_type),
+            false => Err(TypeChkError::ExpressionTypeMismatch),
+        });
     }
-}
 
-fn get_add_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &AddExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        AddExpr::Mul(mul_e) => get_mul_expr_type(spaghet, mul_e, node_id),
-        AddExpr::Add(add_e, _, mul_e) => {
-            let lhs_type = get_mul_expr_type(spaghet, mul_e, node_id)?;
-            let rhs_type = get_add_expr_type(spaghet, add_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
+    fn visit_bool_expr(&mut self, expr: &BoolExpr) {
+        let BoolExpr::Bool(bool_e, _, bit_or_e) = expr else {
+            return visit::walk_bool_expr(self, expr);
+        };
 
-            if ![SymType::Int, SymType::Float].contains(&lhs_type) {
-                return Err(TypeChkError::AttemptedAddOpOnNonNumeric);
-            }
+        // The right operand isn't looked at if the left one's already wrong
+        self.visit_bool_expr(bool_e);
+        self.unary(bool_operand);
+        self.visit_bit_or_expr(bit_or_e);
+        self.binary(|_, rhs_type| bool_operand(rhs_type));
+    }
 
-            Ok(lhs_type)
-        }
+    fn visit_bit_or_expr(&mut self, expr: &BitOrExpr) {
+        let BitOrExpr::BitOr(bit_or_e, bit_and_e) = expr else {
+            return visit::walk_bit_or_expr(self, expr);
+        };
+
+        self.visit_bit_and_expr(bit_and_e);
+        self.visit_bit_or_expr(bit_or_e);
+
+        // Floats have no meaningful bitwise representation to operate on
+        self.binary(|lhs_type, rhs_type| {
+            same_type_of(
+                lhs_type,
+                rhs_type,
+                &[SymType::Int],
+                TypeChkError::AttemptedBitOpOnNonNumeric,
+            )
+        });
     }
-}
 
-fn get_mul_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &MulExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        MulExpr::Exp(exp_e) => get_exp_expr_type(spaghet, exp_e, node_id),
-        MulExpr::Mul(mul_e, _, exp_e) => {
-            let lhs_type = get_exp_expr_type(spaghet, exp_e, node_id)?;
-            let rhs_type = get_mul_expr_type(spaghet, mul_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
+    fn visit_bit_and_expr(&mut self, expr: &BitAndExpr) {
+        let BitAndExpr::BitAnd(bit_and_e, comp_e) = expr else {
+            return visit::walk_bit_and_expr(self, expr);
+        };
+
+        self.visit_comp_expr(comp_e);
+        self.visit_bit_and_expr(bit_and_e);
+        self.binary(|lhs_type, rhs_type| {
+            same_type_of(
+                lhs_type,
+                rhs_type,
+                &[SymType::Int],
+                TypeChkError::AttemptedBitOpOnNonNumeric,
+            )
+        });
+    }
 
-            if ![SymType::Int, SymType::Float].contains(&lhs_type) {
-                return Err(TypeChkError::AttemptedBitOpOnNonNumeric);
-            }
+    fn visit_comp_expr(&mut self, expr: &CompExpr) {
+        let CompExpr::Comp(comp_e, _, shift_e) = expr else {
+            return visit::walk_comp_expr(self, expr);
+        };
 
-            Ok(lhs_type)
-        }
+        self.visit_shift_expr(shift_e);
+        self.visit_comp_expr(comp_e);
+
+        // Comparison always produces a bool
+        self.binary(|lhs_type, rhs_type| match lhs_type == rhs_type {
+            true => Ok(SymType::Bool),
+            false => Err(TypeChkError::ExpressionTypeMismatch),
+        });
     }
-}
 
-fn get_exp_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &ExpExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        ExpExpr::Unary(unary_e) => get_unary_expr_type(spaghet, unary_e, node_id),
-        ExpExpr::Exp(unary_e, exp_e) => {
-            let lhs_type = get_unary_expr_type(spaghet, unary_e, node_id)?;
-            let rhs_type = get_exp_expr_type(spaghet, exp_e, node_id)?;
-
-            if lhs_type != rhs_type {
-                return Err(TypeChkError::ExpressionTypeMismatch);
-            }
+    fn visit_shift_expr(&mut self, expr: &ShiftExpr) {
+        let ShiftExpr::Shift(shift_e, _, add_e) = expr else {
+            return visit::walk_shift_expr(self, expr);
+        };
+
+        self.visit_add_expr(add_e);
+        self.visit_shift_expr(shift_e);
+        self.binary(|lhs_type, rhs_type| {
+            same_type_of(
+                lhs_type,
+                rhs_type,
+                &[SymType::Int],
+                TypeChkError::AttemptedShiftOnNonInt,
+            )
+        });
+    }
 
-            if ![SymType::Int, SymType::Float].contains(&lhs_type) {
-                return Err(TypeChkError::AttemptedExponentiationOfNonNumeric);
-            }
+    fn visit_add_expr(&mut self, expr: &AddExpr) {
+        let AddExpr::Add(add_e, _, mul_e) = expr else {
+            return visit::walk_add_expr(self, expr);
+        };
+
+        self.visit_mul_expr(mul_e);
+        self.visit_add_expr(add_e);
+        self.binary(|lhs_type, rhs_type| {
+            same_type_of(
+                lhs_type,
+                rhs_type,
+                &NUMERIC,
+                TypeChkError::AttemptedAddOpOnNonNumeric,
+            )
+        });
+    }
 
-            Ok(lhs_type)
-        }
+    fn visit_mul_expr(&mut self, expr: &MulExpr) {
+        let MulExpr::Mul(mul_e, _, exp_e) = expr else {
+            return visit::walk_mul_expr(self, expr);
+        };
+
+        self.visit_exp_expr(exp_e);
+        self.visit_mul_expr(mul_e);
+        self.binary(|lhs_type, rhs_type| {
+            same_type_of(
+                lhs_type,
+                rhs_type,
+                &NUMERIC,
+                TypeChkError::AttemptedBitOpOnNonNumeric,
+            )
+        });
     }
-}
 
-fn get_unary_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &UnaryExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        UnaryExpr::Primary(primary_e) => get_primary_expr_type(spaghet, primary_e, node_id),
+    fn visit_exp_expr(&mut self, expr: &ExpExpr) {
+        let ExpExpr::Exp(unary_e, exp_e) = expr else {
+            return visit::walk_exp_expr(self, expr);
+        };
+
+        self.visit_unary_expr(unary_e);
+        self.visit_exp_expr(exp_e);
+        self.binary(|lhs_type, rhs_type| {
+            same_type_of(
+                lhs_type,
+                rhs_type,
+                &NUMERIC,
+                TypeChkError::AttemptedExponentiationOfNonNumeric,
+            )
+        });
+    }
 
-        UnaryExpr::Unary(tok, unary_e) => {
-            let primary_e_type = get_unary_expr_type(spaghet, unary_e, node_id)?;
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        let UnaryExpr::Unary(tok, unary_e) = expr else {
+            return visit::walk_unary_expr(self, expr);
+        };
 
+        self.visit_unary_expr(unary_e);
+        self.unary(|primary_e_type| {
             match tok {
                 Token::SubOp => {
-                    if ![SymType::Int, SymType::Float].contains(&primary_e_type) {
+                    if !NUMERIC.contains(&primary_e_type) {
                         return Err(TypeChkError::AttemptedAddOpOnNonNumeric);
                     }
                 }
@@ -252,52 +293,54 @@ fn get_unary_expr_type(
             }
 
             Ok(primary_e_type)
-        }
-    }
-}
-
-fn get_primary_expr_type(
-    spaghet: &SpaghettiStack,
-    expr: &PrimaryExpr,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    match expr {
-        PrimaryExpr::Ident(ident) => {
-            let sym_info = fetch_guaranteed_info_from_table(spaghet, ident, node_id, true);
-            Ok(sym_info.get_type())
-        }
-        PrimaryExpr::Paren(nested_e) => get_expr_type(spaghet, nested_e, node_id),
-        PrimaryExpr::Call(fn_call) => get_fn_call_type(spaghet, fn_call, node_id),
-        PrimaryExpr::IntLit(_) => Ok(SymType::Int),
-        PrimaryExpr::FloatLit(_) => Ok(SymType::Float),
-        PrimaryExpr::StringLit(_) => Ok(SymType::String),
-        PrimaryExpr::BoolLit(_) => Ok(SymType::Bool),
+        });
     }
-}
 
-fn get_fn_call_type(
-    spaghet: &SpaghettiStack,
-    fn_call: &FnCall,
-    node_id: Id,
-) -> Result<SymType, TypeChkError> {
-    let fn_type = fetch_guaranteed_info_from_table(spaghet, &fn_call.ident, node_id, false);
-    let param_types = spaghet.get_fn_param_types(&fn_call.ident);
+    fn visit_primary_expr(&mut self, expr: &PrimaryExpr) {
+        let primary_e_type = match expr {
+            PrimaryExpr::Ident(ident) => {
+                fetch_guaranteed_info_from_table(self.spaghet, ident, self.node_id, true).get_type()
+            }
+            PrimaryExpr::IntLit(_) => SymType::Int,
+            PrimaryExpr::FloatLit(_) => SymType::Float,
+            PrimaryExpr::StringLit(_) => SymType::String,
+            PrimaryExpr::BoolLit(_) => SymType::Bool,
+
+            // Parentheses and calls are typed by what they hold
+            PrimaryExpr::Paren(_) | PrimaryExpr::Call(_) => {
+                return visit::walk_primary_expr(self, expr)
+            }
+        };
 
-    if param_types.len() != fn_call.args.len() {
-        return Err(TypeChkError::FnCallParamCount);
+        self.push(Ok(primary_e_type));
     }
 
-    for (i, arg) in fn_call.args.iter().enumerate() {
-        if arg.is_none() {
-            unreachable!("argument to function is of type None");
+    fn visit_fn_call(&mut self, fn_call: &FnCall) {
+        let fn_type =
+            fetch_guaranteed_info_from_table(self.spaghet, &fn_call.ident, self.node_id, false);
+        let param_types = self.spaghet.get_fn_param_types(&fn_call.ident);
+
+        if param_types.len() != fn_call.args.len() {
+            return self.push(Err(TypeChkError::FnCallParamCount));
         }
 
-        if get_expr_type(spaghet, arg, node_id)? != param_types[i] {
-            return Err(TypeChkError::FnCallParamType);
+        for (i, arg) in fn_call.args.iter().enumerate() {
+            if arg.is_none() {
+                unreachable!("argument to function is of type None");
+            }
+
+            self.visit_expr(arg);
+            match self.take() {
+                Some(arg_type) if arg_type != param_types[i] => {
+                    return self.push(Err(TypeChkError::FnCallParamType))
+                }
+                Some(_) => {}
+                None => return,
+            }
         }
-    }
 
-    Ok(fn_type.get_type())
+        self.push(Ok(fn_type.get_type()));
+    }
 }
 
 fn fetch_guaranteed_info_from_table(
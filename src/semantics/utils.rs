@@ -36,9 +36,9 @@ pub fn find_info_in_table(
             if ret.is_var() == is_var {
                 return Some(ret.clone());
             }
-        } else {
-            curr_id = symbol_table.get_node_parent_id(curr_id.unwrap());
         }
+
+        curr_id = symbol_table.get_node_parent_id(curr_id.unwrap());
     }
 
     None
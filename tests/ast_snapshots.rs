@@ -0,0 +1,51 @@
+//! Snapshot tests for the AST printer, run over every program in `examples/`.
+//!
+//! Set `NKT_UPDATE_SNAPSHOTS=1` to regenerate the files under `tests/snapshots/ast/`.
+
+use std::fs;
+use std::path::Path;
+
+use nuktah::{parse_src, parser::ast::print::ast_to_string};
+
+#[test]
+fn ast_snapshots_match_examples() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let snapshot_dir = root.join("tests/snapshots/ast");
+    let update = std::env::var_os("NKT_UPDATE_SNAPSHOTS").is_some();
+
+    let mut examples = fs::read_dir(root.join("examples"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "nkt"))
+        .collect::<Vec<_>>();
+    examples.sort();
+    assert!(!examples.is_empty(), "no examples found");
+
+    let mut mismatches = vec![];
+
+    for example in examples {
+        let src = fs::read_to_string(&example).unwrap();
+        let actual = match parse_src(&src) {
+            Ok(ast_root) => ast_to_string(&ast_root),
+            Err(e) => format!("{e:?}\n"),
+        };
+
+        let snapshot = snapshot_dir.join(example.with_extension("ast").file_name().unwrap());
+
+        if update {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&snapshot)
+            .unwrap_or_else(|_| panic!("missing snapshot {}", snapshot.display()));
+        if expected != actual {
+            mismatches.push(snapshot.display().to_string());
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "AST snapshots differ (rerun w/ NKT_UPDATE_SNAPSHOTS=1 to accept): {mismatches:?}"
+    );
+}
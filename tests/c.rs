@@ -0,0 +1,103 @@
+//! The C backend: translations compiled w/ the system's `cc` print, read and exit w/ the same code
+//! as the programs do when interpreted, evaluating operands in the same order, and failing the
+//! same way.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::*;
+use nuktah::{codegen::c::core as c, compile_src, semantics::core::SrcKind};
+
+// Translates `src`, builds it w/ `cc`, and runs it w/ `args` (its path first) and `stdin`
+fn run_c(src: &str, args: &[String], stdin: &str) -> Output {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap();
+    let code = c::generate(&artifacts.ast);
+
+    let stem = std::env::temp_dir().join(format!(
+        "nkt-c-{}-{:x}",
+        std::process::id(),
+        hash(&(src, args))
+    ));
+    let (c_path, exe) = (stem.with_extension("c"), stem.with_extension("out"));
+    std::fs::write(&c_path, &code).unwrap();
+
+    let cc = Command::new("cc")
+        .args(["-std=c11", "-pedantic-errors", "-O2", "-o"])
+        .arg(&exe)
+        .arg(&c_path)
+        .arg("-lm")
+        .output()
+        .unwrap();
+    assert!(
+        cc.status.success(),
+        "{}\n{code}",
+        String::from_utf8_lossy(&cc.stderr)
+    );
+
+    let output = run_piped(Command::new(&exe).args(&args[1..]), stdin);
+    std::fs::remove_file(&c_path).unwrap();
+    std::fs::remove_file(&exe).unwrap();
+    output
+}
+
+fn assert_matches_interp(src: &str, stdin: &str) {
+    for (args, code, out) in interp_runs(src, stdin) {
+        let output = run_c(src, &args, stdin);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            out,
+            "w/ {args:?}:\n{}",
+            c::generate(&compile_src(src, SrcKind::Program).unwrap().ast)
+        );
+        assert_eq!(
+            output.status.code(),
+            Some(code as u8 as i32),
+            "w/ {args:?}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn integer_arithmetic_wraps() {
+    assert_matches_interp(ARITHMETIC, "");
+}
+
+#[test]
+fn floats() {
+    assert_matches_interp(FLOATS, "");
+}
+
+#[test]
+fn strings_and_input() {
+    for stdin in STRINGS_INPUTS {
+        assert_matches_interp(STRINGS, stdin);
+    }
+}
+
+#[test]
+fn calls_and_globals() {
+    assert_matches_interp(CALLS, "");
+}
+
+#[test]
+fn operands_are_evaluated_left_to_right() {
+    assert_matches_interp(ORDER, "");
+}
+
+#[test]
+fn runtime_errors_exit_w_1() {
+    for (argc, err) in RUNTIME_ERROR_CASES {
+        let args = vec![String::new(); argc];
+        let output = run_c(RUNTIME_ERRORS, &args, "");
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            format!("runtime error: {err}\n")
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Control-flow analyses over lowered functions: dominators, dominance frontiers and natural loops.
+
+use std::collections::BTreeSet;
+
+use nuktah::{
+    compile_src,
+    ir::{
+        cfg::{natural_loops, remove_unreachable_blocks, Cfg, Loop},
+        core::{Block, BlockId, Function, Module, Terminator},
+        dom::DomTree,
+        lower::lower,
+    },
+    semantics::{core::SrcKind, spaghetti::SymType},
+};
+
+fn lower_src(src: &str) -> Module {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap_or_else(|e| panic!("{e:?}"));
+    lower(&artifacts.ast)
+}
+
+fn blocks(ids: &[u32]) -> Vec<BlockId> {
+    ids.iter().copied().map(BlockId).collect()
+}
+
+// bb0 -> bb1 (outer cond) -> bb2 -> bb5 (inner cond) -> bb6 (agar) -> bb9 (toro) | bb10 -> bb11
+// -> bb7 (inner updt) -> bb5; bb8 (inner exit) -> bb3 (outer updt) -> bb1; bb4 (outer exit)
+const NESTED_LOOPS: &str = "
+fn ginti shuru() {
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < 3 . i = i + 1) {
+        duhrao (ginti j = 0 . j < i . j = j + 1) {
+            agar (j == 2) { toro . } warna {}
+            s = s + j .
+        }
+    }
+    wapsi s .
+} .
+";
+
+#[test]
+fn dominators_of_nested_loops() {
+    let module = lower_src(NESTED_LOOPS);
+    let f = &module.functions[module.entry.unwrap() as usize];
+    let cfg = Cfg::new(f);
+    let dom = DomTree::new(&cfg);
+
+    assert_eq!(cfg.preds(BlockId(8)), blocks(&[5, 9]));
+    assert_eq!(cfg.succs(BlockId(6)), blocks(&[9, 10]));
+
+    // Reverse post-order puts every block before its successors, back edges aside
+    assert_eq!(cfg.rpo().len(), f.blocks.len());
+    for id in f.block_ids() {
+        for &succ in cfg.succs(id) {
+            let is_back_edge = dom.dominates(succ, id);
+            assert_eq!(cfg.rpo_index(id) < cfg.rpo_index(succ), !is_back_edge);
+        }
+    }
+
+    let idoms = f.block_ids().map(|id| dom.idom(id)).collect::<Vec<_>>();
+    let expected = [None, Some(0), Some(1), Some(8), Some(1), Some(2)]
+        .into_iter()
+        .chain([Some(5), Some(11), Some(5), Some(6), Some(6), Some(10)])
+        .map(|idom| idom.map(BlockId))
+        .collect::<Vec<_>>();
+    assert_eq!(idoms, expected);
+
+    assert!(dom.dominates(BlockId(5), BlockId(7)));
+    assert!(dom.dominates(BlockId(7), BlockId(7)));
+    assert!(!dom.dominates(BlockId(9), BlockId(8)));
+    assert_eq!(dom.pre_order()[..4], blocks(&[0, 1, 4, 2]));
+
+    let frontiers = dom
+        .frontiers(&cfg)
+        .into_iter()
+        .map(|mut df| {
+            df.sort();
+            df
+        })
+        .collect::<Vec<_>>();
+    let expected = [&[][..], &[1], &[1], &[1], &[], &[1, 5], &[5, 8]]
+        .into_iter()
+        .chain([&[5][..], &[1], &[8], &[5], &[5]])
+        .map(blocks)
+        .collect::<Vec<_>>();
+    assert_eq!(frontiers, expected);
+}
+
+#[test]
+fn finds_natural_loops() {
+    let module = lower_src(NESTED_LOOPS);
+    let f = &module.functions[module.entry.unwrap() as usize];
+    let cfg = Cfg::new(f);
+    let loops = natural_loops(&cfg, &DomTree::new(&cfg));
+
+    let body = |ids: &[u32]| ids.iter().copied().map(BlockId).collect::<BTreeSet<_>>();
+    assert_eq!(
+        loops,
+        [
+            Loop {
+                header: BlockId(1),
+                latches: blocks(&[3]),
+                body: body(&[1, 2, 3, 5, 6, 7, 8, 9, 10, 11]),
+            },
+            Loop {
+                header: BlockId(5),
+                latches: blocks(&[7]),
+                body: body(&[5, 6, 7, 10, 11]),
+            },
+        ]
+    );
+
+    // The inner loop is left by its condition failing, or by `toro`
+    assert_eq!(
+        loops[1].exits(&cfg),
+        [(BlockId(5), BlockId(8)), (BlockId(6), BlockId(9))]
+    );
+
+    let straight_line =
+        lower_src("fn ginti shuru() { agar (sach) { wapsi 1 . } warna {} wapsi 0 . } .");
+    let f = &straight_line.functions[0];
+    let cfg = Cfg::new(f);
+    assert!(natural_loops(&cfg, &DomTree::new(&cfg)).is_empty());
+}
+
+#[test]
+fn unreachable_blocks() {
+    let block = |term| Block {
+        insts: vec![],
+        term,
+    };
+
+    // bb1 is only reachable from bb3, which isn't reachable at all
+    let mut f = Function {
+        name: "f".to_string(),
+        params: vec![],
+        ret_type: SymType::Void,
+        reg_types: vec![],
+        blocks: vec![
+            block(Terminator::Jump(BlockId(2))),
+            block(Terminator::Jump(BlockId(2))),
+            block(Terminator::Ret(None)),
+            block(Terminator::Jump(BlockId(1))),
+        ],
+    };
+
+    let cfg = Cfg::new(&f);
+    assert_eq!(cfg.rpo(), blocks(&[0, 2]));
+    assert!(!cfg.is_reachable(BlockId(1)));
+    assert_eq!(cfg.preds(BlockId(2)), blocks(&[0, 1]));
+
+    let dom = DomTree::new(&cfg);
+    assert_eq!(dom.idom(BlockId(2)), Some(BlockId(0)));
+    assert_eq!(dom.idom(BlockId(1)), None);
+
+    remove_unreachable_blocks(&mut f);
+    assert_eq!(f.blocks.len(), 2);
+    assert_eq!(f.blocks[0].term, Terminator::Jump(BlockId(1)));
+}
@@ -0,0 +1,245 @@
+//! What the backends' tests share: programs exercising each part of the language, and the
+//! interpreter, which what they compile to is checked against.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use nuktah::{compile_src, interp::core as interp, semantics::core::SrcKind};
+
+pub const ARITHMETIC: &str = "
+fn ginti shuru(ginti argc) {
+    ginti n = 0 - 7 - argc .
+    likho_ginti(n / 2) .
+    likho_ginti(n % 3) .
+    likho_ginti(17 % (0 - 5)) .
+    likho_ginti(n << 3) .
+    likho_ginti(n >> 1) .
+    likho_ginti(n << 40) .
+    likho_ginti(n >> 33) .
+    likho_ginti(3 ^ (argc + 3)) .
+    likho_ginti(2 ^ 63 + 2 ^ 63) .
+    likho_ginti(0 - -n) .
+    ginti big = 4294967296 * (argc + 3) + 4294967295 .
+    likho_ginti(big * big) .
+    likho_ginti(big / (0 - 7)) .
+    likho_ginti(big % 1000000007) .
+    likho_ginti(big - 8589934592 * argc) .
+    ginti min = 0 - 9223372036854775807 - argc .
+    likho_ginti(min / (0 - 1)) .
+    likho_ginti(min % (0 - 1)) .
+    likho_ginti(min * 3) .
+    likho_ginti(min) .
+    likho_ginti(-min) .
+    likho_ginti(12 | 3 & 6) .
+    agar (n < argc) { likho(\"lt\") . } warna { likho(\"ge\") . }
+    agar (big > 0 - big) { likho(\"gt\") . } warna { likho(\"le\") . }
+    agar (sach == (argc > 1)) { likho(\"many\") . } warna { likho(\"one\") . }
+    agar (argc > 2 || argc > 1 && !(argc == 2)) { likho(\"odd\") . } warna { likho(\"even\") . }
+    wapsi n + 300 .
+} .
+";
+
+pub const FLOATS: &str = "
+asharia scale = 0.5 .
+
+fn asharia mix(asharia a, ginti i, asharia b, asharia c, asharia d, asharia e, asharia f,
+               asharia g, asharia h, asharia j, ginti k) {
+    asharia r = a + b - c * d / e + f - g + h * j * scale .
+    agar (i > k) { r = r + 1.0 . } warna {}
+    wapsi r .
+} .
+
+fn khali compare(asharia x, asharia y) {
+    agar (x < y) { likho(\"<\") . } warna {}
+    agar (x > y) { likho(\">\") . } warna {}
+    agar (x == y) { likho(\"==\") . } warna {}
+    wapsi .
+} .
+
+fn ginti shuru(ginti argc) {
+    asharia x = mix(1.5, argc, 2.0, 3.0, 4.0, 8.0, 0.25, 1.0, 6.0, 2.0, 2) .
+    compare(x, 7.25) .
+    compare(0.0 - x, 0.0 - 8.25) .
+    compare(-x, 0.0 - 8.25) .
+    compare(1.0 - -x, 8.25 + 1.0) .
+    compare(7.5 % 2.0, 1.5) .
+    compare(0.0 - 7.5 % 2.0, 0.0 - 1.5) .
+    compare(1000000.5 % 0.25, 0.0) .
+    compare(2.0 ^ 0.5, 1.4142135623730951) .
+    compare(2.0 ^ 10.0, 1024.0) .
+    compare(2.0 ^ 3.0 ^ 2.0, 512.0) .
+    compare(2.0 ^ (0.0 - 2.0), 0.25) .
+    compare(9.0 ^ 0.5, 3.0) .
+    asharia zero = 0.0 .
+    asharia nan = zero / zero .
+    compare(nan, nan) .
+    compare(nan, 1.0) .
+    wapsi 0 .
+} .
+";
+
+// Run w/ no input, w/ `\r\n` line endings, and stopping early at a `bas`
+pub const STRINGS: &str = "
+jumla greeting = \"salaam, دنیا? \\\"quoted\\\"\" .
+
+fn boli before(jumla a, jumla b) {
+    wapsi a < b .
+} .
+
+fn ginti shuru() {
+    likho(greeting) .
+    likho(\"ghar ka khana\") .
+    ginti lines = 0 .
+    duhrao (ginti i = 0 . i < 4 . i = i + 1) {
+        jumla line = parho() .
+        agar (before(line, \"m\")) { likho(line) . } warna { likho(\"(later)\") . }
+        agar (line == \"bas\") { toro } warna {}
+        lines = lines + 1 .
+    }
+    likho_ginti(lines) .
+    wapsi lines .
+} .
+";
+
+pub const STRINGS_INPUTS: [&str; 3] = ["", "alif\nzoe\r\nbe\n", "alif\nbas\nbe\n"];
+
+pub const CALLS: &str = "
+ginti calls = 0 .
+
+fn ginti fib(ginti n) {
+    calls = calls + 1 .
+    agar (n < 2) { wapsi n . } warna {}
+    wapsi fib(n - 1) + fib(n - 2) .
+} .
+
+ginti base = fib(5) * 2 .
+
+fn ginti weigh(ginti a, ginti b, ginti c, ginti d, ginti e, ginti f, ginti g, ginti h) {
+    wapsi a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h .
+} .
+
+fn boli is_odd(ginti n) {
+    wapsi n % 2 == 1 .
+} .
+
+fn ginti shuru(ginti argc) {
+    likho_ginti(base) .
+    likho_ginti(fib(12 + argc)) .
+    likho_ginti(calls) .
+    likho_ginti(weigh(1, 2, 3, 4, 5, 6, 7, argc)) .
+    ginti odd = 0 .
+    duhrao (ginti i = 0 . i < 10 . i = i + 1) {
+        agar (is_odd(i) && i > argc) { odd = odd + i . } warna {}
+        agar (i == 7) { toro } warna {}
+    }
+    likho_ginti(odd) .
+    wapsi base + odd - 4294967296 .
+} .
+";
+
+// Operands w/ effects, which are evaluated left to right, and short-circuit, and names that C and
+// LLVM have other uses for
+pub const ORDER: &str = "
+ginti g = 1 .
+
+fn ginti say(ginti n) {
+    likho_ginti(n) .
+    g = g * 10 + n .
+    wapsi n .
+} .
+
+fn ginti pair(ginti entry, ginti b) {
+    wapsi entry * 100 + b .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti a = argc .
+    likho_ginti(a + (a = 5) * 2) .
+    likho_ginti(say(1) - say(2) * say(3)) .
+    likho_ginti(g + say(4)) .
+    likho_ginti(pair(say(5), g)) .
+    likho_ginti(pair(a, a = a + 1)) .
+    ginti b = 0 .
+    a = b = 7 .
+    likho_ginti(a + b) .
+    ginti int = 2 . ginti stdout = 3 . ginti NULL = 4 . ginti fn_say = 5 . ginti nkt_t0 = 6 .
+    ginti v_int = 7 . ginti size_t = 8 . ginti شمار = 9 . ginti _x = 10 . ginti PRId64 = 11 .
+    likho_ginti(int + stdout + NULL + fn_say + nkt_t0 + v_int + size_t + شمار + _x + PRId64) .
+    agar (say(6) > 5 || say(7) > 0) { likho(\"short\") . } warna {}
+    wapsi g % 256 .
+} .
+";
+
+// Prints `before`, then fails as `RUNTIME_ERROR_CASES` has it, given that many arguments
+pub const RUNTIME_ERRORS: &str = "
+fn ginti ratio(ginti n, ginti d) {
+    agar (n > 4) { wapsi 2 ^ (0 - n) . } warna {}
+    agar (n > 3) { wapsi 1 << (n * 40) . } warna {}
+    agar (n > 2) { wapsi n >> (0 - n) . } warna {}
+    wapsi n / d .
+} .
+
+fn ginti pick(ginti n) {
+    agar (n > 6) { wapsi n . } warna {}
+} .
+
+fn ginti shuru(ginti argc) {
+    likho(\"before\") .
+    agar (argc > 5) { wapsi pick(argc) . } warna {}
+    wapsi ratio(argc, argc - 1) .
+} .
+";
+
+// argc, the program's path included, and the error as the interpreter names it
+pub const RUNTIME_ERROR_CASES: [(usize, &str); 5] = [
+    (1, "DivisionByZero"),
+    (3, "ShiftOutOfRange(-3)"),
+    (4, "ShiftOutOfRange(160)"),
+    (5, "NegativeExponent(-5)"),
+    (6, "MissingReturn(\"pick\")"),
+];
+
+/// The interpreter's exit code and output, running `src` w/ each of the argument lists (the
+/// program's path first) the backends are checked w/, along w/ those arguments
+pub fn interp_runs(src: &str, stdin: &str) -> Vec<(Vec<String>, i64, String)> {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap();
+
+    let arg_lists = [vec!["prog"], vec!["prog", "a"], vec!["prog", "a", "b"]];
+    arg_lists
+        .into_iter()
+        .map(|args| {
+            let args = args.into_iter().map(String::from).collect::<Vec<_>>();
+            let mut out = vec![];
+            let code = interp::run_with_io(&artifacts.ast, &args, &mut stdin.as_bytes(), &mut out)
+                .unwrap_or_else(|e| panic!("{e}"));
+            (args, code, String::from_utf8(out).unwrap())
+        })
+        .collect()
+}
+
+/// Runs `cmd`, w/ `stdin` as its input, for the backends whose programs run as processes
+#[allow(dead_code)]
+pub fn run_piped(cmd: &mut Command, stdin: &str) -> Output {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// For naming the files those backends build, uniquely per test
+#[allow(dead_code)]
+pub fn hash(value: &impl std::hash::Hash) -> u64 {
+    use std::hash::{DefaultHasher, Hasher};
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
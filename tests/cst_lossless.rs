@@ -0,0 +1,98 @@
+//! The lossless lexer and concrete syntax tree reproduce their source byte-for-byte, and the AST
+//! built alongside the CST is the same one a regular parse would produce.
+
+use std::fs;
+use std::path::Path;
+
+use nuktah::{
+    lexer::core::tokenize_src_code_lossless,
+    parse_src, parse_src_lossless,
+    parser::ast::print::ast_to_string,
+    parser::cst::{SyntaxKind, SyntaxNode},
+};
+
+fn assert_lossless(src: &str) {
+    let lexed = tokenize_src_code_lossless(src).unwrap();
+    assert_eq!(lexed.to_source(), src);
+
+    let (ast_root, cst) = parse_src_lossless(src).unwrap();
+    assert_eq!(cst.text(), src);
+    assert_eq!(
+        ast_to_string(&ast_root),
+        ast_to_string(&parse_src(src).unwrap())
+    );
+}
+
+#[test]
+fn examples_are_lossless() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+
+    for entry in fs::read_dir(examples).unwrap() {
+        let src = fs::read_to_string(entry.unwrap().path()).unwrap();
+
+        // Files the parser rejects still lex losslessly
+        if parse_src(&src).is_err() {
+            assert_eq!(tokenize_src_code_lossless(&src).unwrap().to_source(), src);
+            continue;
+        }
+        assert_lossless(&src);
+    }
+}
+
+#[test]
+fn trivia_is_kept() {
+    assert_lossless("\n\n$ leading\nginti  a =\t1 +  2 .   $ trailing\n\n$ eof\n");
+    assert_lossless("fn khali f() {\r\n\tjumla s = \"a  b\" .\n} .");
+    assert_lossless("  ");
+}
+
+fn find(node: &SyntaxNode, kind: SyntaxKind) -> Option<&SyntaxNode> {
+    if node.kind == kind {
+        return Some(node);
+    }
+    node.child_nodes().find_map(|n| find(n, kind))
+}
+
+#[test]
+fn trivia_attaches_to_neighbouring_tokens() {
+    let (_, cst) = parse_src_lossless("ginti a = 1 . $ one\n$ two\nginti b = 2 .\n").unwrap();
+    let tokens = cst.root.tokens();
+
+    let first_dot = tokens[4];
+    assert_eq!(first_dot.text, ".");
+    assert_eq!(
+        first_dot
+            .trailing
+            .iter()
+            .map(|t| &*t.text)
+            .collect::<String>(),
+        " $ one\n"
+    );
+
+    let second_decl = tokens[5];
+    assert_eq!(second_decl.text, "ginti");
+    assert_eq!(
+        second_decl
+            .leading
+            .iter()
+            .map(|t| &*t.text)
+            .collect::<String>(),
+        "$ two\n"
+    );
+}
+
+#[test]
+fn operators_nest_like_the_ast() {
+    let (_, cst) = parse_src_lossless("ginti a = 1 - 2 - 3 * 4 .").unwrap();
+
+    let add = find(&cst.root, SyntaxKind::AddExpr).unwrap();
+    assert_eq!(add.text(), "1 - 2 - 3 * 4 ");
+
+    // Left-associative: the outer subtraction's first operand is `1 - 2`
+    let inner = add.child_nodes().next().unwrap();
+    assert_eq!(inner.kind, SyntaxKind::AddExpr);
+    assert_eq!(inner.text(), "1 - 2 ");
+
+    let mul = find(&cst.root, SyntaxKind::MulExpr).unwrap();
+    assert_eq!(mul.text(), "3 * 4 ");
+}
@@ -0,0 +1,181 @@
+//! Dead code elimination: unreachable code, constant branches, unread assignments and uncalled
+//! functions are removed, and what's left still does what the source says.
+
+use nuktah::{
+    compile_src, compile_src_opt,
+    interp::core as interp,
+    ir::{
+        core::{Inst, Module},
+        eval,
+        print::function_to_string,
+    },
+    opt::core::OptLevel,
+    semantics::core::SrcKind,
+};
+
+fn optimised(src: &str, kind: SrcKind) -> Module {
+    compile_src_opt(src, kind, OptLevel::O1)
+        .unwrap_or_else(|e| panic!("{e:?}"))
+        .lowered
+        .unwrap()
+        .ssa
+}
+
+fn function(module: &Module, name: &str) -> String {
+    let f = &module.functions[module.function_id(name).unwrap() as usize];
+    function_to_string(module, f)
+}
+
+#[test]
+fn folds_constant_branches() {
+    let module = optimised(
+        "
+boli DEBUG = jhoot .
+
+fn ginti shuru(ginti argc) {
+    agar (DEBUG) { likho(\"debugging\") . } warna { likho(\"not debugging\") . }
+    duhrao (ginti i = 0 . i < argc . i = i + 1) {
+        agar (2 > 1) { toro . likho(\"never\") . } warna { likho(\"never either\") . }
+    }
+    wapsi argc .
+    likho(\"after wapsi\") .
+} .
+",
+        SrcKind::Program,
+    );
+
+    let expected = "\
+fn shuru(%0: ginti) -> ginti {
+bb0:
+    call likho(\"not debugging\")
+    ret %0
+}
+";
+    assert_eq!(function(&module, "shuru"), expected);
+}
+
+#[test]
+fn removes_unread_assignments() {
+    let module = optimised(
+        "
+ginti g = 0 .
+
+fn ginti shuru(ginti argc) {
+    ginti unread = argc * 2 + 1 .
+    ginti overwritten = argc - 1 .
+    overwritten = 3 .
+    ginti risky = 10 / argc .
+    ginti shifted = argc << 2 .
+    g = argc .
+    likho_ginti(argc) .
+    wapsi overwritten .
+} .
+",
+        SrcKind::Program,
+    );
+
+    // Dividing by `argc` might fail, so stays; the store and the call have side effects
+    let expected = "\
+fn shuru(%0: ginti) -> ginti {
+bb0:
+    %9: ginti = div 10, %0
+    store @0 g, %0
+    call likho_ginti(%0)
+    ret 3
+}
+";
+    assert_eq!(function(&module, "shuru"), expected);
+}
+
+#[test]
+fn removes_uncalled_functions() {
+    let src = "
+fn ginti start() { wapsi 4 . } .
+fn ginti helper() { wapsi 1 . } .
+fn ginti unused() { wapsi helper() . } .
+fn ginti twice(ginti n) { wapsi n * 2 . } .
+
+ginti seed = start() .
+
+fn ginti shuru() {
+    agar (jhoot) { wapsi unused() . } warna {}
+    wapsi twice(seed) .
+} .
+";
+    let module = optimised(src, SrcKind::Program);
+    let names = module
+        .functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["start", "twice", "shuru", "<init>"]);
+
+    let args = ["prog.nkt".to_string()];
+    let res = eval::run_with_io(&module, &args, &mut "".as_bytes(), &mut vec![]);
+    assert_eq!(res.unwrap(), 8);
+
+    // W/o an entry point, there's no telling what a library's users will call
+    let lib = src.replace("fn ginti shuru()", "fn ginti not_shuru()");
+    assert_eq!(optimised(&lib, SrcKind::Library).functions.len(), 6);
+}
+
+#[test]
+fn preserves_behaviour() {
+    let programs = [
+        "
+ginti calls = 0 .
+
+fn ginti fib(ginti n) {
+    calls = calls + 1 .
+    agar (n < 2) { wapsi n . } warna {}
+    wapsi fib(n - 1) + fib(n - 2) .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti a = fib(10) .
+    agar (argc > 1 && sach) { likho_ginti(calls) . } warna { likho(\"no args\") . }
+    wapsi a .
+} .
+",
+        "
+fn ginti shuru() {
+    ginti a = 1 .
+    ginti b = 2 .
+    duhrao (ginti i = 0 . i < 5 . i = i + 1) {
+        ginti t = a .
+        a = b .
+        b = t .
+        agar (i == 3) { toro . } warna {}
+    }
+    wapsi a * 10 + b .
+} .
+",
+    ];
+
+    for src in programs {
+        let artifacts = compile_src(src, SrcKind::Program).unwrap();
+        let run = |module: Option<&Module>| {
+            let args = ["prog.nkt".to_string(), "arg".to_string()];
+            let mut out = vec![];
+            let mut input = "".as_bytes();
+            let code = match module {
+                Some(module) => eval::run_with_io(module, &args, &mut input, &mut out),
+                None => interp::run_with_io(&artifacts.ast, &args, &mut input, &mut out),
+            };
+            (code.unwrap(), String::from_utf8(out).unwrap())
+        };
+
+        let optimised = compile_src_opt(src, SrcKind::Program, OptLevel::O1)
+            .unwrap()
+            .lowered
+            .unwrap();
+        assert!(optimised
+            .ir
+            .functions
+            .iter()
+            .flat_map(|f| &f.blocks)
+            .all(|b| !b.insts.iter().any(Inst::is_phi)));
+        assert_eq!(run(Some(&optimised.ssa)), run(None), "in SSA:\n{src}");
+        assert_eq!(run(Some(&optimised.ir)), run(None), "out of SSA:\n{src}");
+    }
+}
@@ -0,0 +1,84 @@
+//! `--emit=*-dot`: the graphs, whole, for a program w/ a label of each kind.
+
+use nuktah::{
+    compile_src,
+    emit::dot::{ast_to_dot, scopes_to_dot},
+    parse_src,
+    semantics::core::SrcKind,
+};
+
+// The string's label quotes it w/ `{:?}`, escaping its quotes and backslashes, which DOT needs
+// escaped once more
+const SRC: &str = "jumla s = \"a\\\"b\\\\c\" .
+fn ginti shuru(ginti argc) {
+    agar (argc > 1) { wapsi -argc . } warna {}
+    wapsi 0 .
+} .
+";
+
+#[test]
+fn ast() {
+    let expected = r#"
+digraph ast {
+    node [shape=box, fontname="monospace"];
+    n0 [label="TranslationUnit"];
+    n1 [label="VarDecl jumla s"];
+    n2 [label="StringLit \"a\\\\\\\"b\\\\\\\\c\""];
+    n1 -> n2;
+    n0 -> n1;
+    n3 [label="FnDecl ginti shuru"];
+    n4 [label="Param ginti argc"];
+    n3 -> n4;
+    n5 [label="Block"];
+    n6 [label="If"];
+    n7 [label="Ident argc"];
+    n8 [label="IntLit 1"];
+    n9 [label="Comp >"];
+    n9 -> n7;
+    n9 -> n8;
+    n10 [label="Block"];
+    n11 [label="Ret"];
+    n12 [label="Unary -"];
+    n13 [label="Ident argc"];
+    n12 -> n13;
+    n11 -> n12;
+    n10 -> n11;
+    n14 [label="Block"];
+    n6 -> n9 [label="cond"];
+    n6 -> n10 [label="agar"];
+    n6 -> n14 [label="warna"];
+    n5 -> n6;
+    n15 [label="Ret"];
+    n16 [label="IntLit 0"];
+    n15 -> n16;
+    n5 -> n15;
+    n3 -> n5;
+    n0 -> n3;
+}
+"#;
+    assert_eq!(ast_to_dot(&parse_src(SRC).unwrap()), expected.trim_start());
+}
+
+#[test]
+fn scopes() {
+    let expected = r#"
+digraph scopes {
+    node [shape=record, fontname="monospace"];
+    s0 [label="{#0 Root\l|fn likho: khali\lfn likho_ginti: khali\lfn parho: jumla\ls: jumla\lfn shuru: ginti\l}"];
+    s1 [label="{#1 Intrinsic\lparams: jumla\l|s: jumla\l}"];
+    s2 [label="{#2 Intrinsic\lparams: ginti\l|i: ginti\l}"];
+    s3 [label="{#3 Intrinsic\l|}"];
+    s4 [label="{#4 FnBlock\lparams: ginti\l|argc: ginti\l}"];
+    s5 [label="{#5 IfBlock\l|}"];
+    s6 [label="{#6 IfBlock\l|}"];
+    s0 -> s1 [label="likho"];
+    s0 -> s2 [label="likho_ginti"];
+    s0 -> s3 [label="parho"];
+    s0 -> s4 [label="shuru"];
+    s4 -> s5;
+    s4 -> s6;
+}
+"#;
+    let artifacts = compile_src(SRC, SrcKind::Program).unwrap();
+    assert_eq!(scopes_to_dot(&artifacts.sym_table), expected.trim_start());
+}
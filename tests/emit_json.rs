@@ -0,0 +1,336 @@
+//! `--emit=*-json`: every document is valid JSON, w/ the shapes `emit::json` documents.
+
+use std::ops::Index;
+
+use nuktah::{
+    compile_src,
+    emit::json::{ast_to_json, symtab_to_json, tokens_to_json, SCHEMA_VERSION},
+    lexer::{
+        core::{tokenize_src_code_with_spans, SpannedTokens},
+        Span, Token,
+    },
+    parse_src,
+    semantics::core::SrcKind,
+};
+
+// Just enough of a JSON reader to check the documents w/; it's strict, so as to catch anything
+// other readers would choke on
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn items(&self) -> &[Json] {
+        match self {
+            Json::Arr(items) => items,
+            _ => panic!("not an array: {self:?}"),
+        }
+    }
+
+    fn num(&self) -> f64 {
+        match self {
+            Json::Num(n) => *n,
+            _ => panic!("not a number: {self:?}"),
+        }
+    }
+
+    fn str(&self) -> &str {
+        match self {
+            Json::Str(s) => s,
+            _ => panic!("not a string: {self:?}"),
+        }
+    }
+}
+
+impl Index<&str> for Json {
+    type Output = Json;
+
+    fn index(&self, key: &str) -> &Json {
+        match self {
+            Json::Obj(fields) => match fields.iter().find(|(k, _)| k == key) {
+                Some((_, value)) => value,
+                None => panic!("no `{key}` in {self:?}"),
+            },
+            _ => panic!("not an object: {self:?}"),
+        }
+    }
+}
+
+impl Index<usize> for Json {
+    type Output = Json;
+
+    fn index(&self, i: usize) -> &Json {
+        &self.items()[i]
+    }
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn eat(&mut self, lit: &str) -> bool {
+        let found = self.rest().starts_with(lit);
+        if found {
+            self.pos += lit.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, lit: &str) {
+        assert!(self.eat(lit), "expected `{lit}` at {}", self.pos);
+    }
+
+    fn skip_ws(&mut self) {
+        while self.rest().starts_with([' ', '\n', '\r', '\t']) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Json {
+        self.skip_ws();
+        let value = if self.eat("null") {
+            Json::Null
+        } else if self.eat("true") {
+            Json::Bool(true)
+        } else if self.eat("false") {
+            Json::Bool(false)
+        } else if self.eat("\"") {
+            Json::Str(self.string())
+        } else if self.eat("[") {
+            Json::Arr(self.list("]", Reader::value))
+        } else if self.eat("{") {
+            Json::Obj(self.list("}", |r| {
+                r.skip_ws();
+                r.expect("\"");
+                let key = r.string();
+                r.skip_ws();
+                r.expect(":");
+                (key, r.value())
+            }))
+        } else {
+            self.number()
+        };
+
+        self.skip_ws();
+        value
+    }
+
+    fn list<T>(&mut self, close: &str, mut item: impl FnMut(&mut Self) -> T) -> Vec<T> {
+        let mut items = vec![];
+        self.skip_ws();
+        if self.eat(close) {
+            return items;
+        }
+
+        loop {
+            items.push(item(self));
+            if self.eat(close) {
+                return items;
+            }
+            self.expect(",");
+        }
+    }
+
+    fn number(&mut self) -> Json {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_digit() || "-+.eE".contains(c)))
+            .unwrap_or(rest.len());
+        let lit = &rest[..len];
+
+        let digits = lit.strip_prefix('-').unwrap_or(lit);
+        assert!(
+            digits.starts_with(|c: char| c.is_ascii_digit()) && !lit.ends_with('.'),
+            "expected a value at {}: {:?}",
+            self.pos,
+            &rest[..rest.len().min(20)]
+        );
+        self.pos += len;
+        Json::Num(lit.parse().unwrap())
+    }
+
+    // After the opening quote
+    fn string(&mut self) -> String {
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return s;
+                }
+                '\\' => match chars.next().unwrap().1 {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'u' => {
+                        let hex = (0..4).map(|_| chars.next().unwrap().1).collect::<String>();
+                        s.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+                    }
+                    c @ ('"' | '\\' | '/') => s.push(c),
+                    c => panic!("invalid escape `\\{c}`"),
+                },
+                c if (c as u32) < 0x20 => panic!("unescaped control character {c:?}"),
+                c => s.push(c),
+            }
+        }
+
+        panic!("unterminated string")
+    }
+}
+
+fn parse(text: &str) -> Json {
+    let mut reader = Reader { text, pos: 0 };
+    let doc = reader.value();
+    assert_eq!(reader.pos, text.len(), "trailing characters");
+    doc
+}
+
+fn header(doc: &Json, kind: &str) {
+    assert_eq!(doc["schema_version"].num(), SCHEMA_VERSION as f64);
+    assert_eq!(doc["kind"].str(), kind);
+}
+
+fn span(json: &Json) -> [f64; 4] {
+    ["start", "end", "line", "col"].map(|field| json[field].num())
+}
+
+const SRC: &str = "jumla s = \"q\\\"b\\\\s\tt\" .
+fn ginti shuru(ginti argc) {
+    agar (argc > 1) { asharia x = 0.5 . } warna {}
+    duhrao (ginti i = 0 . i < argc . i = i + 1) {}
+    wapsi argc << 2 .
+} .
+";
+
+#[test]
+fn tokens() {
+    let doc = parse(&tokens_to_json(&tokenize_src_code_with_spans(SRC).unwrap()));
+    header(&doc, "tokens");
+
+    let tokens = doc["tokens"].items();
+    assert_eq!(tokens[0]["kind"].str(), "String");
+    assert_eq!(span(&tokens[0]["span"]), [0.0, 5.0, 1.0, 1.0]);
+
+    // Quotes, backslashes and control characters survive, as they're written in source
+    let lit = tokens
+        .iter()
+        .find(|t| t["kind"].str() == "StringLit")
+        .unwrap();
+    assert_eq!(lit["value"].str(), "q\\\"b\\\\s\tt");
+
+    let fn_tok = tokens
+        .iter()
+        .find(|t| t["kind"].str() == "Function")
+        .unwrap();
+    let start = SRC.find("fn").unwrap() as f64;
+    assert_eq!(span(&fn_tok["span"]), [start, start + 2.0, 2.0, 1.0]);
+
+    let two = tokens
+        .iter()
+        .find(|t| t["kind"].str() == "IntLit" && t["value"].num() == 2.0);
+    assert!(two.is_some());
+}
+
+#[test]
+fn ast() {
+    let doc = parse(&ast_to_json(&parse_src(SRC).unwrap()));
+    header(&doc, "ast");
+
+    let decls = doc["decls"].items();
+    assert_eq!(decls[0]["node"].str(), "VarDecl");
+    assert_eq!(decls[0]["init"]["value"].str(), "q\\\"b\\\\s\tt");
+
+    let shuru = &decls[1];
+    assert_eq!(shuru["node"].str(), "FnDecl");
+    assert_eq!(shuru["ident"].str(), "shuru");
+    assert_eq!(shuru["params"][0]["type"].str(), "ginti");
+    assert_eq!(shuru["span"]["line"].num(), 2.0);
+
+    let body = shuru["body"].items();
+    let kinds = body.iter().map(|s| s["node"].str()).collect::<Vec<_>>();
+    assert_eq!(kinds, ["If", "For", "Ret"]);
+    assert_eq!(body[0]["then"][0]["init"]["value"].num(), 0.5);
+
+    let ret = &body[2];
+    let start = SRC.find("wapsi").unwrap() as f64;
+    assert_eq!(span(&ret["span"])[0], start);
+    assert_eq!(ret["span"]["line"].num(), 5.0);
+    assert_eq!(ret["expr"]["op"].str(), "<<");
+    assert_eq!(ret["expr"]["lhs"]["name"].str(), "argc");
+    assert_eq!(ret["expr"]["rhs"]["value"].num(), 2.0);
+}
+
+#[test]
+fn symtab() {
+    let artifacts = compile_src(SRC, SrcKind::Program).unwrap();
+    let doc = parse(&symtab_to_json(&artifacts.sym_table));
+    header(&doc, "symtab");
+
+    let scopes = doc["scopes"].items();
+    assert_eq!(scopes[0]["id"].num(), 0.0);
+    assert_eq!(scopes[0]["scope_type"].str(), "Root");
+    assert_eq!(scopes[0]["parent"], Json::Null);
+
+    // Scopes are in order of id, and parents and children agree on each other
+    for (i, scope) in scopes.iter().enumerate() {
+        assert_eq!(scope["id"].num(), i as f64);
+
+        for child in scope["children"].items() {
+            let child = &scopes[child["id"].num() as usize];
+            assert_eq!(child["parent"].num(), i as f64);
+        }
+        if i > 0 {
+            let parent = &scopes[scope["parent"].num() as usize];
+            let ids = parent["children"].items().iter().map(|c| c["id"].num());
+            assert_eq!(ids.filter(|&id| id == i as f64).count(), 1);
+        }
+
+        let names = scope["symbols"].items().iter().map(|s| s["name"].str());
+        assert!(names.clone().is_sorted(), "{:?}", names.collect::<Vec<_>>());
+    }
+
+    let shuru = scopes[0]["children"]
+        .items()
+        .iter()
+        .find(|c| c["name"] == Json::Str("shuru".to_string()))
+        .unwrap();
+    let shuru = &scopes[shuru["id"].num() as usize];
+    assert_eq!(shuru["scope_type"].str(), "FnBlock");
+    assert_eq!(shuru["param_types"][0].str(), "ginti");
+
+    // `agar`'s block and `warna`'s, then `duhrao`'s
+    let blocks = shuru["children"]
+        .items()
+        .iter()
+        .map(|c| &scopes[c["id"].num() as usize]);
+    let types = blocks.map(|s| s["scope_type"].str()).collect::<Vec<_>>();
+    assert_eq!(types, ["IfBlock", "IfBlock", "ForBlock"]);
+}
+
+#[test]
+fn non_finite_floats_are_null() {
+    let lexed = SpannedTokens {
+        tokens: vec![Token::FloatLit(f64::NAN), Token::FloatLit(f64::INFINITY)],
+        spans: vec![Span::default(); 2],
+        comments: vec![],
+    };
+
+    let doc = parse(&tokens_to_json(&lexed));
+    for token in doc["tokens"].items() {
+        assert_eq!(token["value"], Json::Null);
+    }
+}
@@ -0,0 +1,58 @@
+//! Programs must define `shuru` w/ one of the supported signatures; libraries needn't.
+
+use nuktah::{compile_src, semantics::core::SrcKind, CompilerError};
+
+fn entry_point_error(src: &str) -> String {
+    match compile_src(src, SrcKind::Program) {
+        Err(e @ CompilerError::SemanticErr(_)) => format!("{e:?}"),
+        res => panic!("expected a semantic error, got {res:?}"),
+    }
+}
+
+#[test]
+fn supported_signatures() {
+    for src in [
+        "fn ginti shuru() { wapsi 0 . } .",
+        "fn ginti shuru(ginti argc) { wapsi argc . } .",
+        "fn khali shuru() { wapsi . } .",
+        "ginti g = 1 . fn khali shuru(ginti argc) { g = argc . wapsi . } .",
+    ] {
+        assert!(compile_src(src, SrcKind::Program).is_ok(), "{src}");
+    }
+}
+
+#[test]
+fn missing_or_malformed_entry_point() {
+    let lib = "fn ginti helper(ginti x) { wapsi x * 2 . } .";
+    assert_eq!(
+        entry_point_error(lib),
+        "SemanticErr(EntryPointErr(Missing))"
+    );
+    assert!(compile_src(lib, SrcKind::Library).is_ok());
+
+    assert_eq!(
+        entry_point_error("ginti shuru = 1 ."),
+        "SemanticErr(EntryPointErr(NotAFunction))"
+    );
+    assert_eq!(
+        entry_point_error("fn jumla shuru() { wapsi \"x\" . } ."),
+        "SemanticErr(EntryPointErr(InvalidReturnType(String)))"
+    );
+    assert_eq!(
+        entry_point_error("fn ginti shuru(ginti a, ginti b) { wapsi a . } ."),
+        "SemanticErr(EntryPointErr(InvalidParams([Int, Int])))"
+    );
+    assert_eq!(
+        entry_point_error("fn ginti shuru(jumla arg) { wapsi 0 . } ."),
+        "SemanticErr(EntryPointErr(InvalidParams([String])))"
+    );
+}
+
+#[test]
+fn locals_may_share_a_function_name() {
+    let src = "
+        fn ginti f() { wapsi 1 . } .
+        fn ginti shuru() { ginti f = 2 . wapsi f . } .
+    ";
+    assert!(compile_src(src, SrcKind::Program).is_ok());
+}
@@ -0,0 +1,275 @@
+//! Round-trip properties of `nktc fmt`: formatting never changes what a program parses to, and
+//! formatting formatted code is a no-op. It keeps comments, and `--check` only fails on files it
+//! would change.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use nuktah::{formatter::core::format_src, parse_src, parser::ast::print::ast_to_string};
+
+fn ast(src: &str) -> String {
+    ast_to_string(&parse_src(src).unwrap_or_else(|e| panic!("{e:?} in:\n{src}")))
+}
+
+fn assert_round_trips(src: &str) {
+    let formatted = format_src(src).unwrap_or_else(|e| panic!("{e:?} in:\n{src}"));
+
+    assert_eq!(
+        ast(src),
+        ast(&formatted),
+        "formatting changed the AST of:\n{src}\nformatted:\n{formatted}"
+    );
+    assert_eq!(
+        formatted,
+        format_src(&formatted).unwrap(),
+        "formatting isn't idempotent for:\n{src}"
+    );
+}
+
+#[test]
+fn examples_round_trip() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+
+    for entry in fs::read_dir(examples).unwrap() {
+        let src = fs::read_to_string(entry.unwrap().path()).unwrap();
+        if parse_src(&src).is_ok() {
+            assert_round_trips(&src);
+        }
+    }
+}
+
+#[test]
+fn random_programs_round_trip() {
+    let mut gen = Gen(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..500 {
+        let src = gen.program();
+        assert_round_trips(&src);
+    }
+}
+
+// Comments on lines of their own and after code, at the top level and in blocks
+const COMMENTED: &str = "$ header
+ginti g = 1 . $ after a decl
+
+fn ginti shuru() {
+    $ leads the block
+    agar (g > 0) { $ opens the branch
+        g = 2 . $ trails a statement
+    } warna {
+        $ alone in a block
+    }
+    wapsi g .
+    $ closes the block
+} .
+$ the end
+";
+
+#[test]
+fn comments_are_kept() {
+    assert_round_trips(COMMENTED);
+    assert_eq!(
+        format_src(COMMENTED).unwrap(),
+        "$ header
+ginti g = 1 . $ after a decl
+
+fn ginti shuru() {
+\t$ leads the block
+\tagar (g > 0) {
+\t\t$ opens the branch
+\t\tg = 2 . $ trails a statement
+\t} warna {
+\t\t$ alone in a block
+\t}
+\twapsi g .
+\t$ closes the block
+} .
+$ the end
+"
+    );
+}
+
+#[test]
+fn comments_inside_statements_go_before_them() {
+    let src = "fn ginti shuru() {
+    ginti h = 1 + $ mid
+        2 .
+    wapsi h .
+} .
+";
+    assert_round_trips(src);
+    assert_eq!(
+        format_src(src).unwrap(),
+        "fn ginti shuru() {
+\t$ mid
+\tginti h = 1 + 2 .
+\twapsi h .
+} .
+"
+    );
+}
+
+#[test]
+fn parentheses_are_kept() {
+    let src = "fn ginti shuru() {
+    ginti b = 1 .
+    (b) = 3 .
+    wapsi -(-b) + ((2 * 3)) .
+} .
+";
+    assert_round_trips(src);
+    let formatted = format_src(src).unwrap();
+    assert!(formatted.contains("\t(b) = 3 .\n"), "{formatted}");
+    assert!(formatted.contains("\twapsi -(-b) + ((2 * 3)) .\n"), "{formatted}");
+}
+
+#[test]
+fn check_only_fails_on_unformatted_files() {
+    let path = std::env::temp_dir().join(format!("nkt-fmt-{}.nkt", std::process::id()));
+    fs::write(&path, COMMENTED).unwrap();
+    let nktc = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_nktc"))
+            .args(args)
+            .arg(&path)
+            .output()
+            .unwrap()
+    };
+
+    let check = nktc(&["fmt", "--check"]);
+    assert_eq!(check.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&check.stdout).ends_with(" is not formatted\n"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), COMMENTED);
+
+    assert!(nktc(&["fmt"]).status.success());
+    assert_eq!(nktc(&["fmt", "--check"]).status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format_src(COMMENTED).unwrap()
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+// Tiny xorshift PRNG, generating syntactically valid (though not necessarily well-typed)
+// programs w/ arbitrary whitespace, comments and redundant parentheses.
+struct Gen(u64);
+
+const IDENTS: [&str; 4] = ["a", "b_2", "ginti_x", "nuqta"];
+const TYPES: [&str; 4] = ["ginti", "asharia", "jumla", "boli"];
+const BINARY_OPS: [&str; 17] = [
+    "=", "&&", "||", "|", "&", "<", ">", "==", "<<", ">>", "+", "-", "*", "/", "%", "^", "=",
+];
+const UNARY_OPS: [&str; 3] = ["-", "!", "~"];
+
+impl Gen {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, options: &[&'a str]) -> &'a str {
+        options[self.below(options.len())]
+    }
+
+    fn space(&mut self) -> &'static str {
+        self.pick(&[" ", " ", "  ", "\t", "\n", " \n\n "])
+    }
+
+    fn comment(&mut self) -> String {
+        match self.below(4) {
+            0 => format!(" $ note {}\n", self.below(100)),
+            _ => String::new(),
+        }
+    }
+
+    fn expr(&mut self, depth: usize) -> String {
+        if depth == 0 {
+            return match self.below(6) {
+                0 => self.below(1000).to_string(),
+                1 => format!("{}.{}", self.below(100), 1 + self.below(99)),
+                2 => format!("\"str {}\"", self.below(10)),
+                3 => self.pick(&["sach", "jhoot"]).to_string(),
+                _ => self.pick(&IDENTS).to_string(),
+            };
+        }
+
+        match self.below(6) {
+            0 => format!("({})", self.expr(depth - 1)),
+            1 => format!("{}{}", self.pick(&UNARY_OPS), self.expr(depth - 1)),
+            2 => {
+                let args = (0..self.below(3))
+                    .map(|_| self.expr(depth - 1))
+                    .collect::<Vec<_>>();
+                format!("{}({})", self.pick(&IDENTS), args.join(", "))
+            }
+            _ => {
+                let op = self.pick(&BINARY_OPS);
+                let space = self.space();
+                format!("{} {op}{space}{}", self.expr(depth - 1), self.expr(depth - 1))
+            }
+        }
+    }
+
+    fn var_decl(&mut self) -> String {
+        let (ty, ident) = (self.pick(&TYPES), self.pick(&IDENTS));
+        format!("{ty} {ident} = {} .", self.expr(3))
+    }
+
+    fn block(&mut self, depth: usize) -> String {
+        let stmts = (0..self.below(4))
+            .map(|_| format!("{}{}{}", self.space(), self.stmt(depth), self.comment()))
+            .collect::<String>();
+        format!("{{{}{stmts}}}", self.comment())
+    }
+
+    fn stmt(&mut self, depth: usize) -> String {
+        match self.below(if depth == 0 { 4 } else { 6 }) {
+            0 => self.var_decl(),
+            1 => format!("{} .", self.expr(3)),
+            2 => format!("wapsi {} .", self.expr(2)),
+            3 => "toro".to_string(),
+            4 => {
+                let init = match self.below(2) {
+                    0 => self.var_decl(),
+                    _ => ".".to_string(),
+                };
+                let updt = match self.below(2) {
+                    0 => self.expr(2),
+                    _ => String::new(),
+                };
+                let (cond, block) = (self.expr(2), self.block(depth - 1));
+                format!("duhrao ({init} {cond} . {updt}) {block}")
+            }
+            _ => {
+                let cond = self.expr(2);
+                let (if_block, else_block) = (self.block(depth - 1), self.block(depth - 1));
+                format!("agar ({cond}) {if_block} warna {else_block}")
+            }
+        }
+    }
+
+    fn program(&mut self) -> String {
+        (0..1 + self.below(4))
+            .map(|_| {
+                let decl = match self.below(2) {
+                    0 => self.var_decl(),
+                    _ => {
+                        let params = (0..self.below(3))
+                            .map(|i| format!("{} p{i}", self.pick(&TYPES)))
+                            .collect::<Vec<_>>();
+                        let ty = self.pick(&["ginti", "khali", "boli"]);
+                        let block = self.block(2);
+                        format!("fn {ty} f({}) {block} .", params.join(", "))
+                    }
+                };
+                format!("{}{decl}{}", self.comment(), self.space())
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,141 @@
+//! Inlining: small, non-recursive callees are inlined at their call sites (up to a threshold),
+//! and what's left still does what the source says.
+
+use nuktah::{
+    compile_src, compile_src_opt,
+    interp::core as interp,
+    ir::{
+        core::{Callee, Inst, Module},
+        eval,
+        verify::verify_ssa,
+    },
+    opt::{core::OptLevel, inline},
+    semantics::core::SrcKind,
+};
+
+const PROGRAM: &str = "
+fn ginti a(ginti x, ginti y) {
+    wapsi x + y .
+} .
+
+fn ginti fact(ginti n) {
+    agar (n < 2) { wapsi 1 . } warna {}
+    wapsi n * fact(n - 1) .
+} .
+
+fn boli is_even(ginti n) {
+    ginti r = n % 2 .
+    wapsi r == 0 .
+} .
+
+fn khali report(ginti x) {
+    ginti s = x * 2 .
+    likho_ginti(s) .
+    wapsi .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti s = 0 .
+    ginti x = 7 .
+    duhrao (ginti i = 0 . i < 10 . i = i + 1) {
+        s = a(s, i * x) .
+    }
+    report(s) .
+    agar (is_even(argc)) { s = s + fact(5) . } warna { s = s - 1 . }
+    wapsi s + x .
+} .
+";
+
+fn lowered(src: &str) -> Module {
+    compile_src_opt(src, SrcKind::Program, OptLevel::O0)
+        .unwrap()
+        .lowered
+        .unwrap()
+        .ssa
+}
+
+// The functions `name` (still) calls, by name
+fn calls<'m>(module: &'m Module, name: &str) -> Vec<&'m str> {
+    let f = &module.functions[module.function_id(name).unwrap() as usize];
+    f.blocks
+        .iter()
+        .flat_map(|b| &b.insts)
+        .filter_map(|inst| match inst {
+            Inst::Call {
+                callee: Callee::Fn(func),
+                ..
+            } => Some(module.functions[*func as usize].name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn inlines_small_non_recursive_callees() {
+    let mut module = lowered(PROGRAM);
+    inline::run(&mut module).unwrap();
+    assert_eq!(verify_ssa(&module), Ok(()));
+
+    // `fact` calls itself, so neither its call to itself nor `shuru`'s to it is inlined
+    assert_eq!(calls(&module, "shuru"), ["fact"]);
+    assert_eq!(calls(&module, "fact"), ["fact"]);
+}
+
+#[test]
+fn respects_the_threshold() {
+    let mut module = lowered(PROGRAM);
+    inline::run_with_threshold(&mut module, 0).unwrap();
+    assert_eq!(module, lowered(PROGRAM));
+
+    // `a` is copies of its parameters, an `add` and a `ret`, but the others are bigger
+    inline::run_with_threshold(&mut module, 4).unwrap();
+    assert_eq!(calls(&module, "shuru"), ["report", "is_even", "fact"]);
+}
+
+#[test]
+fn inlining_through_callees() {
+    // `outer`'s call to `inner` is inlined first, so `outer` then inlines in one go
+    let mut module = lowered(
+        "
+fn ginti inner(ginti x) { wapsi x * x . } .
+fn ginti outer(ginti x) { wapsi inner(x) + inner(x + 1) . } .
+fn ginti shuru() { wapsi outer(3) . } .
+",
+    );
+    inline::run(&mut module).unwrap();
+    assert_eq!(calls(&module, "outer"), Vec::<&str>::new());
+    assert_eq!(calls(&module, "shuru"), Vec::<&str>::new());
+
+    let args = ["prog.nkt".to_string()];
+    let res = eval::run_with_io(&module, &args, &mut "".as_bytes(), &mut vec![]);
+    assert_eq!(res.unwrap(), 25);
+}
+
+#[test]
+fn preserves_behaviour() {
+    let artifacts = compile_src(PROGRAM, SrcKind::Program).unwrap();
+    let optimised = compile_src_opt(PROGRAM, SrcKind::Program, OptLevel::O2)
+        .unwrap()
+        .lowered
+        .unwrap();
+
+    for argv in [vec!["prog.nkt"], vec!["prog.nkt", "arg"]] {
+        let args = argv.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let run = |module: Option<&Module>| {
+            let mut out = vec![];
+            let mut input = "".as_bytes();
+            let code = match module {
+                Some(module) => eval::run_with_io(module, &args, &mut input, &mut out),
+                None => interp::run_with_io(&artifacts.ast, &args, &mut input, &mut out),
+            };
+            (code.unwrap(), String::from_utf8(out).unwrap())
+        };
+
+        assert_eq!(run(Some(&optimised.ssa)), run(None), "in SSA, w/ {argv:?}");
+        assert_eq!(
+            run(Some(&optimised.ir)),
+            run(None),
+            "out of SSA, w/ {argv:?}"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! `nktc run`: programs evaluate to the exit code their `shuru` returns, and runtime errors point
+//! at the statement that raised them.
+
+use nuktah::{
+    bytecode::{compiler::compile, vm},
+    compile_src, compile_src_opt,
+    interp::core::{run, RuntimeError, RuntimeErrorKind},
+    ir::eval,
+    opt::core::OptLevel,
+    semantics::core::SrcKind,
+};
+
+fn exec_with_args(src: &str, args: &[&str]) -> Result<i64, RuntimeError> {
+    let artifacts =
+        compile_src(src, SrcKind::Program).unwrap_or_else(|e| panic!("{e:?} in:\n{src}"));
+    let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    run(&artifacts.ast, &args)
+}
+
+fn exec(src: &str) -> Result<i64, RuntimeError> {
+    exec_with_args(src, &["prog.nkt"])
+}
+
+fn exit_code(src: &str) -> i64 {
+    exec(src).unwrap_or_else(|e| panic!("{e} in:\n{src}"))
+}
+
+fn runtime_error(src: &str) -> (RuntimeErrorKind, usize) {
+    let e = exec(src).expect_err("program should've failed");
+    (e.kind, e.span.line)
+}
+
+#[test]
+fn arithmetic_follows_precedence() {
+    assert_eq!(
+        exit_code("fn ginti shuru() { wapsi 2 + 3 * 4 - 10 / 3 % 2 .} ."),
+        13
+    );
+    assert_eq!(exit_code("fn ginti shuru() { wapsi 2 ^ 3 ^ 2 .} ."), 512);
+    assert_eq!(
+        exit_code("fn ginti shuru() { wapsi 1 << 4 | 3 & 1 .} ."),
+        17
+    );
+    assert_eq!(
+        exit_code("fn ginti shuru() { wapsi -(7 - 10) * -2 .} ."),
+        -6
+    );
+}
+
+#[test]
+fn integers_wrap() {
+    let src = "fn ginti shuru() { ginti max = 9223372036854775807 . wapsi max + 1 .} .";
+    assert_eq!(exit_code(src), i64::MIN);
+}
+
+#[test]
+fn globals_are_initialised_in_order_and_shared() {
+    let src = "
+        ginti counter = 1 .
+
+        fn khali bump(ginti by) {
+            counter = counter + by .
+            wapsi .
+        } .
+
+        ginti twice = counter * 2 .
+
+        fn ginti shuru() {
+            bump(twice) .
+            bump(10) .
+            wapsi counter .
+        } .
+    ";
+    assert_eq!(exit_code(src), 13);
+}
+
+#[test]
+fn loops_break_and_return() {
+    let src = "
+        fn ginti sum_below(ginti n) {
+            ginti s = 0 .
+            duhrao (ginti i = 0 . sach . i = i + 1) {
+                agar (i == n) { toro } warna { s = s + i . }
+            }
+            wapsi s .
+        } .
+
+        fn ginti first_square_above(ginti n) {
+            duhrao (ginti i = 0 . . i = i + 1) {
+                agar (i * i > n) { wapsi i . } warna {}
+            }
+            wapsi -1 .
+        } .
+
+        fn ginti shuru() { wapsi sum_below(10) + first_square_above(50) . } .
+    ";
+    assert_eq!(exit_code(src), 45 + 8);
+}
+
+#[test]
+fn recursion() {
+    let src = "
+        fn ginti fib(ginti n) {
+            agar (n < 2) { wapsi n . } warna {}
+            wapsi fib(n - 1) + fib(n - 2) .
+        } .
+
+        fn ginti shuru() { wapsi fib(15) . } .
+    ";
+    assert_eq!(exit_code(src), 610);
+}
+
+#[test]
+fn other_types() {
+    let src = "
+        fn ginti shuru() {
+            asharia x = 1.5 * 4.0 .
+            jumla a = \"abc\" .
+            boli ok = x == 6.0 && a < \"abd\" && !(a == \"xyz\") .
+            agar (ok) { wapsi 1 . } warna { wapsi 0 . }
+        } .
+    ";
+    assert_eq!(exit_code(src), 1);
+}
+
+#[test]
+fn boolean_operators_short_circuit() {
+    let src = "
+        ginti calls = 0 .
+        fn boli noisy() { calls = calls + 1 . wapsi sach . } .
+
+        fn ginti shuru() {
+            boli a = jhoot && noisy() .
+            boli b = sach || noisy() .
+            boli c = sach && noisy() .
+            wapsi calls .
+        } .
+    ";
+    assert_eq!(exit_code(src), 1);
+}
+
+#[test]
+fn assignments_chain() {
+    let src = "fn ginti shuru() { ginti a = 0 . ginti b = 0 . a = b = 21 . wapsi a + b . } .";
+    assert_eq!(exit_code(src), 42);
+}
+
+#[test]
+fn khali_entry_point_exits_w_zero() {
+    assert_eq!(exit_code("fn khali shuru() { ginti a = 1 . wapsi . } ."), 0);
+}
+
+#[test]
+fn entry_point_receives_argc() {
+    let src = "fn ginti shuru(ginti argc) { wapsi argc . } .";
+    assert_eq!(exec_with_args(src, &["prog.nkt", "a", "b"]), Ok(3));
+}
+
+#[test]
+fn runtime_errors_carry_their_location() {
+    let src =
+        "fn ginti shuru(ginti argc) {\n    ginti zero = argc - 1 .\n    wapsi 1 / zero .\n} .";
+    assert_eq!(runtime_error(src), (RuntimeErrorKind::DivisionByZero, 3));
+
+    let src = "ginti big = 64 .\nginti bad = 1 << big .\nfn ginti shuru() { wapsi 0 . } .";
+    assert_eq!(
+        runtime_error(src),
+        (RuntimeErrorKind::ShiftOutOfRange(64), 2)
+    );
+
+    let src = "fn ginti f(ginti x) {\n agar (x > 0) { wapsi x . } warna {}\n} .\nfn ginti shuru() { wapsi f(0) . } .";
+    assert_eq!(
+        runtime_error(src),
+        (RuntimeErrorKind::MissingReturn("f".to_string()), 4)
+    );
+
+    let src = "fn ginti f(ginti x) { wapsi f(x + 1) . } .\nfn ginti shuru() { wapsi f(0) . } .";
+    assert_eq!(runtime_error(src).0, RuntimeErrorKind::StackOverflow);
+}
+
+#[test]
+fn recursion_goes_as_deep_as_on_the_vm() {
+    let src = "
+fn ginti depth(ginti n) {
+    agar (n == 0) { wapsi 0 . } warna {}
+    wapsi 1 + depth(n - 1) .
+} .
+
+fn ginti shuru() {
+    wapsi depth(20000) % 256 .
+} .
+";
+    let artifacts = compile_src_opt(src, SrcKind::Program, OptLevel::O0).unwrap();
+    let args = ["prog.nkt".to_string()];
+
+    assert_eq!(run(&artifacts.ast, &args), Ok(20000 % 256));
+    assert_eq!(vm::run(&compile(&artifacts.ast), &args), Ok(20000 % 256));
+    let ir = &artifacts.lowered.unwrap().ir;
+    assert_eq!(eval::run(ir, &args), Ok(20000 % 256));
+}
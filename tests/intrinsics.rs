@@ -0,0 +1,65 @@
+//! The built-in I/O functions: checked like user-defined ones, impossible to redefine, and
+//! implemented by the interpreter.
+
+use nuktah::{compile_src, interp::core::run_with_io, semantics::core::SrcKind};
+
+fn compile_err(src: &str) -> String {
+    format!("{:?}", compile_src(src, SrcKind::Program).unwrap_err())
+}
+
+fn run_w_stdin(src: &str, stdin: &str) -> (i64, String) {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap();
+    let mut input = stdin.as_bytes();
+    let mut output = vec![];
+
+    let code = run_with_io(&artifacts.ast, &[], &mut input, &mut output).unwrap();
+    (code, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn calls_are_type_checked() {
+    assert_eq!(
+        compile_err("fn ginti shuru() { likho(1) . wapsi 0 . } ."),
+        "SemanticErr(TypeChkErr(FnCallParamType))"
+    );
+    assert_eq!(
+        compile_err("fn ginti shuru() { likho_ginti() . wapsi 0 . } ."),
+        "SemanticErr(TypeChkErr(FnCallParamCount))"
+    );
+    assert_eq!(
+        compile_err("fn ginti shuru() { ginti n = parho() . wapsi n . } ."),
+        "SemanticErr(TypeChkErr(ErroneousVarDecl))"
+    );
+}
+
+#[test]
+fn intrinsics_cant_be_redefined() {
+    assert_eq!(
+        compile_err("fn khali likho(jumla s) { wapsi . } . fn ginti shuru() { wapsi 0 . } ."),
+        "SemanticErr(ScopeErr(IntrinsicRedefinition))"
+    );
+    assert_eq!(
+        compile_err("jumla parho = \"x\" . fn ginti shuru() { wapsi 0 . } ."),
+        "SemanticErr(ScopeErr(IntrinsicRedefinition))"
+    );
+}
+
+#[test]
+fn io() {
+    let src = "
+        fn ginti shuru() {
+            jumla name = parho() .
+            duhrao (ginti i = 1 . i < 4 . i = i + 1) {
+                likho_ginti(i * 10) .
+            }
+            likho(name) .
+            likho(parho()) .
+            likho(parho()) .
+            wapsi 7 .
+        } .
+    ";
+
+    let (code, stdout) = run_w_stdin(src, "nuktah\r\nsecond\n");
+    assert_eq!(code, 7);
+    assert_eq!(stdout, "10\n20\n30\nnuktah\nsecond\n\n");
+}
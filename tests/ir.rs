@@ -0,0 +1,182 @@
+//! Lowering to three-address code: control flow becomes branches b/w basic blocks, and whatever
+//! is lowered passes the verifier, which in turn rejects malformed modules.
+
+use std::fs;
+use std::path::Path;
+
+use nuktah::{
+    compile_src,
+    ir::{
+        core::{BinOp, BlockId, Const, Inst, Module, Operand, Reg, Terminator},
+        lower::lower,
+        print::module_to_string,
+        verify::{verify, VerifyErrorKind},
+    },
+    lexer::Span,
+    semantics::{core::SrcKind, spaghetti::SymType},
+};
+
+fn lower_src(src: &str) -> Module {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap_or_else(|e| panic!("{e:?}"));
+    lower(&artifacts.ast)
+}
+
+#[test]
+fn lowers_control_flow() {
+    let module = lower_src(
+        "
+ginti g = 1 .
+fn ginti shuru() {
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < 10 . i = i + 1) {
+        agar (i == 5 && g == 1) { toro . } warna { s = s + i . }
+    }
+    wapsi s .
+} .
+",
+    );
+
+    let expected = "\
+global @0 g: ginti
+
+fn shuru() -> ginti {
+bb0:
+    %0: ginti = copy 0
+    %1: ginti = copy 0
+    jump bb1
+bb1:
+    %2: ginti = copy %1
+    %3: boli = lt %2, 10
+    br %3, bb2, bb4
+bb2:
+    %4: ginti = copy %1
+    %5: boli = eq %4, 5
+    %6: boli = copy %5
+    br %6, bb5, bb6
+bb3:
+    %12: ginti = copy %1
+    %13: ginti = add %12, 1
+    %1: ginti = copy %13
+    jump bb1
+bb4:
+    %14: ginti = copy %0
+    ret %14
+bb5:
+    %7: ginti = load @0 g
+    %8: boli = eq %7, 1
+    %6: boli = copy %8
+    jump bb6
+bb6:
+    br %6, bb7, bb8
+bb7:
+    jump bb4
+bb8:
+    %9: ginti = copy %0
+    %10: ginti = copy %1
+    %11: ginti = add %9, %10
+    %0: ginti = copy %11
+    jump bb9
+bb9:
+    jump bb3
+}
+
+fn <init>() -> khali {
+bb0:
+    store @0 g, 1
+    ret
+}
+";
+    assert_eq!(module_to_string(&module), expected);
+    assert_eq!(verify(&module), Ok(()));
+}
+
+#[test]
+fn lowered_programs_verify() {
+    let programs = [
+        "fn ginti shuru(ginti argc) { wapsi -argc * 7 ^ 2 . } .",
+        "
+asharia half = 0.5 .
+jumla greeting = \"salaam\" .
+
+fn khali greet(jumla who) { likho(who) . wapsi . } .
+
+fn ginti tri(ginti n) {
+    agar (n < 1) { wapsi 0 . } warna {}
+    wapsi n + tri(n - 1) .
+} .
+
+fn ginti shuru() {
+    boli ok = half * 2.0 == 1.0 || !sach .
+    greet(greeting) .
+    greet(parho()) .
+    duhrao (. ok . ) { ok = jhoot . toro . }
+    agar (ok) { wapsi 1 . } warna { wapsi tri(4) << 1 | 1 . }
+} .
+",
+    ];
+
+    for src in programs {
+        let module = lower_src(src);
+        assert_eq!(verify(&module), Ok(()), "in:\n{src}");
+    }
+
+    // ... as do the examples that make it through analysis
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    for entry in fs::read_dir(examples).unwrap() {
+        let src = fs::read_to_string(entry.unwrap().path()).unwrap();
+        if let Ok(artifacts) = compile_src(&src, SrcKind::Library) {
+            assert_eq!(verify(&lower(&artifacts.ast)), Ok(()), "in:\n{src}");
+        }
+    }
+}
+
+#[test]
+fn verifier_rejects_malformed_modules() {
+    let base = lower_src("fn ginti shuru(ginti argc) { wapsi argc + 1 . } .");
+    let shuru = base.entry.unwrap() as usize;
+    assert_eq!(verify(&base), Ok(()));
+
+    let rejects = |tweak: &dyn Fn(&mut Module), expected: VerifyErrorKind| {
+        let mut module = base.clone();
+        tweak(&mut module);
+        assert_eq!(verify(&module).map_err(|e| e.kind), Err(expected));
+    };
+
+    rejects(
+        &|m| m.functions[shuru].blocks[0].term = Terminator::Jump(BlockId(7)),
+        VerifyErrorKind::UnknownBlock(BlockId(7)),
+    );
+    rejects(
+        &|m| {
+            let f = &mut m.functions[shuru];
+            let r = f.new_reg(SymType::Int);
+            f.blocks[0].term = Terminator::Ret(Some(Operand::Reg(r)));
+        },
+        VerifyErrorKind::UnassignedReg(Reg(3)),
+    );
+    rejects(
+        &|m| {
+            m.functions[shuru].blocks[0].term =
+                Terminator::Ret(Some(Operand::Const(Const::Bool(true))))
+        },
+        VerifyErrorKind::TypeMismatch(SymType::Int, SymType::Bool),
+    );
+    rejects(
+        &|m| {
+            let f = &mut m.functions[shuru];
+            let dst = f.new_reg(SymType::Bool);
+            f.blocks[0].insts.push(Inst::Binary {
+                dst,
+                op: BinOp::BitAnd,
+                lhs: Operand::Const(Const::Bool(true)),
+                rhs: Operand::Const(Const::Bool(false)),
+                span: Span::default(),
+            });
+        },
+        VerifyErrorKind::InvalidOperator,
+    );
+    rejects(
+        &|m| m.functions[shuru].blocks.clear(),
+        VerifyErrorKind::NoBlocks,
+    );
+}
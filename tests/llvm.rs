@@ -0,0 +1,152 @@
+//! The LLVM backend: modules run w/ the system's `lli`, as they are and once `opt -O2` has made SSA
+//! of their `alloca`s (and exploited anything undefined they do), print, read and exit w/ the same
+//! code as the programs do when interpreted, and fail the same way.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+
+use common::*;
+use nuktah::{codegen::llvm::core as llvm, compile_src, semantics::core::SrcKind};
+
+// The flags `opt` and `lli` need to read opaque pointers, or `None` if either isn't installed
+fn llvm_flags() -> Option<&'static [&'static str]> {
+    static FLAGS: OnceLock<Option<&[&str]>> = OnceLock::new();
+    *FLAGS.get_or_init(|| {
+        let version = |tool| Command::new(tool).arg("--version").output().ok();
+        let (Some(lli), Some(_)) = (version("lli"), version("opt")) else {
+            return None;
+        };
+
+        // They're the default from LLVM 15, whose tools no longer take the flag
+        let major = String::from_utf8_lossy(&lli.stdout)
+            .split("LLVM version ")
+            .nth(1)
+            .and_then(|v| v.split('.').next()?.parse::<u32>().ok());
+        match major {
+            Some(major) if major < 15 => Some(&["-opaque-pointers"]),
+            _ => Some(&[]),
+        }
+    })
+}
+
+// Whether there's an LLVM to run the tests w/; if not, they pass, saying they were skipped
+fn llvm_installed() -> bool {
+    let installed = llvm_flags().is_some();
+    if !installed {
+        eprintln!("skipped: `opt` and `lli` aren't both on PATH");
+    }
+    installed
+}
+
+// Generates a module for `src`, optimised w/ `opt` or not, and runs it w/ `lli`, `args` (its path
+// first) and `stdin`
+fn run_ll(src: &str, optimise: bool, args: &[String], stdin: &str) -> Output {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap();
+    let ll = llvm::generate(&artifacts.ast);
+
+    let path = std::env::temp_dir().join(format!(
+        "nkt-llvm-{}-{:x}.ll",
+        std::process::id(),
+        hash(&(src, optimise, args))
+    ));
+    std::fs::write(&path, &ll).unwrap();
+
+    let flags = llvm_flags().unwrap();
+    if optimise {
+        let opt = Command::new("opt")
+            .args(flags)
+            .args(["-O2", "-S", "-o"])
+            .arg(&path)
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(
+            opt.status.success(),
+            "{}\n{ll}",
+            String::from_utf8_lossy(&opt.stderr)
+        );
+    }
+
+    let output = run_piped(
+        Command::new("lli").args(flags).arg(&path).args(&args[1..]),
+        stdin,
+    );
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+fn assert_matches_interp(src: &str, stdin: &str) {
+    if !llvm_installed() {
+        return;
+    }
+
+    for (args, code, out) in interp_runs(src, stdin) {
+        for optimise in [false, true] {
+            let output = run_ll(src, optimise, &args, stdin);
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                out,
+                "optimised: {optimise}, w/ {args:?}: {}\n{}",
+                String::from_utf8_lossy(&output.stderr),
+                llvm::generate(&compile_src(src, SrcKind::Program).unwrap().ast)
+            );
+            assert_eq!(
+                output.status.code(),
+                Some(code as u8 as i32),
+                "optimised: {optimise}, w/ {args:?}: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+}
+
+#[test]
+fn integer_arithmetic_wraps() {
+    assert_matches_interp(ARITHMETIC, "");
+}
+
+#[test]
+fn floats_as_doubles() {
+    assert_matches_interp(FLOATS, "");
+}
+
+#[test]
+fn strings_and_input() {
+    for stdin in STRINGS_INPUTS {
+        assert_matches_interp(STRINGS, stdin);
+    }
+}
+
+#[test]
+fn calls_and_globals() {
+    assert_matches_interp(CALLS, "");
+}
+
+#[test]
+fn operands_are_evaluated_left_to_right() {
+    assert_matches_interp(ORDER, "");
+}
+
+#[test]
+fn runtime_errors_exit_w_1() {
+    if !llvm_installed() {
+        return;
+    }
+
+    for optimise in [false, true] {
+        for (argc, err) in RUNTIME_ERROR_CASES {
+            let args = vec![String::new(); argc];
+            let output = run_ll(RUNTIME_ERRORS, optimise, &args, "");
+            assert_eq!(output.status.code(), Some(1));
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+            assert_eq!(
+                String::from_utf8_lossy(&output.stderr),
+                format!("runtime error: {err}\n")
+            );
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Loop optimisations: invariant code is hoisted out of loops, and multiplications of induction
+//! variables are replaced by accumulators, leaving fewer instructions to run on every iteration.
+
+use nuktah::{
+    compile_src, compile_src_opt,
+    interp::core as interp,
+    ir::{
+        cfg::{natural_loops, Cfg},
+        core::{BinOp, Function, Inst, Module},
+        dom::DomTree,
+        eval,
+        verify::verify_ssa,
+    },
+    opt::{core::OptLevel, dce, licm, strength},
+    semantics::core::SrcKind,
+};
+
+const INVARIANT: &str = "
+ginti LIMIT = 0 .
+
+fn ginti shuru(ginti argc) {
+    LIMIT = argc + 9 .
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < 100 . i = i + 1) {
+        ginti scale = argc * 3 + 1 .
+        duhrao (ginti j = 0 . j < LIMIT . j = j + 1) {
+            s = s + scale * (argc - 1) + j .
+        }
+    }
+    wapsi s .
+} .
+";
+
+const INDUCTION: &str = "
+fn ginti shuru(ginti argc) {
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < 50 . i = i + 2) {
+        s = s + i * 4 + 8 * i .
+    }
+    duhrao (ginti k = argc * 10 . k > 0 . k = k - 1) {
+        s = s - k * 3 .
+    }
+    wapsi s .
+} .
+";
+
+// W/ every pass up to, but not including, the loop optimisations
+fn before_loop_opts(src: &str) -> Module {
+    compile_src_opt(src, SrcKind::Program, OptLevel::O1)
+        .unwrap_or_else(|e| panic!("{e:?}"))
+        .lowered
+        .unwrap()
+        .ssa
+}
+
+// The instructions in any of `f`'s loops
+fn loop_insts(f: &Function) -> Vec<&Inst> {
+    let cfg = Cfg::new(f);
+    let dom = DomTree::new(&cfg);
+    let loops = natural_loops(&cfg, &dom);
+    f.block_ids()
+        .filter(|&id| loops.iter().any(|lp| lp.contains(id)))
+        .flat_map(|id| &f.block(id).insts)
+        .collect()
+}
+
+fn n_muls(insts: &[&Inst]) -> usize {
+    insts
+        .iter()
+        .filter(|inst| matches!(inst, Inst::Binary { op: BinOp::Mul, .. }))
+        .count()
+}
+
+#[test]
+fn hoists_invariant_code() {
+    let mut module = before_loop_opts(INVARIANT);
+    let before = loop_insts(&module.functions[0]).len();
+    assert_eq!(n_muls(&loop_insts(&module.functions[0])), 2);
+
+    licm::run(&mut module).unwrap();
+    assert_eq!(verify_ssa(&module), Ok(()));
+
+    // `argc * 3 + 1`, `argc - 1`, `scale * ...` and reading `LIMIT` (which is never stored to
+    // in the loop) all leave both loops
+    let after = loop_insts(&module.functions[0]);
+    assert_eq!(n_muls(&after), 0);
+    assert!(!after
+        .iter()
+        .any(|inst| matches!(inst, Inst::LoadGlobal { .. })));
+    assert_eq!(after.len(), before - 5, "{after:#?}");
+}
+
+#[test]
+fn keeps_code_that_might_fail_or_change() {
+    let src = "
+ginti calls = 0 .
+
+fn khali bump() {
+    calls = calls + 1 .
+    wapsi .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti d = argc - 1 .
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < d . i = i + 1) {
+        s = s + 10 / d .
+    }
+    duhrao (ginti i = 0 . i < 3 . i = i + 1) {
+        bump() .
+        s = s + calls .
+    }
+    wapsi s .
+} .
+";
+    let mut module = before_loop_opts(src);
+    let shuru = module.function_id("shuru").unwrap() as usize;
+    let before = loop_insts(&module.functions[shuru]).len();
+
+    // `10 / d` would fail when the loop doesn't run; `calls` changes on every call
+    licm::run(&mut module).unwrap();
+    assert_eq!(loop_insts(&module.functions[shuru]).len(), before);
+
+    let args = ["prog.nkt".to_string()];
+    let res = eval::run_with_io(&module, &args, &mut "".as_bytes(), &mut vec![]);
+    assert_eq!(res.unwrap(), 6);
+}
+
+#[test]
+fn reduces_induction_variable_multiplications() {
+    let non_phis = |insts: &[&Inst]| insts.iter().filter(|inst| !inst.is_phi()).count();
+
+    let mut module = before_loop_opts(INDUCTION);
+    let before = loop_insts(&module.functions[0]);
+    assert_eq!(n_muls(&before), 3);
+    let before = non_phis(&before);
+
+    strength::run(&mut module).unwrap();
+    assert_eq!(verify_ssa(&module), Ok(()));
+
+    // Each multiplication (by `k`, too, whose starting value isn't known) becomes an addition,
+    // once `dce` has cleaned up the copies of the accumulators left in their place
+    dce::run(&mut module).unwrap();
+    let after = loop_insts(&module.functions[0]);
+    assert_eq!(n_muls(&after), 0);
+    assert_eq!(non_phis(&after), before, "{after:#?}");
+}
+
+#[test]
+fn preserves_behaviour() {
+    for src in [INVARIANT, INDUCTION] {
+        let artifacts = compile_src(src, SrcKind::Program).unwrap();
+        let optimised = compile_src_opt(src, SrcKind::Program, OptLevel::O2)
+            .unwrap()
+            .lowered
+            .unwrap();
+
+        for argv in [vec!["prog.nkt"], vec!["prog.nkt", "a", "b"]] {
+            let args = argv.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+            let run = |module: Option<&Module>| {
+                let mut out = vec![];
+                let mut input = "".as_bytes();
+                let code = match module {
+                    Some(module) => eval::run_with_io(module, &args, &mut input, &mut out),
+                    None => interp::run_with_io(&artifacts.ast, &args, &mut input, &mut out),
+                };
+                (code.unwrap(), String::from_utf8(out).unwrap())
+            };
+
+            assert_eq!(run(Some(&optimised.ssa)), run(None), "in SSA:\n{src}");
+            assert_eq!(run(Some(&optimised.ir)), run(None), "out of SSA:\n{src}");
+        }
+    }
+}
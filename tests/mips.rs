@@ -0,0 +1,78 @@
+//! The MIPS32 backend: programs run on the in-crate simulator print, read and exit w/ the same
+//! code as they do when interpreted, optimised or not.
+
+mod common;
+
+use common::*;
+use nuktah::{
+    codegen::{
+        mips::{core as mips, sim},
+        regalloc::core::Allocator,
+    },
+    compile_src_opt,
+    opt::core::OptLevel,
+    semantics::core::SrcKind,
+};
+
+// Compiles `src` at `level`, and runs it on the simulator w/ `args` and `stdin`
+fn run_mips(src: &str, level: OptLevel, args: &[String], stdin: &str) -> (i32, String) {
+    let lowered = compile_src_opt(src, SrcKind::Program, level)
+        .unwrap()
+        .lowered
+        .unwrap();
+    let asm = mips::generate(&lowered.ir, Allocator::for_level(level));
+
+    let mut out = vec![];
+    let code = sim::run_with_io(&asm, args, &mut stdin.as_bytes(), &mut out)
+        .unwrap_or_else(|e| panic!("{e:?}\n{asm}"));
+    (code, String::from_utf8(out).unwrap())
+}
+
+fn assert_matches_interp(src: &str, stdin: &str) {
+    for (args, code, out) in interp_runs(src, stdin) {
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let (mips_code, mips_out) = run_mips(src, level, &args, stdin);
+            assert_eq!(mips_out, out, "at {level:?}, w/ {args:?}:\n{src}");
+            assert_eq!(mips_code, code as i32, "at {level:?}, w/ {args:?}");
+        }
+    }
+}
+
+#[test]
+fn integer_arithmetic_in_pairs_of_words() {
+    assert_matches_interp(ARITHMETIC, "");
+}
+
+#[test]
+fn floats_in_coprocessor_1() {
+    assert_matches_interp(FLOATS, "");
+}
+
+#[test]
+fn strings_and_input() {
+    for stdin in STRINGS_INPUTS {
+        assert_matches_interp(STRINGS, stdin);
+    }
+}
+
+#[test]
+fn calls_and_globals() {
+    assert_matches_interp(CALLS, "");
+}
+
+#[test]
+fn operands_are_evaluated_left_to_right() {
+    assert_matches_interp(ORDER, "");
+}
+
+#[test]
+fn runtime_errors_exit_w_1() {
+    for level in [OptLevel::O0, OptLevel::O2] {
+        for (argc, err) in RUNTIME_ERROR_CASES {
+            let args = vec![String::new(); argc];
+            let (code, out) = run_mips(RUNTIME_ERRORS, level, &args, "");
+            assert_eq!(code, 1);
+            assert_eq!(out, format!("before\nruntime error: {err}\n"));
+        }
+    }
+}
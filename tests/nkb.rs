@@ -0,0 +1,170 @@
+//! `.nkb` files: programs survive a round trip through the format, and malformed files are
+//! rejected rather than run.
+
+use nuktah::{
+    bytecode::{
+        compiler::compile,
+        core::{Op, Program},
+        disasm::disassemble,
+        nkb::{deserialize, serialize, NkbError, VERSION},
+        vm,
+    },
+    compile_src,
+    interp::core::RuntimeErrorKind,
+    semantics::core::SrcKind,
+};
+
+const SRC: &str = "
+ginti scale = 3 .
+asharia half = 0.5 .
+
+fn ginti tri(ginti n) {
+    agar (n < 1) { wapsi 0 . } warna {}
+    wapsi n + tri(n - 1) .
+} .
+
+fn ginti shuru(ginti argc) {
+    boli ok = half * 2.0 == 1.0 && sach .
+    likho(\"tri:\") .
+    likho_ginti(tri(10) * scale) .
+    agar (ok) { wapsi argc . } warna { wapsi -1 . }
+} .
+";
+
+fn program() -> Program {
+    compile(&compile_src(SRC, SrcKind::Program).unwrap().ast)
+}
+
+#[test]
+fn round_trip() {
+    let program = program();
+    let bytes = serialize(&program);
+    assert!(bytes.starts_with(b"NKB\0"));
+
+    let loaded = deserialize(&bytes).unwrap();
+    assert_eq!(loaded, program);
+
+    let args = ["prog.nkb".to_string(), "x".to_string()];
+    let mut out = vec![];
+    let code = vm::run_with_io(&loaded, &args, &mut "".as_bytes(), &mut out).unwrap();
+    assert_eq!(code, 2);
+    assert_eq!(String::from_utf8(out).unwrap(), "tri:\n165\n");
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    let bytes = serialize(&program());
+
+    // Cut short anywhere
+    for len in 6..bytes.len() {
+        assert_eq!(
+            deserialize(&bytes[..len]),
+            Err(NkbError::Truncated),
+            "{len}"
+        );
+    }
+
+    let mut bad = bytes.clone();
+    bad[0] = b'X';
+    assert_eq!(deserialize(&bad), Err(NkbError::BadMagic));
+
+    let mut bad = bytes.clone();
+    bad[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert_eq!(
+        deserialize(&bad),
+        Err(NkbError::UnsupportedVersion(VERSION + 1))
+    );
+
+    let mut bad = bytes.clone();
+    bad.push(0);
+    assert_eq!(deserialize(&bad), Err(NkbError::TrailingBytes));
+
+    let mut program = program();
+    program.functions[0].code[0] = Op::Const(1000);
+    assert_eq!(
+        deserialize(&serialize(&program)),
+        Err(NkbError::IndexOutOfBounds)
+    );
+}
+
+#[test]
+fn tampered_code_is_an_error_not_a_crash() {
+    let src = "fn ginti shuru() { jumla s = \"x\" . ginti a = 1 . wapsi a + a . } .";
+    let mut program = compile(&compile_src(src, SrcKind::Program).unwrap().ast);
+    let shuru = program.entry.unwrap() as usize;
+
+    // Adding the jumla to itself: the stack's depth is right, but not its operands' types
+    let code = &mut program.functions[shuru].code;
+    let load_a = code.iter().position(|&op| op == Op::LoadLocal(1)).unwrap();
+    code[load_a] = Op::LoadLocal(0);
+    let loaded = deserialize(&serialize(&program)).unwrap();
+    let err = vm::run_with_io(&loaded, &[], &mut "".as_bytes(), &mut vec![]).unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::IllTyped);
+
+    // Popping what was never pushed
+    let code = &mut program.functions[shuru].code;
+    code.insert(0, Op::Pop);
+    assert_eq!(
+        deserialize(&serialize(&program)),
+        Err(NkbError::UnbalancedStack("shuru".to_string()))
+    );
+}
+
+#[test]
+fn disassembly_is_annotated_w_source() {
+    let listing = disassemble(&program(), Some(SRC));
+
+    assert!(listing.contains("fn #0 tri: arity 1, locals 1\n"));
+    assert!(listing.contains("fn #1 shuru [entry]: arity 1, locals 2\n"));
+    assert!(listing.contains("    ; line 7: wapsi n + tri(n - 1) .\n"));
+    assert!(listing.contains("Call 0                  ; tri\n"));
+    assert!(listing.contains("CallIntrinsic 1         ; likho_ginti\n"));
+
+    let without_src = disassemble(&program(), None);
+    assert!(without_src.contains("    ; line 7\n"));
+}
+
+#[test]
+fn functions_only_fall_off_their_end_if_they_can() {
+    let listing = disassemble(&program(), None);
+
+    // `tri` returns on its last line, so needn't check it did
+    assert!(listing.contains(
+        "\
+    ; line 7
+    0007  LoadLocal 0
+    0008  LoadLocal 0
+    0009  Const 2                 ; Int(1)
+    0010  Sub
+    0011  Call 0                  ; tri
+    0012  Add
+    0013  Ret
+
+fn #1 shuru"
+    ));
+    // Whereas `shuru` only does in its branches
+    assert!(listing.contains(
+        "\
+    0024  Ret
+    0025  MissingRet
+
+fn #2 <init>"
+    ));
+
+    let src = "fn khali hi() { likho(\"hi\") . wapsi . } . fn ginti shuru() { hi() . wapsi 0 . } .";
+    let listing = disassemble(
+        &compile(&compile_src(src, SrcKind::Program).unwrap().ast),
+        None,
+    );
+    assert!(listing.contains(
+        "\
+fn #0 hi: arity 0, locals 0
+    ; line 1
+    0000  Const 0                 ; String(\"hi\")
+    0001  CallIntrinsic 0         ; likho
+    0002  Pop
+    0003  RetVoid
+
+fn #1"
+    ));
+}
@@ -0,0 +1,334 @@
+//! Constant folding/propagation: what it folds away, the compile-time errors it reports, and that
+//! optimised code still does what the source says.
+
+use nuktah::{
+    compile_src, compile_src_opt,
+    interp::core::{self as interp, RuntimeErrorKind},
+    ir::{
+        core::{Const, Inst, Module, Operand, Terminator},
+        eval,
+        print::function_to_string,
+    },
+    opt::{
+        constfold,
+        core::{OptError, OptLevel},
+    },
+    semantics::core::SrcKind,
+    CompilerError,
+};
+
+fn optimised(src: &str) -> Module {
+    compile_src_opt(src, SrcKind::Program, OptLevel::O1)
+        .unwrap_or_else(|e| panic!("{e:?}"))
+        .lowered
+        .unwrap()
+        .ssa
+}
+
+// The error `src` fails to compile w/, which is the same however hard it's optimised
+fn opt_error(src: &str) -> OptError {
+    let errors = [OptLevel::O0, OptLevel::O1, OptLevel::O2].map(|level| {
+        match compile_src_opt(src, SrcKind::Program, level) {
+            Err(CompilerError::OptErr(e)) => e,
+            res => panic!("expected an optimisation error at {level:?}, got {res:?}"),
+        }
+    });
+    assert!(errors.iter().all(|e| *e == errors[0]), "{errors:?}");
+    errors.into_iter().next().unwrap()
+}
+
+#[test]
+fn folds_constant_expressions() {
+    let mut module = compile_src_opt(
+        "
+fn ginti shuru() {
+    ginti f = 4 .
+    ginti b = 2 .
+    ginti z = 3 .
+    ginti h = 6 * f + 3 * 2 - b * b * z * z .
+    boli big = h > 10 && !(1 << 3 == 8) .
+    agar (big) { wapsi h . } warna { wapsi -h . }
+} .
+",
+        SrcKind::Program,
+        OptLevel::O0,
+    )
+    .unwrap()
+    .lowered
+    .unwrap()
+    .ssa;
+    constfold::run(&mut module).unwrap();
+
+    // `&&` leaves a branch behind (for `dce` to fold), but nothing is computed at runtime any more
+    let expected = "\
+fn shuru() -> ginti {
+bb0:
+    br jhoot, bb1, bb2
+bb1:
+    jump bb2
+bb2:
+    br jhoot, bb3, bb4
+bb3:
+    ret -6
+bb4:
+    ret 6
+}
+";
+    assert_eq!(function_to_string(&module, &module.functions[0]), expected);
+}
+
+#[test]
+fn propagates_constant_globals() {
+    let module = optimised(
+        "
+ginti limit = 10 .
+ginti counter = 0 .
+
+fn ginti bump() {
+    counter = counter + limit .
+    wapsi counter .
+} .
+
+fn ginti shuru() {
+    wapsi bump() * limit .
+} .
+",
+    );
+
+    // `limit` is never reassigned, so it's folded into its readers; `counter` is, so it's not
+    let loads = |f: &str| {
+        let f = &module.functions[module.function_id(f).unwrap() as usize];
+        f.blocks
+            .iter()
+            .flat_map(|b| &b.insts)
+            .filter_map(|inst| match inst {
+                Inst::LoadGlobal { global, .. } => {
+                    Some(module.globals[*global as usize].name.as_str())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(loads("bump"), ["counter", "counter"]);
+    assert_eq!(loads("shuru"), Vec::<&str>::new());
+
+    let shuru = &module.functions[module.function_id("shuru").unwrap() as usize];
+    assert!(shuru
+        .blocks
+        .iter()
+        .flat_map(|b| &b.insts)
+        .any(|inst| matches!(
+            inst,
+            Inst::Binary {
+                rhs: Operand::Const(Const::Int(10)),
+                ..
+            }
+        )));
+}
+
+#[test]
+fn propagates_through_phis() {
+    // `x` is assigned the same constant along both paths, and isn't changed by the loop
+    let module = optimised(
+        "
+fn ginti shuru(ginti argc) {
+    ginti x = 0 .
+    agar (argc > 1) { x = 5 . } warna { x = 5 . }
+    duhrao (ginti i = 0 . i < argc . i = i + 1) {
+        x = x * 1 .
+    }
+    wapsi x .
+} .
+",
+    );
+
+    let f = &module.functions[0];
+    assert!(f
+        .blocks
+        .iter()
+        .any(|b| b.term == Terminator::Ret(Some(Operand::Const(Const::Int(5))))));
+    let n_phis = f
+        .blocks
+        .iter()
+        .flat_map(|b| &b.insts)
+        .filter(|inst| inst.is_phi())
+        .count();
+    assert_eq!(
+        n_phis,
+        1,
+        "only `i` should need one:\n{}",
+        function_to_string(&module, f)
+    );
+}
+
+#[test]
+fn reports_constant_errors() {
+    let e = opt_error("fn ginti shuru() {\n    ginti a = 1 .\n    wapsi a / (2 - 2) .\n} .");
+    assert_eq!(e.to_string(), "3:5: division by zero, in `shuru`");
+    assert!(matches!(e, OptError::ConstDivisionByZero(..)));
+
+    // Whether or not it's called, w/ or w/o optimising
+    let e = opt_error("fn ginti f() { wapsi 1 << 64 . } . fn ginti shuru() { wapsi 0 . } .");
+    assert!(matches!(e, OptError::ConstShiftOutOfRange(f, 64, _) if f == "f"));
+    let e = opt_error("fn ginti shuru() { wapsi 1 % 0 . } .");
+    assert!(matches!(e, OptError::ConstDivisionByZero(..)));
+
+    // After a loop, and after a branch, both ways round which control gets to it
+    let e = opt_error(
+        "
+fn ginti shuru(ginti argc) {
+    duhrao (ginti i = 0 . i < argc . i = i + 1) { likho_ginti(i) . }
+    agar (argc > 1) { likho(\"many\") . } warna { likho(\"one\") . }
+    ginti n = 0 - 2 .
+    wapsi 2 ^ n .
+} .
+",
+    );
+    assert_eq!(
+        e,
+        OptError::ConstNegativeExponent("shuru".to_string(), -2, e.span())
+    );
+    assert_eq!(e.span().line, 6);
+
+    // In a global's initialiser
+    let e =
+        opt_error("ginti g = 1 .\nginti h = g + 2 / (1 - 1) .\nfn ginti shuru() { wapsi h . } .");
+    assert!(matches!(e, OptError::ConstDivisionByZero(..)));
+    assert_eq!(e.span().line, 2);
+
+    // Code behind a condition that's never met is folded away
+    let module = optimised(
+        "fn ginti shuru() { agar (jhoot) { likho_ginti(1 / 0) . } warna {} wapsi 0 . } .",
+    );
+    assert_eq!(
+        function_to_string(&module, &module.functions[0]),
+        "fn shuru() -> ginti {\nbb0:\n    ret 0\n}\n"
+    );
+}
+
+#[test]
+fn leaves_failures_that_might_not_happen() {
+    // Only divides when there are more than 5 arguments, however hard it's optimised
+    let src = "
+fn ginti shuru(ginti argc) {
+    agar (argc > 5 && 1 / 0 == 1) { wapsi 1 . } warna {}
+    duhrao (ginti i = 0 . i < argc . i = i + 1) {
+        agar (i > 6) { likho_ginti(1 << 64) . } warna {}
+    }
+    wapsi 0 .
+} .
+";
+
+    for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+        let lowered = compile_src_opt(src, SrcKind::Program, level)
+            .unwrap_or_else(|e| panic!("at {level:?}: {e:?}"))
+            .lowered
+            .unwrap();
+
+        let run = |argc| {
+            let args = vec![String::new(); argc];
+            eval::run_with_io(&lowered.ir, &args, &mut "".as_bytes(), &mut vec![])
+        };
+        assert_eq!(run(1), Ok(0));
+        let e = run(6).unwrap_err();
+        assert_eq!((e.kind, e.span.line), (RuntimeErrorKind::DivisionByZero, 3));
+    }
+}
+
+#[test]
+fn only_finds_constant_errors_once_lowered() {
+    // Interpreting, or compiling from the AST, needs no IR, so the error waits until it's run
+    let src = "fn ginti shuru() { wapsi 1 / 0 . } .";
+    let artifacts = compile_src(src, SrcKind::Program).unwrap();
+    assert!(artifacts.lowered.is_none());
+    let e = interp::run(&artifacts.ast, &["prog.nkt".to_string()]).unwrap_err();
+    assert_eq!(e.kind, RuntimeErrorKind::DivisionByZero);
+
+    assert!(matches!(opt_error(src), OptError::ConstDivisionByZero(..)));
+}
+
+#[test]
+fn folds_nan() {
+    // NaN isn't equal to itself, but folding it must still settle, in a loop too
+    let module = optimised(
+        "
+fn ginti shuru(ginti argc) {
+    asharia x = 0.0 / 0.0 .
+    duhrao (ginti i = 0 . i < argc . i = i + 1) {
+        x = x * 1.0 .
+    }
+    agar (x == x) { wapsi 1 . } warna { wapsi 2 . }
+} .
+",
+    );
+
+    let f = &module.functions[0];
+    assert!(
+        f.blocks
+            .iter()
+            .any(|b| b.term == Terminator::Ret(Some(Operand::Const(Const::Int(2))))),
+        "{}",
+        function_to_string(&module, f)
+    );
+    assert!(!f
+        .blocks
+        .iter()
+        .any(|b| b.term == Terminator::Ret(Some(Operand::Const(Const::Int(1))))));
+}
+
+#[test]
+fn preserves_behaviour() {
+    let programs = [
+        // Looks constant on the first trip round the loop, but isn't
+        "
+fn ginti shuru() {
+    ginti d = 1 .
+    ginti q = 0 .
+    duhrao (ginti i = 0 . i < 4 . i = i + 1) {
+        d = d + 1 .
+        q = q + 12 / (d - 1) .
+    }
+    wapsi q .
+} .
+",
+        "
+ginti base = 3 .
+asharia half = 0.5 .
+
+fn asharia scale(asharia x) {
+    wapsi x * half * 2.0 .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < 10 . i = i + 1) {
+        agar (i % base == 0 || 2 > 3) { s = s + i * (1 << 4) . } warna { s = s - base ^ 2 . }
+    }
+    likho_ginti(s) .
+    agar (scale(1.5) == 1.5 && !jhoot) { wapsi s + argc . } warna { wapsi 0 . }
+} .
+",
+    ];
+
+    for src in programs {
+        let lowered = compile_src(src, SrcKind::Program).unwrap();
+
+        let run = |module: Option<&Module>| {
+            let args = ["prog.nkt".to_string(), "arg".to_string()];
+            let mut out = vec![];
+            let mut input = "salaam\n".as_bytes();
+            let code = match module {
+                Some(module) => eval::run_with_io(module, &args, &mut input, &mut out),
+                None => interp::run_with_io(&lowered.ast, &args, &mut input, &mut out),
+            };
+            (code.unwrap(), String::from_utf8(out).unwrap())
+        };
+
+        let optimised = compile_src_opt(src, SrcKind::Program, OptLevel::O1)
+            .unwrap()
+            .lowered
+            .unwrap();
+        assert_eq!(run(Some(&optimised.ssa)), run(None), "in SSA:\n{src}");
+        assert_eq!(run(Some(&optimised.ir)), run(None), "out of SSA:\n{src}");
+    }
+}
@@ -0,0 +1,272 @@
+//! Register allocation: neither allocator hands one register to two values live at once, or a
+//! caller-saved one to a value live across a call, for any of the native targets, and programs
+//! that need more registers than there are run the same once values are spilled.
+
+use std::collections::BTreeSet;
+
+use nuktah::{
+    codegen::{
+        mips,
+        regalloc::core::{allocate, Allocation, Allocator, Loc, RegFile},
+        riscv64, x86_64,
+    },
+    compile_src, compile_src_opt,
+    interp::core as interp,
+    ir::{
+        cfg::Cfg,
+        core::{Function, Operand, Reg},
+        live::Liveness,
+    },
+    opt::core::OptLevel,
+    semantics::{core::SrcKind, spaghetti::SymType},
+};
+
+// More values live at once, and across calls, than any target has registers for
+const PRESSURE: &str = "
+fn ginti id(ginti x) {
+    wapsi x .
+} .
+
+fn asharia half(asharia x) {
+    wapsi x / 2.0 .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti a = argc + 1 . ginti b = argc + 2 . ginti c = argc + 3 . ginti d = argc + 4 .
+    ginti e = argc + 5 . ginti f = argc + 6 . ginti g = argc + 7 . ginti h = argc + 8 .
+    ginti i = argc + 9 . ginti j = argc + 10 . ginti k = argc + 11 . ginti l = argc + 12 .
+    ginti m = argc + 13 . ginti n = argc + 14 . ginti o = argc + 15 . ginti p = argc + 16 .
+    asharia x = 1.5 . asharia y = 2.5 . asharia z = 3.5 . asharia w = 4.5 .
+    asharia u = 5.5 . asharia v = 6.5 . asharia q = 7.5 . asharia r = 8.5 .
+    boli big = argc > 1 .
+    jumla s = \"spilled\" .
+
+    ginti total = 0 .
+    duhrao (ginti t = 0 . t < 3 . t = t + 1) {
+        total = total + id(a) * b - c + d * e - f + g * h - i + j * k - l + m * n - o + p .
+        x = half(x + y) + z - w + u - v + q - r .
+    }
+
+    likho_ginti(total + a + b + c + d + e + f + g + h + i + j + k + l + m + n + o + p) .
+    agar (x > y + z + w + u + v + q + r) { likho(\"x\") . } warna { likho(s) . }
+    agar (big) { likho(\"big\") . } warna { likho(\"small\") . }
+    wapsi total % 100 .
+} .
+";
+
+const BRANCHY: &str = "
+fn ginti pick(ginti argc, ginti a, ginti b) {
+    ginti r = 0 .
+    agar (argc > 1) { r = a * b . } warna { r = a - b . }
+    duhrao (ginti i = 0 . i < argc . i = i + 1) {
+        agar (i == 1) { toro . } warna { r = r + i ^ 2 . }
+    }
+    wapsi r .
+} .
+
+fn ginti shuru(ginti argc) {
+    ginti x = pick(argc, 6, 7) .
+    ginti y = pick(argc + 1, x, 3) .
+    likho_ginti(x << 2) .
+    likho_ginti(y / 2) .
+    wapsi x + y .
+} .
+";
+
+const TARGETS: [(&str, &RegFile); 3] = [
+    ("x86-64", &x86_64::REG_FILE),
+    ("rv64", &riscv64::core::REG_FILE),
+    ("mips32", &mips::core::REG_FILE),
+];
+
+const ALLOCATORS: [Allocator; 2] = [Allocator::LinearScan, Allocator::GraphColouring];
+
+fn regs(operands: Vec<&Operand>) -> impl Iterator<Item = Reg> + '_ {
+    operands.into_iter().filter_map(|operand| match operand {
+        Operand::Reg(reg) => Some(*reg),
+        Operand::Const(_) => None,
+    })
+}
+
+// Each instruction's destination, w/ the registers live right after it, and whether it calls
+// out, backwards through each block
+fn live_after_each(f: &Function, file: &RegFile) -> Vec<(Option<Reg>, BTreeSet<Reg>, bool)> {
+    let liveness = Liveness::new(f, &Cfg::new(f));
+    let mut points = vec![];
+
+    for id in f.block_ids() {
+        let block = f.block(id);
+        let mut live = liveness.live_out[id.0 as usize].clone();
+        live.extend(regs(block.term.operands()));
+
+        for inst in block.insts.iter().rev() {
+            points.push((inst.dst(), live.clone(), (file.clobbers)(f, inst)));
+            if let Some(dst) = inst.dst() {
+                live.remove(&dst);
+            }
+            live.extend(regs(inst.operands()));
+        }
+    }
+
+    // Parameters are all defined on entry
+    let mut on_entry: BTreeSet<Reg> = f.params.iter().copied().collect();
+    on_entry.extend(&liveness.live_in[0]);
+    points.push((None, on_entry, false));
+    points
+}
+
+fn used(f: &Function, reg: Reg) -> bool {
+    f.params.contains(&reg)
+        || f.blocks.iter().any(|b| {
+            let mut insts = b.insts.iter();
+            insts.any(|inst| inst.dst() == Some(reg) || regs(inst.operands()).any(|r| r == reg))
+                || regs(b.term.operands()).any(|r| r == reg)
+        })
+}
+
+fn each_allocation(src: &str, mut check: impl FnMut(&str, &RegFile, &Function, &Allocation)) {
+    for level in [OptLevel::O0, OptLevel::O2] {
+        let lowered = compile_src_opt(src, SrcKind::Program, level)
+            .unwrap()
+            .lowered
+            .unwrap();
+        for (target, file) in TARGETS {
+            for allocator in ALLOCATORS {
+                for f in &lowered.ir.functions {
+                    let alloc = allocate(f, file, allocator);
+                    let ctx = format!("{target}, {allocator:?}, at {level:?}, in `{}`", f.name);
+                    check(&ctx, file, f, &alloc);
+                }
+            }
+        }
+    }
+}
+
+fn assert_sound(src: &str) {
+    each_allocation(src, |ctx, file, f, alloc| {
+        for (dst, live, calls_out) in live_after_each(f, file) {
+            let mut held = vec![];
+            for &reg in live.iter().chain(&dst) {
+                if let Loc::Reg(name) = alloc.loc(reg) {
+                    held.push((name, reg));
+                }
+            }
+
+            held.sort();
+            held.dedup();
+            for pair in held.windows(2) {
+                assert_ne!(
+                    pair[0].0,
+                    pair[1].0,
+                    "{ctx}: {:?} share",
+                    (pair[0], pair[1])
+                );
+            }
+
+            // Those live across a call, but not what it defines
+            for &reg in live.iter().filter(|&&reg| calls_out && Some(reg) != dst) {
+                if let Loc::Reg(name) = alloc.loc(reg) {
+                    let saved = [&file.int, &file.float]
+                        .iter()
+                        .any(|regs| regs.callee_saved.contains(&name));
+                    assert!(saved, "{ctx}: {reg:?} is in {name} across a call");
+                }
+            }
+        }
+    });
+}
+
+#[test]
+fn registers_live_at_once_never_share() {
+    assert_sound(PRESSURE);
+    assert_sound(BRANCHY);
+}
+
+#[test]
+fn callee_saved_registers_are_saved() {
+    each_allocation(PRESSURE, |ctx, file, f, alloc| {
+        for reg in (0..f.reg_types.len() as u32)
+            .map(Reg)
+            .filter(|&r| used(f, r))
+        {
+            if let Loc::Reg(name) = alloc.loc(reg) {
+                let callee_saved = [&file.int, &file.float]
+                    .iter()
+                    .any(|regs| regs.callee_saved.contains(&name));
+                let saved = alloc.callee_saved.iter().any(|&(_, r)| r == name);
+                assert_eq!(callee_saved, saved, "{ctx}: {name}");
+            }
+        }
+    });
+}
+
+#[test]
+fn too_many_values_are_spilled() {
+    each_allocation(PRESSURE, |ctx, _, f, alloc| {
+        if f.name == "shuru" {
+            assert!(alloc.n_slots > 0, "{ctx}: nothing spilled");
+        }
+    });
+}
+
+#[test]
+fn mips32_keeps_ginti_in_slots() {
+    let lowered = compile_src_opt(PRESSURE, SrcKind::Program, OptLevel::O2)
+        .unwrap()
+        .lowered
+        .unwrap();
+    for f in &lowered.ir.functions {
+        for allocator in ALLOCATORS {
+            let alloc = allocate(f, &mips::core::REG_FILE, allocator);
+            for reg in (0..f.reg_types.len() as u32)
+                .map(Reg)
+                .filter(|&r| used(f, r))
+            {
+                if f.reg_type(reg) == SymType::Int {
+                    assert!(matches!(alloc.loc(reg), Loc::Slot(_)), "{reg:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn spilled_programs_run_the_same() {
+    for src in [PRESSURE, BRANCHY] {
+        let artifacts = compile_src(src, SrcKind::Program).unwrap();
+
+        for argc in 1..=3 {
+            let args = (0..argc).map(|i| format!("arg{i}")).collect::<Vec<_>>();
+            let mut out = vec![];
+            let code = interp::run_with_io(&artifacts.ast, &args, &mut "".as_bytes(), &mut out)
+                .unwrap_or_else(|e| panic!("{e}"));
+            let out = String::from_utf8(out).unwrap();
+
+            for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+                let ir = compile_src_opt(src, SrcKind::Program, level)
+                    .unwrap()
+                    .lowered
+                    .unwrap()
+                    .ir;
+
+                for allocator in ALLOCATORS {
+                    let ctx = format!("{allocator:?} at {level:?}, w/ argc {argc}");
+
+                    let asm = riscv64::core::generate(&ir, allocator);
+                    let mut rv_out = vec![];
+                    let res =
+                        riscv64::sim::run_with_io(&asm, &args, &mut "".as_bytes(), &mut rv_out);
+                    assert_eq!(res, Ok(code as i32), "rv64, {ctx}:\n{asm}");
+                    assert_eq!(String::from_utf8(rv_out).unwrap(), out, "rv64, {ctx}");
+
+                    let asm = mips::core::generate(&ir, allocator);
+                    let mut mips_out = vec![];
+                    let res =
+                        mips::sim::run_with_io(&asm, &args, &mut "".as_bytes(), &mut mips_out);
+                    assert_eq!(res, Ok(code as i32), "mips32, {ctx}:\n{asm}");
+                    assert_eq!(String::from_utf8(mips_out).unwrap(), out, "mips32, {ctx}");
+                }
+            }
+        }
+    }
+}
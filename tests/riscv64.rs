@@ -0,0 +1,122 @@
+//! The RISC-V backend: programs run on the in-crate RV64 interpreter print, read and exit w/ the
+//! same code as they do when interpreted, optimised or not.
+
+mod common;
+
+use common::*;
+use nuktah::{
+    codegen::{
+        regalloc::core::Allocator,
+        riscv64::{core as riscv64, sim},
+    },
+    compile_src_opt,
+    opt::core::OptLevel,
+    semantics::core::SrcKind,
+};
+
+const SPILLED_ARGS: &str = "
+fn asharia spread(ginti a, asharia x, ginti b, asharia y, ginti c, asharia z, ginti d, asharia w,
+                  ginti e, asharia v, ginti f, asharia u, ginti g, asharia t, ginti h, asharia s,
+                  ginti i, asharia r, ginti j, asharia q) {
+    ginti ints = a + 2 * b + 3 * c + 4 * d + 5 * e + 6 * f + 7 * g + 8 * h + 9 * i + 10 * j .
+    likho_ginti(ints) .
+    wapsi x + 2.0 * y + 3.0 * z + 4.0 * w + 5.0 * v + 6.0 * u + 7.0 * t + 8.0 * s + 9.0 * r
+          + 10.0 * q .
+} .
+
+fn ginti shuru(ginti argc) {
+    asharia total = spread(argc, 0.5, 2, 1.5, 3, 2.5, 4, 3.5, 5, 4.5, 6, 5.5, 7, 6.5, 8, 7.5,
+                           9, 8.5, 10, 9.5) .
+    agar (total == 357.5) { likho(\"sahi\") . } warna { likho(\"ghalat\") . }
+    wapsi argc .
+} .
+";
+
+// Compiles `src` at `level`, and runs it on the interpreter w/ `args` and `stdin`
+fn run_riscv64(
+    src: &str,
+    level: OptLevel,
+    args: &[String],
+    stdin: &str,
+) -> (Result<i32, sim::SimError>, String) {
+    let lowered = compile_src_opt(src, SrcKind::Program, level)
+        .unwrap()
+        .lowered
+        .unwrap();
+    let asm = riscv64::generate(&lowered.ir, Allocator::for_level(level));
+
+    let mut out = vec![];
+    let res = sim::run_with_io(&asm, args, &mut stdin.as_bytes(), &mut out);
+    if let Err(e @ (sim::SimError::Syntax(..) | sim::SimError::UnknownSymbol(_))) = &res {
+        panic!("{e:?}\n{asm}");
+    }
+    (res, String::from_utf8(out).unwrap())
+}
+
+fn assert_matches_interp(src: &str, stdin: &str) {
+    for (args, code, out) in interp_runs(src, stdin) {
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let (res, riscv64_out) = run_riscv64(src, level, &args, stdin);
+            assert_eq!(riscv64_out, out, "at {level:?}, w/ {args:?}:\n{src}");
+            assert_eq!(res, Ok(code as i32), "at {level:?}, w/ {args:?}");
+        }
+    }
+}
+
+#[test]
+fn integer_arithmetic() {
+    assert_matches_interp(ARITHMETIC, "");
+}
+
+#[test]
+fn floats_in_float_registers() {
+    assert_matches_interp(FLOATS, "");
+}
+
+#[test]
+fn strings_and_input() {
+    for stdin in STRINGS_INPUTS {
+        assert_matches_interp(STRINGS, stdin);
+    }
+}
+
+#[test]
+fn calls_and_globals() {
+    assert_matches_interp(CALLS, "");
+}
+
+#[test]
+fn operands_are_evaluated_left_to_right() {
+    assert_matches_interp(ORDER, "");
+}
+
+#[test]
+fn args_past_the_registers_go_on_the_stack() {
+    assert_matches_interp(SPILLED_ARGS, "");
+}
+
+#[test]
+fn frames_too_big_for_immediate_offsets() {
+    let mut src = String::from("fn ginti shuru(ginti argc) {\n    ginti v0 = argc .\n");
+    for i in 1..400 {
+        src.push_str(&format!("    ginti v{i} = v{} * 3 + argc .\n", i - 1));
+    }
+    src.push_str("    likho_ginti(v399) .\n    wapsi v399 % 100 .\n} .\n");
+
+    assert_matches_interp(&src, "");
+}
+
+#[test]
+fn runtime_errors_end_the_program() {
+    for level in [OptLevel::O0, OptLevel::O2] {
+        for (argc, err) in RUNTIME_ERROR_CASES {
+            let args = vec![String::new(); argc];
+            let (res, out) = run_riscv64(RUNTIME_ERRORS, level, &args, "");
+            match res {
+                Err(sim::SimError::Runtime(kind)) => assert_eq!(format!("{kind:?}"), err),
+                res => panic!("expected {err}, got {res:?}"),
+            }
+            assert_eq!(out, "before\n");
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! SSA construction and destruction: both preserve what a program does, the constructed form
+//! passes the SSA verifier, and the verifier rejects modules that break its invariants.
+
+use nuktah::{
+    compile_src,
+    interp::core as interp,
+    ir::{
+        core::{Const, Inst, Module, Operand},
+        eval,
+        lower::lower,
+        print::function_to_string,
+        ssa,
+        verify::{verify, verify_ssa, VerifyErrorKind},
+    },
+    semantics::core::SrcKind,
+};
+
+const PROGRAMS: [&str; 4] = [
+    "
+fn ginti shuru(ginti argc) {
+    ginti s = 0 .
+    duhrao (ginti i = 0 . i < 10 . i = i + 1) {
+        duhrao (ginti j = 0 . j < i . j = j + 1) {
+            agar (j == 3 || i == j + 5) { toro . } warna { s = s + i * j . }
+        }
+    }
+    wapsi s + argc .
+} .
+",
+    "
+ginti calls = 0 .
+
+fn ginti fib(ginti n) {
+    calls = calls + 1 .
+    agar (n < 2) { wapsi n . } warna {}
+    wapsi fib(n - 1) + fib(n - 2) .
+} .
+
+fn ginti shuru() {
+    ginti a = fib(12) .
+    likho_ginti(calls) .
+    wapsi a .
+} .
+",
+    "
+fn asharia sqrt(asharia x) {
+    asharia guess = x / 2.0 .
+    duhrao (ginti i = 0 . i < 20 . i = i + 1) {
+        guess = (guess + x / guess) / 2.0 .
+    }
+    wapsi guess .
+} .
+
+fn ginti shuru() {
+    jumla line = parho() .
+    likho(line) .
+    boli close = sqrt(2.0) * sqrt(2.0) - 2.0 < 0.000001 .
+    agar (close && !jhoot) { wapsi 1 . } warna { wapsi 0 . }
+} .
+",
+    // Swapping through a loop, the classic case for naive out-of-SSA copies going wrong
+    "
+fn ginti shuru() {
+    ginti a = 1 .
+    ginti b = 2 .
+    duhrao (ginti i = 0 . i < 5 . i = i + 1) {
+        ginti t = a .
+        a = b .
+        b = t .
+    }
+    wapsi a * 10 + b .
+} .
+",
+];
+
+fn lower_src(src: &str) -> Module {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap_or_else(|e| panic!("{e:?}"));
+    lower(&artifacts.ast)
+}
+
+fn eval_module(module: &Module) -> (i64, String) {
+    let args = ["prog.nkt".to_string(), "arg".to_string()];
+    let mut out = vec![];
+    let code = eval::run_with_io(module, &args, &mut "salaam\n".as_bytes(), &mut out).unwrap();
+    (code, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn round_trip_preserves_behaviour() {
+    for src in PROGRAMS {
+        let artifacts = compile_src(src, SrcKind::Program).unwrap();
+        let args = ["prog.nkt".to_string(), "arg".to_string()];
+        let mut out = vec![];
+        let code = interp::run_with_io(&artifacts.ast, &args, &mut "salaam\n".as_bytes(), &mut out);
+        let expected = (code.unwrap(), String::from_utf8(out).unwrap());
+
+        let mut module = lower(&artifacts.ast);
+        assert_eq!(eval_module(&module), expected, "lowered:\n{src}");
+
+        ssa::construct(&mut module);
+        assert_eq!(verify_ssa(&module), Ok(()), "in:\n{src}");
+        assert_eq!(eval_module(&module), expected, "in SSA:\n{src}");
+
+        ssa::destruct(&mut module);
+        assert_eq!(verify(&module), Ok(()), "in:\n{src}");
+        assert!(module
+            .functions
+            .iter()
+            .flat_map(|f| &f.blocks)
+            .all(|b| !b.insts.iter().any(Inst::is_phi)));
+        assert_eq!(eval_module(&module), expected, "out of SSA:\n{src}");
+    }
+}
+
+#[test]
+fn phis_only_where_needed() {
+    let mut module = lower_src(
+        "
+fn ginti shuru() {
+    ginti x = 0 .
+    duhrao (ginti i = 0 . i < 3 . i = i + 1) {
+        ginti dead = i * 2 .
+        x = x + 1 .
+    }
+    wapsi x .
+} .
+",
+    );
+    ssa::construct(&mut module);
+
+    // `dead` isn't live across blocks, so it gets no phi; `x` and `i` do, at the loop's header
+    let expected = "\
+fn shuru() -> ginti {
+bb0:
+    %0: ginti = copy 0
+    %1: ginti = copy 0
+    jump bb1
+bb1:
+    %12: ginti = phi [bb0: %0], [bb3: %14]
+    %13: ginti = phi [bb0: %1], [bb3: %15]
+    %2: ginti = copy %13
+    %3: boli = lt %2, 3
+    br %3, bb2, bb4
+bb2:
+    %4: ginti = copy %13
+    %5: ginti = mul %4, 2
+    %6: ginti = copy %5
+    %7: ginti = copy %12
+    %8: ginti = add %7, 1
+    %14: ginti = copy %8
+    jump bb3
+bb3:
+    %9: ginti = copy %13
+    %10: ginti = add %9, 1
+    %15: ginti = copy %10
+    jump bb1
+bb4:
+    %11: ginti = copy %12
+    ret %11
+}
+";
+    assert_eq!(function_to_string(&module, &module.functions[0]), expected);
+}
+
+#[test]
+fn verifier_rejects_broken_ssa() {
+    let mut base = lower_src(PROGRAMS[3]);
+    ssa::construct(&mut base);
+    assert_eq!(verify_ssa(&base), Ok(()));
+
+    // Non-SSA code is fine by `verify`, but not by `verify_ssa`
+    let lowered = lower_src(PROGRAMS[3]);
+    assert_eq!(verify(&lowered), Ok(()));
+    assert!(matches!(
+        verify_ssa(&lowered).map_err(|e| e.kind),
+        Err(VerifyErrorKind::MultipleAssignment(_))
+    ));
+
+    let rejects = |tweak: &dyn Fn(&mut Module), expected: VerifyErrorKind| {
+        let mut module = base.clone();
+        tweak(&mut module);
+        assert_eq!(verify_ssa(&module).map_err(|e| e.kind), Err(expected));
+    };
+
+    // The loop header's first phi
+    let (phi_dst, phi_args) = match &base.functions[0].blocks[1].insts[0] {
+        Inst::Phi { dst, args } => (*dst, args.clone()),
+        inst => panic!("expected a phi, found {inst:?}"),
+    };
+
+    rejects(
+        &|m| {
+            let args = phi_args[1..].to_vec();
+            m.functions[0].blocks[1].insts[0] = Inst::Phi { dst: phi_dst, args };
+        },
+        VerifyErrorKind::PhiArgs,
+    );
+    rejects(
+        &|m| {
+            let phi = m.functions[0].blocks[1].insts.remove(0);
+            m.functions[0].blocks[1].insts.push(phi);
+        },
+        VerifyErrorKind::MisplacedPhi,
+    );
+    rejects(
+        &|m| {
+            // Read in the entry, before the header is ever reached
+            let f = &mut m.functions[0];
+            let tmp = f.new_reg(f.reg_type(phi_dst));
+            f.blocks[0].insts.push(Inst::Copy {
+                dst: tmp,
+                src: Operand::Reg(phi_dst),
+            });
+        },
+        VerifyErrorKind::UseNotDominated(phi_dst),
+    );
+    rejects(
+        &|m| {
+            m.functions[0].blocks[1].insts.push(Inst::Copy {
+                dst: phi_dst,
+                src: Operand::Const(Const::Int(0)),
+            })
+        },
+        VerifyErrorKind::MultipleAssignment(phi_dst),
+    );
+}
@@ -0,0 +1,49 @@
+//! Expressions the type checker rejects, that nothing after it could make sense of.
+
+use nuktah::{compile_src, semantics::core::SrcKind, CompilerError};
+
+fn type_error(src: &str) -> String {
+    match compile_src(src, SrcKind::Program) {
+        Err(e @ CompilerError::SemanticErr(_)) => format!("{e:?}"),
+        res => panic!("expected a semantic error, got {res:?}"),
+    }
+}
+
+fn in_shuru(stmt: &str) -> String {
+    format!("fn ginti shuru() {{ ginti a = 1 . asharia x = 1.0 . {stmt} wapsi a . }} .")
+}
+
+#[test]
+fn only_variables_are_assigned_to() {
+    for stmt in ["1 = 2 .", "a + 1 = 2 .", "a = a + 1 = 2 ."] {
+        assert_eq!(
+            type_error(&in_shuru(stmt)),
+            "SemanticErr(TypeChkErr(InvalidAssignmentTarget))",
+            "{stmt}"
+        );
+    }
+
+    for stmt in ["a = 2 .", "a = a = 3 .", "x = 2.5 ."] {
+        assert!(
+            compile_src(&in_shuru(stmt), SrcKind::Program).is_ok(),
+            "{stmt}"
+        );
+    }
+}
+
+#[test]
+fn bitwise_operators_take_gintis() {
+    for stmt in [
+        "x = 1.0 | 2.0 .",
+        "x = x & 2.0 .",
+        "boli b = sach | jhoot .",
+    ] {
+        assert_eq!(
+            type_error(&in_shuru(stmt)),
+            "SemanticErr(TypeChkErr(AttemptedBitOpOnNonNumeric))",
+            "{stmt}"
+        );
+    }
+
+    assert!(compile_src(&in_shuru("a = 12 | 3 & a ."), SrcKind::Program).is_ok());
+}
@@ -0,0 +1,82 @@
+//! `VisitorMut`: a pass overriding a few `visit_*_mut` methods reaches every node of their kind,
+//! however deeply nested, and its edits stick.
+
+use nuktah::{
+    parse_src,
+    parser::ast::{
+        core::*,
+        print::ast_to_string,
+        visit::{self, VisitorMut},
+    },
+};
+
+// Renames a variable, wherever it's declared or used, leaving functions of the same name be
+struct Rename<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+impl Rename<'_> {
+    fn rename(&self, ident: &mut String) {
+        if ident == self.from {
+            *ident = self.to.to_string();
+        }
+    }
+}
+
+impl VisitorMut for Rename<'_> {
+    fn visit_var_decl_mut(&mut self, var_decl: &mut VarDecl) {
+        self.rename(&mut var_decl.ident);
+        visit::walk_var_decl_mut(self, var_decl);
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        self.rename(&mut param.ident);
+    }
+
+    fn visit_primary_expr_mut(&mut self, expr: &mut PrimaryExpr) {
+        if let PrimaryExpr::Ident(ident) = expr {
+            self.rename(ident);
+        }
+        visit::walk_primary_expr_mut(self, expr);
+    }
+}
+
+#[test]
+fn renames_a_variable() {
+    let src = "
+ginti n = 3 .
+
+fn ginti n2(ginti n) {
+    wapsi n * (n + 1) .
+} .
+
+fn ginti shuru() {
+    duhrao (ginti i = n . i > 0 . i = i - 1) {
+        agar (i == n) { n = n2(-n) . } warna { ginti n2 = n . }
+    }
+    wapsi n .
+} .
+";
+    let renamed = "
+ginti k = 3 .
+
+fn ginti n2(ginti k) {
+    wapsi k * (k + 1) .
+} .
+
+fn ginti shuru() {
+    duhrao (ginti i = k . i > 0 . i = i - 1) {
+        agar (i == k) { k = n2(-k) . } warna { ginti n2 = k . }
+    }
+    wapsi k .
+} .
+";
+
+    let mut ast_root = parse_src(src).unwrap();
+    Rename { from: "n", to: "k" }.visit_translation_unit_mut(&mut ast_root);
+    assert_eq!(
+        ast_to_string(&ast_root),
+        ast_to_string(&parse_src(renamed).unwrap())
+    );
+}
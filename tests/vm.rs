@@ -0,0 +1,138 @@
+//! The bytecode VM must behave exactly like the tree-walking interpreter: same exit codes, same
+//! output, and the same runtime errors at the same locations.
+
+use nuktah::{
+    bytecode::{compiler::compile, vm},
+    compile_src,
+    interp::core::{self as interp, RuntimeError, RuntimeErrorKind},
+    semantics::core::SrcKind,
+};
+
+type Outcome = (Result<i64, RuntimeError>, String);
+
+fn run_both(src: &str, stdin: &str) -> (Outcome, Outcome) {
+    let artifacts = compile_src(src, SrcKind::Program).unwrap_or_else(|e| panic!("{e:?}"));
+    let args = ["prog.nkt".to_string(), "arg".to_string()];
+
+    let mut out = vec![];
+    let res = interp::run_with_io(&artifacts.ast, &args, &mut stdin.as_bytes(), &mut out);
+    let interp_outcome = (res, String::from_utf8(out).unwrap());
+
+    let program = compile(&artifacts.ast);
+    let mut out = vec![];
+    let res = vm::run_with_io(&program, &args, &mut stdin.as_bytes(), &mut out);
+    let vm_outcome = (res, String::from_utf8(out).unwrap());
+
+    (interp_outcome, vm_outcome)
+}
+
+fn assert_same(src: &str) -> Outcome {
+    let (interp, vm) = run_both(src, "line one\nline two\n");
+    assert_eq!(interp, vm, "in:\n{src}");
+    vm
+}
+
+#[test]
+fn matches_interpreter() {
+    let programs = [
+        "fn ginti shuru() { wapsi 2 + 3 * 4 - 10 / 3 % 2 + 2 ^ 3 ^ 2 - (1 << 4 | 3 & 1) . } .",
+        "fn ginti shuru(ginti argc) { wapsi -argc * 7 . } .",
+        "
+        ginti counter = 1 .
+        fn khali bump(ginti by) { counter = counter + by . wapsi . } .
+        ginti twice = counter * 2 .
+        fn ginti shuru() { bump(twice) . bump(10) . wapsi counter . } .
+        ",
+        "
+        fn ginti shuru() {
+            ginti s = 0 .
+            duhrao (ginti i = 0 . sach . i = i + 1) {
+                agar (i == 10) { toro } warna {}
+                duhrao (ginti j = 0 . j < i . j = j + 1) {
+                    agar (j > 3) { toro } warna { s = s + j . }
+                }
+            }
+            duhrao (ginti k = 0 . . k = k + 1) {
+                agar (k * k > 50) { wapsi s * 100 + k . } warna {}
+            }
+            wapsi -1 .
+        } .
+        ",
+        "
+        fn ginti fib(ginti n) {
+            agar (n < 2) { wapsi n . } warna {}
+            wapsi fib(n - 1) + fib(n - 2) .
+        } .
+        fn ginti shuru() { wapsi fib(17) . } .
+        ",
+        "
+        ginti calls = 0 .
+        fn boli noisy(boli b) { calls = calls + 1 . wapsi b . } .
+        fn ginti shuru() {
+            boli a = jhoot && noisy(sach) .
+            boli b = sach || noisy(sach) .
+            boli c = noisy(sach) && noisy(jhoot) || noisy(sach) .
+            agar (!a && b && c) { calls = calls * 10 . } warna {}
+            wapsi calls .
+        } .
+        ",
+        "
+        fn ginti shuru() {
+            asharia x = 1.5 * 4.0 - -0.5 .
+            jumla a = \"abc\" .
+            ginti p = 0 . ginti q = 0 .
+            p = q = 21 .
+            agar (x == 6.5 && a < \"abd\") { likho(a) . } warna {}
+            likho(parho()) .
+            likho(parho()) .
+            likho(parho()) .
+            likho_ginti(p + q) .
+            wapsi 0 .
+        } .
+        ",
+        "fn khali shuru() { ginti a = 9223372036854775807 . a = a + 1 . likho_ginti(a) . wapsi . } .",
+    ];
+
+    for src in programs {
+        assert!(assert_same(src).0.is_ok(), "in:\n{src}");
+    }
+}
+
+#[test]
+fn runtime_errors_match_interpreter() {
+    let programs = [
+        (
+            "fn ginti shuru(ginti argc) {\n    ginti zero = argc - 2 .\n    likho(\"before\") .\n    wapsi 1 / zero .\n} .",
+            RuntimeErrorKind::DivisionByZero,
+            4,
+        ),
+        (
+            "ginti big = 64 .\nginti bad = 1 << big .\nfn ginti shuru() { wapsi 0 . } .",
+            RuntimeErrorKind::ShiftOutOfRange(64),
+            2,
+        ),
+        (
+            "fn ginti f(ginti x) {\n agar (x > 0) { wapsi x . } warna {}\n} .\nfn ginti shuru() {\n wapsi f(0) .\n} .",
+            RuntimeErrorKind::MissingReturn("f".to_string()),
+            5,
+        ),
+        (
+            "fn ginti shuru() {\n duhrao (ginti i = 3 . . i = i - 1) {\n  ginti x = 2 ^ i .\n }\n wapsi 0 .\n} .",
+            RuntimeErrorKind::NegativeExponent(-1),
+            3,
+        ),
+    ];
+
+    for (src, kind, line) in programs {
+        let (res, _) = assert_same(src);
+        let e = res.unwrap_err();
+        assert_eq!((e.kind, e.span.line), (kind, line), "in:\n{src}");
+    }
+}
+
+#[test]
+fn unbounded_recursion_overflows() {
+    let src = "fn ginti f(ginti x) { wapsi f(x + 1) . } .\nfn ginti shuru() { wapsi f(0) . } .";
+    let (_, (res, _)) = run_both(src, "");
+    assert_eq!(res.unwrap_err().kind, RuntimeErrorKind::StackOverflow);
+}
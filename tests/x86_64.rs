@@ -0,0 +1,102 @@
+//! The x86-64 backend: programs assembled and linked into native executables print, read and exit
+//! w/ the same code as they do when interpreted, optimised or not.
+
+#![cfg(all(target_arch = "x86_64", target_os = "linux"))]
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::*;
+use nuktah::{
+    codegen::{
+        core::{build_executable, Target},
+        regalloc::core::Allocator,
+        x86_64,
+    },
+    compile_src_opt,
+    opt::core::OptLevel,
+    semantics::core::SrcKind,
+};
+
+// Builds `src` into an executable at `level`, and runs it w/ `args` (its path first) and `stdin`
+fn run_native(src: &str, level: OptLevel, args: &[String], stdin: &str) -> Output {
+    let lowered = compile_src_opt(src, SrcKind::Program, level)
+        .unwrap()
+        .lowered
+        .unwrap();
+    let asm = x86_64::generate(&lowered.ir, Allocator::for_level(level));
+
+    let exe = std::env::temp_dir().join(format!(
+        "nkt-x86_64-{}-{:x}",
+        std::process::id(),
+        hash(&(src, level as u8, args))
+    ));
+    build_executable(Target::X86_64Linux, &asm, &exe).unwrap_or_else(|e| panic!("{e}\n{asm}"));
+
+    let output = run_piped(Command::new(&exe).args(&args[1..]), stdin);
+    std::fs::remove_file(&exe).unwrap();
+    output
+}
+
+fn assert_matches_interp(src: &str, stdin: &str) {
+    for (args, code, out) in interp_runs(src, stdin) {
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let output = run_native(src, level, &args, stdin);
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                out,
+                "at {level:?}, w/ {args:?}:\n{src}"
+            );
+            assert_eq!(
+                output.status.code(),
+                Some(code as u8 as i32),
+                "at {level:?}, w/ {args:?}: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+}
+
+#[test]
+fn integer_arithmetic() {
+    assert_matches_interp(ARITHMETIC, "");
+}
+
+#[test]
+fn floats_in_sse_registers() {
+    assert_matches_interp(FLOATS, "");
+}
+
+#[test]
+fn strings_and_input() {
+    for stdin in STRINGS_INPUTS {
+        assert_matches_interp(STRINGS, stdin);
+    }
+}
+
+#[test]
+fn calls_and_globals() {
+    assert_matches_interp(CALLS, "");
+}
+
+#[test]
+fn operands_are_evaluated_left_to_right() {
+    assert_matches_interp(ORDER, "");
+}
+
+#[test]
+fn runtime_errors_exit_w_1() {
+    for level in [OptLevel::O0, OptLevel::O2] {
+        for (argc, err) in RUNTIME_ERROR_CASES {
+            let args = vec![String::new(); argc];
+            let output = run_native(RUNTIME_ERRORS, level, &args, "");
+            assert_eq!(output.status.code(), Some(1));
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+            assert_eq!(
+                String::from_utf8_lossy(&output.stderr),
+                format!("runtime error: {err}\n")
+            );
+        }
+    }
+}